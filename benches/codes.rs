@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Compares the throughput of generating codes one at a time with [Hotp::codes_into].
+use otpee::{Hotp, Otp};
+use sha1::Sha1;
+use std::{hint::black_box, time::Instant};
+
+const CODES: usize = 1 << 16;
+
+fn main() {
+	let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+	let mut codes: Vec<Otp> = vec![hotp.code().unwrap(); CODES];
+
+	let start = Instant::now();
+	for (counter, slot) in (0..).zip(codes.iter_mut()) {
+		*slot = black_box(&mut hotp).code_at(counter).unwrap();
+	}
+	report("code_at", start);
+	black_box(&codes);
+
+	let start = Instant::now();
+	black_box(&mut hotp)
+		.codes_into(0, black_box(&mut codes))
+		.unwrap();
+	report("codes_into", start);
+	black_box(&codes);
+}
+
+fn report(name: &str, start: Instant) {
+	let elapsed = start.elapsed();
+	println!(
+		"{name}: {CODES} codes in {elapsed:?} ({:.0} codes/s)",
+		CODES as f64 / elapsed.as_secs_f64()
+	);
+}
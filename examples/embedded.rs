@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Generates codes from a hardware clock, such as an RTC on an embedded board.
+//!
+//! The RTC here is faked with a tick counter, but a real one would read the time
+//! over I2C or SPI, and can report that it hasn't been set yet instead of a bogus time.
+use otpee::{error::TimeError, TimeSource, Totp};
+use sha1::Sha1;
+
+/// A fake RTC, which counts ticks from the time it was set at.
+struct FakeRtc {
+	set_at: Option<u64>,
+	ticks: u64,
+}
+
+impl FakeRtc {
+	fn set(&mut self, unix_seconds: u64) {
+		self.set_at = Some(unix_seconds);
+		self.ticks = 0;
+	}
+}
+
+impl TimeSource for FakeRtc {
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		let set_at = self.set_at.ok_or(TimeError::NotSet)?;
+		self.ticks += 1;
+		Ok(set_at + self.ticks)
+	}
+}
+
+fn main() {
+	let rtc = FakeRtc {
+		set_at: None,
+		ticks: 0,
+	};
+	let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, rtc).unwrap();
+	match totp.code() {
+		Ok(code) => println!("code: {}", code),
+		Err(err) => println!("no code yet: {}", err),
+	}
+
+	// Once the RTC has been set, such as from the network, codes can be generated.
+	let mut rtc = FakeRtc {
+		set_at: None,
+		ticks: 0,
+	};
+	rtc.set(1111111109);
+	totp.set_time_source(rtc);
+	let code = totp.code().unwrap();
+	println!("code: {}", code);
+	assert!(totp.validate_code(*code));
+}
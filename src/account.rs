@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+#[cfg(feature = "qr")]
+use crate::qr::{QrImage, QrOptions};
+use crate::{
+	algorithm::HashAlgorithm,
+	error::OtpError,
+	generator::OtpGenerator,
+	hotp::Hotp,
+	mac::OtpMac,
+	otp::Otp,
+	totp::Totp,
+	uri::{ParsedHotp, ParsedTotp},
+};
+use alloc::string::String;
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// A generator, along with the account it belongs to.
+///
+/// ```rust
+/// use otpee::{Account, Hotp};
+/// use sha1::Sha1;
+///
+/// let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+/// let mut account = Account::new(hotp, "alice@example.com", Some("Example"));
+/// assert_eq!(account.label(), "alice@example.com");
+/// assert_eq!(account.issuer(), Some("Example"));
+/// assert!(account.validate_code(755224));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Account<G> {
+	issuer: Option<String>,
+	label: String,
+	generator: G,
+}
+
+impl<G> Account<G> {
+	/// Creates a new account, wrapping the given generator.
+	pub fn new<L: Into<String>, I: Into<String>>(
+		generator: G,
+		label: L,
+		issuer: Option<I>,
+	) -> Self {
+		Self {
+			issuer: issuer.map(Into::into),
+			label: label.into(),
+			generator,
+		}
+	}
+
+	/// Returns the label of the account, usually the account name.
+	#[inline]
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+
+	/// Sets the label of the account.
+	#[inline]
+	pub fn set_label<L: Into<String>>(&mut self, label: L) {
+		self.label = label.into();
+	}
+
+	/// Returns the issuer of the account, if any.
+	#[inline]
+	pub fn issuer(&self) -> Option<&str> {
+		self.issuer.as_deref()
+	}
+
+	/// Sets the issuer of the account.
+	#[inline]
+	pub fn set_issuer<I: Into<String>>(&mut self, issuer: Option<I>) {
+		self.issuer = issuer.map(Into::into);
+	}
+
+	/// Returns the generator.
+	#[inline]
+	pub fn generator(&self) -> &G {
+		&self.generator
+	}
+
+	/// Returns the generator, mutably.
+	#[inline]
+	pub fn generator_mut(&mut self) -> &mut G {
+		&mut self.generator
+	}
+
+	/// Returns the generator, discarding the account metadata.
+	#[inline]
+	pub fn into_inner(self) -> G {
+		self.generator
+	}
+}
+
+impl<G: OtpGenerator> Account<G> {
+	/// Calculates the current OTP value, see [OtpGenerator::current_code].
+	#[inline]
+	pub fn code(&mut self) -> Result<Otp, OtpError> {
+		self.generator.current_code()
+	}
+
+	/// Validates the code, see [OtpGenerator::validate].
+	#[inline]
+	pub fn validate_code(&mut self, code: u64) -> bool {
+		self.generator.validate(code)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm> Account<Hotp<D>> {
+	/// Creates a new HOTP account from an `otpauth://hotp` provisioning URI,
+	/// keeping its label and issuer. See [Hotp::from_uri].
+	pub fn from_uri(uri: &str) -> Result<Self, OtpError> {
+		let parsed = ParsedHotp::parse(uri)?;
+		let hotp = parsed.build()?;
+		Ok(Self {
+			issuer: parsed.issuer,
+			label: parsed.label,
+			generator: hotp,
+		})
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, M: OtpMac> Account<Hotp<D, M>> {
+	/// Generates an `otpauth://hotp` provisioning URI for the account, see [Hotp::to_uri].
+	#[inline]
+	pub fn to_uri(&self, secret: &[u8]) -> String {
+		self.generator.to_uri(secret, &self.label, self.issuer())
+	}
+
+	#[cfg(feature = "qr")]
+	/// Generates a QR code of the account's provisioning URI, with the default options.
+	#[inline]
+	pub fn provisioning_qr(&self, secret: &[u8]) -> Result<QrImage, OtpError> {
+		self.provisioning_qr_with(secret, QrOptions::default())
+	}
+
+	#[cfg(feature = "qr")]
+	/// Generates a QR code of the account's provisioning URI, with the given options.
+	#[inline]
+	pub fn provisioning_qr_with(
+		&self,
+		secret: &[u8],
+		options: QrOptions,
+	) -> Result<QrImage, OtpError> {
+		QrImage::new(self.to_uri(secret), options)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, C> Account<Totp<D, C>> {
+	/// Creates a new TOTP account from an `otpauth://totp` provisioning URI,
+	/// keeping its label and issuer. See [Totp::from_uri].
+	pub fn from_uri(uri: &str, time_source: C) -> Result<Self, OtpError> {
+		let parsed = ParsedTotp::parse(uri)?;
+		let totp = parsed.build(time_source)?;
+		Ok(Self {
+			issuer: parsed.issuer,
+			label: parsed.label,
+			generator: totp,
+		})
+	}
+
+	/// Generates an `otpauth://totp` provisioning URI for the account, see [Totp::to_uri].
+	#[inline]
+	pub fn to_uri(&self, secret: &[u8]) -> String {
+		self.generator.to_uri(secret, &self.label, self.issuer())
+	}
+
+	#[cfg(feature = "qr")]
+	/// Generates a QR code of the account's provisioning URI, with the default options.
+	#[inline]
+	pub fn provisioning_qr(&self, secret: &[u8]) -> Result<QrImage, OtpError> {
+		self.provisioning_qr_with(secret, QrOptions::default())
+	}
+
+	#[cfg(feature = "qr")]
+	/// Generates a QR code of the account's provisioning URI, with the given options.
+	#[inline]
+	pub fn provisioning_qr_with(
+		&self,
+		secret: &[u8],
+		options: QrOptions,
+	) -> Result<QrImage, OtpError> {
+		QrImage::new(self.to_uri(secret), options)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Account;
+	use crate::hotp::Hotp;
+	use sha1::Sha1;
+
+	#[test]
+	fn account_metadata() {
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut account = Account::new(hotp, "alice", None::<&str>);
+		assert_eq!(account.issuer(), None);
+		account.set_label("bob");
+		account.set_issuer(Some("Example"));
+		assert_eq!(account.label(), "bob");
+		assert_eq!(account.issuer(), Some("Example"));
+		assert!(account.validate_code(755224));
+		assert_eq!(account.into_inner().counter(), 1);
+	}
+
+	#[cfg(feature = "sha1")]
+	#[test]
+	fn account_uri_round_trip() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_counter(3).unwrap();
+		let account = Account::new(hotp, "alice@example.com", Some("Example: Corp"));
+		let uri = account.to_uri(b"12345678901234567890");
+		let mut restored = Account::<Hotp<Sha1>>::from_uri(&uri).unwrap();
+		assert_eq!(restored.label(), "alice@example.com");
+		assert_eq!(restored.issuer(), Some("Example: Corp"));
+		assert_eq!(restored.generator().counter(), 3);
+		assert_eq!(restored.code().unwrap(), 26969429);
+
+		restored.set_issuer(None::<String>);
+		let restored =
+			Account::<Hotp<Sha1>>::from_uri(&restored.to_uri(b"12345678901234567890")).unwrap();
+		assert_eq!(restored.issuer(), None);
+		assert_eq!(restored.label(), "alice@example.com");
+	}
+
+	#[cfg(feature = "sha1")]
+	#[test]
+	fn account_totp_uri_round_trip() {
+		use crate::totp::Totp;
+
+		let totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		let account = Account::new(totp, "alice", Some("Example"));
+		let uri = account.to_uri(b"12345678901234567890");
+		let mut restored = Account::<Totp<Sha1, _>>::from_uri(&uri, || 59).unwrap();
+		assert_eq!(restored.label(), "alice");
+		assert_eq!(restored.issuer(), Some("Example"));
+		assert_eq!(restored.code().unwrap(), 94287082);
+	}
+}
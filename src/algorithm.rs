@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use core::fmt::{self, Display, Formatter};
+
+/// A hash algorithm that can be selected at runtime, such as when importing a token.
+///
+/// More algorithms may be added in the future, so matching on this should include a wildcard arm.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+	/// SHA-1, the default for both HOTP and TOTP.
+	#[default]
+	Sha1,
+	/// SHA-256.
+	Sha256,
+	/// SHA-512.
+	Sha512,
+}
+
+impl Algorithm {
+	/// Returns the name of the algorithm, as used in provisioning URIs.
+	pub fn name(self) -> &'static str {
+		match self {
+			Algorithm::Sha1 => "SHA1",
+			Algorithm::Sha256 => "SHA256",
+			Algorithm::Sha512 => "SHA512",
+		}
+	}
+
+	/// Returns the algorithm with the given name, as used in provisioning URIs, ignoring case.
+	pub fn from_name(name: &str) -> Option<Self> {
+		[Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512]
+			.into_iter()
+			.find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+	}
+
+	/// Returns the output size of the algorithm in bytes, which is also the recommended secret length.
+	pub fn output_size(self) -> usize {
+		match self {
+			Algorithm::Sha1 => 20,
+			Algorithm::Sha256 => 32,
+			Algorithm::Sha512 => 64,
+		}
+	}
+}
+
+impl Display for Algorithm {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// A hash function with a known [Algorithm], allowing it to be named in provisioning URIs.
+///
+/// This is implemented for the SHA-1 and SHA-2 hash functions from RustCrypto
+/// when the `sha1` and `sha2` features are enabled, respectively.
+pub trait HashAlgorithm {
+	/// The algorithm this hash function implements.
+	const ALGORITHM: Algorithm;
+}
+
+#[cfg(feature = "sha1")]
+impl HashAlgorithm for sha1::Sha1 {
+	const ALGORITHM: Algorithm = Algorithm::Sha1;
+}
+
+#[cfg(feature = "sha2")]
+impl HashAlgorithm for sha2::Sha256 {
+	const ALGORITHM: Algorithm = Algorithm::Sha256;
+}
+
+#[cfg(feature = "sha2")]
+impl HashAlgorithm for sha2::Sha512 {
+	const ALGORITHM: Algorithm = Algorithm::Sha512;
+}
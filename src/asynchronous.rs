@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, otp::Otp, time::AsyncTimeSource, totp::Totp, validation::Validation};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// A wrapper around a [Totp] instance that gets the current time from an [AsyncTimeSource],
+/// such as a time service behind an async API, instead of a synchronous one.
+///
+/// Only the methods that need the current time are async; everything else,
+/// such as generating codes for a given time, is available through [AsyncTotp::inner_mut].
+/// No executor is needed beyond whatever the caller already uses.
+///
+/// ```rust
+/// use otpee::AsyncTotp;
+/// use sha1::Sha1;
+///
+/// async fn check(code: u64) -> bool {
+///     let mut totp = AsyncTotp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || async { 59 })
+///         .unwrap();
+///     totp.validate_code(code).await
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AsyncTotp<D: Digest + BlockSizeUser + FixedOutputReset, C> {
+	totp: Totp<D, C>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: AsyncTimeSource> AsyncTotp<D, C> {
+	/// Creates a new instance, with the same parameters as [Totp::new].
+	pub fn new<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		Totp::new(key, length, interval, skew, time_source).map(Self::from_totp)
+	}
+
+	/// Wraps the given TOTP instance, such as one created with [Totp::builder]
+	/// and an [AsyncTimeSource].
+	#[inline]
+	pub fn from_totp(totp: Totp<D, C>) -> Self {
+		Self { totp }
+	}
+
+	/// Returns a reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner(&self) -> &Totp<D, C> {
+		&self.totp
+	}
+
+	/// Returns a mutable reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner_mut(&mut self) -> &mut Totp<D, C> {
+		&mut self.totp
+	}
+
+	/// Unwraps the TOTP instance.
+	#[inline]
+	pub fn into_inner(self) -> Totp<D, C> {
+		self.totp
+	}
+
+	/// Returns the time step for the current time, like [Totp::counter].
+	pub async fn counter(&mut self) -> Result<u64, OtpError> {
+		let millis = self.totp.time_source_mut().unix_millis_wide().await?;
+		self.totp.expected_step_at_millis(millis)
+	}
+
+	/// Calculate the OTP value for the current time, like [Totp::code].
+	pub async fn code(&mut self) -> Result<Otp, OtpError> {
+		let millis = self.totp.time_source_mut().unix_millis_wide().await?;
+		let step = self.totp.expected_step_at_millis(millis)?;
+		self.totp.hotp_mut().code_at(step)
+	}
+
+	/// Validates the code as being valid for the current time, like [Totp::validate_code].
+	pub async fn validate_code(&mut self, code: u64) -> bool {
+		self.validate_code_detailed(code).await.is_valid()
+	}
+
+	/// Validates the code like [AsyncTotp::validate_code], returning a detailed result,
+	/// like [Totp::validate_code_detailed].
+	pub async fn validate_code_detailed(&mut self, code: u64) -> Validation {
+		let millis = self.totp.time_source_mut().unix_millis_wide().await;
+		self.totp.validate_code_at_reported_time(code, millis)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::AsyncTotp;
+	use crate::{
+		error::{OtpError, TimeError},
+		time::AsyncTimeSource,
+		validation::Validation,
+	};
+	use core::{
+		future::{self, Future},
+		pin::pin,
+		task::{Context, Poll, Waker},
+	};
+	use sha1::Sha1;
+
+	fn block_on<F: Future>(future: F) -> F::Output {
+		let mut future = pin!(future);
+		let mut cx = Context::from_waker(Waker::noop());
+		loop {
+			if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+				return output;
+			}
+		}
+	}
+
+	#[test]
+	fn async_totp_code() {
+		let mut totp =
+			AsyncTotp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || future::ready(59))
+				.unwrap();
+		assert_eq!(block_on(totp.counter()), Ok(1));
+		assert_eq!(block_on(totp.code()).unwrap(), 94287082);
+		assert!(block_on(totp.validate_code(94287082)));
+		assert_eq!(
+			block_on(totp.validate_code_detailed(94287082)),
+			Validation::Replayed
+		);
+		assert_eq!(totp.inner_mut().code_at_time(59).unwrap(), 94287082);
+
+		// Times in seconds that don't fit in milliseconds still work.
+		let mut totp = AsyncTotp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || {
+			future::ready(u64::MAX)
+		})
+		.unwrap();
+		assert_eq!(block_on(totp.counter()), Ok(u64::MAX / 30));
+		let code = block_on(totp.code()).unwrap();
+		assert_eq!(code, totp.inner_mut().code_at_time(u64::MAX).unwrap());
+	}
+
+	#[test]
+	fn async_totp_drift_offset() {
+		let mut totp =
+			AsyncTotp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, || future::ready(59))
+				.unwrap();
+		totp.inner_mut().set_drift_offset(-1);
+		assert_eq!(block_on(totp.counter()), Ok(0));
+		let code = block_on(totp.code()).unwrap();
+		assert_eq!(code, 84755224);
+		assert!(block_on(totp.validate_code(*code)));
+	}
+
+	#[test]
+	fn async_totp_time_unavailable() {
+		struct Unsynced;
+
+		impl AsyncTimeSource for Unsynced {
+			async fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+				Err(TimeError::ReadFailed)
+			}
+		}
+
+		let mut totp =
+			AsyncTotp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, Unsynced).unwrap();
+		assert_eq!(
+			block_on(totp.code()),
+			Err(OtpError::TimeUnavailable(TimeError::ReadFailed))
+		);
+		assert!(!block_on(totp.validate_code(94287082)));
+	}
+}
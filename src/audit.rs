@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/// An event reported to the audit hook of a [Hotp](crate::Hotp) or [Totp](crate::Totp) instance,
+/// such as for compliance logging.
+///
+/// Events never contain codes or key material, so they are safe to log as-is.
+/// For TOTP, counters are time steps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AuditEvent {
+	/// A code was generated for the given counter.
+	CodeGenerated {
+		/// The counter the code was generated for.
+		counter: u64,
+	},
+	/// A code was validated and accepted.
+	ValidationSucceeded {
+		/// The counter the code matched.
+		counter: u64,
+		/// How far the matched counter was from the expected one: for HOTP, how far ahead of the
+		/// stored counter it was, and for TOTP, how many steps before or after the current one.
+		drift: i64,
+	},
+	/// A code was rejected, either because it didn't match, or because it couldn't be accepted.
+	ValidationFailed,
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing accounts from the backups of other authenticator apps.
+//!
+//! Every importer returns the same [ImportedAccount]s, so they only have to be converted once.
+use crate::{algorithm::Algorithm, secret::Secret};
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "backup-aegis")]
+pub mod aegis;
+#[cfg(feature = "backup-andotp")]
+pub mod andotp;
+#[cfg(feature = "backup-freeotp")]
+pub mod freeotp;
+#[cfg(feature = "backup-keepass")]
+pub mod keepass;
+#[cfg(feature = "backup-twofas")]
+pub mod twofas;
+
+/// The type of an imported account, along with its type-specific settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportedType {
+	/// A time-based account, with the given interval in seconds.
+	Totp { period: u64 },
+	/// A counter-based account, with the given counter.
+	Hotp { counter: u64 },
+	/// A Steam Guard account, see [SteamTotp](crate::steam::SteamTotp).
+	Steam { period: u64 },
+}
+
+/// An account imported from a backup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedAccount {
+	/// The secret.
+	pub secret: Secret,
+	/// The name of the account.
+	pub name: String,
+	/// The issuer, if any.
+	pub issuer: Option<String>,
+	/// The hash algorithm.
+	pub algorithm: Algorithm,
+	/// The number of digits, or characters for Steam Guard accounts.
+	pub digits: usize,
+	/// The type of the account.
+	pub otp_type: ImportedType,
+}
+
+/// An entry in a backup that wasn't imported, such as one of a type that isn't supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+	/// The position of the entry in the backup, starting at 0.
+	pub index: usize,
+	/// The name of the entry, if it has one.
+	pub name: String,
+	/// Why the entry was skipped.
+	pub reason: String,
+}
+
+/// The accounts imported from a backup, along with the entries that were skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Backup {
+	/// The accounts that were imported.
+	pub accounts: Vec<ImportedAccount>,
+	/// The entries that couldn't be imported.
+	pub skipped: Vec<SkippedEntry>,
+}
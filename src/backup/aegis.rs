@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing plaintext vaults exported by Aegis Authenticator.
+use super::{Backup, ImportedAccount, ImportedType, SkippedEntry};
+use crate::{
+	algorithm::Algorithm,
+	error::{BackupError, OtpError},
+	hotp::MAX_DIGITS,
+	secret::Secret,
+};
+use alloc::{format, string::String, vec::Vec};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+struct Vault {
+	db: Value,
+}
+
+#[derive(Deserialize)]
+struct Database {
+	entries: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+	#[serde(rename = "type")]
+	kind: String,
+	#[serde(default)]
+	name: String,
+	#[serde(default)]
+	issuer: String,
+	info: Value,
+}
+
+#[derive(Deserialize)]
+struct Info {
+	secret: String,
+	algo: String,
+	digits: usize,
+	#[serde(default)]
+	period: Option<u64>,
+	#[serde(default)]
+	counter: Option<u64>,
+}
+
+/// Parses a plaintext vault exported by Aegis, returning the TOTP, HOTP and Steam accounts in it.
+///
+/// Entries of other types, and entries that don't match the expected format, are skipped,
+/// and listed in [Backup::skipped]. Returns [OtpError::InvalidBackup] if the vault itself
+/// doesn't match the expected format, or is encrypted.
+///
+/// ```rust
+/// use otpee::backup::{aegis, ImportedType};
+///
+/// let backup = aegis::parse(
+///     r#"{"version": 1, "db": {"version": 2, "entries": [{"type": "totp", "name": "alice",
+///         "issuer": "Example", "info": {"secret": "MZXW6YQ", "algo": "SHA1", "digits": 6,
+///         "period": 30}}]}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(backup.accounts[0].name, "alice");
+/// assert_eq!(backup.accounts[0].otp_type, ImportedType::Totp { period: 30 });
+/// ```
+pub fn parse(json: &str) -> Result<Backup, OtpError> {
+	let vault: Vault = serde_json::from_str(json)
+		.map_err(|err| BackupError::new(format!("invalid vault: {}", err)))?;
+	if vault.db.is_string() {
+		return Err(BackupError::new("encrypted vaults aren't supported").into());
+	}
+	let db: Database = serde_json::from_value(vault.db)
+		.map_err(|err| BackupError::new(format!("invalid vault database: {}", err)))?;
+
+	let mut backup = Backup::default();
+	for (index, entry) in db.entries.into_iter().enumerate() {
+		let entry: Entry = match serde_json::from_value(entry) {
+			Ok(entry) => entry,
+			Err(err) => {
+				backup.skipped.push(SkippedEntry {
+					index,
+					name: String::new(),
+					reason: format!("invalid entry: {}", err),
+				});
+				continue;
+			}
+		};
+		match parse_entry(&entry) {
+			Ok(account) => backup.accounts.push(account),
+			Err(reason) => backup.skipped.push(SkippedEntry {
+				index,
+				name: entry.name,
+				reason,
+			}),
+		}
+	}
+	Ok(backup)
+}
+
+/// Converts an entry into an account, or returns why it can't be.
+fn parse_entry(entry: &Entry) -> Result<ImportedAccount, String> {
+	if !matches!(entry.kind.as_str(), "totp" | "hotp" | "steam") {
+		return Err(format!("unsupported entry type `{}`", entry.kind));
+	}
+	let info =
+		Info::deserialize(&entry.info).map_err(|err| format!("invalid entry info: {}", err))?;
+	let secret = Secret::from_base32(&info.secret).map_err(|_| String::from("invalid secret"))?;
+	let algorithm = Algorithm::from_name(&info.algo)
+		.ok_or_else(|| format!("unsupported algorithm `{}`", info.algo))?;
+	if !(1..=MAX_DIGITS).contains(&info.digits) {
+		return Err(format!("unsupported number of digits ({})", info.digits));
+	}
+	let period = || match info.period {
+		Some(0) => Err(String::from("invalid period (0)")),
+		Some(period) => Ok(period),
+		None => Err(String::from("missing period")),
+	};
+	let otp_type = match entry.kind.as_str() {
+		"hotp" => ImportedType::Hotp {
+			counter: info
+				.counter
+				.ok_or_else(|| String::from("missing counter"))?,
+		},
+		"steam" => ImportedType::Steam { period: period()? },
+		_ => ImportedType::Totp { period: period()? },
+	};
+	Ok(ImportedAccount {
+		secret,
+		name: entry.name.clone(),
+		issuer: Some(entry.issuer.clone()).filter(|issuer| !issuer.is_empty()),
+		algorithm,
+		digits: info.digits,
+		otp_type,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse;
+	use crate::{
+		algorithm::Algorithm,
+		backup::{ImportedAccount, ImportedType, SkippedEntry},
+		error::OtpError,
+		secret::Secret,
+	};
+
+	#[test]
+	fn aegis_parse() {
+		let backup = parse(include_str!("fixtures/aegis.json")).unwrap();
+		assert_eq!(
+			backup.accounts,
+			[
+				ImportedAccount {
+					secret: Secret::from(b"12345678901234567890"),
+					name: "alice@example.com".into(),
+					issuer: Some("Example".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 8,
+					otp_type: ImportedType::Totp { period: 30 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"foobar"),
+					name: "bob".into(),
+					issuer: None,
+					algorithm: Algorithm::Sha256,
+					digits: 6,
+					otp_type: ImportedType::Hotp { counter: 42 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"foob"),
+					name: "gamer".into(),
+					issuer: Some("Steam".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 5,
+					otp_type: ImportedType::Steam { period: 30 },
+				},
+			]
+		);
+		assert_eq!(
+			backup.skipped,
+			[
+				SkippedEntry {
+					index: 3,
+					name: "vpn".into(),
+					reason: "unsupported entry type `motp`".into(),
+				},
+				SkippedEntry {
+					index: 4,
+					name: "broken".into(),
+					reason: "invalid entry info: missing field `secret`".into(),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn aegis_invalid_vault() {
+		let message = |json| match parse(json) {
+			Err(OtpError::InvalidBackup(err)) => err.message().to_owned(),
+			other => panic!("unexpected result: {:?}", other),
+		};
+		assert_eq!(
+			message(r#"{"version": 1, "header": {}, "db": "c2VjcmV0"}"#),
+			"encrypted vaults aren't supported"
+		);
+		assert_eq!(
+			message(r#"{"version": 1}"#),
+			"invalid vault: missing field `db` at line 1 column 14"
+		);
+		assert_eq!(
+			message(r#"{"version": 1, "db": {"version": 3}}"#),
+			"invalid vault database: missing field `entries`"
+		);
+	}
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing plaintext backups exported by andOTP.
+use super::{Backup, ImportedAccount, ImportedType, SkippedEntry};
+use crate::{
+	algorithm::Algorithm,
+	error::{BackupError, OtpError},
+	hotp::MAX_DIGITS,
+	secret::Secret,
+};
+use alloc::{format, string::String, vec::Vec};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The interval andOTP uses for entries that don't specify one.
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Deserialize)]
+struct Entry {
+	secret: String,
+	#[serde(default)]
+	issuer: String,
+	#[serde(default)]
+	label: String,
+	digits: usize,
+	#[serde(rename = "type")]
+	kind: String,
+	algorithm: String,
+	#[serde(default)]
+	period: Option<u64>,
+	#[serde(default)]
+	counter: Option<u64>,
+}
+
+/// Parses a plaintext backup exported by andOTP, returning the TOTP, HOTP and Steam accounts in it.
+///
+/// Entries of other types, and entries that don't match the expected format, are skipped,
+/// and listed in [Backup::skipped]. Returns [OtpError::InvalidBackup] if the backup itself
+/// isn't a list of entries.
+///
+/// ```rust
+/// use otpee::backup::{andotp, ImportedType};
+///
+/// let backup = andotp::parse(
+///     r#"[{"secret": "MZXW6YQ", "issuer": "Example", "label": "alice", "digits": 6,
+///         "type": "TOTP", "algorithm": "SHA1", "period": 30}]"#,
+/// )
+/// .unwrap();
+/// assert_eq!(backup.accounts[0].name, "alice");
+/// assert_eq!(backup.accounts[0].otp_type, ImportedType::Totp { period: 30 });
+/// ```
+pub fn parse(json: &str) -> Result<Backup, OtpError> {
+	let entries: Vec<Value> = serde_json::from_str(json)
+		.map_err(|err| BackupError::new(format!("invalid backup: {}", err)))?;
+
+	let mut backup = Backup::default();
+	for (index, entry) in entries.into_iter().enumerate() {
+		let name = entry
+			.get("label")
+			.and_then(Value::as_str)
+			.map(String::from)
+			.unwrap_or_default();
+		let result = Entry::deserialize(&entry)
+			.map_err(|err| format!("invalid entry: {}", err))
+			.and_then(|entry| parse_entry(&entry));
+		match result {
+			Ok(account) => backup.accounts.push(account),
+			Err(reason) => backup.skipped.push(SkippedEntry {
+				index,
+				name,
+				reason,
+			}),
+		}
+	}
+	Ok(backup)
+}
+
+/// Converts an entry into an account, or returns why it can't be.
+fn parse_entry(entry: &Entry) -> Result<ImportedAccount, String> {
+	let period = || match entry.period {
+		Some(0) => Err(String::from("invalid period (0)")),
+		Some(period) => Ok(period),
+		None => Ok(DEFAULT_PERIOD),
+	};
+	let otp_type = match entry.kind.as_str() {
+		"TOTP" => ImportedType::Totp { period: period()? },
+		"HOTP" => ImportedType::Hotp {
+			counter: entry
+				.counter
+				.ok_or_else(|| String::from("missing counter"))?,
+		},
+		"STEAM" => ImportedType::Steam { period: period()? },
+		_ => return Err(format!("unsupported entry type `{}`", entry.kind)),
+	};
+	let secret = Secret::from_base32(&entry.secret).map_err(|_| String::from("invalid secret"))?;
+	let algorithm = Algorithm::from_name(&entry.algorithm)
+		.ok_or_else(|| format!("unsupported algorithm `{}`", entry.algorithm))?;
+	if !(1..=MAX_DIGITS).contains(&entry.digits) {
+		return Err(format!("unsupported number of digits ({})", entry.digits));
+	}
+	Ok(ImportedAccount {
+		secret,
+		name: entry.label.clone(),
+		issuer: Some(entry.issuer.clone()).filter(|issuer| !issuer.is_empty()),
+		algorithm,
+		digits: entry.digits,
+		otp_type,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse;
+	use crate::{
+		algorithm::Algorithm,
+		backup::{ImportedAccount, ImportedType, SkippedEntry},
+		error::OtpError,
+		secret::Secret,
+	};
+
+	#[test]
+	fn andotp_parse() {
+		let backup = parse(include_str!("fixtures/andotp.json")).unwrap();
+		assert_eq!(
+			backup.accounts,
+			[
+				ImportedAccount {
+					secret: Secret::from(b"12345678901234567890"),
+					name: "alice@example.com".into(),
+					issuer: Some("Example".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 6,
+					otp_type: ImportedType::Totp { period: 30 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"12345678901234567890"),
+					name: "carol".into(),
+					issuer: Some("Corp".into()),
+					algorithm: Algorithm::Sha512,
+					digits: 8,
+					otp_type: ImportedType::Totp { period: 60 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"foobar"),
+					name: "bob".into(),
+					issuer: None,
+					algorithm: Algorithm::Sha256,
+					digits: 8,
+					otp_type: ImportedType::Hotp { counter: 7 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"foob"),
+					name: "gamer".into(),
+					issuer: Some("Steam".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 5,
+					otp_type: ImportedType::Steam { period: 30 },
+				},
+			]
+		);
+		assert_eq!(
+			backup.skipped,
+			[
+				SkippedEntry {
+					index: 4,
+					name: "vpn".into(),
+					reason: "unsupported entry type `MOTP`".into(),
+				},
+				SkippedEntry {
+					index: 5,
+					name: "broken".into(),
+					reason: "invalid entry: missing field `secret`".into(),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn andotp_invalid_backup() {
+		match parse(r#"{"secret": "MZXW6YQ"}"#) {
+			Err(OtpError::InvalidBackup(err)) => assert!(
+				err.message()
+					.starts_with("invalid backup: invalid type: map"),
+				"{}",
+				err
+			),
+			other => panic!("unexpected result: {:?}", other),
+		}
+	}
+}
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing JSON backups exported by FreeOTP+.
+use super::{Backup, ImportedAccount, ImportedType, SkippedEntry};
+use crate::{
+	algorithm::Algorithm,
+	error::{BackupError, OtpError},
+	hotp::MAX_DIGITS,
+	secret::Secret,
+};
+use alloc::{format, string::String, vec::Vec};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The interval FreeOTP+ uses for tokens that don't specify one.
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Deserialize)]
+struct Document {
+	tokens: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Token {
+	secret: EncodedSecret,
+	#[serde(default = "default_algorithm")]
+	algo: String,
+	digits: usize,
+	#[serde(rename = "type")]
+	kind: String,
+	#[serde(default)]
+	label: String,
+	#[serde(default, rename = "issuerExt")]
+	issuer_ext: Option<String>,
+	#[serde(default, rename = "issuerInt")]
+	issuer_int: Option<String>,
+	#[serde(default)]
+	period: Option<u64>,
+	#[serde(default)]
+	counter: Option<u64>,
+}
+
+/// The secret, which newer versions export as Base32, and older ones as an array of
+/// signed bytes, as Java has no unsigned bytes.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EncodedSecret {
+	Base32(String),
+	Bytes(Vec<i64>),
+}
+
+fn default_algorithm() -> String {
+	String::from("SHA1")
+}
+
+impl EncodedSecret {
+	fn decode(&self) -> Result<Secret, String> {
+		match self {
+			EncodedSecret::Base32(secret) => {
+				Secret::from_base32(secret).map_err(|_| String::from("invalid secret"))
+			}
+			EncodedSecret::Bytes(bytes) => bytes
+				.iter()
+				.map(|&byte| {
+					i8::try_from(byte)
+						.map(|byte| byte as u8)
+						.map_err(|_| format!("invalid secret byte ({})", byte))
+				})
+				.collect::<Result<Vec<u8>, _>>()
+				.map(Secret::from),
+		}
+	}
+}
+
+/// Parses a backup exported by FreeOTP+, returning the TOTP and HOTP accounts in it.
+///
+/// Secrets are accepted both as Base32 and as the signed byte arrays older versions export.
+/// Tokens of other types, and tokens that don't match the expected format, are skipped,
+/// and listed in [Backup::skipped]. Returns [OtpError::InvalidBackup] if the backup itself
+/// doesn't match the expected format.
+///
+/// ```rust
+/// use otpee::backup::freeotp;
+///
+/// let backup = freeotp::parse(
+///     r#"{"tokenOrder": ["Example:alice"], "tokens": [{"algo": "SHA1", "digits": 6,
+///         "issuerExt": "Example", "label": "alice", "period": 30, "type": "TOTP",
+///         "secret": [102, 111, 111, 98]}]}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(backup.accounts[0].secret.as_bytes(), b"foob");
+/// ```
+pub fn parse(json: &str) -> Result<Backup, OtpError> {
+	let document: Document = serde_json::from_str(json)
+		.map_err(|err| BackupError::new(format!("invalid backup: {}", err)))?;
+
+	let mut backup = Backup::default();
+	for (index, token) in document.tokens.into_iter().enumerate() {
+		let name = token
+			.get("label")
+			.and_then(Value::as_str)
+			.map(String::from)
+			.unwrap_or_default();
+		let result = Token::deserialize(&token)
+			.map_err(|err| format!("invalid entry: {}", err))
+			.and_then(|token| parse_token(&token));
+		match result {
+			Ok(account) => backup.accounts.push(account),
+			Err(reason) => backup.skipped.push(SkippedEntry {
+				index,
+				name,
+				reason,
+			}),
+		}
+	}
+	Ok(backup)
+}
+
+/// Converts a token into an account, or returns why it can't be.
+fn parse_token(token: &Token) -> Result<ImportedAccount, String> {
+	let otp_type = match token.kind.as_str() {
+		"TOTP" => ImportedType::Totp {
+			period: match token.period {
+				Some(0) => return Err(String::from("invalid period (0)")),
+				Some(period) => period,
+				None => DEFAULT_PERIOD,
+			},
+		},
+		"HOTP" => ImportedType::Hotp {
+			counter: token.counter.unwrap_or(0),
+		},
+		_ => return Err(format!("unsupported entry type `{}`", token.kind)),
+	};
+	let secret = token.secret.decode()?;
+	let algorithm = Algorithm::from_name(&token.algo)
+		.ok_or_else(|| format!("unsupported algorithm `{}`", token.algo))?;
+	if !(1..=MAX_DIGITS).contains(&token.digits) {
+		return Err(format!("unsupported number of digits ({})", token.digits));
+	}
+	Ok(ImportedAccount {
+		secret,
+		name: token.label.clone(),
+		issuer: token
+			.issuer_ext
+			.iter()
+			.chain(&token.issuer_int)
+			.find(|issuer| !issuer.is_empty())
+			.cloned(),
+		algorithm,
+		digits: token.digits,
+		otp_type,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse;
+	use crate::{
+		algorithm::Algorithm,
+		backup::{ImportedAccount, ImportedType, SkippedEntry},
+		secret::Secret,
+	};
+
+	#[test]
+	fn freeotp_secret_encodings() {
+		let base32 = parse(include_str!("fixtures/freeotp-base32.json")).unwrap();
+		let bytes = parse(include_str!("fixtures/freeotp-bytes.json")).unwrap();
+		assert_eq!(base32, bytes);
+		assert!(base32.skipped.is_empty());
+		assert_eq!(
+			base32.accounts,
+			[
+				ImportedAccount {
+					secret: Secret::from(&[
+						0xde, 0xad, 0xbe, 0xef, 0x00, 0x01, 0x7f, 0x80, 0xff, 0x12, 0x34, 0x56,
+						0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x0f, 0xa5, 0x5a,
+					]),
+					name: "alice@example.com".into(),
+					issuer: Some("Example".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 6,
+					otp_type: ImportedType::Totp { period: 30 },
+				},
+				ImportedAccount {
+					secret: Secret::from(b"foobar"),
+					name: "bob".into(),
+					issuer: None,
+					algorithm: Algorithm::Sha256,
+					digits: 8,
+					otp_type: ImportedType::Hotp { counter: 3 },
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn freeotp_invalid_tokens() {
+		let backup = parse(
+			r#"{"tokenOrder": [], "tokens": [
+				{"algo": "SHA1", "digits": 6, "label": "big", "type": "TOTP", "secret": [1, 128]},
+				{"algo": "MD5", "digits": 6, "label": "md5", "type": "TOTP", "secret": "MZXW6YQ"},
+				{"algo": "SHA1", "digits": 6, "label": "yubi", "type": "YUBI", "secret": "MZXW6YQ"}
+			]}"#,
+		)
+		.unwrap();
+		assert!(backup.accounts.is_empty());
+		assert_eq!(
+			backup.skipped,
+			[
+				SkippedEntry {
+					index: 0,
+					name: "big".into(),
+					reason: "invalid secret byte (128)".into(),
+				},
+				SkippedEntry {
+					index: 1,
+					name: "md5".into(),
+					reason: "unsupported algorithm `MD5`".into(),
+				},
+				SkippedEntry {
+					index: 2,
+					name: "yubi".into(),
+					reason: "unsupported entry type `YUBI`".into(),
+				},
+			]
+		);
+	}
+}
@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing the OTP settings stored in KeePass entries, either in the KeeOTP format
+//! (`key=...&step=30&size=6`), or as an `otpauth://` URI in KeePassXC's `otp` attribute.
+use super::{ImportedAccount, ImportedType};
+use crate::{
+	algorithm::Algorithm,
+	base32,
+	error::{BackupError, OtpError, UriError},
+	hotp::MAX_DIGITS,
+	secret::Secret,
+	uri,
+};
+use alloc::{format, string::String};
+use core::fmt::Write;
+
+/// The interval KeeOTP uses when `step` is absent.
+const DEFAULT_STEP: u64 = 30;
+/// The number of digits KeeOTP uses when `size` is absent.
+const DEFAULT_SIZE: usize = 6;
+
+/// Parses the OTP settings of a KeePass entry, either in the KeeOTP format, or as an
+/// `otpauth://` URI, as KeePassXC stores them.
+///
+/// KeeOTP strings don't include a name, so the account's name is left empty. Returns
+/// [OtpError::InvalidBackup] if the value is in neither format, and [OtpError::UriParse]
+/// if it's a malformed URI.
+///
+/// ```rust
+/// use otpee::backup::{keepass, ImportedType};
+///
+/// let account = keepass::parse("key=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&size=8").unwrap();
+/// assert_eq!(account.secret.as_bytes(), b"12345678901234567890");
+/// assert_eq!(account.digits, 8);
+/// assert_eq!(account.otp_type, ImportedType::Totp { period: 30 });
+/// ```
+pub fn parse(value: &str) -> Result<ImportedAccount, OtpError> {
+	let value = value.trim();
+	if value
+		.get(..10)
+		.is_some_and(|scheme| scheme.eq_ignore_ascii_case("otpauth://"))
+	{
+		parse_uri(value)
+	} else if value.split('&').any(|pair| pair.starts_with("key=")) {
+		parse_keeotp(value)
+	} else {
+		Err(BackupError::new("the value is neither a KeeOTP string nor an otpauth URI").into())
+	}
+}
+
+/// Parses a KeePassXC `otp` attribute, which marks Steam Guard accounts with `encoder=steam`.
+fn parse_uri(value: &str) -> Result<ImportedAccount, OtpError> {
+	let parsed = uri::parse(value)?;
+	let steam = value
+		.split_once('?')
+		.is_some_and(|(_, query)| query.split('&').any(|pair| pair == "encoder=steam"));
+	let period = parsed.period.unwrap_or(DEFAULT_STEP);
+	let otp_type = match parsed.kind.as_str() {
+		"totp" if steam => ImportedType::Steam { period },
+		"totp" => ImportedType::Totp { period },
+		"hotp" => ImportedType::Hotp {
+			counter: parsed.counter.ok_or(UriError::MissingCounter)?,
+		},
+		_ => return Err(UriError::WrongType.into()),
+	};
+	Ok(ImportedAccount {
+		secret: parsed.secret,
+		name: parsed.label,
+		issuer: parsed.issuer,
+		algorithm: parsed.algorithm,
+		// KeePassXC stores 5 digits for Steam Guard, but set it explicitly in case it doesn't.
+		digits: if steam { 5 } else { parsed.digits },
+		otp_type,
+	})
+}
+
+/// Parses a KeeOTP string, in which the key is Base32 and the other parameters are optional.
+fn parse_keeotp(value: &str) -> Result<ImportedAccount, OtpError> {
+	let invalid = |key: &str| BackupError::new(format!("invalid `{}` in the KeeOTP string", key));
+	let mut secret = None;
+	let mut step = DEFAULT_STEP;
+	let mut size = DEFAULT_SIZE;
+	let mut algorithm = Algorithm::Sha1;
+	let mut hotp = false;
+	let mut counter = 0;
+	for pair in value.split('&').filter(|pair| !pair.is_empty()) {
+		let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+		let value = uri::percent_decode(value, true)?;
+		match key {
+			"key" => secret = Some(Secret::from_base32(&value).map_err(|_| invalid(key))?),
+			"step" => {
+				step = value
+					.parse()
+					.ok()
+					.filter(|&step| step > 0)
+					.ok_or_else(|| invalid(key))?
+			}
+			"size" => {
+				size = value
+					.parse()
+					.ok()
+					.filter(|size| (1..=MAX_DIGITS).contains(size))
+					.ok_or_else(|| invalid(key))?
+			}
+			"otpHashMode" => {
+				algorithm = Algorithm::from_name(&value).ok_or_else(|| invalid(key))?
+			}
+			"type" if value.eq_ignore_ascii_case("totp") => hotp = false,
+			"type" if value.eq_ignore_ascii_case("hotp") => hotp = true,
+			"type" => return Err(invalid(key).into()),
+			"counter" => counter = value.parse().map_err(|_| invalid(key))?,
+			// Unknown parameters are ignored, as newer plugins may add their own.
+			_ => {}
+		}
+	}
+	Ok(ImportedAccount {
+		secret: secret
+			.filter(|secret| !secret.is_empty())
+			.ok_or_else(|| invalid("key"))?,
+		name: String::new(),
+		issuer: None,
+		algorithm,
+		digits: size,
+		otp_type: if hotp {
+			ImportedType::Hotp { counter }
+		} else {
+			ImportedType::Totp { period: step }
+		},
+	})
+}
+
+/// Formats the account as a KeeOTP string, leaving out the parameters that have their default
+/// values. The name and issuer aren't part of the format, so they're lost.
+///
+/// Returns [OtpError::InvalidBackup] for Steam Guard accounts, which KeeOTP doesn't support.
+///
+/// ```rust
+/// use otpee::backup::{keepass, ImportedAccount, ImportedType};
+/// use otpee::{Algorithm, Secret};
+///
+/// let account = ImportedAccount {
+///     secret: Secret::from(b"12345678901234567890"),
+///     name: "alice".into(),
+///     issuer: None,
+///     algorithm: Algorithm::Sha1,
+///     digits: 8,
+///     otp_type: ImportedType::Totp { period: 30 },
+/// };
+/// assert_eq!(
+///     keepass::to_keeotp_string(&account).unwrap(),
+///     "key=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&size=8",
+/// );
+/// ```
+pub fn to_keeotp_string(account: &ImportedAccount) -> Result<String, OtpError> {
+	let mut out = String::from("key=");
+	out.push_str(&base32::encode(account.secret.as_bytes()));
+	// Writing to a string can't fail.
+	match account.otp_type {
+		ImportedType::Totp { period } if period != DEFAULT_STEP => {
+			let _ = write!(out, "&step={}", period);
+		}
+		ImportedType::Totp { .. } => {}
+		ImportedType::Hotp { counter } => {
+			let _ = write!(out, "&type=Hotp&counter={}", counter);
+		}
+		ImportedType::Steam { .. } => {
+			return Err(BackupError::new("KeeOTP doesn't support Steam Guard accounts").into())
+		}
+	}
+	if account.digits != DEFAULT_SIZE {
+		let _ = write!(out, "&size={}", account.digits);
+	}
+	match account.algorithm {
+		Algorithm::Sha1 => {}
+		Algorithm::Sha256 => out.push_str("&otpHashMode=Sha256"),
+		Algorithm::Sha512 => out.push_str("&otpHashMode=Sha512"),
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse, to_keeotp_string};
+	use crate::{
+		algorithm::Algorithm,
+		backup::{ImportedAccount, ImportedType},
+		error::{OtpError, UriError},
+		secret::Secret,
+	};
+
+	fn account(algorithm: Algorithm, digits: usize, otp_type: ImportedType) -> ImportedAccount {
+		ImportedAccount {
+			secret: Secret::from(b"12345678901234567890"),
+			name: String::new(),
+			issuer: None,
+			algorithm,
+			digits,
+			otp_type,
+		}
+	}
+
+	#[test]
+	fn keepass_keeotp() {
+		let defaults = account(Algorithm::Sha1, 6, ImportedType::Totp { period: 30 });
+		let string = "key=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+		assert_eq!(parse(string).unwrap(), defaults);
+		assert_eq!(to_keeotp_string(&defaults).unwrap(), string);
+		assert_eq!(
+			parse(&(string.to_owned() + "&step=30&size=6")).unwrap(),
+			defaults
+		);
+
+		let custom = account(Algorithm::Sha256, 8, ImportedType::Totp { period: 60 });
+		let string = "key=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&step=60&size=8&otpHashMode=Sha256";
+		assert_eq!(parse(string).unwrap(), custom);
+		assert_eq!(to_keeotp_string(&custom).unwrap(), string);
+
+		let hotp = account(Algorithm::Sha1, 6, ImportedType::Hotp { counter: 5 });
+		let string = "key=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&type=Hotp&counter=5";
+		assert_eq!(parse(string).unwrap(), hotp);
+		assert_eq!(to_keeotp_string(&hotp).unwrap(), string);
+
+		let steam = account(Algorithm::Sha1, 5, ImportedType::Steam { period: 30 });
+		assert!(matches!(
+			to_keeotp_string(&steam),
+			Err(OtpError::InvalidBackup(_))
+		));
+	}
+
+	#[test]
+	fn keepass_uri() {
+		let account = parse(
+			"otpauth://totp/Example:alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+			 &period=60&digits=8&algorithm=SHA512&issuer=Example",
+		)
+		.unwrap();
+		assert_eq!(
+			account,
+			ImportedAccount {
+				name: "alice@example.com".into(),
+				issuer: Some("Example".into()),
+				..self::account(Algorithm::Sha512, 8, ImportedType::Totp { period: 60 })
+			}
+		);
+
+		let steam = parse(
+			"otpauth://totp/Steam:gamer?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&period=30\
+			 &digits=5&issuer=Steam&encoder=steam",
+		)
+		.unwrap();
+		assert_eq!(steam.otp_type, ImportedType::Steam { period: 30 });
+		assert_eq!(
+			parse("otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"),
+			Err(OtpError::UriParse(UriError::MissingCounter))
+		);
+	}
+
+	#[test]
+	fn keepass_invalid() {
+		let message = |value| match parse(value) {
+			Err(OtpError::InvalidBackup(err)) => err.message().to_owned(),
+			other => panic!("unexpected result: {:?}", other),
+		};
+		assert_eq!(
+			message("hunter2"),
+			"the value is neither a KeeOTP string nor an otpauth URI"
+		);
+		assert_eq!(message(""), message("https://example.com/?key=value"));
+		assert_eq!(
+			message("key=GEZDGNBV&size=0"),
+			"invalid `size` in the KeeOTP string"
+		);
+		assert_eq!(message("key=1"), "invalid `key` in the KeeOTP string");
+		assert_eq!(
+			message("key=&step=30"),
+			"invalid `key` in the KeeOTP string"
+		);
+	}
+}
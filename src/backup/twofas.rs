@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing unencrypted `.2fas` backups exported by 2FAS.
+use super::{Backup, ImportedAccount, ImportedType, SkippedEntry};
+use crate::{
+	algorithm::Algorithm,
+	error::{BackupError, OtpError},
+	hotp::MAX_DIGITS,
+	secret::Secret,
+};
+use alloc::{format, string::String, vec::Vec};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The interval 2FAS uses for entries that don't specify one.
+const DEFAULT_PERIOD: u64 = 30;
+
+#[derive(Deserialize)]
+struct Document {
+	#[serde(default)]
+	services: Vec<Value>,
+	#[serde(default, rename = "servicesEncrypted")]
+	services_encrypted: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Service {
+	#[serde(default)]
+	name: String,
+	secret: String,
+	otp: Otp,
+}
+
+/// The settings of a service. Version 3 of the schema names the account `label` and the
+/// type `type`, while version 4 renames them to `account` and `tokenType`.
+#[derive(Deserialize)]
+struct Otp {
+	#[serde(default)]
+	account: Option<String>,
+	#[serde(default)]
+	label: Option<String>,
+	#[serde(default)]
+	issuer: Option<String>,
+	#[serde(default, rename = "tokenType")]
+	token_type: Option<String>,
+	#[serde(default, rename = "type")]
+	kind: Option<String>,
+	#[serde(default)]
+	algorithm: Option<String>,
+	digits: usize,
+	#[serde(default)]
+	period: Option<u64>,
+	#[serde(default)]
+	counter: Option<u64>,
+}
+
+/// Parses an unencrypted backup exported by 2FAS, returning the TOTP, HOTP and Steam accounts
+/// in it. Both version 3 and version 4 of the schema are supported.
+///
+/// Entries of other types, and entries that don't match the expected format, are skipped,
+/// and listed in [Backup::skipped]. Returns [OtpError::InvalidBackup] if the backup itself
+/// doesn't match the expected format, or is encrypted.
+///
+/// ```rust
+/// use otpee::backup::{twofas, ImportedType};
+///
+/// let backup = twofas::parse(
+///     r#"{"schemaVersion": 4, "services": [{"name": "Example", "secret": "MZXW6YQ",
+///         "otp": {"account": "alice", "digits": 6, "period": 30, "algorithm": "SHA1",
+///         "tokenType": "TOTP"}}]}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(backup.accounts[0].name, "alice");
+/// assert_eq!(backup.accounts[0].otp_type, ImportedType::Totp { period: 30 });
+/// ```
+pub fn parse(json: &str) -> Result<Backup, OtpError> {
+	let document: Document = serde_json::from_str(json)
+		.map_err(|err| BackupError::new(format!("invalid backup: {}", err)))?;
+	if document.services_encrypted.is_some() {
+		return Err(BackupError::new("encrypted backups aren't supported").into());
+	}
+
+	let mut backup = Backup::default();
+	for (index, service) in document.services.into_iter().enumerate() {
+		let name = service
+			.get("name")
+			.and_then(Value::as_str)
+			.map(String::from)
+			.unwrap_or_default();
+		let result = Service::deserialize(&service)
+			.map_err(|err| format!("invalid entry: {}", err))
+			.and_then(|service| parse_service(&service));
+		match result {
+			Ok(account) => backup.accounts.push(account),
+			Err(reason) => backup.skipped.push(SkippedEntry {
+				index,
+				name,
+				reason,
+			}),
+		}
+	}
+	Ok(backup)
+}
+
+/// Converts a service into an account, or returns why it can't be.
+fn parse_service(service: &Service) -> Result<ImportedAccount, String> {
+	let otp = &service.otp;
+	let period = || match otp.period {
+		Some(0) => Err(String::from("invalid period (0)")),
+		Some(period) => Ok(period),
+		None => Ok(DEFAULT_PERIOD),
+	};
+	let kind = otp
+		.token_type
+		.as_deref()
+		.or(otp.kind.as_deref())
+		.unwrap_or("TOTP");
+	let otp_type = match kind {
+		"TOTP" => ImportedType::Totp { period: period()? },
+		"HOTP" => ImportedType::Hotp {
+			counter: otp.counter.ok_or_else(|| String::from("missing counter"))?,
+		},
+		"STEAM" => ImportedType::Steam { period: period()? },
+		_ => return Err(format!("unsupported entry type `{}`", kind)),
+	};
+	let secret =
+		Secret::from_base32(&service.secret).map_err(|_| String::from("invalid secret"))?;
+	let algorithm = match otp.algorithm.as_deref() {
+		Some(name) => {
+			Algorithm::from_name(name).ok_or_else(|| format!("unsupported algorithm `{}`", name))?
+		}
+		None => Algorithm::Sha1,
+	};
+	if !(1..=MAX_DIGITS).contains(&otp.digits) {
+		return Err(format!("unsupported number of digits ({})", otp.digits));
+	}
+	let non_empty = |value: Option<&String>| value.filter(|value| !value.is_empty()).cloned();
+	Ok(ImportedAccount {
+		secret,
+		// The service name is what 2FAS shows, so it's used when there's no account name.
+		name: non_empty(otp.account.as_ref())
+			.or_else(|| non_empty(otp.label.as_ref()))
+			.unwrap_or_else(|| service.name.clone()),
+		issuer: non_empty(otp.issuer.as_ref()).or_else(|| non_empty(Some(&service.name))),
+		algorithm,
+		digits: otp.digits,
+		otp_type,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse;
+	use crate::{
+		algorithm::Algorithm,
+		backup::{ImportedAccount, ImportedType, SkippedEntry},
+		error::OtpError,
+		secret::Secret,
+	};
+	use alloc::{vec, vec::Vec};
+
+	fn expected_accounts() -> Vec<ImportedAccount> {
+		vec![
+			ImportedAccount {
+				secret: Secret::from(b"12345678901234567890"),
+				name: "alice@example.com".into(),
+				issuer: Some("Example".into()),
+				algorithm: Algorithm::Sha1,
+				digits: 6,
+				otp_type: ImportedType::Totp { period: 30 },
+			},
+			ImportedAccount {
+				secret: Secret::from(b"foobar"),
+				name: "bob".into(),
+				issuer: Some("Corp".into()),
+				algorithm: Algorithm::Sha256,
+				digits: 8,
+				otp_type: ImportedType::Hotp { counter: 7 },
+			},
+			ImportedAccount {
+				secret: Secret::from(b"foob"),
+				name: "gamer".into(),
+				issuer: Some("Steam".into()),
+				algorithm: Algorithm::Sha1,
+				digits: 5,
+				otp_type: ImportedType::Steam { period: 30 },
+			},
+		]
+	}
+
+	#[test]
+	fn twofas_parse_v4() {
+		let backup = parse(include_str!("fixtures/twofas-v4.json")).unwrap();
+		assert_eq!(backup.accounts, expected_accounts());
+		assert_eq!(
+			backup.skipped,
+			[SkippedEntry {
+				index: 3,
+				name: "Broken".into(),
+				reason: "invalid entry: missing field `secret`".into(),
+			}]
+		);
+	}
+
+	#[test]
+	fn twofas_parse_v3() {
+		let backup = parse(include_str!("fixtures/twofas-v3.json")).unwrap();
+		assert_eq!(backup.accounts, expected_accounts());
+		assert_eq!(
+			backup.skipped,
+			[SkippedEntry {
+				index: 3,
+				name: "Legacy".into(),
+				reason: "unsupported entry type `MOTP`".into(),
+			}]
+		);
+	}
+
+	#[test]
+	fn twofas_encrypted() {
+		let json = r#"{"services": [], "groups": [], "schemaVersion": 4,
+			"servicesEncrypted": "c2VjcmV0:c2FsdA==:aXY=", "reference": "cmVm"}"#;
+		match parse(json) {
+			Err(OtpError::InvalidBackup(err)) => {
+				assert_eq!(err.message(), "encrypted backups aren't supported")
+			}
+			other => panic!("unexpected result: {:?}", other),
+		}
+	}
+}
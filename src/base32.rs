@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::error::OtpError;
+use alloc::{string::String, vec::Vec};
+
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Decodes RFC 4648 Base32, with or without padding, case-insensitively.
+/// Spaces and dashes are ignored, as provisioning UIs often insert them for readability.
+pub(crate) fn decode(input: &str) -> Result<Vec<u8>, OtpError> {
+	let mut out = Vec::with_capacity(input.len() * 5 / 8);
+	let mut buffer = 0u16;
+	let mut bits = 0u32;
+	let mut padding = false;
+	for c in input.bytes() {
+		let value = match c {
+			b' ' | b'-' => continue,
+			b'=' => {
+				padding = true;
+				continue;
+			}
+			_ if padding => return Err(OtpError::InvalidBase32),
+			b'A'..=b'Z' => c - b'A',
+			b'a'..=b'z' => c - b'a',
+			b'2'..=b'7' => c - b'2' + 26,
+			_ => return Err(OtpError::InvalidBase32),
+		};
+		buffer = (buffer << 5) | u16::from(value);
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((buffer >> bits) as u8);
+			buffer &= (1 << bits) - 1;
+		}
+	}
+	// Any leftover bits must be less than one character's worth,
+	// otherwise the input was truncated in the middle of a byte.
+	if bits >= 5 {
+		return Err(OtpError::InvalidBase32);
+	}
+	Ok(out)
+}
+
+/// Encodes RFC 4648 Base32, in uppercase and without padding, as expected by provisioning URIs.
+pub(crate) fn encode(input: &[u8]) -> String {
+	let mut out = String::with_capacity((input.len() * 8).div_ceil(5));
+	let mut buffer = 0u16;
+	let mut bits = 0u32;
+	for &byte in input {
+		buffer = (buffer << 8) | u16::from(byte);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			out.push(ALPHABET[usize::from((buffer >> bits) & 0x1F)] as char);
+		}
+		buffer &= (1 << bits) - 1;
+	}
+	if bits > 0 {
+		out.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1F)] as char);
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode, encode};
+	use crate::error::OtpError;
+
+	#[test]
+	fn base32_decode() {
+		assert_eq!(
+			decode("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap(),
+			b"12345678901234567890"
+		);
+		assert_eq!(
+			decode("gezd gnbv-gy3t qojq gezd gnbv gy3t qojq").unwrap(),
+			b"12345678901234567890"
+		);
+		assert_eq!(decode("MZXW6YQ=").unwrap(), b"foob");
+		assert_eq!(decode("MZXW6YQ").unwrap(), b"foob");
+		assert_eq!(decode("MZXW6===").unwrap(), b"foo");
+		assert_eq!(decode("").unwrap(), b"");
+		assert_eq!(decode("MZXW6YQ1"), Err(OtpError::InvalidBase32));
+		assert_eq!(decode("MZ=XW6YQ"), Err(OtpError::InvalidBase32));
+		assert_eq!(decode("MZXW6Y"), Err(OtpError::InvalidBase32));
+	}
+
+	#[test]
+	fn base32_encode() {
+		assert_eq!(
+			encode(b"12345678901234567890"),
+			"GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+		);
+		assert_eq!(encode(b""), "");
+		assert_eq!(encode(b"f"), "MY");
+		assert_eq!(encode(b"foob"), "MZXW6YQ");
+		assert_eq!(encode(b"fooba"), "MZXW6YTB");
+		for len in 0..=32 {
+			let bytes = (0..len)
+				.map(|i: u8| i.wrapping_mul(37))
+				.collect::<Vec<u8>>();
+			assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+		}
+	}
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use alloc::{string::String, vec::Vec};
+
+const URL_SAFE_ALPHABET: &[u8; 64] =
+	b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Decodes RFC 4648 Base64, with or without padding, accepting both the standard
+/// and the URL-safe alphabet. Returns [None] if the input isn't valid Base64.
+pub(crate) fn decode(input: &str) -> Option<Vec<u8>> {
+	let input = input.trim_end_matches('=');
+	let mut out = Vec::with_capacity(input.len() * 3 / 4);
+	let mut buffer = 0u32;
+	let mut bits = 0u32;
+	for c in input.bytes() {
+		let value = match c {
+			b'A'..=b'Z' => c - b'A',
+			b'a'..=b'z' => c - b'a' + 26,
+			b'0'..=b'9' => c - b'0' + 52,
+			b'+' | b'-' => 62,
+			b'/' | b'_' => 63,
+			_ => return None,
+		};
+		buffer = (buffer << 6) | u32::from(value);
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((buffer >> bits) as u8);
+			buffer &= (1 << bits) - 1;
+		}
+	}
+	// A single leftover character can't hold a whole byte.
+	if bits >= 6 {
+		return None;
+	}
+	Some(out)
+}
+
+/// Encodes unpadded Base64 with the URL-safe alphabet, so it can be used in URIs as it is.
+pub(crate) fn encode_url_safe(input: &[u8]) -> String {
+	let mut out = String::with_capacity((input.len() * 4).div_ceil(3));
+	let mut buffer = 0u32;
+	let mut bits = 0u32;
+	for &byte in input {
+		buffer = (buffer << 8) | u32::from(byte);
+		bits += 8;
+		while bits >= 6 {
+			bits -= 6;
+			out.push(URL_SAFE_ALPHABET[((buffer >> bits) & 0x3F) as usize] as char);
+		}
+		buffer &= (1 << bits) - 1;
+	}
+	if bits > 0 {
+		out.push(URL_SAFE_ALPHABET[((buffer << (6 - bits)) & 0x3F) as usize] as char);
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode, encode_url_safe};
+	use alloc::vec::Vec;
+
+	#[test]
+	fn base64_decode() {
+		assert_eq!(decode("").unwrap(), b"");
+		assert_eq!(decode("Zm9vYmFy").unwrap(), b"foobar");
+		assert_eq!(decode("Zm9vYg==").unwrap(), b"foob");
+		assert_eq!(decode("Zm9vYg").unwrap(), b"foob");
+		assert_eq!(decode("-_8=").unwrap(), decode("+/8").unwrap());
+		assert_eq!(decode("Zm9vY"), None);
+		assert_eq!(decode("Zm9v!"), None);
+	}
+
+	#[test]
+	fn base64_encode() {
+		assert_eq!(encode_url_safe(b""), "");
+		assert_eq!(encode_url_safe(b"foobar"), "Zm9vYmFy");
+		assert_eq!(encode_url_safe(b"foob"), "Zm9vYg");
+		assert_eq!(encode_url_safe(&[0xfb, 0xff]), "-_8");
+		for len in 0..32 {
+			let bytes = (0..len).map(|i: u32| (i * 37) as u8).collect::<Vec<_>>();
+			assert_eq!(decode(&encode_url_safe(&bytes)).unwrap(), bytes);
+		}
+	}
+}
@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{algorithm::Algorithm, error::OtpError, hotp::Hotp, otp::Otp};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+#[cfg(feature = "alloc")]
+use {
+	crate::{
+		time::TimeSource,
+		totp::{BoxedTotp, Totp},
+	},
+	alloc::boxed::Box,
+};
+
+/// Calls the given expression on whichever generic instance is inside.
+macro_rules! dispatch {
+	($inner:expr, $otp:ident => $body:expr) => {
+		match $inner {
+			Inner::Sha1($otp) => $body,
+			Inner::Sha256($otp) => $body,
+			Inner::Sha512($otp) => $body,
+		}
+	};
+}
+
+/// A HOTP generator whose hash algorithm is chosen at runtime.
+///
+/// This dispatches to a [Hotp] for the chosen [Algorithm], and exposes the same API.
+///
+/// ```rust
+/// use otpee::{Algorithm, DynHotp};
+///
+/// let mut hotp = DynHotp::new(Algorithm::Sha1, b"12345678901234567890", 6).unwrap();
+/// assert_eq!(hotp.code().unwrap(), 755224);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynHotp(Inner<Hotp<Sha1>, Hotp<Sha256>, Hotp<Sha512>>);
+
+/// A TOTP generator whose hash algorithm is chosen at runtime.
+///
+/// This dispatches to a [Totp] for the chosen [Algorithm], and exposes the same API.
+///
+/// ```rust
+/// use otpee::{Algorithm, DynTotp};
+///
+/// let mut totp = DynTotp::new(Algorithm::Sha1, b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// assert_eq!(totp.code().unwrap(), 94287082);
+/// ```
+#[cfg(feature = "alloc")]
+pub struct DynTotp(Inner<BoxedTotp<Sha1>, BoxedTotp<Sha256>, BoxedTotp<Sha512>>);
+
+#[derive(Debug, Clone)]
+enum Inner<A, B, C> {
+	Sha1(A),
+	Sha256(B),
+	Sha512(C),
+}
+
+impl DynHotp {
+	/// Creates a new HOTP instance using the given algorithm.
+	/// See [Hotp::new] for the other parameters.
+	pub fn new<A: AsRef<[u8]>, L: Into<Option<usize>>>(
+		algorithm: Algorithm,
+		key: A,
+		length: L,
+	) -> Result<Self, OtpError> {
+		Ok(Self(match algorithm {
+			Algorithm::Sha1 => Inner::Sha1(Hotp::new(key, length)?),
+			Algorithm::Sha256 => Inner::Sha256(Hotp::new(key, length)?),
+			Algorithm::Sha512 => Inner::Sha512(Hotp::new(key, length)?),
+		}))
+	}
+
+	/// Returns the algorithm used by this instance.
+	pub fn algorithm(&self) -> Algorithm {
+		match self.0 {
+			Inner::Sha1(_) => Algorithm::Sha1,
+			Inner::Sha256(_) => Algorithm::Sha256,
+			Inner::Sha512(_) => Algorithm::Sha512,
+		}
+	}
+
+	/// See [Hotp::counter].
+	pub fn counter(&self) -> u64 {
+		dispatch!(&self.0, hotp => hotp.counter())
+	}
+
+	/// See [Hotp::set_counter].
+	pub fn set_counter(&mut self, counter: u64) -> Result<(), OtpError> {
+		dispatch!(&mut self.0, hotp => hotp.set_counter(counter))
+	}
+
+	/// See [Hotp::increment_counter].
+	pub fn increment_counter(&mut self) -> Result<u64, OtpError> {
+		dispatch!(&mut self.0, hotp => hotp.increment_counter())
+	}
+
+	/// See [Hotp::code].
+	pub fn code(&mut self) -> Result<Otp, OtpError> {
+		dispatch!(&mut self.0, hotp => hotp.code())
+	}
+
+	/// See [Hotp::code_at].
+	pub fn code_at(&mut self, counter: u64) -> Result<Otp, OtpError> {
+		dispatch!(&mut self.0, hotp => hotp.code_at(counter))
+	}
+
+	/// See [Hotp::code_increment].
+	pub fn code_increment(&mut self) -> Result<Otp, OtpError> {
+		dispatch!(&mut self.0, hotp => hotp.code_increment())
+	}
+
+	/// See [Hotp::validate].
+	pub fn validate(&mut self, code: u64, window: usize) -> Option<u64> {
+		dispatch!(&mut self.0, hotp => hotp.validate(code, window))
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl DynTotp {
+	/// Creates a new TOTP instance using the given algorithm.
+	/// See [Totp::new] for the other parameters.
+	pub fn new<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+		C: TimeSource + 'static,
+	>(
+		algorithm: Algorithm,
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		let time_source: Box<dyn TimeSource> = Box::new(time_source);
+		Ok(Self(match algorithm {
+			Algorithm::Sha1 => Inner::Sha1(Totp::new(key, length, interval, skew, time_source)?),
+			Algorithm::Sha256 => {
+				Inner::Sha256(Totp::new(key, length, interval, skew, time_source)?)
+			}
+			Algorithm::Sha512 => {
+				Inner::Sha512(Totp::new(key, length, interval, skew, time_source)?)
+			}
+		}))
+	}
+
+	/// Returns the algorithm used by this instance.
+	pub fn algorithm(&self) -> Algorithm {
+		match self.0 {
+			Inner::Sha1(_) => Algorithm::Sha1,
+			Inner::Sha256(_) => Algorithm::Sha256,
+			Inner::Sha512(_) => Algorithm::Sha512,
+		}
+	}
+
+	/// See [Totp::counter].
+	pub fn counter(&mut self) -> Result<u64, OtpError> {
+		dispatch!(&mut self.0, totp => totp.counter())
+	}
+
+	/// See [Totp::skew].
+	pub fn skew(&self) -> usize {
+		dispatch!(&self.0, totp => totp.skew())
+	}
+
+	/// See [Totp::set_skew].
+	pub fn set_skew(&mut self, skew: usize) {
+		dispatch!(&mut self.0, totp => totp.set_skew(skew))
+	}
+
+	/// See [Totp::code].
+	pub fn code(&mut self) -> Result<Otp, OtpError> {
+		dispatch!(&mut self.0, totp => totp.code())
+	}
+
+	/// See [Totp::code_at_time].
+	pub fn code_at_time(&mut self, time: u64) -> Result<Otp, OtpError> {
+		dispatch!(&mut self.0, totp => totp.code_at_time(time))
+	}
+
+	/// See [Totp::validate_code].
+	pub fn validate_code(&mut self, code: u64) -> bool {
+		dispatch!(&mut self.0, totp => totp.validate_code(code))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DynHotp;
+	use crate::{algorithm::Algorithm, hotp::Hotp, vectors::HOTP_SHA1};
+	use sha1::Sha1;
+	#[cfg(feature = "alloc")]
+	use {
+		super::DynTotp,
+		crate::{totp::Totp, vectors::totp_vectors},
+		sha2::{Sha256, Sha512},
+	};
+
+	#[test]
+	fn dyn_hotp_rfc4226() {
+		let mut dynamic = DynHotp::new(Algorithm::Sha1, b"12345678901234567890", 6).unwrap();
+		let mut generic = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		assert_eq!(dynamic.algorithm(), Algorithm::Sha1);
+		for vector in HOTP_SHA1 {
+			assert_eq!(dynamic.code().unwrap(), generic.code().unwrap());
+			assert_eq!(*dynamic.code_increment().unwrap(), vector.code);
+			generic.increment_counter().unwrap();
+			assert_eq!(dynamic.counter(), generic.counter());
+		}
+		dynamic.set_counter(2).unwrap();
+		assert_eq!(dynamic.validate(254676, 3), Some(5));
+		assert_eq!(dynamic.counter(), 6);
+	}
+
+	#[cfg(feature = "alloc")]
+	#[test]
+	fn dyn_totp_rfc6238() {
+		for algorithm in [Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512] {
+			for vector in totp_vectors(algorithm) {
+				let (key, time, expected) = (vector.secret, vector.moving_factor, vector.code);
+				let mut dynamic = DynTotp::new(algorithm, key, 8, 30, 1, move || time).unwrap();
+				let generic = match algorithm {
+					Algorithm::Sha1 => Totp::<Sha1, _>::new(key, 8, 30, 1, move || time)
+						.unwrap()
+						.code_at_time(time),
+					Algorithm::Sha256 => Totp::<Sha256, _>::new(key, 8, 30, 1, move || time)
+						.unwrap()
+						.code_at_time(time),
+					_ => Totp::<Sha512, _>::new(key, 8, 30, 1, move || time)
+						.unwrap()
+						.code_at_time(time),
+				};
+				assert_eq!(dynamic.algorithm(), algorithm);
+				assert_eq!(*dynamic.code_at_time(time).unwrap(), expected);
+				assert_eq!(dynamic.code().unwrap(), generic.unwrap());
+				assert_eq!(dynamic.counter(), Ok(time / 30));
+				assert_eq!(dynamic.skew(), 1);
+				assert!(dynamic.validate_code(expected));
+			}
+		}
+	}
+}
@@ -0,0 +1,286 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Enrolling a user in TOTP, where the token only becomes usable once the user has proven
+//! that their authenticator app was set up correctly, by entering a code from it.
+use crate::{
+	algorithm::HashAlgorithm, error::OtpError, secret::Secret, time::TimeSource, totp::Totp,
+};
+use alloc::string::String;
+use core::fmt::{self, Debug, Display, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// The number of wrong codes that lock an enrollment, unless changed with
+/// [Enrollment::with_max_attempts].
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// An error from [Enrollment::confirm].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnrollError {
+	/// The code was wrong.
+	InvalidCode { remaining_attempts: u32 },
+	/// Too many wrong codes were entered, so the enrollment can't be confirmed anymore,
+	/// and has to be started over with a new secret.
+	Locked,
+	/// The enrollment was already confirmed.
+	AlreadyActive,
+}
+
+impl Display for EnrollError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			EnrollError::InvalidCode { remaining_attempts } => write!(
+				f,
+				"the code is invalid, {} attempts remaining",
+				remaining_attempts
+			),
+			EnrollError::Locked => f.write_str("too many failed attempts, enrollment is locked"),
+			EnrollError::AlreadyActive => f.write_str("the enrollment was already confirmed"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl Error for EnrollError {}
+
+/// The state of an [Enrollment].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EnrollmentState {
+	/// The secret was handed out, but no code has been confirmed yet.
+	Pending,
+	/// A code was confirmed, so the token can be used.
+	Active,
+	/// Too many wrong codes were entered.
+	Locked,
+}
+
+/// A TOTP token that is being enrolled, which can only be turned into a usable [Totp]
+/// instance once a code from it has been [confirmed](Enrollment::confirm).
+///
+/// Tokens use 6 digits and an interval of 30 seconds, as expected by most authenticator apps.
+///
+/// ```rust
+/// # #[cfg(feature = "sha1")]
+/// # {
+/// use otpee::{enroll::{EnrollError, Enrollment}, Secret};
+/// use sha1::Sha1;
+///
+/// let secret = Secret::from(b"12345678901234567890");
+/// let mut enrollment =
+///     Enrollment::<Sha1>::from_secret(secret, "alice@example.com", Some("Example"), || 59)
+///         .unwrap();
+/// assert!(enrollment.provisioning_uri().starts_with("otpauth://totp/Example:alice@example.com?"));
+/// assert_eq!(enrollment.confirm(123456), Err(EnrollError::InvalidCode { remaining_attempts: 4 }));
+/// assert_eq!(enrollment.confirm(287082), Ok(()));
+/// let totp = enrollment.into_totp().unwrap();
+/// # }
+/// ```
+pub struct Enrollment<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	totp: Totp<D, C>,
+	secret: Secret,
+	label: String,
+	issuer: Option<String>,
+	state: EnrollmentState,
+	max_attempts: u32,
+	failures: u32,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Enrollment<D, C> {
+	/// Starts enrolling a token with the given secret, for the given account.
+	pub fn from_secret<S: Into<Secret>, L: Into<String>, I: Into<String>>(
+		secret: S,
+		label: L,
+		issuer: Option<I>,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		let secret = secret.into();
+		let totp = Totp::new(secret.as_bytes(), 6, 30, 1, time_source)?;
+		Ok(Self {
+			totp,
+			secret,
+			label: label.into(),
+			issuer: issuer.map(Into::into),
+			state: EnrollmentState::Pending,
+			max_attempts: DEFAULT_MAX_ATTEMPTS,
+			failures: 0,
+		})
+	}
+
+	#[cfg(feature = "getrandom")]
+	/// Starts enrolling a token with a random secret of the recommended length for the
+	/// hash function, see [Secret::generate_for].
+	pub fn generate<L: Into<String>, I: Into<String>>(
+		label: L,
+		issuer: Option<I>,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		Self::from_secret(Secret::generate_for::<D>()?, label, issuer, time_source)
+	}
+
+	/// Sets the number of wrong codes that lock the enrollment, which is 5 by default.
+	pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+		self.max_attempts = max_attempts;
+		self
+	}
+
+	/// Returns the state of the enrollment.
+	#[inline]
+	pub fn state(&self) -> EnrollmentState {
+		self.state
+	}
+
+	/// Returns the secret of the token, such as for showing it to the user
+	/// when they can't scan a QR code.
+	#[inline]
+	pub fn secret(&self) -> &Secret {
+		&self.secret
+	}
+
+	/// Returns the TOTP instance if the enrollment was confirmed,
+	/// otherwise gives the enrollment back.
+	#[allow(clippy::result_large_err)]
+	pub fn into_totp(self) -> Result<Totp<D, C>, Self> {
+		match self.state {
+			EnrollmentState::Active => Ok(self.totp),
+			_ => Err(self),
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, C> Enrollment<D, C> {
+	/// Generates the `otpauth://totp` provisioning URI to show to the user, see [Totp::to_uri].
+	pub fn provisioning_uri(&self) -> String {
+		self.totp
+			.to_uri(self.secret.as_bytes(), &self.label, self.issuer.as_deref())
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> Enrollment<D, C> {
+	/// Confirms the enrollment with a code from the user's authenticator app, accepting
+	/// the codes of the time steps right before and after the current one, to allow for
+	/// the time it takes to type it in.
+	///
+	/// Once the maximum number of wrong codes is reached, the enrollment is locked.
+	pub fn confirm(&mut self, code: u64) -> Result<(), EnrollError> {
+		match self.state {
+			EnrollmentState::Active => return Err(EnrollError::AlreadyActive),
+			EnrollmentState::Locked => return Err(EnrollError::Locked),
+			EnrollmentState::Pending => {}
+		}
+		if self.totp.validate_code(code) {
+			self.state = EnrollmentState::Active;
+			return Ok(());
+		}
+		self.failures = self.failures.saturating_add(1);
+		if self.failures >= self.max_attempts {
+			self.state = EnrollmentState::Locked;
+			return Err(EnrollError::Locked);
+		}
+		Err(EnrollError::InvalidCode {
+			remaining_attempts: self.max_attempts - self.failures,
+		})
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Debug for Enrollment<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Enrollment")
+			.field("label", &self.label)
+			.field("issuer", &self.issuer)
+			.field("state", &self.state)
+			.field("failures", &self.failures)
+			.field("max_attempts", &self.max_attempts)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{EnrollError, Enrollment, EnrollmentState};
+	use sha1::Sha1;
+	use std::{cell::Cell, rc::Rc};
+
+	#[test]
+	fn enrollment_confirm() {
+		let now = Rc::new(Cell::new(59));
+		let time = now.clone();
+		let mut enrollment = Enrollment::<Sha1, _>::from_secret(
+			b"12345678901234567890",
+			"alice@example.com",
+			None::<&str>,
+			move || time.get(),
+		)
+		.unwrap();
+		assert_eq!(enrollment.state(), EnrollmentState::Pending);
+		// The code for 59 is still accepted a step later.
+		now.set(89);
+		assert_eq!(enrollment.confirm(287082), Ok(()));
+		assert_eq!(enrollment.state(), EnrollmentState::Active);
+		assert_eq!(enrollment.confirm(287082), Err(EnrollError::AlreadyActive));
+		// The confirmed code can't be replayed once the token is in use.
+		let mut totp = enrollment.into_totp().unwrap();
+		assert!(!totp.validate_code(287082));
+		assert!(totp.validate_code(359152));
+	}
+
+	#[cfg(feature = "sha1")]
+	#[test]
+	fn enrollment_provisioning_uri() {
+		let enrollment = Enrollment::<Sha1>::from_secret(
+			b"12345678901234567890",
+			"alice@example.com",
+			None::<&str>,
+			|| 59,
+		)
+		.unwrap();
+		assert_eq!(
+			enrollment.provisioning_uri(),
+			"otpauth://totp/alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+			 &algorithm=SHA1&digits=6&period=30"
+		);
+	}
+
+	#[test]
+	fn enrollment_lockout() {
+		let mut enrollment = Enrollment::<Sha1>::from_secret(
+			b"12345678901234567890",
+			"alice@example.com",
+			Some("Example"),
+			|| 59,
+		)
+		.unwrap()
+		.with_max_attempts(3);
+		assert_eq!(
+			enrollment.confirm(1),
+			Err(EnrollError::InvalidCode {
+				remaining_attempts: 2
+			})
+		);
+		assert_eq!(
+			enrollment.confirm(2),
+			Err(EnrollError::InvalidCode {
+				remaining_attempts: 1
+			})
+		);
+		assert_eq!(enrollment.confirm(3), Err(EnrollError::Locked));
+		assert_eq!(enrollment.state(), EnrollmentState::Locked);
+		// Even the right code isn't accepted anymore.
+		assert_eq!(enrollment.confirm(287082), Err(EnrollError::Locked));
+		assert!(enrollment.into_totp().is_err());
+	}
+
+	#[test]
+	fn enrollment_pending_into_totp() {
+		let enrollment = Enrollment::<Sha1>::from_secret(
+			b"12345678901234567890",
+			"alice@example.com",
+			Some("Example"),
+			|| 59,
+		)
+		.unwrap();
+		let mut enrollment = enrollment.into_totp().unwrap_err();
+		assert_eq!(enrollment.state(), EnrollmentState::Pending);
+		assert_eq!(enrollment.confirm(287082), Ok(()));
+		assert!(enrollment.into_totp().is_ok());
+	}
+}
@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
-use alloc::fmt::{self, Display, Formatter};
+use crate::algorithm::Algorithm;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter};
 #[cfg(feature = "std")]
 use std::error::Error;
 
@@ -8,6 +11,210 @@ pub enum OtpError {
 	InvalidLength,
 	HashTooShort,
 	CounterOverflow,
+	PersistenceFailed,
+	InvalidDigits {
+		got: usize,
+	},
+	MissingSecret,
+	StateTooShort,
+	UnsupportedStateVersion {
+		got: u8,
+	},
+	MissingTimeSource,
+	InvalidBase32,
+	InvalidHex {
+		position: usize,
+	},
+	RandomFailed,
+	KeyTooShort {
+		got: usize,
+		minimum: usize,
+	},
+	MacFailed,
+	CounterBelowMinimum {
+		minimum: u64,
+	},
+	UriParse(UriError),
+	DecryptionFailed,
+	TimeBeforeEpoch,
+	TimeUnavailable(TimeError),
+	TimeOutOfRange,
+	InvalidInterval,
+	QrFailed,
+	Migration(MigrationError),
+	#[cfg(feature = "alloc")]
+	InvalidBackup(BackupError),
+	DuplicateAccount,
+	InvalidKeyring,
+	UnsupportedKeyringVersion {
+		got: u32,
+	},
+	#[cfg(feature = "std")]
+	Io(std::io::ErrorKind),
+	InvalidRecoveryFormat,
+	RateLimited {
+		retry_at: u64,
+	},
+	AlgorithmMismatch {
+		expected: Algorithm,
+		got: Algorithm,
+	},
+}
+
+/// The reason a provisioning URI couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UriError {
+	/// The URI doesn't start with `otpauth://`.
+	InvalidScheme,
+	/// The URI is for a different kind of token, such as a TOTP URI given where HOTP was expected.
+	WrongType,
+	/// The URI contains invalid percent-encoding, or isn't valid UTF-8 once decoded.
+	InvalidEncoding,
+	/// The `secret` parameter is missing or empty.
+	MissingSecret,
+	/// The `secret` parameter isn't valid Base32.
+	InvalidSecret,
+	/// The `algorithm` parameter isn't a supported algorithm.
+	InvalidAlgorithm,
+	/// The `algorithm` parameter doesn't match the expected hash function.
+	AlgorithmMismatch,
+	/// The `digits` parameter isn't a number between 1 and [MAX_DIGITS](crate::hotp::MAX_DIGITS).
+	InvalidDigits,
+	/// The `counter` parameter is missing from a HOTP URI.
+	MissingCounter,
+	/// The `counter` parameter isn't a valid number.
+	InvalidCounter,
+	/// The `period` parameter isn't a positive number.
+	InvalidPeriod,
+}
+
+impl Display for UriError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			UriError::InvalidScheme => "the URI doesn't use the otpauth scheme",
+			UriError::WrongType => "the URI is for a different type of token",
+			UriError::InvalidEncoding => "the URI contains invalid percent-encoding",
+			UriError::MissingSecret => "the URI is missing the secret",
+			UriError::InvalidSecret => "the secret in the URI isn't valid Base32",
+			UriError::InvalidAlgorithm => "the algorithm in the URI isn't supported",
+			UriError::AlgorithmMismatch => {
+				"the algorithm in the URI doesn't match the hash function"
+			}
+			UriError::InvalidDigits => "the number of digits in the URI is invalid",
+			UriError::MissingCounter => "the HOTP URI is missing the counter",
+			UriError::InvalidCounter => "the counter in the URI is invalid",
+			UriError::InvalidPeriod => "the period in the URI is invalid",
+		})
+	}
+}
+
+/// The reason a Google Authenticator migration URI, or an account in it, couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MigrationError {
+	/// The URI doesn't start with `otpauth-migration://offline`.
+	InvalidScheme,
+	/// The `data` parameter is missing, or isn't valid Base64.
+	InvalidEncoding,
+	/// The payload isn't a valid migration payload.
+	Malformed,
+	/// The account uses an algorithm that isn't supported, such as MD5.
+	UnsupportedAlgorithm { got: u64 },
+	/// The account uses a number of digits that isn't supported.
+	UnsupportedDigits { got: u64 },
+	/// The account is of a type that isn't supported.
+	UnsupportedType { got: u64 },
+}
+
+impl Display for MigrationError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			MigrationError::InvalidScheme => {
+				f.write_str("the URI doesn't use the otpauth-migration scheme")
+			}
+			MigrationError::InvalidEncoding => {
+				f.write_str("the migration data is missing or isn't valid Base64")
+			}
+			MigrationError::Malformed => f.write_str("the migration data is malformed"),
+			MigrationError::UnsupportedAlgorithm { got } => {
+				write!(f, "the account uses an unsupported algorithm ({})", got)
+			}
+			MigrationError::UnsupportedDigits { got } => {
+				write!(
+					f,
+					"the account uses an unsupported number of digits ({})",
+					got
+				)
+			}
+			MigrationError::UnsupportedType { got } => {
+				write!(f, "the account is of an unsupported type ({})", got)
+			}
+		}
+	}
+}
+
+#[cfg(feature = "alloc")]
+/// The reason a backup from another authenticator app couldn't be imported,
+/// such as a missing field, described by the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupError(String);
+
+#[cfg(feature = "alloc")]
+impl BackupError {
+	#[cfg(any(
+		feature = "backup-aegis",
+		feature = "backup-andotp",
+		feature = "backup-freeotp",
+		feature = "backup-keepass",
+		feature = "backup-twofas"
+	))]
+	pub(crate) fn new<M: Into<String>>(message: M) -> Self {
+		Self(message.into())
+	}
+
+	/// Returns the description of the problem.
+	#[inline]
+	pub fn message(&self) -> &str {
+		&self.0
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl Display for BackupError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+/// The reason a [TimeSource](crate::time::TimeSource) couldn't report the current time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeError {
+	/// The clock hasn't been set yet, such as an RTC that lost power.
+	NotSet,
+	/// Reading the clock failed, such as a bus error when talking to an RTC.
+	ReadFailed,
+	/// The wall clock and a [MonotonicAnchor](crate::time::MonotonicAnchor) disagree by
+	/// more than its maximum divergence.
+	Diverged,
+	/// The clock reported a time too far in the future to be represented in milliseconds,
+	/// such as an RTC returning all ones.
+	OutOfRange,
+	/// The clock is set to before the unix epoch.
+	BeforeEpoch,
+}
+
+impl Display for TimeError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			TimeError::NotSet => "the clock hasn't been set",
+			TimeError::ReadFailed => "failed to read the clock",
+			TimeError::Diverged => "the wall clock and the anchored time disagree",
+			TimeError::OutOfRange => "the clock reported a time too far in the future",
+			TimeError::BeforeEpoch => "the clock is set to before the unix epoch",
+		})
+	}
 }
 
 impl Display for OtpError {
@@ -20,9 +227,119 @@ impl Display for OtpError {
 				f.write_str("the hash function used in the HOTP instance's output is too short")
 			}
 			OtpError::CounterOverflow => f.write_str("the HOTP instance's counter has overflowed"),
+			OtpError::PersistenceFailed => {
+				f.write_str("failed to persist the HOTP instance's counter")
+			}
+			OtpError::InvalidDigits { got } => write!(
+				f,
+				"attempted to create a HOTP instance with {} digits, which is outside of 1..={}",
+				got,
+				crate::hotp::MAX_DIGITS
+			),
+			OtpError::MissingSecret => {
+				f.write_str("attempted to restore a HOTP instance from a state without a secret")
+			}
+			OtpError::StateTooShort => f.write_str("the HOTP state buffer is too short"),
+			OtpError::UnsupportedStateVersion { got } => {
+				write!(f, "the HOTP state has an unsupported version ({})", got)
+			}
+			OtpError::MissingTimeSource => {
+				f.write_str("attempted to create a TOTP instance without a time source")
+			}
+			OtpError::InvalidBase32 => f.write_str("the secret is not valid base32"),
+			OtpError::InvalidHex { position } => {
+				write!(f, "the secret is not valid hex, at position {}", position)
+			}
+			OtpError::RandomFailed => f.write_str("failed to generate a random secret"),
+			OtpError::KeyTooShort { got, minimum } => write!(
+				f,
+				"the secret is {} bytes long, but at least {} bytes are required",
+				got, minimum
+			),
+			OtpError::MacFailed => f.write_str("the MAC backend failed to compute the code"),
+			OtpError::CounterBelowMinimum { minimum } => write!(
+				f,
+				"the counter can't be set below {}, as codes for it may have been accepted",
+				minimum
+			),
+			OtpError::UriParse(err) => write!(f, "failed to parse the provisioning URI: {}", err),
+			OtpError::DecryptionFailed => {
+				f.write_str("the sealed state is corrupted, or was sealed with a different key")
+			}
+			OtpError::TimeBeforeEpoch => f.write_str("the time is before the unix epoch"),
+			OtpError::TimeUnavailable(err) => write!(f, "the current time is unavailable: {}", err),
+			OtpError::TimeOutOfRange => {
+				f.write_str("the time is too far in the future for a time step")
+			}
+			OtpError::InvalidInterval => f.write_str("the TOTP interval can't be zero"),
+			OtpError::QrFailed => f.write_str("the data is too long to fit in a QR code"),
+			OtpError::Migration(err) => write!(f, "failed to parse the migration URI: {}", err),
+			#[cfg(feature = "alloc")]
+			OtpError::InvalidBackup(err) => write!(f, "failed to import the backup: {}", err),
+			OtpError::DuplicateAccount => {
+				f.write_str("the keyring already has an account with the same issuer and label")
+			}
+			OtpError::InvalidKeyring => {
+				f.write_str("the keyring is corrupted, or was saved for a different hash algorithm")
+			}
+			OtpError::UnsupportedKeyringVersion { got } => {
+				write!(f, "the keyring has an unsupported version ({})", got)
+			}
+			#[cfg(feature = "std")]
+			OtpError::Io(kind) => write!(f, "failed to access the keyring file: {}", kind),
+			OtpError::InvalidRecoveryFormat => {
+				f.write_str("recovery codes need at least one group of at least one character")
+			}
+			OtpError::RateLimited { retry_at } => {
+				write!(f, "too many validation attempts, try again at {}", retry_at)
+			}
+			OtpError::AlgorithmMismatch { expected, got } => {
+				write!(f, "expected the {} algorithm, but got {}", expected, got)
+			}
 		}
 	}
 }
 
 #[cfg(feature = "std")]
 impl Error for OtpError {}
+
+#[cfg(feature = "std")]
+impl Error for UriError {}
+
+#[cfg(feature = "std")]
+impl Error for TimeError {}
+
+#[cfg(feature = "std")]
+impl Error for MigrationError {}
+
+#[cfg(feature = "std")]
+impl Error for BackupError {}
+
+impl From<UriError> for OtpError {
+	#[inline]
+	fn from(err: UriError) -> Self {
+		OtpError::UriParse(err)
+	}
+}
+
+impl From<MigrationError> for OtpError {
+	#[inline]
+	fn from(err: MigrationError) -> Self {
+		OtpError::Migration(err)
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl From<BackupError> for OtpError {
+	#[inline]
+	fn from(err: BackupError) -> Self {
+		OtpError::InvalidBackup(err)
+	}
+}
+
+impl From<TimeError> for OtpError {
+	#[inline]
+	fn from(err: TimeError) -> Self {
+		OtpError::TimeUnavailable(err)
+	}
+}
@@ -0,0 +1,323 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{
+	error::OtpError,
+	hotp::{find_match, truncated_code, MAX_DIGITS, MIN_HASH_OUTPUT_SIZE},
+	mac::{Hmac, OtpMac},
+};
+use core::{
+	fmt::{self, Debug, Formatter},
+	marker::PhantomData,
+};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+use subtle::ConstantTimeEq;
+
+/// A HOTP generator with the number of digits fixed at compile time,
+/// which returns codes as arrays of ASCII digits.
+///
+/// This has no settings to get wrong, and never allocates, which suits embedded targets.
+/// The number of digits must be between 1 and [MAX_DIGITS], otherwise it fails to compile:
+///
+/// ```rust,compile_fail
+/// use otpee::HotpConst;
+/// use sha1::Sha1;
+///
+/// let hotp = HotpConst::<Sha1, 11>::new(b"12345678901234567890");
+/// ```
+///
+/// For the other features of HOTP, such as checksums or persistence hooks, use [Hotp](crate::Hotp).
+///
+/// ```rust
+/// use otpee::HotpConst;
+/// use sha1::Sha1;
+///
+/// let mut hotp = HotpConst::<Sha1, 6>::new(b"12345678901234567890").unwrap();
+/// assert_eq!(&hotp.code().unwrap(), b"755224");
+/// ```
+pub struct HotpConst<
+	D: Digest + BlockSizeUser + FixedOutputReset,
+	const DIGITS: usize,
+	M: OtpMac = Hmac<D>,
+> {
+	mac: M,
+	counter: u64,
+	_digest: PhantomData<fn() -> D>,
+}
+
+/// A TOTP generator with the number of digits fixed at compile time,
+/// which returns codes as arrays of ASCII digits. See [HotpConst].
+///
+/// Instead of a boxed callback, the current time is read from a function pointer, so this never allocates.
+///
+/// ```rust
+/// use otpee::TotpConst;
+/// use sha1::Sha1;
+///
+/// let mut totp = TotpConst::<Sha1, 8>::new(b"12345678901234567890", 30, 1, || 59).unwrap();
+/// assert_eq!(&totp.code().unwrap(), b"94287082");
+/// ```
+pub struct TotpConst<
+	D: Digest + BlockSizeUser + FixedOutputReset,
+	const DIGITS: usize,
+	M: OtpMac = Hmac<D>,
+> {
+	hotp: HotpConst<D, DIGITS, M>,
+	interval: u64,
+	skew: usize,
+	last_accepted_step: Option<u64>,
+	time_source: fn() -> u64,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize> HotpConst<D, DIGITS> {
+	/// Creates a new HOTP instance, using the given bytes as the secret.
+	///
+	/// Like [Hotp::new](crate::Hotp::new), hash functions with an output shorter than
+	/// [MIN_HASH_OUTPUT_SIZE] are rejected with [OtpError::HashTooShort].
+	pub fn new<A: AsRef<[u8]>>(key: A) -> Result<Self, OtpError> {
+		if <D as Digest>::output_size() < MIN_HASH_OUTPUT_SIZE {
+			return Err(OtpError::HashTooShort);
+		}
+		Ok(Self::with_mac(Hmac::new_from_slice(key.as_ref())?))
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize, M: OtpMac>
+	HotpConst<D, DIGITS, M>
+{
+	/// Fails to compile if the number of digits is out of range.
+	const VALID_DIGITS: () = assert!(
+		DIGITS >= 1 && DIGITS <= MAX_DIGITS,
+		"the number of digits must be between 1 and MAX_DIGITS"
+	);
+
+	/// Creates a new HOTP instance, using a MAC backend given by the caller.
+	/// See [Hotp::with_mac](crate::Hotp::with_mac).
+	pub fn with_mac(mac: M) -> Self {
+		#[allow(clippy::let_unit_value)]
+		let () = Self::VALID_DIGITS;
+		Self {
+			mac,
+			counter: 0,
+			_digest: PhantomData,
+		}
+	}
+
+	/// Returns the current counter value.
+	#[inline]
+	pub fn counter(&self) -> u64 {
+		self.counter
+	}
+
+	/// Sets the counter to the specified value.
+	#[inline]
+	pub fn set_counter(&mut self, counter: u64) {
+		self.counter = counter;
+	}
+
+	/// Increments the counter value.
+	pub fn increment_counter(&mut self) -> Result<u64, OtpError> {
+		self.counter = self
+			.counter
+			.checked_add(1)
+			.ok_or(OtpError::CounterOverflow)?;
+		Ok(self.counter)
+	}
+
+	/// Calculate the OTP value, using the current counter.
+	/// This does NOT increment the counter!
+	#[inline]
+	pub fn code(&mut self) -> Result<[u8; DIGITS], OtpError> {
+		self.code_at(self.counter)
+	}
+
+	/// Calculate the OTP value for the given counter.
+	/// This does not read or modify the stored counter.
+	pub fn code_at(&mut self, counter: u64) -> Result<[u8; DIGITS], OtpError> {
+		let mut code = truncated_code(&mut self.mac, &counter.to_be_bytes(), None, DIGITS)?;
+		let mut digits = [b'0'; DIGITS];
+		for digit in digits.iter_mut().rev() {
+			*digit = b'0' + (code % 10) as u8;
+			code /= 10;
+		}
+		Ok(digits)
+	}
+
+	/// Calculates the OTP value using the current counter,
+	/// and then increments the counter afterwards.
+	pub fn code_increment(&mut self) -> Result<[u8; DIGITS], OtpError> {
+		let code = self.code()?;
+		self.increment_counter()?;
+		Ok(code)
+	}
+
+	/// Validates the code against the current counter, as well as the next `window` counters.
+	/// See [Hotp::validate](crate::Hotp::validate).
+	pub fn validate(&mut self, code: u64, window: usize) -> Option<u64> {
+		let start = self.counter;
+		let counters = (0..=window as u64)
+			.map_while(|offset| start.checked_add(offset))
+			.take_while(|&counter| counter != u64::MAX);
+		let matched = self.find_counter(counters, code)?;
+		self.counter = matched + 1;
+		Some(matched)
+	}
+
+	/// Returns the first of the given counters that the code matches, in constant time.
+	fn find_counter<I: IntoIterator<Item = u64>>(&mut self, counters: I, code: u64) -> Option<u64> {
+		let mac = &mut self.mac;
+		find_match(counters, |counter| {
+			truncated_code(mac, &counter.to_be_bytes(), None, DIGITS)
+				.map(|expected| expected.ct_eq(&code))
+				.unwrap_or_else(|_| 0.into())
+		})
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize> TotpConst<D, DIGITS> {
+	/// Creates a new TOTP instance, using the given bytes as the secret,
+	/// the given interval and skew, and the given function to get the current time,
+	/// represented as seconds from the unix epoch.
+	pub fn new<A: AsRef<[u8]>>(
+		key: A,
+		interval: u64,
+		skew: usize,
+		time_source: fn() -> u64,
+	) -> Result<Self, OtpError> {
+		Ok(Self::with_hotp(
+			HotpConst::new(key)?,
+			interval,
+			skew,
+			time_source,
+		))
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize, M: OtpMac>
+	TotpConst<D, DIGITS, M>
+{
+	/// Creates a new TOTP instance, using a MAC backend given by the caller.
+	/// See [TotpConst::new] for the other parameters.
+	pub fn with_mac(mac: M, interval: u64, skew: usize, time_source: fn() -> u64) -> Self {
+		Self::with_hotp(HotpConst::with_mac(mac), interval, skew, time_source)
+	}
+
+	fn with_hotp(
+		hotp: HotpConst<D, DIGITS, M>,
+		interval: u64,
+		skew: usize,
+		time_source: fn() -> u64,
+	) -> Self {
+		Self {
+			hotp,
+			interval,
+			skew,
+			last_accepted_step: None,
+			time_source,
+		}
+	}
+
+	/// Returns the time step for the current time, as reported by the time source.
+	#[inline]
+	pub fn counter(&self) -> u64 {
+		(self.time_source)() / self.interval
+	}
+
+	/// Calculate the OTP value for the given time, represented as seconds from the unix epoch.
+	#[inline]
+	pub fn code_at_time(&mut self, time: u64) -> Result<[u8; DIGITS], OtpError> {
+		self.hotp.code_at(time / self.interval)
+	}
+
+	/// Calculate the OTP value for the current time.
+	#[inline]
+	pub fn code(&mut self) -> Result<[u8; DIGITS], OtpError> {
+		let counter = self.counter();
+		self.hotp.code_at(counter)
+	}
+
+	/// Validates the code as being valid for the current time, taking the skew value into account.
+	/// Like [Totp::validate_code](crate::Totp::validate_code), each code can only be used once.
+	pub fn validate_code(&mut self, code: u64) -> bool {
+		let counter = self.counter();
+		let last_accepted = self.last_accepted_step;
+		let steps = (counter.saturating_sub(self.skew as u64)
+			..=counter.saturating_add(self.skew as u64))
+			.filter(|&step| last_accepted.is_none_or(|last| step > last));
+		match self.hotp.find_counter(steps, code) {
+			Some(step) => {
+				self.last_accepted_step = Some(step);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+/// The secret-derived MAC state is never printed, to avoid leaking key material into logs.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize, M: OtpMac> Debug
+	for HotpConst<D, DIGITS, M>
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HotpConst")
+			.field("mac", &format_args!("<redacted>"))
+			.field("digits", &DIGITS)
+			.field("counter", &self.counter)
+			.finish()
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, const DIGITS: usize, M: OtpMac> Debug
+	for TotpConst<D, DIGITS, M>
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TotpConst")
+			.field("hotp", &self.hotp)
+			.field("interval", &self.interval)
+			.field("skew", &self.skew)
+			.field("last_accepted_step", &self.last_accepted_step)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{HotpConst, TotpConst};
+	use crate::vectors::HOTP_SHA1;
+	use sha1::Sha1;
+	use sha2::{Sha256, Sha512};
+
+	#[test]
+	fn hotp_const_rfc4226() {
+		let mut hotp = HotpConst::<Sha1, 6>::new(b"12345678901234567890").unwrap();
+		for vector in HOTP_SHA1 {
+			let code = hotp.code_increment().unwrap();
+			let code = core::str::from_utf8(&code).unwrap();
+			assert_eq!(code.parse::<u64>().unwrap(), vector.code);
+		}
+		hotp.set_counter(2);
+		assert_eq!(hotp.validate(254676, 3), Some(5));
+		assert_eq!(hotp.counter(), 6);
+		assert_eq!(hotp.validate(254676, 3), None);
+	}
+
+	#[test]
+	fn totp_const_rfc6238() {
+		let mut totp = TotpConst::<Sha1, 8>::new(b"12345678901234567890", 30, 1, || 59).unwrap();
+		assert_eq!(&totp.code().unwrap(), b"94287082");
+		assert_eq!(&totp.code_at_time(1111111109).unwrap(), b"07081804");
+		assert!(totp.validate_code(94287082));
+		assert!(!totp.validate_code(94287082));
+		let mut totp =
+			TotpConst::<Sha256, 8>::new(b"12345678901234567890123456789012", 30, 0, || 0).unwrap();
+		assert_eq!(&totp.code_at_time(1234567890).unwrap(), b"91819424");
+		let mut totp = TotpConst::<Sha512, 8>::new(
+			b"1234567890123456789012345678901234567890123456789012345678901234",
+			30,
+			0,
+			|| 0,
+		)
+		.unwrap();
+		assert_eq!(&totp.code_at_time(20000000000).unwrap(), b"47863826");
+		let mut totp = TotpConst::<Sha1, 6>::new(b"12345678901234567890", 30, 0, || 0).unwrap();
+		assert_eq!(&totp.code_at_time(1111111109).unwrap(), b"081804");
+	}
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+#[cfg(feature = "dynamic")]
+use crate::dynamic::{DynHotp, DynTotp};
+use crate::{algorithm::Algorithm, error::OtpError, hotp::Hotp, time::TimeSource, totp::Totp};
+use alloc::vec::Vec;
+use arbitrary::{Arbitrary, Unstructured};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// A random, but structurally valid, generator configuration, for fuzzing code that uses this crate.
+///
+/// Generated configurations always build successfully, with a key of 16 to 64 bytes,
+/// 1 to 9 digits, an interval of 1 to 300 seconds, and a skew of 0 to 10.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtpConfig {
+	/// The secret.
+	pub key: Vec<u8>,
+	/// The number of digits.
+	pub digits: usize,
+	/// The length of a TOTP time step, in seconds.
+	pub interval: u64,
+	/// The TOTP skew.
+	pub skew: usize,
+	/// The hash algorithm, which is only used by the dynamic constructors.
+	pub algorithm: Algorithm,
+}
+
+impl<'a> Arbitrary<'a> for OtpConfig {
+	fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+		let key_length = u.int_in_range(16..=64)?;
+		Ok(Self {
+			key: u.bytes(key_length)?.into(),
+			digits: u.int_in_range(1..=9)?,
+			interval: u.int_in_range(1..=300)?,
+			skew: u.int_in_range(0..=10)?,
+			algorithm: *u.choose(&[Algorithm::Sha1, Algorithm::Sha256, Algorithm::Sha512])?,
+		})
+	}
+}
+
+impl OtpConfig {
+	/// Builds a HOTP instance with this configuration, using the given hash function.
+	pub fn hotp<D: Digest + BlockSizeUser + FixedOutputReset>(&self) -> Result<Hotp<D>, OtpError> {
+		Hotp::new(&self.key, self.digits)
+	}
+
+	/// Builds a TOTP instance with this configuration, using the given hash function.
+	pub fn totp<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource>(
+		&self,
+		time_callback: C,
+	) -> Result<Totp<D, C>, OtpError> {
+		Totp::new(
+			&self.key,
+			self.digits,
+			self.interval,
+			self.skew,
+			time_callback,
+		)
+	}
+
+	#[cfg(feature = "dynamic")]
+	/// Builds a HOTP instance with this configuration, using the configured algorithm.
+	pub fn dyn_hotp(&self) -> Result<DynHotp, OtpError> {
+		DynHotp::new(self.algorithm, &self.key, self.digits)
+	}
+
+	#[cfg(feature = "dynamic")]
+	/// Builds a TOTP instance with this configuration, using the configured algorithm.
+	pub fn dyn_totp<C: TimeSource + 'static>(&self, time_callback: C) -> Result<DynTotp, OtpError> {
+		DynTotp::new(
+			self.algorithm,
+			&self.key,
+			self.digits,
+			self.interval,
+			self.skew,
+			time_callback,
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::OtpConfig;
+	use crate::{algorithm::Algorithm, otp::Otp};
+	use arbitrary::{Arbitrary, Unstructured};
+	use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+	use proptest::prelude::*;
+	use sha1::Sha1;
+	use sha2::{Sha256, Sha512};
+
+	fn round_trip<D: Digest + BlockSizeUser + FixedOutputReset>(config: &OtpConfig, time: u64) {
+		let mut totp = config.totp::<D, _>(move || time).unwrap();
+		let code = totp.code().unwrap();
+		assert_eq!(code.length(), config.digits);
+		assert!(totp.validate_code(*code));
+
+		let mut hotp = config.hotp::<D>().unwrap();
+		let code = hotp.code().unwrap();
+		assert_eq!(hotp.validate(*code, 0), Some(0));
+	}
+
+	proptest! {
+		#[test]
+		fn fuzz_config_round_trip(bytes in proptest::collection::vec(any::<u8>(), 96..256), time: u64) {
+			let config = OtpConfig::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+			match config.algorithm {
+				Algorithm::Sha1 => round_trip::<Sha1>(&config, time),
+				Algorithm::Sha256 => round_trip::<Sha256>(&config, time),
+				Algorithm::Sha512 => round_trip::<Sha512>(&config, time),
+			}
+		}
+
+		#[test]
+		fn fuzz_otp_fits_length(bytes in proptest::collection::vec(any::<u8>(), 0..32)) {
+			let otp = Otp::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+			prop_assert_eq!(otp.to_string().len(), otp.length());
+		}
+	}
+}
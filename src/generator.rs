@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, otp::Otp};
+
+/// A one-time password generator, implemented by both [Hotp](crate::Hotp) and
+/// [Totp](crate::Totp), for code that handles both kinds of tokens.
+///
+/// This trait is object-safe, so the hash function used can be erased behind a
+/// `Box<dyn OtpGenerator>`.
+///
+/// ```rust
+/// use otpee::{Hotp, OtpGenerator, Totp};
+/// use sha1::Sha1;
+/// use sha2::Sha256;
+///
+/// let mut tokens: Vec<Box<dyn OtpGenerator>> = vec![
+///     Box::new(Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap()),
+///     Box::new(Totp::<Sha256>::new(b"12345678901234567890123456789012", 8, 30, 1, || 59).unwrap()),
+/// ];
+/// assert!(tokens[0].validate(755224));
+/// assert!(tokens[1].validate(46119246));
+/// ```
+pub trait OtpGenerator {
+	/// Calculates the current OTP value, without advancing any state.
+	fn current_code(&mut self) -> Result<Otp, OtpError>;
+
+	/// Validates the code, using the generator's own configuration to decide which
+	/// codes are accepted: the look-ahead window for HOTP, and the skew for TOTP.
+	fn validate(&mut self, code: u64) -> bool;
+
+	/// Returns the number of digits in generated codes, not counting the checksum digit.
+	fn digits(&self) -> usize;
+}
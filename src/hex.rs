@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::error::OtpError;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Keys up to this many bytes are decoded on the stack, without allocating.
+const STACK_BUFFER_LEN: usize = 128;
+
+/// Iterates over the hex digits of the input, skipping an optional `0x` prefix and any whitespace,
+/// yielding the position and value of each digit.
+fn digits(input: &str) -> impl Iterator<Item = Result<(usize, u8), OtpError>> + '_ {
+	let trimmed = input.trim_start();
+	let mut start = input.len() - trimmed.len();
+	if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
+		start += 2;
+	}
+	input
+		.bytes()
+		.enumerate()
+		.skip(start)
+		.filter_map(|(position, c)| {
+			let value = match c {
+				b'0'..=b'9' => c - b'0',
+				b'a'..=b'f' => c - b'a' + 10,
+				b'A'..=b'F' => c - b'A' + 10,
+				_ if c.is_ascii_whitespace() => return None,
+				_ => return Some(Err(OtpError::InvalidHex { position })),
+			};
+			Some(Ok((position, value)))
+		})
+}
+
+/// Validates the input, returning the number of bytes it decodes to.
+fn decoded_len(input: &str) -> Result<usize, OtpError> {
+	let mut count = 0;
+	let mut last = 0;
+	for digit in digits(input) {
+		last = digit?.0;
+		count += 1;
+	}
+	if count % 2 != 0 {
+		return Err(OtpError::InvalidHex { position: last });
+	}
+	Ok(count / 2)
+}
+
+/// Decodes validated input into the given buffer, which must be exactly the decoded length.
+fn decode_into(input: &str, out: &mut [u8]) {
+	let mut high = None;
+	let mut bytes = out.iter_mut();
+	for (_, value) in digits(input).flatten() {
+		match high.take() {
+			None => high = Some(value),
+			Some(high) => {
+				if let Some(byte) = bytes.next() {
+					*byte = (high << 4) | value;
+				}
+			}
+		}
+	}
+}
+
+/// Decodes hex, upper- or lower-case, with an optional `0x` prefix and any whitespace ignored.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode(input: &str) -> Result<Vec<u8>, OtpError> {
+	let mut out = alloc::vec![0; decoded_len(input)?];
+	decode_into(input, &mut out);
+	Ok(out)
+}
+
+/// Decodes hex like [decode], passing the result to the given function.
+/// Short keys are decoded on the stack, so this only allocates for long keys.
+pub(crate) fn with_decoded<T, F: FnOnce(&[u8]) -> Result<T, OtpError>>(
+	input: &str,
+	f: F,
+) -> Result<T, OtpError> {
+	let len = decoded_len(input)?;
+	if len <= STACK_BUFFER_LEN {
+		let mut buf = [0u8; STACK_BUFFER_LEN];
+		decode_into(input, &mut buf[..len]);
+		let result = f(&buf[..len]);
+		#[cfg(feature = "zeroize")]
+		zeroize::Zeroize::zeroize(&mut buf);
+		return result;
+	}
+	#[cfg(feature = "alloc")]
+	{
+		let mut buf = alloc::vec![0; len];
+		decode_into(input, &mut buf);
+		let result = f(&buf);
+		#[cfg(feature = "zeroize")]
+		zeroize::Zeroize::zeroize(&mut buf);
+		result
+	}
+	#[cfg(not(feature = "alloc"))]
+	Err(OtpError::InvalidLength)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode, with_decoded};
+	use crate::error::OtpError;
+
+	#[test]
+	fn hex_decode() {
+		assert_eq!(
+			decode("3132333435363738393031323334353637383930").unwrap(),
+			b"12345678901234567890"
+		);
+		assert_eq!(decode(" 0x31 32 33\n34 ").unwrap(), b"1234");
+		assert_eq!(decode("0XdeadBEEF").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+		assert_eq!(decode("").unwrap(), b"");
+		assert_eq!(decode("31g2"), Err(OtpError::InvalidHex { position: 2 }));
+		assert_eq!(decode("0x313"), Err(OtpError::InvalidHex { position: 4 }));
+		let long = "ab".repeat(200);
+		assert_eq!(with_decoded(&long, |key| Ok(key.len())), Ok(200));
+	}
+}
@@ -1,7 +1,80 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
-use crate::{error::OtpError, otp::Otp};
-use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset, KeyInit};
-use hmac::{Mac, SimpleHmac};
+#[cfg(feature = "alloc")]
+use crate::{algorithm::HashAlgorithm, secret::Secret, state::HotpState, uri};
+use crate::{
+	audit::AuditEvent,
+	error::OtpError,
+	generator::OtpGenerator,
+	hex,
+	mac::{Hmac, OtpMac, MAX_OUTPUT_SIZE},
+	otp::{self, Otp},
+	state,
+	totp::Totp,
+	validation::Validation,
+};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String};
+use core::{
+	fmt::{self, Debug, Formatter},
+	marker::PhantomData,
+	ops::Range,
+};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+use hmac::SimpleHmac;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeLess};
+
+/// The maximum number of digits a code can have, not counting the checksum digit.
+///
+/// The truncated value is only 31 bits long, so any more digits than this
+/// would never be anything but leading zeroes.
+pub const MAX_DIGITS: usize = 10;
+
+/// The minimum secret length, in bytes, required by RFC 4226 section 4.
+/// This is only enforced when asked for, see [HotpBuilder::enforce_minimum_key_length].
+pub const MIN_KEY_LENGTH: usize = 16;
+
+/// The minimum output size of the hash function, in bytes, which is that of SHA-1.
+///
+/// Dynamic truncation reads 4 bytes from an offset of up to 15, so shorter hashes
+/// can't be used with it. Hashes shorter than this are rejected with [OtpError::HashTooShort]
+/// when building a HOTP instance from a key or hasher.
+pub const MIN_HASH_OUTPUT_SIZE: usize = 20;
+
+/// What happens when the counter of a [Hotp] instance would be advanced past [u64::MAX].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+	/// Fail with [OtpError::CounterOverflow].
+	#[default]
+	Error,
+	/// Wrap around to 0.
+	Wrap,
+	/// Stay at [u64::MAX].
+	Saturate,
+}
+
+/// The settings of a [Hotp] instance, shared by it and [HotpBuilder],
+/// so that what is configured when building is exactly what the accessors return.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct HotpConfig {
+	pub digits: usize,
+	pub checksum: bool,
+	pub truncation_offset: Option<u8>,
+	pub window: usize,
+	pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for HotpConfig {
+	fn default() -> Self {
+		Self {
+			digits: 6,
+			checksum: false,
+			truncation_offset: None,
+			window: 0,
+			overflow_policy: OverflowPolicy::Error,
+		}
+	}
+}
 
 /// A hash-based One-Time Password (HOTP) generator.
 ///
@@ -19,77 +92,668 @@ use hmac::{Mac, SimpleHmac};
 /// assert_eq!(otp, 755224);
 /// assert_eq!(otp.to_string(), "755224");
 /// ```
-#[derive(Debug, Clone)]
-pub struct Hotp<D: Digest + BlockSizeUser + FixedOutputReset> {
-	hasher: SimpleHmac<D>,
+pub struct Hotp<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac = Hmac<D>> {
+	mac: M,
 	counter: u64,
-	length: usize,
+	config: HotpConfig,
+	minimum_counter: u64,
+	#[cfg(feature = "alloc")]
+	counter_hook: Option<CounterHook>,
+	#[cfg(feature = "alloc")]
+	audit_hook: Option<AuditHook>,
+	_digest: PhantomData<fn() -> D>,
+}
+
+#[cfg(feature = "alloc")]
+struct CounterHook(Box<dyn FnMut(u64) -> Result<(), OtpError> + Send + Sync>);
+
+#[cfg(feature = "alloc")]
+impl Debug for CounterHook {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("<counter hook>")
+	}
+}
+
+#[cfg(feature = "alloc")]
+struct AuditHook(Box<dyn FnMut(AuditEvent) + Send + Sync>);
+
+#[cfg(feature = "alloc")]
+impl Debug for AuditHook {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("<audit hook>")
+	}
+}
+
+/// The secret-derived MAC state is never printed, to avoid leaking key material into logs.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac> Debug for Hotp<D, M> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut debug = f.debug_struct("Hotp");
+		debug
+			.field("mac", &format_args!("<redacted>"))
+			.field("counter", &self.counter)
+			.field("digits", &self.config.digits)
+			.field("checksum", &self.config.checksum)
+			.field("truncation_offset", &self.config.truncation_offset)
+			.field("window", &self.config.window)
+			.field("overflow_policy", &self.config.overflow_policy)
+			.field("minimum_counter", &self.minimum_counter);
+		#[cfg(feature = "alloc")]
+		debug
+			.field("counter_hook", &self.counter_hook)
+			.field("audit_hook", &self.audit_hook);
+		debug.finish()
+	}
+}
+
+/// Cloning a HOTP instance does not clone its counter change callback,
+/// as two instances persisting to the same place would quickly disagree,
+/// nor its audit hook.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac + Clone> Clone for Hotp<D, M> {
+	fn clone(&self) -> Self {
+		Hotp {
+			mac: self.mac.clone(),
+			counter: self.counter,
+			config: self.config,
+			minimum_counter: self.minimum_counter,
+			#[cfg(feature = "alloc")]
+			counter_hook: None,
+			#[cfg(feature = "alloc")]
+			audit_hook: None,
+			_digest: PhantomData,
+		}
+	}
 }
 
 impl<D: Digest + BlockSizeUser + FixedOutputReset> Hotp<D> {
 	/// Creates a new HOTP instance, using the given bytes as the secret.
+	///
+	/// The length must be between 1 and [MAX_DIGITS], otherwise
+	/// [OtpError::InvalidDigits] is returned.
 	pub fn new<A: AsRef<[u8]>, L: Into<Option<usize>>>(
 		key: A,
 		length: L,
 	) -> Result<Self, OtpError> {
-		let length = length.into().unwrap_or(6);
-		<SimpleHmac<D> as KeyInit>::new_from_slice(key.as_ref())
-			.map(|hasher| Hotp {
-				hasher,
-				counter: 0,
-				length,
-			})
-			.map_err(|_| OtpError::InvalidLength)
+		Self::builder()
+			.digits(length.into().unwrap_or(6))
+			.build(key)
+	}
+
+	/// Creates a new HOTP instance, using the given bytes as the secret,
+	/// rejecting secrets shorter than [MIN_KEY_LENGTH] with [OtpError::KeyTooShort].
+	pub fn new_strict<A: AsRef<[u8]>, L: Into<Option<usize>>>(
+		key: A,
+		length: L,
+	) -> Result<Self, OtpError> {
+		Self::builder()
+			.digits(length.into().unwrap_or(6))
+			.enforce_minimum_key_length(true)
+			.build(key)
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Creates a new HOTP instance, using the given Base32 string as the secret.
+	/// See [Secret::from_base32] for the accepted format.
+	pub fn new_base32<L: Into<Option<usize>>>(key: &str, length: L) -> Result<Self, OtpError> {
+		Self::new(Secret::from_base32(key)?, length)
+	}
+
+	/// Creates a new HOTP instance, using the given hex string as the secret.
+	/// See [Secret::from_hex](crate::Secret::from_hex) for the accepted format.
+	///
+	/// Keys of up to 128 bytes are decoded without allocating.
+	pub fn new_hex<L: Into<Option<usize>>>(key: &str, length: L) -> Result<Self, OtpError> {
+		hex::with_decoded(key, |key| Self::new(key, length))
 	}
 
 	/// Creates a new HOTP instance, using a hasher given by the caller.
-	pub fn with_hasher<L: Into<Option<usize>>>(hasher: SimpleHmac<D>, length: L) -> Self {
-		let length = length.into().unwrap_or(6);
-		Hotp {
-			hasher,
-			counter: 0,
-			length,
+	///
+	/// The length must be between 1 and [MAX_DIGITS], otherwise
+	/// [OtpError::InvalidDigits] is returned.
+	pub fn with_hasher<L: Into<Option<usize>>>(
+		hasher: SimpleHmac<D>,
+		length: L,
+	) -> Result<Self, OtpError> {
+		Self::builder()
+			.digits(length.into().unwrap_or(6))
+			.build_with_hasher(hasher)
+	}
+
+	/// Creates a builder for configuring a new HOTP instance.
+	#[inline]
+	pub fn builder() -> HotpBuilder<D> {
+		HotpBuilder::new()
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Restores a HOTP instance from a previously exported state,
+	/// using the secret stored in it.
+	///
+	/// Returns [OtpError::MissingSecret] if the state has no secret.
+	pub fn from_state(state: &HotpState) -> Result<Self, OtpError> {
+		let secret = state.secret.as_ref().ok_or(OtpError::MissingSecret)?;
+		Self::from_state_with_key(secret, state)
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Restores a HOTP instance from a previously exported state, using the given bytes as
+	/// the secret. Any secret stored in the state is ignored.
+	pub fn from_state_with_key<A: AsRef<[u8]>>(
+		key: A,
+		state: &HotpState,
+	) -> Result<Self, OtpError> {
+		let mut hotp = Self::new(key, state.digits)?;
+		hotp.set_checksum(state.checksum);
+		hotp.set_truncation_offset(state.truncation_offset)?;
+		hotp.set_window(state.window);
+		hotp.set_overflow_policy(state.overflow_policy);
+		hotp.counter = state.counter;
+		hotp.minimum_counter = state.minimum_counter;
+		Ok(hotp)
+	}
+
+	/// Restores a HOTP instance from a binary snapshot written by [Hotp::to_state_bytes],
+	/// using the given bytes as the secret.
+	///
+	/// Returns [OtpError::StateTooShort] if the snapshot is truncated,
+	/// and [OtpError::UnsupportedStateVersion] if it was written in an unknown format.
+	pub fn from_state_bytes(key: &[u8], buf: &[u8]) -> Result<Self, OtpError> {
+		let (counter, length) = state::decode(buf)?;
+		let mut hotp = Self::new(key, length)?;
+		hotp.counter = counter;
+		Ok(hotp)
+	}
+
+	/// Replaces the secret with the given bytes, keeping the counter and all other settings.
+	pub fn set_key<A: AsRef<[u8]>>(&mut self, key: A) -> Result<(), OtpError> {
+		self.mac = Hmac::new_from_slice(key.as_ref())?;
+		Ok(())
+	}
+
+	/// Converts this into a TOTP instance with the same secret and number of digits,
+	/// without needing the secret again. See [Totp::from_hotp].
+	///
+	/// ```rust
+	/// use otpee::Hotp;
+	/// use sha1::Sha1;
+	///
+	/// let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+	/// let mut totp = hotp.into_totp(30, 1, || 59).unwrap();
+	/// assert_eq!(totp.code().unwrap(), 94287082);
+	/// ```
+	#[inline]
+	pub fn into_totp<C>(
+		self,
+		interval: u64,
+		skew: usize,
+		time_source: C,
+	) -> Result<Totp<D, C>, OtpError> {
+		Totp::from_hotp(self, interval, skew, time_source)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac> Hotp<D, M> {
+	/// Creates a new HOTP instance, using a MAC backend given by the caller,
+	/// such as a hardware HMAC engine.
+	///
+	/// The length must be between 1 and [MAX_DIGITS], otherwise
+	/// [OtpError::InvalidDigits] is returned.
+	pub fn with_mac<L: Into<Option<usize>>>(mac: M, length: L) -> Result<Self, OtpError> {
+		HotpBuilder::new()
+			.digits(length.into().unwrap_or(6))
+			.build_with_mac(mac)
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Exports the state of this HOTP instance, for persisting it.
+	/// The secret isn't retained by HOTP instances, so it's left empty.
+	pub fn export_state(&self) -> HotpState {
+		HotpState {
+			secret: None,
+			counter: self.counter,
+			digits: self.config.digits,
+			checksum: self.config.checksum,
+			truncation_offset: self.config.truncation_offset,
+			window: self.config.window,
+			overflow_policy: self.config.overflow_policy,
+			minimum_counter: self.minimum_counter,
 		}
 	}
 
+	/// Writes a compact binary snapshot of the counter and digit length into the given buffer,
+	/// returning the number of bytes written, which is [STATE_BYTES_LEN](state::STATE_BYTES_LEN).
+	///
+	/// The other settings, including the [minimum counter](Hotp::minimum_counter), are not included.
+	/// Returns [OtpError::StateTooShort] if the buffer is too small.
+	pub fn to_state_bytes(&self, buf: &mut [u8]) -> Result<usize, OtpError> {
+		state::encode(self.counter, self.config.digits, buf)
+	}
+
 	/// Returns the current counter value.
 	#[inline]
 	pub fn counter(&self) -> u64 {
 		self.counter
 	}
 
-	/// Increments the counter value.
+	/// Increments the counter value, following the [OverflowPolicy] at [u64::MAX].
+	#[inline]
 	pub fn increment_counter(&mut self) -> Result<u64, OtpError> {
-		self.counter = self
-			.counter
-			.checked_add(1)
-			.ok_or(OtpError::CounterOverflow)?;
-		Ok(self.counter)
+		self.increment_counter_by(1)
+	}
+
+	/// Advances the counter by `n`, returning the new value.
+	/// Going past [u64::MAX] follows the [OverflowPolicy], and with the default
+	/// [OverflowPolicy::Error], the counter is left unchanged.
+	pub fn increment_counter_by(&mut self, n: u64) -> Result<u64, OtpError> {
+		let counter = self.add_to_counter(self.counter, n)?;
+		self.update_counter(counter)
+	}
+
+	/// Sets the counter to the one after the given counter, such as one that was just used,
+	/// returning the new value. This follows the [OverflowPolicy] like [Hotp::increment_counter_by].
+	#[inline]
+	pub fn advance_past(&mut self, counter: u64) -> Result<u64, OtpError> {
+		let counter = self.add_to_counter(counter, 1)?;
+		self.update_counter(counter)
+	}
+
+	/// Returns the policy followed when the counter would be advanced past [u64::MAX].
+	#[inline]
+	pub fn overflow_policy(&self) -> OverflowPolicy {
+		self.config.overflow_policy
+	}
+
+	/// Sets the policy followed when the counter would be advanced past [u64::MAX].
+	#[inline]
+	pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+		self.config.overflow_policy = policy;
+	}
+
+	/// Adds `n` to the given counter, following the [OverflowPolicy].
+	fn add_to_counter(&self, counter: u64, n: u64) -> Result<u64, OtpError> {
+		match self.config.overflow_policy {
+			OverflowPolicy::Error => counter.checked_add(n).ok_or(OtpError::CounterOverflow),
+			OverflowPolicy::Wrap => Ok(counter.wrapping_add(n)),
+			OverflowPolicy::Saturate => Ok(counter.saturating_add(n)),
+		}
 	}
 
 	/// Sets the counter to the specified value.
+	///
+	/// Counters below the [minimum counter](Hotp::minimum_counter) are rejected with
+	/// [OtpError::CounterBelowMinimum], as codes for them may have already been accepted.
+	/// Use [Hotp::force_set_counter] to set them anyway.
 	#[inline]
-	pub fn set_counter(&mut self, counter: u64) {
-		self.counter = counter;
+	pub fn set_counter(&mut self, counter: u64) -> Result<(), OtpError> {
+		if counter < self.minimum_counter {
+			return Err(OtpError::CounterBelowMinimum {
+				minimum: self.minimum_counter,
+			});
+		}
+		self.update_counter(counter).map(|_| ())
+	}
+
+	/// Sets the counter to the specified value, even if it is below the
+	/// [minimum counter](Hotp::minimum_counter), which is set to the same value.
+	///
+	/// This allows codes that were already accepted to be accepted again,
+	/// so it should only be used when deliberately resynchronizing a token.
+	pub fn force_set_counter(&mut self, counter: u64) -> Result<(), OtpError> {
+		self.minimum_counter = counter;
+		self.update_counter(counter).map(|_| ())
+	}
+
+	/// Resets the counter, and the [minimum counter](Hotp::minimum_counter), to 0.
+	/// See [Hotp::force_set_counter].
+	#[inline]
+	pub fn reset(&mut self) -> Result<(), OtpError> {
+		self.force_set_counter(0)
+	}
+
+	/// Returns the lowest counter that a code can still be accepted for.
+	///
+	/// This is raised past the matched counter whenever validation succeeds, so a code can never
+	/// be accepted twice, even if the counter is later set back. It starts at 0.
+	#[inline]
+	pub fn minimum_counter(&self) -> u64 {
+		self.minimum_counter
+	}
+
+	/// Sets the lowest counter that a code can still be accepted for,
+	/// such as one restored from a record of previously accepted codes.
+	///
+	/// Counters below it are skipped when validating, and can't be set with [Hotp::set_counter].
+	#[inline]
+	pub fn set_minimum_counter(&mut self, minimum: u64) {
+		self.minimum_counter = minimum;
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Registers a callback that is invoked with the new counter value whenever the
+	/// counter changes, allowing it to be persisted.
+	///
+	/// The callback is invoked after the counter has been updated. If it returns an error,
+	/// the previous counter is restored, so a code isn't accepted unless its counter was
+	/// persisted, and the error is propagated to the caller of the method that changed it.
+	/// Only one callback can be registered at a time; registering another replaces it.
+	/// It must be [Send] and [Sync], so that the instance stays usable across threads.
+	pub fn on_counter_change<F: FnMut(u64) -> Result<(), OtpError> + Send + Sync + 'static>(
+		&mut self,
+		f: F,
+	) {
+		self.counter_hook = Some(CounterHook(Box::new(f)));
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Registers a hook that is invoked for every code generated, and every validation attempt,
+	/// such as for audit logging. See [AuditEvent] for what is reported.
+	///
+	/// Only one hook can be registered at a time; registering another replaces it.
+	/// Like [Hotp::on_counter_change], it must be [Send] and [Sync].
+	pub fn set_audit_hook<F: FnMut(AuditEvent) + Send + Sync + 'static>(&mut self, f: F) {
+		self.audit_hook = Some(AuditHook(Box::new(f)));
+	}
+
+	/// Reports the event to the audit hook, if any.
+	#[inline]
+	pub(crate) fn audit(&mut self, _event: AuditEvent) {
+		#[cfg(feature = "alloc")]
+		if let Some(AuditHook(hook)) = &mut self.audit_hook {
+			hook(_event);
+		}
+	}
+
+	/// Sets the counter, restoring the previous one if the counter hook fails,
+	/// so that a counter that wasn't persisted doesn't take effect.
+	fn update_counter(&mut self, counter: u64) -> Result<u64, OtpError> {
+		#[cfg(feature = "alloc")]
+		let previous = core::mem::replace(&mut self.counter, counter);
+		#[cfg(not(feature = "alloc"))]
+		{
+			self.counter = counter;
+		}
+		#[cfg(feature = "alloc")]
+		if let Some(CounterHook(hook)) = &mut self.counter_hook {
+			if let Err(err) = hook(counter) {
+				self.counter = previous;
+				return Err(err);
+			}
+		}
+		Ok(counter)
+	}
+
+	/// Returns the number of digits in generated codes, not counting the checksum digit.
+	#[inline]
+	pub fn digits(&self) -> usize {
+		self.config.digits
+	}
+
+	/// Returns whether a checksum digit is appended to generated codes.
+	#[inline]
+	pub fn checksum(&self) -> bool {
+		self.config.checksum
+	}
+
+	/// Sets whether a checksum digit, as described in RFC 4226 appendix E.1,
+	/// should be appended to generated codes.
+	/// The displayed code will be one digit longer than the configured length.
+	#[inline]
+	pub fn set_checksum(&mut self, checksum: bool) {
+		self.config.checksum = checksum;
+	}
+
+	/// Returns the fixed truncation offset, if one is set.
+	#[inline]
+	pub fn truncation_offset(&self) -> Option<u8> {
+		self.config.truncation_offset
+	}
+
+	/// Sets a fixed truncation offset, as allowed by RFC 4226 section 5.3.
+	/// `None` uses the standard dynamic truncation.
+	///
+	/// The offset must be less than the MAC's output size minus 4,
+	/// otherwise [OtpError::InvalidLength] is returned.
+	pub fn set_truncation_offset(&mut self, offset: Option<u8>) -> Result<(), OtpError> {
+		if let Some(offset) = offset {
+			if offset as usize + 4 >= self.mac.output_size() {
+				return Err(OtpError::InvalidLength);
+			}
+		}
+		self.config.truncation_offset = offset;
+		Ok(())
+	}
+
+	/// Sets a fixed truncation offset, returning the modified instance.
+	/// See [Hotp::set_truncation_offset].
+	pub fn with_truncation_offset(mut self, offset: Option<u8>) -> Result<Self, OtpError> {
+		self.set_truncation_offset(offset)?;
+		Ok(self)
+	}
+
+	/// Returns the look-ahead window used when validating through [OtpGenerator::validate].
+	#[inline]
+	pub fn window(&self) -> usize {
+		self.config.window
+	}
+
+	/// Sets the look-ahead window used when validating through [OtpGenerator::validate].
+	/// See [Hotp::validate].
+	#[inline]
+	pub fn set_window(&mut self, window: usize) {
+		self.config.window = window;
 	}
 
 	/// Calculate the OTP value, using the current counter.
 	/// This does NOT increment the counter!
 	pub fn code(&mut self) -> Result<Otp, OtpError> {
-		// Calculate the hash of the current counter, in big-endian format
-		let counter = self.counter.to_be_bytes();
-		self.hasher.update(&counter);
-		let digest = self.hasher.finalize_fixed_reset();
-		// Now, we need to get the length of the hash, minus 4.
-		// The offset is calculated from moduloing the last byte of the hash with that.
-		let offset = (*digest.last().ok_or(OtpError::HashTooShort)? & 0xF) as usize;
-		// Now, to get our 4 bytes and turn it into a u32;
-		let mut code = [0u8; 4];
-		code.copy_from_slice(&digest[offset..offset + 4]);
-		let binary = u32::from_be_bytes(code) & 0x7fff_ffff;
-		// And here we go calculating the OTP value.
-		let code = binary % 10_u32.pow(self.length as u32);
-		Ok(Otp::new(code, self.length))
+		self.code_at(self.counter)
+	}
+
+	/// Calculate the OTP value for the given counter.
+	/// This does not read or modify the stored counter.
+	pub fn code_at(&mut self, counter: u64) -> Result<Otp, OtpError> {
+		let code = self.expected_code(counter)?;
+		self.audit(AuditEvent::CodeGenerated { counter });
+		Ok(code)
+	}
+
+	/// Calculates the OTP value for the given counter, without reporting it to the audit hook,
+	/// for comparing against codes being validated.
+	#[inline]
+	fn expected_code(&mut self, counter: u64) -> Result<Otp, OtpError> {
+		self.code_for_factor(&counter.to_be_bytes())
+	}
+
+	/// Calculates the OTP value for an arbitrary moving factor, rather than the 8-byte
+	/// big-endian counter, such as a counter followed by a challenge for challenge-response
+	/// schemes like OCRA. The same truncation and checksum settings are used.
+	/// This does not read or modify the stored counter, and isn't reported to the audit hook.
+	///
+	/// ```rust
+	/// use otpee::Hotp;
+	/// use sha1::Sha1;
+	///
+	/// let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+	/// assert_eq!(hotp.code_for_factor(&0_u64.to_be_bytes()).unwrap(), 755224);
+	/// ```
+	pub fn code_for_factor(&mut self, factor: &[u8]) -> Result<Otp, OtpError> {
+		let code = truncated_code(
+			&mut self.mac,
+			factor,
+			self.config.truncation_offset,
+			self.config.digits,
+		)?;
+		if self.config.checksum {
+			let code = code * 10 + calculate_checksum(code, self.config.digits);
+			Ok(Otp::new(code, self.config.digits + 1))
+		} else {
+			Ok(Otp::new(code, self.config.digits))
+		}
+	}
+
+	/// Returns an iterator over the OTP values for each counter in the given range.
+	/// This does not read or modify the stored counter.
+	pub fn codes(&mut self, range: Range<u64>) -> impl Iterator<Item = Result<Otp, OtpError>> + '_ {
+		range.map(move |counter| self.code_at(counter))
+	}
+
+	/// Fills the slice with the OTP values for consecutive counters, starting at the given one.
+	/// This does not read or modify the stored counter.
+	///
+	/// The MAC is reused between codes, and nothing is allocated.
+	/// Returns [OtpError::CounterOverflow] if the counters would go past [u64::MAX].
+	pub fn codes_into(&mut self, start: u64, out: &mut [Otp]) -> Result<(), OtpError> {
+		if let Some(last) = (out.len() as u64).checked_sub(1) {
+			start.checked_add(last).ok_or(OtpError::CounterOverflow)?;
+		}
+		for (offset, slot) in out.iter_mut().enumerate() {
+			*slot = self.code_at(start + offset as u64)?;
+		}
+		Ok(())
+	}
+
+	/// Checks the code against the codes for each of the given counters, returning the first
+	/// counter that matched, if any.
+	///
+	/// Every counter is checked, even after a match is found, and the results are combined in
+	/// constant time, so the time taken doesn't reveal whether or where a match was found.
+	/// If checksums are enabled, the checksum digit is verified and stripped first;
+	/// as it only depends on the given code, an invalid checksum is rejected immediately.
+	pub(crate) fn find_counter<I: IntoIterator<Item = u64>>(
+		&mut self,
+		counters: I,
+		code: u64,
+	) -> Option<u64> {
+		let code = if self.config.checksum {
+			if code % 10 != calculate_checksum(code / 10, self.config.digits) {
+				return None;
+			}
+			code / 10
+		} else {
+			code
+		};
+		find_match(counters, |counter| {
+			self.truncated_code(counter)
+				.map(|expected| expected.ct_eq(&code))
+				.unwrap_or_else(|_| Choice::from(0))
+		})
+	}
+
+	/// Checks each of the codes against the codes for each of the given counters, like
+	/// [Hotp::find_counter], returning the index of the first code that matched,
+	/// along with the first counter it matched.
+	///
+	/// The code for each counter is calculated once, and compared against every code,
+	/// combining the results in constant time.
+	pub(crate) fn find_counter_any<I: IntoIterator<Item = u64>>(
+		&mut self,
+		counters: I,
+		codes: &[u64],
+	) -> Option<(usize, u64)> {
+		let mut found = Choice::from(0);
+		let mut index = 0_u64;
+		let mut matched = 0;
+		for counter in counters {
+			let Ok(expected) = self.truncated_code(counter) else {
+				continue;
+			};
+			for (i, &code) in codes.iter().enumerate() {
+				let is_match = if self.config.checksum {
+					let valid = code % 10 == calculate_checksum(code / 10, self.config.digits);
+					Choice::from(u8::from(valid)) & expected.ct_eq(&(code / 10))
+				} else {
+					expected.ct_eq(&code)
+				};
+				let i = i as u64;
+				let better = is_match & (!found | i.ct_lt(&index));
+				index.conditional_assign(&i, better);
+				matched.conditional_assign(&counter, better);
+				found |= is_match;
+			}
+		}
+		if bool::from(found) {
+			Some((index as usize, matched))
+		} else {
+			None
+		}
+	}
+
+	#[cfg(feature = "sha1")]
+	/// Returns the first of the given counters whose 31-bit value, as returned by
+	/// [Hotp::raw_code_at], matches according to the given function,
+	/// for validating non-standard codes built on top of HOTP.
+	///
+	/// Like [Hotp::find_counter], every counter is checked, so the function should compare in
+	/// constant time too.
+	pub(crate) fn find_counter_raw<I: IntoIterator<Item = u64>, F: FnMut(u32) -> Choice>(
+		&mut self,
+		counters: I,
+		mut is_match: F,
+	) -> Option<u64> {
+		find_match(counters, |counter| {
+			self.expected_raw_code(counter)
+				.map(&mut is_match)
+				.unwrap_or_else(|_| Choice::from(0))
+		})
+	}
+
+	/// Checks the digits of a code typed by a user against the codes for each of the given
+	/// counters, comparing them with the zero-padded form of each code, returning the first
+	/// counter that matched, if any. Like [Hotp::find_counter], this is done in constant time.
+	///
+	/// Digits of the wrong length are rejected immediately.
+	pub(crate) fn find_counter_digits<I: IntoIterator<Item = u64>>(
+		&mut self,
+		counters: I,
+		digits: &[u8],
+	) -> Option<u64> {
+		if digits.len() != self.config.digits + usize::from(self.config.checksum) {
+			return None;
+		}
+		let mut buf = [0; otp::MAX_LENGTH];
+		find_match(counters, |counter| {
+			self.expected_code(counter)
+				.map(|expected| expected.write_digits(&mut buf).ct_eq(digits))
+				.unwrap_or_else(|_| Choice::from(0))
+		})
+	}
+
+	/// Calculates the code for the given counter, without any checksum digit.
+	#[inline]
+	fn truncated_code(&mut self, counter: u64) -> Result<u64, OtpError> {
+		truncated_code(
+			&mut self.mac,
+			&counter.to_be_bytes(),
+			self.config.truncation_offset,
+			self.config.digits,
+		)
+	}
+
+	/// Returns the 31-bit value extracted from the MAC of the current counter, before it is
+	/// reduced to the number of digits, for building non-standard codes on top of HOTP.
+	/// This does NOT increment the counter!
+	///
+	/// Any [fixed truncation offset](Hotp::set_truncation_offset) is used.
+	/// See [dynamic_truncate] for the details.
+	pub fn raw_code(&mut self) -> Result<u32, OtpError> {
+		self.raw_code_at(self.counter)
+	}
+
+	/// Returns the 31-bit value extracted from the MAC of the given counter, like [Hotp::raw_code].
+	/// This does not read or modify the stored counter.
+	pub fn raw_code_at(&mut self, counter: u64) -> Result<u32, OtpError> {
+		let code = self.expected_raw_code(counter)?;
+		self.audit(AuditEvent::CodeGenerated { counter });
+		Ok(code)
+	}
+
+	/// Calculates the 31-bit value for the given counter, without reporting it to the audit hook.
+	#[inline]
+	fn expected_raw_code(&mut self, counter: u64) -> Result<u32, OtpError> {
+		raw_code(
+			&mut self.mac,
+			&counter.to_be_bytes(),
+			self.config.truncation_offset,
+		)
 	}
 
 	/// Calculates the OTP value using the current counter,
@@ -99,25 +763,1360 @@ impl<D: Digest + BlockSizeUser + FixedOutputReset> Hotp<D> {
 		self.increment_counter()?;
 		Ok(code)
 	}
+
+	/// Starts issuing a code for the current counter, which only advances the counter once
+	/// [committed](PendingCode::commit), such as after the code was successfully delivered.
+	///
+	/// The HOTP instance is borrowed until the pending code is committed or aborted,
+	/// so no other codes can be issued in the meantime.
+	///
+	/// ```rust
+	/// use otpee::Hotp;
+	/// use sha1::Sha1;
+	///
+	/// let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+	/// let mut pending = hotp.begin();
+	/// assert_eq!(pending.code().unwrap(), 755224);
+	/// // The code was sent, so it can't be issued again.
+	/// pending.commit().unwrap();
+	/// assert_eq!(hotp.counter(), 1);
+	/// ```
+	#[inline]
+	pub fn begin(&mut self) -> PendingCode<'_, D, M> {
+		PendingCode { hotp: self }
+	}
+
+	/// Validates the code against the current counter, as well as the next `window` counters,
+	/// as described in RFC 4226 section 7.4.
+	///
+	/// On success, the counter that matched is returned, and the stored counter and the
+	/// [minimum counter](Hotp::minimum_counter) are advanced to the one after it,
+	/// so that the same code can't be used again.
+	/// With [OverflowPolicy::Wrap], the window wraps around to 0 after [u64::MAX].
+	/// Otherwise, a code matching [u64::MAX] is never accepted, as the counter can't be
+	/// advanced past it, and saturating would allow the code to be used again.
+	/// Likewise, if the counter change callback fails, the code is not accepted.
+	///
+	/// Every counter in the window is checked, regardless of whether an earlier one matched,
+	/// so the time taken doesn't reveal which counter matched, or whether any did.
+	/// This means the cost grows linearly with the window, so it should be kept small.
+	pub fn validate(&mut self, code: u64, window: usize) -> Option<u64> {
+		let start = self.counter;
+		let counters = self.window_counters(window);
+		let matched = self.find_counter(counters, code);
+		self.finish_validation(start, matched).ok().flatten()
+	}
+
+	/// Validates the code like [Hotp::validate], returning a detailed result.
+	///
+	/// If the code doesn't match, but is the code for the counter just before the
+	/// [minimum counter](Hotp::minimum_counter), such as the last one accepted,
+	/// [Validation::Replayed] is returned.
+	pub fn validate_detailed(&mut self, code: u64, window: usize) -> Validation {
+		let start = self.counter;
+		let counters = self.window_counters(window);
+		let matched = self.find_counter(counters, code);
+		match self.finish_validation(start, matched) {
+			Ok(Some(step)) => Validation::Valid {
+				step,
+				drift: step.wrapping_sub(start) as i64,
+			},
+			Ok(None) => {
+				let previous = self.minimum_counter.checked_sub(1);
+				match self.find_counter(previous, code) {
+					Some(_) => Validation::Replayed,
+					None => Validation::Invalid,
+				}
+			}
+			Err(_) => Validation::Invalid,
+		}
+	}
+
+	/// Validates the code like [Hotp::validate], also reporting how far ahead
+	/// of the stored counter the code was, such as for logging counter drift.
+	///
+	/// A code that doesn't match isn't an error, and is reported as such in the outcome.
+	/// Errors are only returned if the code matched, but the counter couldn't be advanced,
+	/// such as when the counter change callback fails.
+	pub fn verify_window(&mut self, code: u64, window: usize) -> Result<VerifyOutcome, OtpError> {
+		let start = self.counter;
+		let counters = self.window_counters(window);
+		let matched = self.find_counter(counters, code);
+		let Some(matched) = self.finish_validation(start, matched)? else {
+			return Ok(VerifyOutcome {
+				matched_counter: None,
+				steps_ahead: None,
+				advanced: false,
+			});
+		};
+		let steps_ahead = matched.wrapping_sub(start);
+		Ok(VerifyOutcome {
+			matched_counter: Some(matched),
+			steps_ahead: Some(steps_ahead),
+			advanced: true,
+		})
+	}
+
+	/// Validates a code typed by a user, using the configured look-ahead window.
+	/// See [Hotp::set_window] and [Hotp::validate].
+	///
+	/// Any ASCII whitespace and dashes are ignored, so "123 456" and "123-456\n" are accepted.
+	/// The code must have exactly as many digits as generated codes, including any leading zeroes
+	/// and the checksum digit, and it is compared against the zero-padded form of each code.
+	pub fn validate_str(&mut self, code: &str) -> bool {
+		let start = self.counter;
+		let mut buf = [0; otp::MAX_LENGTH];
+		let matched = match otp::parse_digits(code, &mut buf) {
+			Some(digits) => {
+				let counters = self.window_counters(self.config.window);
+				self.find_counter_digits(counters, digits)
+			}
+			None => None,
+		};
+		matches!(self.finish_validation(start, matched), Ok(Some(_)))
+	}
+
+	/// Accepts the matched counter, if any, reporting the outcome to the audit hook.
+	fn finish_validation(
+		&mut self,
+		start: u64,
+		matched: Option<u64>,
+	) -> Result<Option<u64>, OtpError> {
+		let result = match matched {
+			Some(matched) => self.accept(matched).map(|_| Some(matched)),
+			None => Ok(None),
+		};
+		match result {
+			Ok(Some(counter)) => self.audit(AuditEvent::ValidationSucceeded {
+				counter,
+				drift: counter.wrapping_sub(start) as i64,
+			}),
+			_ => self.audit(AuditEvent::ValidationFailed),
+		}
+		result
+	}
+
+	/// Advances the counter, and the minimum counter, past an accepted counter.
+	fn accept(&mut self, matched: u64) -> Result<u64, OtpError> {
+		let counter = self.advance_past(matched)?;
+		self.minimum_counter = counter;
+		Ok(counter)
+	}
+
+	/// Returns the counters checked when validating with the given look-ahead window,
+	/// skipping any below the minimum counter.
+	///
+	/// With [OverflowPolicy::Wrap], counters are compared by how far they are from the current
+	/// one, so that the counters after 0 are still accepted in a window that wraps around.
+	/// The minimum counter is only considered ahead of the current one if it is in the window.
+	fn window_counters(&self, window: usize) -> impl Iterator<Item = u64> {
+		let start = self.counter;
+		let minimum = self.minimum_counter;
+		let wrap = self.config.overflow_policy == OverflowPolicy::Wrap;
+		let minimum_ahead = minimum.wrapping_sub(start);
+		(0..=window as u64)
+			.map_while(move |offset| {
+				if wrap {
+					Some(start.wrapping_add(offset))
+				} else {
+					start
+						.checked_add(offset)
+						.filter(|&counter| counter != u64::MAX)
+				}
+			})
+			.filter(move |&counter| {
+				if wrap {
+					minimum_ahead > window as u64 || counter.wrapping_sub(start) >= minimum_ahead
+				} else {
+					counter >= minimum
+				}
+			})
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm> Hotp<D> {
+	/// Creates a new HOTP instance from an `otpauth://hotp` provisioning URI.
+	/// See [ParsedHotp](crate::uri::ParsedHotp) for parsing URIs without knowing the algorithm first.
+	///
+	/// Returns [OtpError::UriParse] if the URI is invalid, or names a different algorithm.
+	pub fn from_uri(uri: &str) -> Result<Self, OtpError> {
+		uri::ParsedHotp::parse(uri)?.build()
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, M: OtpMac> Hotp<D, M> {
+	/// Creates an `otpauth://hotp` provisioning URI for this HOTP instance, such as for
+	/// showing it as a QR code. The secret has to be given, as it isn't retained.
+	///
+	/// The label, usually the account name, is prefixed with the issuer if one is given,
+	/// and both are percent-encoded. Checksum digits aren't supported by authenticator apps,
+	/// so they aren't included.
+	///
+	/// This requires the hash function to implement [HashAlgorithm], such as `sha1::Sha1`
+	/// with the `sha1` feature enabled.
+	///
+	/// ```rust
+	/// # #[cfg(feature = "sha1")]
+	/// # {
+	/// use otpee::Hotp;
+	/// use sha1::Sha1;
+	///
+	/// let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+	/// assert_eq!(
+	///     hotp.to_uri(b"12345678901234567890", "alice@example.com", Some("Example Co")),
+	///     "otpauth://hotp/Example%20Co:alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+	///      &issuer=Example%20Co&algorithm=SHA1&digits=6&counter=0"
+	/// );
+	/// # }
+	/// ```
+	pub fn to_uri(&self, secret: &[u8], label: &str, issuer: Option<&str>) -> String {
+		uri::build(&uri::UriParameters {
+			kind: "hotp",
+			secret,
+			label,
+			issuer,
+			algorithm: D::ALGORITHM,
+			digits: self.config.digits,
+			counter: Some(self.counter),
+			period: None,
+		})
+	}
+}
+
+/// The outcome of [Hotp::verify_window].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VerifyOutcome {
+	/// The counter the code matched, if it matched.
+	pub matched_counter: Option<u64>,
+	/// How many counters ahead of the stored counter the code matched, if it matched.
+	/// This is 0 if it matched the stored counter itself.
+	pub steps_ahead: Option<u64>,
+	/// Whether the stored counter was advanced past the matched counter.
+	pub advanced: bool,
+}
+
+impl VerifyOutcome {
+	/// Returns whether the code was accepted.
+	#[inline]
+	pub fn is_valid(&self) -> bool {
+		self.matched_counter.is_some()
+	}
+}
+
+/// A code being issued by a [Hotp] instance, created by [Hotp::begin].
+///
+/// Dropping this without committing leaves the counter untouched, like [PendingCode::abort].
+#[derive(Debug)]
+#[must_use = "the counter is only advanced once the pending code is committed"]
+pub struct PendingCode<'a, D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac = Hmac<D>> {
+	hotp: &'a mut Hotp<D, M>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac> PendingCode<'_, D, M> {
+	/// Returns the counter the code is being issued for.
+	#[inline]
+	pub fn counter(&self) -> u64 {
+		self.hotp.counter
+	}
+
+	/// Calculates the OTP value being issued.
+	#[inline]
+	pub fn code(&mut self) -> Result<Otp, OtpError> {
+		self.hotp.code()
+	}
+
+	/// Advances the counter past the issued code, returning the new counter value.
+	/// See [Hotp::increment_counter].
+	#[inline]
+	pub fn commit(self) -> Result<u64, OtpError> {
+		self.hotp.increment_counter()
+	}
+
+	/// Abandons the issued code, leaving the counter untouched.
+	#[inline]
+	pub fn abort(self) {}
+}
+
+/// Validation uses the look-ahead window configured with [Hotp::set_window].
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac> OtpGenerator for Hotp<D, M> {
+	#[inline]
+	fn current_code(&mut self) -> Result<Otp, OtpError> {
+		self.code()
+	}
+
+	#[inline]
+	fn validate(&mut self, code: u64) -> bool {
+		Hotp::validate(self, code, self.config.window).is_some()
+	}
+
+	#[inline]
+	fn digits(&self) -> usize {
+		Hotp::digits(self)
+	}
+}
+
+/// A builder for [Hotp] instances.
+///
+/// ```rust
+/// use otpee::Hotp;
+/// use sha1::Sha1;
+///
+/// let mut hotp = Hotp::<Sha1>::builder()
+///     .digits(8)
+///     .counter(1)
+///     .build(b"12345678901234567890")
+///     .unwrap();
+/// assert_eq!(hotp.code().unwrap(), 94287082);
+/// ```
+pub struct HotpBuilder<D> {
+	config: HotpConfig,
+	counter: u64,
+	enforce_minimum_key_length: bool,
+	_digest: PhantomData<fn() -> D>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> HotpBuilder<D> {
+	/// Creates a new builder, with the default of 6 digits and a counter of 0.
+	pub fn new() -> Self {
+		Self {
+			config: HotpConfig::default(),
+			counter: 0,
+			enforce_minimum_key_length: false,
+			_digest: PhantomData,
+		}
+	}
+
+	/// Sets the number of digits in generated codes, which must be between 1 and [MAX_DIGITS].
+	#[inline]
+	pub fn digits(mut self, digits: usize) -> Self {
+		self.config.digits = digits;
+		self
+	}
+
+	/// Sets the initial counter value.
+	#[inline]
+	pub fn counter(mut self, counter: u64) -> Self {
+		self.counter = counter;
+		self
+	}
+
+	/// Sets whether a checksum digit is appended to generated codes.
+	/// See [Hotp::set_checksum].
+	#[inline]
+	pub fn checksum(mut self, checksum: bool) -> Self {
+		self.config.checksum = checksum;
+		self
+	}
+
+	/// Sets a fixed truncation offset.
+	/// See [Hotp::set_truncation_offset].
+	#[inline]
+	pub fn truncation_offset(mut self, offset: Option<u8>) -> Self {
+		self.config.truncation_offset = offset;
+		self
+	}
+
+	/// Sets the look-ahead window used when validating through [OtpGenerator::validate].
+	#[inline]
+	pub fn window(mut self, window: usize) -> Self {
+		self.config.window = window;
+		self
+	}
+
+	/// Sets the policy followed when the counter would be advanced past [u64::MAX].
+	/// Defaults to [OverflowPolicy::Error].
+	#[inline]
+	pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+		self.config.overflow_policy = policy;
+		self
+	}
+
+	/// Sets whether secrets shorter than [MIN_KEY_LENGTH] should be rejected.
+	/// This is off by default, for interoperability with providers that use shorter secrets.
+	#[inline]
+	pub fn enforce_minimum_key_length(mut self, enforce: bool) -> Self {
+		self.enforce_minimum_key_length = enforce;
+		self
+	}
+
+	/// Builds the HOTP instance, using the given bytes as the secret.
+	pub fn build<A: AsRef<[u8]>>(self, key: A) -> Result<Hotp<D>, OtpError> {
+		let key = key.as_ref();
+		if self.enforce_minimum_key_length && key.len() < MIN_KEY_LENGTH {
+			return Err(OtpError::KeyTooShort {
+				got: key.len(),
+				minimum: MIN_KEY_LENGTH,
+			});
+		}
+		if <D as Digest>::output_size() < MIN_HASH_OUTPUT_SIZE {
+			return Err(OtpError::HashTooShort);
+		}
+		self.build_with_mac(Hmac::new_from_slice(key)?)
+	}
+
+	/// Builds the HOTP instance, using a hasher given by the caller.
+	pub fn build_with_hasher(self, hasher: SimpleHmac<D>) -> Result<Hotp<D>, OtpError> {
+		if <D as Digest>::output_size() < MIN_HASH_OUTPUT_SIZE {
+			return Err(OtpError::HashTooShort);
+		}
+		self.build_with_mac(Hmac::from(hasher))
+	}
+
+	/// Builds the HOTP instance, using a MAC backend given by the caller.
+	/// See [Hotp::with_mac].
+	///
+	/// Unlike the other constructors, MACs shorter than [MIN_HASH_OUTPUT_SIZE] are allowed,
+	/// as long as they work with the truncation offset. Otherwise, calculating a code fails
+	/// with [OtpError::HashTooShort], rather than panicking.
+	pub fn build_with_mac<M: OtpMac>(self, mac: M) -> Result<Hotp<D, M>, OtpError> {
+		if !(1..=MAX_DIGITS).contains(&self.config.digits) {
+			return Err(OtpError::InvalidDigits {
+				got: self.config.digits,
+			});
+		}
+		let mut hotp = Hotp {
+			mac,
+			counter: self.counter,
+			config: HotpConfig {
+				truncation_offset: None,
+				..self.config
+			},
+			minimum_counter: 0,
+			#[cfg(feature = "alloc")]
+			counter_hook: None,
+			#[cfg(feature = "alloc")]
+			audit_hook: None,
+			_digest: PhantomData,
+		};
+		hotp.set_truncation_offset(self.config.truncation_offset)?;
+		Ok(hotp)
+	}
+}
+
+impl<D> Debug for HotpBuilder<D> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HotpBuilder")
+			.field("digits", &self.config.digits)
+			.field("counter", &self.counter)
+			.field("checksum", &self.config.checksum)
+			.field("truncation_offset", &self.config.truncation_offset)
+			.field("window", &self.config.window)
+			.field("overflow_policy", &self.config.overflow_policy)
+			.field(
+				"enforce_minimum_key_length",
+				&self.enforce_minimum_key_length,
+			)
+			.finish()
+	}
+}
+
+impl<D> Clone for HotpBuilder<D> {
+	fn clone(&self) -> Self {
+		Self {
+			config: self.config,
+			counter: self.counter,
+			enforce_minimum_key_length: self.enforce_minimum_key_length,
+			_digest: PhantomData,
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> Default for HotpBuilder<D> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Calculates the HOTP code for the given counter, without keeping a [Hotp] instance around.
+///
+/// ```rust
+/// use sha1::Sha1;
+///
+/// let code = otpee::hotp::generate::<Sha1>(b"12345678901234567890", 1, 6).unwrap();
+/// assert_eq!(code, 287082);
+/// ```
+pub fn generate<D: Digest + BlockSizeUser + FixedOutputReset>(
+	secret: &[u8],
+	counter: u64,
+	digits: usize,
+) -> Result<Otp, OtpError> {
+	Hotp::<D>::new(secret, digits)?.code_at(counter)
+}
+
+/// Validates the code against the given counter and the `window` counters after it,
+/// like [Hotp::validate], without keeping a [Hotp] instance around.
+///
+/// Returns the counter that matched, which the stored counter should be advanced past.
+/// Every counter in the window is checked, so the time taken doesn't reveal which one matched.
+///
+/// ```rust
+/// use sha1::Sha1;
+///
+/// let secret = b"12345678901234567890";
+/// assert_eq!(otpee::hotp::verify::<Sha1>(secret, 969429, 1, 6, 2), Some(3));
+/// assert_eq!(otpee::hotp::verify::<Sha1>(secret, 969429, 4, 6, 2), None);
+/// ```
+pub fn verify<D: Digest + BlockSizeUser + FixedOutputReset>(
+	secret: &[u8],
+	code: u64,
+	counter: u64,
+	digits: usize,
+	window: usize,
+) -> Option<u64> {
+	let mut hotp = Hotp::<D>::new(secret, digits).ok()?;
+	hotp.set_counter(counter).ok()?;
+	hotp.validate(code, window)
+}
+
+/// Extracts the 31-bit value from the digest, as described in RFC 4226 section 5.3,
+/// before it is reduced to the number of digits.
+///
+/// The offset the 4 bytes are read from is the lowest 4 bits of the last byte of the digest,
+/// so it must be at least 19 bytes long, or [OtpError::HashTooShort] is returned.
+///
+/// ```rust
+/// use otpee::hotp::dynamic_truncate;
+///
+/// // The HMAC-SHA-1 digest for counter 0 from RFC 4226 appendix D.
+/// let digest = [
+///     0xcc, 0x93, 0xcf, 0x18, 0x50, 0x8d, 0x94, 0x93, 0x4c, 0x64, 0xb6, 0x5d, 0x8b, 0xa7, 0x66,
+///     0x7f, 0xb7, 0xcd, 0xe4, 0xb0,
+/// ];
+/// assert_eq!(dynamic_truncate(&digest).unwrap(), 0x4c93cf18);
+/// ```
+pub fn dynamic_truncate(digest: &[u8]) -> Result<u32, OtpError> {
+	truncate(digest, None)
+}
+
+/// Extracts the 31-bit value from the digest, reading from the given offset,
+/// or the dynamic one if none is given.
+fn truncate(digest: &[u8], truncation_offset: Option<u8>) -> Result<u32, OtpError> {
+	// The offset is the lowest 4 bits of the last byte of the MAC.
+	let offset = match truncation_offset {
+		Some(offset) => offset as usize,
+		None => (*digest.last().ok_or(OtpError::HashTooShort)? & 0xF) as usize,
+	};
+	// Now, to get our 4 bytes and turn it into a u32;
+	let mut code = [0u8; 4];
+	code.copy_from_slice(
+		digest
+			.get(offset..offset + 4)
+			.ok_or(OtpError::HashTooShort)?,
+	);
+	Ok(u32::from_be_bytes(code) & 0x7fff_ffff)
+}
+
+/// Calculates the MAC of the moving factor, usually the big-endian counter,
+/// and extracts the 31-bit value from it.
+pub(crate) fn raw_code<M: OtpMac>(
+	mac: &mut M,
+	factor: &[u8],
+	truncation_offset: Option<u8>,
+) -> Result<u32, OtpError> {
+	let mut digest = [0u8; MAX_OUTPUT_SIZE];
+	let len = mac.compute(factor, &mut digest)?;
+	let digest = digest.get(..len).ok_or(OtpError::MacFailed)?;
+	truncate(digest, truncation_offset)
+}
+
+/// Calculates the code for the given counter with the given MAC, without any checksum digit.
+pub(crate) fn truncated_code<M: OtpMac>(
+	mac: &mut M,
+	factor: &[u8],
+	truncation_offset: Option<u8>,
+	digits: usize,
+) -> Result<u64, OtpError> {
+	let binary = u64::from(raw_code(mac, factor, truncation_offset)?);
+	// And here we go calculating the OTP value.
+	// This is done in 64 bits, as 10 to the power of 10 doesn't fit in a u32.
+	Ok(binary % 10_u64.pow(digits as u32))
+}
+
+/// Returns the first of the given counters that matches, if any.
+///
+/// Every counter is checked, even after a match is found, and the results are combined in
+/// constant time, so the time taken doesn't reveal whether or where a match was found.
+pub(crate) fn find_match<I: IntoIterator<Item = u64>, F: FnMut(u64) -> Choice>(
+	counters: I,
+	mut is_match: F,
+) -> Option<u64> {
+	let mut found = Choice::from(0);
+	let mut matched = 0;
+	for counter in counters {
+		let is_match = is_match(counter);
+		matched.conditional_assign(&counter, is_match & !found);
+		found |= is_match;
+	}
+	if bool::from(found) {
+		Some(matched)
+	} else {
+		None
+	}
+}
+
+/// Calculates the checksum digit of the given code, as described in RFC 4226 appendix E.1.
+fn calculate_checksum(mut code: u64, digits: usize) -> u64 {
+	const DOUBLE_DIGITS: [u64; 10] = [0, 2, 4, 6, 8, 1, 3, 5, 7, 9];
+	let mut double_digit = true;
+	let mut total = 0;
+	for _ in 0..digits {
+		let digit = code % 10;
+		code /= 10;
+		total += if double_digit {
+			DOUBLE_DIGITS[digit as usize]
+		} else {
+			digit
+		};
+		double_digit = !double_digit;
+	}
+	(10 - total % 10) % 10
 }
 
 #[cfg(test)]
 mod tests {
-	use super::Hotp;
+	use super::{dynamic_truncate, Hotp, OverflowPolicy};
+	use crate::{
+		error::OtpError,
+		mac::{Hmac, OtpMac},
+		validation::Validation,
+		vectors::HOTP_SHA1,
+	};
+	use md5::Md5;
 	use sha1::Sha1;
+	use sha2::Sha512;
 
 	#[test]
 	fn hotp_sha1() {
 		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		for vector in HOTP_SHA1 {
+			assert_eq!(*hotp.code_increment().unwrap(), vector.code);
+		}
+	}
+
+	#[test]
+	fn hotp_one_shot() {
+		for vector in HOTP_SHA1 {
+			let (secret, counter) = (vector.secret, vector.moving_factor);
+			assert_eq!(
+				*super::generate::<Sha1>(secret, counter, vector.digits).unwrap(),
+				vector.code
+			);
+			let code = vector.code;
+			assert_eq!(
+				super::verify::<Sha1>(secret, code, counter, 6, 0),
+				Some(counter)
+			);
+			assert_eq!(
+				super::verify::<Sha1>(secret, code, counter.saturating_sub(2), 6, 2),
+				Some(counter)
+			);
+			assert_eq!(super::verify::<Sha1>(secret, code, counter + 1, 6, 3), None);
+		}
+		assert_eq!(
+			super::generate::<Sha1>(b"12345678901234567890", 0, 11),
+			Err(OtpError::InvalidDigits { got: 11 })
+		);
+		assert_eq!(
+			super::verify::<Sha1>(b"12345678901234567890", 755224, 0, 0, 0),
+			None
+		);
+	}
+
+	#[test]
+	fn hotp_codes_into() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut codes = [hotp.code().unwrap(); 10];
+		hotp.codes_into(0, &mut codes).unwrap();
+		for (counter, code) in (0..).zip(codes) {
+			assert_eq!(code, hotp.code_at(counter).unwrap());
+		}
+		assert_eq!(codes[9], 520489);
+		assert_eq!(hotp.counter(), 0);
+		assert_eq!(
+			hotp.codes_into(u64::MAX - 1, &mut codes),
+			Err(OtpError::CounterOverflow)
+		);
+		assert_eq!(hotp.codes_into(u64::MAX - 1, &mut codes[..2]), Ok(()));
+		assert_eq!(hotp.codes_into(u64::MAX, &mut []), Ok(()));
+	}
+
+	#[test]
+	fn hotp_builder_getters() {
+		let mut hotp = Hotp::<Sha1>::builder()
+			.digits(8)
+			.counter(7)
+			.checksum(true)
+			.truncation_offset(Some(3))
+			.window(4)
+			.overflow_policy(OverflowPolicy::Saturate)
+			.build(b"12345678901234567890")
+			.unwrap();
+		assert_eq!(hotp.digits(), 8);
+		assert_eq!(hotp.counter(), 7);
+		assert!(hotp.checksum());
+		assert_eq!(hotp.truncation_offset(), Some(3));
+		assert_eq!(hotp.window(), 4);
+		assert_eq!(hotp.overflow_policy(), OverflowPolicy::Saturate);
+		hotp.reset().unwrap();
+		assert_eq!(hotp.counter(), 0);
+		assert_eq!(hotp.digits(), 8);
+	}
+
+	#[test]
+	fn hotp_code_for_factor() {
+		use hmac::{Mac, SimpleHmac};
+
+		let key = b"12345678901234567890";
+		let mut hotp = Hotp::<Sha1>::new(key, 6).unwrap();
+		for counter in 0..10_u64 {
+			assert_eq!(
+				hotp.code_for_factor(&counter.to_be_bytes()).unwrap(),
+				hotp.code_at(counter).unwrap()
+			);
+		}
+		// A 16-byte factor, such as a counter followed by a challenge.
+		let factor = *b"\0\0\0\0\0\0\0\x05challnge";
+		let mut mac = <SimpleHmac<Sha1> as Mac>::new_from_slice(key).unwrap();
+		mac.update(&factor);
+		let expected = dynamic_truncate(&mac.finalize().into_bytes()).unwrap() % 1_000_000;
+		assert_eq!(*hotp.code_for_factor(&factor).unwrap(), u64::from(expected));
+		// The OCRA-1:HOTP-SHA1-6:QN08 vectors from RFC 6287 appendix C.1,
+		// where the factor is the suite, a zero byte, and the padded 128-byte question.
+		let mut factor = [0; 24 + 128];
+		factor[..23].copy_from_slice(b"OCRA-1:HOTP-SHA1-6:QN08");
+		assert_eq!(hotp.code_for_factor(&factor).unwrap(), 237653);
+		// 11111111 is 0xA98AC7.
+		factor[24..27].copy_from_slice(&[0xa9, 0x8a, 0xc7]);
+		assert_eq!(hotp.code_for_factor(&factor).unwrap(), 243178);
+		assert_eq!(hotp.counter(), 0);
+	}
+
+	#[test]
+	fn hotp_raw_code() {
+		// The truncated values from RFC 4226 appendix D.
+		let expected = [
+			0x4c93cf18, 0x41397eea, 0x082fef30, 0x66ef7655, 0x61c5938a, 0x33c083d4, 0x7256c032,
+			0x04e5b397, 0x2823443f, 0x2679dc69,
+		];
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		for expected in expected {
+			let raw = hotp.raw_code().unwrap();
+			assert_eq!(raw, expected);
+			assert_eq!(*hotp.code_increment().unwrap(), u64::from(raw) % 1_000_000);
+		}
+		assert_eq!(dynamic_truncate(&[0xff; 18]), Err(OtpError::HashTooShort));
+		assert_eq!(dynamic_truncate(&[]), Err(OtpError::HashTooShort));
+	}
+
+	#[test]
+	fn hotp_validate_window() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(2).unwrap();
+		assert_eq!(hotp.validate(254676, 3), Some(5));
+		assert_eq!(hotp.counter(), 6);
+		// The same code can't be used twice.
+		assert_eq!(hotp.validate(254676, 3), None);
+		assert_eq!(hotp.counter(), 6);
+		// A window of 0 only accepts the current counter.
+		assert_eq!(hotp.validate(162583, 0), None);
+		assert_eq!(hotp.validate(287922, 0), Some(6));
+		assert_eq!(hotp.counter(), 7);
+	}
+
+	#[test]
+	fn hotp_validate_overflow() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(u64::MAX - 1).unwrap();
+		let code = *hotp.code().unwrap();
+		assert_eq!(hotp.validate(code, usize::MAX), Some(u64::MAX - 1));
+		assert_eq!(hotp.counter(), u64::MAX);
+		let code = *hotp.code().unwrap();
+		assert_eq!(hotp.validate(code, usize::MAX), None);
+		assert_eq!(hotp.counter(), u64::MAX);
+	}
+
+	#[test]
+	fn hotp_increment_counter_by() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		assert_eq!(hotp.increment_counter_by(0), Ok(0));
+		assert_eq!(hotp.increment_counter_by(9), Ok(9));
+		assert_eq!(hotp.code().unwrap(), 520489);
+		assert_eq!(hotp.increment_counter_by(u64::MAX - 10), Ok(u64::MAX - 1));
+		assert_eq!(hotp.increment_counter_by(2), Err(OtpError::CounterOverflow));
+		assert_eq!(hotp.counter(), u64::MAX - 1);
+		assert_eq!(hotp.increment_counter_by(1), Ok(u64::MAX));
+		assert_eq!(hotp.increment_counter_by(0), Ok(u64::MAX));
+
+		assert_eq!(hotp.advance_past(4), Ok(5));
+		assert_eq!(hotp.code().unwrap(), 254676);
+		assert_eq!(hotp.advance_past(u64::MAX), Err(OtpError::CounterOverflow));
+		assert_eq!(hotp.counter(), 5);
+	}
+
+	#[test]
+	fn hotp_overflow_policy() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(u64::MAX).unwrap();
+		assert_eq!(hotp.increment_counter(), Err(OtpError::CounterOverflow));
+		assert_eq!(hotp.code_increment(), Err(OtpError::CounterOverflow));
+		assert_eq!(hotp.counter(), u64::MAX);
+
+		hotp.set_overflow_policy(OverflowPolicy::Saturate);
+		assert_eq!(hotp.increment_counter(), Ok(u64::MAX));
+		assert!(hotp.code_increment().is_ok());
+		assert_eq!(hotp.counter(), u64::MAX);
+		let code = *hotp.code().unwrap();
+		assert_eq!(hotp.validate(code, 1), None);
+
+		hotp.set_overflow_policy(OverflowPolicy::Wrap);
+		assert!(hotp.code_increment().is_ok());
+		assert_eq!(hotp.counter(), 0);
+		assert_eq!(hotp.code().unwrap(), 755224);
+		// The window wraps around too.
+		hotp.set_counter(u64::MAX).unwrap();
+		assert_eq!(hotp.validate(287082, 2), Some(1));
+		assert_eq!(hotp.counter(), 2);
+		let mut hotp = Hotp::<Sha1>::builder()
+			.overflow_policy(OverflowPolicy::Wrap)
+			.counter(u64::MAX)
+			.build(b"12345678901234567890")
+			.unwrap();
+		let code = *hotp.code().unwrap();
+		assert_eq!(hotp.validate(code, 0), Some(u64::MAX));
+		assert_eq!(hotp.counter(), 0);
+
+		// After accepting a code near the end, the window still wraps around,
+		// even though the minimum counter is now past 0.
+		hotp.set_counter(u64::MAX - 3).unwrap();
+		let near_end = *hotp.code_at(u64::MAX - 2).unwrap();
+		assert_eq!(hotp.validate(near_end, 1), Some(u64::MAX - 2));
+		assert_eq!(hotp.minimum_counter(), u64::MAX - 1);
+		let after_zero = *hotp.code_at(1).unwrap();
+		assert_eq!(hotp.validate(after_zero, 5), Some(1));
+		assert_eq!(hotp.counter(), 2);
+		assert_eq!(hotp.validate(near_end, 5), None);
+	}
+
+	/// A MAC that ignores the counter and always returns the same value.
+	struct FixedMac(&'static [u8]);
+
+	impl OtpMac for FixedMac {
+		fn output_size(&self) -> usize {
+			self.0.len()
+		}
+
+		fn compute(&mut self, _: &[u8], out: &mut [u8]) -> Result<usize, OtpError> {
+			out[..self.0.len()].copy_from_slice(self.0);
+			Ok(self.0.len())
+		}
+	}
+
+	#[test]
+	fn hotp_custom_mac() {
+		// The example from RFC 4226 section 5.4: the offset is 0xa,
+		// giving 0x50ef7f19, which is 1357872921.
+		let mut hotp = Hotp::<Sha1, _>::with_mac(
+			FixedMac(&[
+				0x1f, 0x86, 0x98, 0x69, 0x0e, 0x02, 0xca, 0x16, 0x61, 0x85, 0x50, 0xef, 0x7f, 0x19,
+				0xda, 0x8e, 0x94, 0x5b, 0x55, 0x5a,
+			]),
+			6,
+		)
+		.unwrap();
+		assert_eq!(hotp.code().unwrap(), 872921);
+		// A MAC shorter than any digest: the offset is 3, giving 0x00123456, which is 1193046.
+		let mut hotp =
+			Hotp::<Sha1, _>::with_mac(FixedMac(&[0, 0, 0, 0, 0x12, 0x34, 0x56, 0x03]), 6).unwrap();
+		assert_eq!(hotp.code().unwrap(), 193046);
+		assert_eq!(hotp.validate(193046, 0), Some(0));
+		assert_eq!(
+			hotp.set_truncation_offset(Some(4)),
+			Err(OtpError::InvalidLength)
+		);
+		// A fixed offset of 2 gives 0x00001234, which is 4660.
+		hotp.set_truncation_offset(Some(2)).unwrap();
+		assert_eq!(hotp.code().unwrap(), 4660);
+		// An offset pointing past the end of the MAC is an error, not a panic.
+		let mut hotp = Hotp::<Sha1, _>::with_mac(FixedMac(&[0, 0, 0, 0x0f]), 6).unwrap();
+		assert_eq!(hotp.code(), Err(OtpError::HashTooShort));
+	}
+
+	#[test]
+	fn hotp_validate_str() {
+		let mut hotp = Hotp::<Sha1>::builder()
+			.window(2)
+			.build(b"12345678901234567890")
+			.unwrap();
+		assert!(hotp.validate_str("755 224"));
+		assert!(hotp.validate_str("287-082\n"));
+		assert!(!hotp.validate_str("287082"));
+		assert!(!hotp.validate_str("35915"));
+		assert!(!hotp.validate_str("0359152"));
+		assert!(!hotp.validate_str("359152a"));
+		assert!(!hotp.validate_str("359.152"));
+		assert!(hotp.validate_str(" 359152 "));
+		assert_eq!(hotp.counter(), 3);
+
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_counter(1111111109 / 30).unwrap();
+		// The code is 07081804, so it has to be given with the leading zero.
+		assert!(!hotp.validate_str("7081804"));
+		assert!(hotp.validate_str("0708 1804"));
+		hotp.set_checksum(true);
+		let otp = hotp.code().unwrap();
+		assert!(hotp.validate_str(&otp.to_string()));
+	}
+
+	#[test]
+	fn hotp_pending_code() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		{
+			let mut pending = hotp.begin();
+			assert_eq!(pending.counter(), 0);
+			assert_eq!(pending.code().unwrap(), 755224);
+		}
+		assert_eq!(hotp.counter(), 0);
+		let mut pending = hotp.begin();
+		assert_eq!(pending.code().unwrap(), 755224);
+		pending.abort();
+		assert_eq!(hotp.counter(), 0);
+		let mut pending = hotp.begin();
+		assert_eq!(pending.code().unwrap(), 755224);
+		assert_eq!(pending.commit(), Ok(1));
+		assert_eq!(hotp.counter(), 1);
+		assert_eq!(hotp.begin().code().unwrap(), 287082);
+	}
+
+	#[test]
+	fn hotp_short_digest() {
+		assert_eq!(
+			Hotp::<Md5>::new(b"12345678901234567890", 6).err(),
+			Some(OtpError::HashTooShort)
+		);
+		// Through a custom MAC, a 16 byte digest is allowed, but codes whose dynamic offset
+		// points past its end fail instead of panicking.
+		let mac = Hmac::<Md5>::new_from_slice(b"12345678901234567890").unwrap();
+		let mut hotp = Hotp::<Md5, _>::with_mac(mac, 6).unwrap();
+		let mut too_short = 0;
+		for counter in 0..64 {
+			match hotp.code_at(counter) {
+				Ok(_) => {}
+				Err(err) => {
+					assert_eq!(err, OtpError::HashTooShort);
+					too_short += 1;
+				}
+			}
+		}
+		assert!(too_short > 0);
+		assert_eq!(hotp.validate(0, 64), None);
+		// A fixed offset that fits in the digest always works.
+		hotp.set_truncation_offset(Some(11)).unwrap();
+		assert!(hotp.codes(0..64).all(|code| code.is_ok()));
+	}
+
+	#[test]
+	fn hotp_replay_protection() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		assert_eq!(hotp.validate(359152, 3), Some(2));
+		assert_eq!(hotp.minimum_counter(), 3);
+		assert_eq!(hotp.validate(359152, 3), None);
+		// Setting the counter back doesn't allow the code to be used again.
+		assert_eq!(
+			hotp.set_counter(2),
+			Err(OtpError::CounterBelowMinimum { minimum: 3 })
+		);
+		assert!(!hotp.validate_str("359152"));
+		hotp.set_minimum_counter(5);
+		assert_eq!(hotp.validate(969429, 3), None);
+		assert_eq!(hotp.validate(254676, 3), Some(5));
+		// Unless it's forced.
+		hotp.force_set_counter(2).unwrap();
+		assert_eq!(hotp.minimum_counter(), 2);
+		assert_eq!(hotp.validate(359152, 0), Some(2));
+		hotp.reset().unwrap();
+		assert_eq!((hotp.counter(), hotp.minimum_counter()), (0, 0));
+	}
+
+	#[test]
+	fn hotp_verify_window() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(2).unwrap();
+		let outcome = hotp.verify_window(254676, 5).unwrap();
+		assert!(outcome.is_valid());
+		assert_eq!(outcome.matched_counter, Some(5));
+		assert_eq!(outcome.steps_ahead, Some(3));
+		assert!(outcome.advanced);
+		assert_eq!(hotp.counter(), 6);
+		let outcome = hotp.verify_window(287922, 0).unwrap();
+		assert_eq!(outcome.steps_ahead, Some(0));
+		let outcome = hotp.verify_window(287922, 3).unwrap();
+		assert!(!outcome.is_valid());
+		assert_eq!(outcome.steps_ahead, None);
+		assert!(!outcome.advanced);
+		assert_eq!(hotp.counter(), 7);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn hotp_audit_hook() {
+		use crate::audit::AuditEvent;
+		use std::sync::{Arc, Mutex};
+
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let sink = events.clone();
+		hotp.set_audit_hook(move |event| sink.lock().unwrap().push(event));
+		assert_eq!(hotp.code_increment().unwrap(), 755224);
+		assert!(hotp.validate(254676, 5).is_some());
+		assert!(hotp.validate(254676, 5).is_none());
+		assert!(!hotp.validate_str("not a code"));
+		assert_eq!(
+			*events.lock().unwrap(),
+			[
+				AuditEvent::CodeGenerated { counter: 0 },
+				AuditEvent::ValidationSucceeded {
+					counter: 5,
+					drift: 4
+				},
+				AuditEvent::ValidationFailed,
+				AuditEvent::ValidationFailed,
+			]
+		);
+	}
+
+	#[test]
+	fn hotp_validate_detailed() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(2).unwrap();
+		assert_eq!(hotp.validate_detailed(338314, 1), Validation::Invalid);
+		assert_eq!(
+			hotp.validate_detailed(338314, 2),
+			Validation::Valid { step: 4, drift: 2 }
+		);
+		assert_eq!(hotp.validate_detailed(338314, 2), Validation::Replayed);
+		assert_eq!(hotp.validate_detailed(969429, 2), Validation::Invalid);
+		assert_eq!(
+			hotp.validate_detailed(254676, 0),
+			Validation::Valid { step: 5, drift: 0 }
+		);
+	}
+
+	#[cfg(all(feature = "sha1", feature = "sha2"))]
+	#[test]
+	fn hotp_to_uri() {
+		use crate::Secret;
+		use sha2::Sha256;
+
+		// The example from the Key Uri Format documentation, with the HOTP parameters.
+		let secret = Secret::from_base32("HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ").unwrap();
+		let hotp = Hotp::<Sha1>::new(&secret, 6).unwrap();
+		assert_eq!(
+			hotp.to_uri(secret.as_bytes(), "john.doe@email.com", Some("ACME Co")),
+			"otpauth://hotp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+			 &issuer=ACME%20Co&algorithm=SHA1&digits=6&counter=0"
+		);
+		let mut hotp = Hotp::<Sha256>::new(&secret, 8).unwrap();
+		hotp.set_counter(5).unwrap();
+		assert_eq!(
+			hotp.to_uri(secret.as_bytes(), "alice", None),
+			"otpauth://hotp/alice?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+			 &algorithm=SHA256&digits=8&counter=5"
+		);
+		// Unicode, colons, and reserved characters are percent-encoded.
+		assert_eq!(
+			hotp.to_uri(b"foob", "a:b&c=d", Some("Café Corp")),
+			"otpauth://hotp/Caf%C3%A9%20Corp:a%3Ab%26c%3Dd?secret=MZXW6YQ\
+			 &issuer=Caf%C3%A9%20Corp&algorithm=SHA256&digits=8&counter=5"
+		);
+	}
+
+	#[test]
+	fn hotp_checksum() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_checksum(true);
+		let otp = hotp.code().unwrap();
+		assert_eq!(otp, 7552243);
+		assert_eq!(otp.to_string(), "7552243");
+		hotp.set_counter(5).unwrap();
+		assert_eq!(hotp.code().unwrap(), 2546760);
+		// A code with an invalid checksum is rejected, even if the rest matches.
+		assert_eq!(hotp.validate(2546761, 0), None);
+		assert_eq!(hotp.validate(254676, 0), None);
+		assert_eq!(hotp.validate(2546760, 0), Some(5));
+	}
+
+	#[test]
+	fn hotp_checksum_padding() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_checksum(true);
+		hotp.set_counter(1111111109 / 30).unwrap();
+		let otp = hotp.code().unwrap();
+		assert_eq!(otp, 70818042);
+		assert_eq!(otp.length(), 9);
+		assert_eq!(otp.to_string(), "070818042");
+	}
+
+	#[test]
+	fn hotp_truncation_offset() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6)
+			.unwrap()
+			.with_truncation_offset(Some(15))
+			.unwrap();
+		assert_eq!(hotp.code_increment().unwrap(), 752228);
+		hotp.set_truncation_offset(Some(0)).unwrap();
+		assert_eq!(hotp.code().unwrap(), 717529);
+		hotp.set_truncation_offset(None).unwrap();
+		assert_eq!(hotp.code().unwrap(), 287082);
+
+		assert_eq!(
+			hotp.set_truncation_offset(Some(16)),
+			Err(OtpError::InvalidLength)
+		);
+		assert_eq!(hotp.truncation_offset(), None);
+
+		let mut hotp = Hotp::<Sha512>::new(b"12345678901234567890", 6).unwrap();
+		assert!(hotp.set_truncation_offset(Some(59)).is_ok());
+		assert_eq!(
+			hotp.set_truncation_offset(Some(60)),
+			Err(OtpError::InvalidLength)
+		);
+		assert_eq!(hotp.truncation_offset(), Some(59));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn hotp_counter_hook() {
+		use std::sync::{Arc, Mutex};
+
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let persisted = Arc::new(Mutex::new(Vec::new()));
+		let hook_persisted = persisted.clone();
+		hotp.on_counter_change(move |counter| {
+			hook_persisted.lock().unwrap().push(counter);
+			Ok(())
+		});
+		hotp.code().unwrap();
+		assert!(persisted.lock().unwrap().is_empty());
+		hotp.increment_counter().unwrap();
+		hotp.code_increment().unwrap();
+		hotp.set_counter(4).unwrap();
+		assert_eq!(hotp.validate(254676, 3), Some(5));
+		assert_eq!(hotp.validate(254676, 3), None);
+		assert_eq!(*persisted.lock().unwrap(), [1, 2, 4, 6]);
+
+		hotp.on_counter_change(|_| Err(OtpError::PersistenceFailed));
+		assert_eq!(hotp.increment_counter(), Err(OtpError::PersistenceFailed));
+		assert_eq!(hotp.counter(), 6);
+		assert_eq!(hotp.validate(162583, 3), None);
+		// The failed validation doesn't advance the counter without the minimum counter.
+		assert_eq!((hotp.counter(), hotp.minimum_counter()), (6, 6));
+		assert_eq!(
+			hotp.verify_window(162583, 3).unwrap_err(),
+			OtpError::PersistenceFailed
+		);
+		assert_eq!((hotp.counter(), hotp.minimum_counter()), (6, 6));
+
+		// Once the counter can be persisted again, the code is accepted exactly once.
+		hotp.on_counter_change(|_| Ok(()));
+		assert_eq!(hotp.validate(162583, 3), Some(7));
+		assert_eq!((hotp.counter(), hotp.minimum_counter()), (8, 8));
+		hotp.set_counter(6).unwrap_err();
+		assert_eq!(hotp.validate(162583, 3), None);
+	}
+
+	#[test]
+	fn hotp_set_key() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut expected = Hotp::<Sha1>::new(b"new secret", 6).unwrap();
+		hotp.set_counter(3).unwrap();
+		expected.set_counter(3).unwrap();
+		hotp.set_key(b"new secret").unwrap();
+		assert_eq!(hotp.counter(), 3);
+		assert_eq!(hotp.code().unwrap(), expected.code().unwrap());
+		assert_eq!(hotp.validate(969429, 0), None);
+		assert_eq!(hotp.validate(338314, 0), None);
+		assert_eq!(hotp.validate(*expected.code().unwrap(), 0), Some(3));
+
+		hotp.reset().unwrap();
+		assert_eq!(hotp.counter(), 0);
+	}
+
+	#[test]
+	fn hotp_invalid_digits() {
+		let key = b"12345678901234567890";
+		assert_eq!(
+			Hotp::<Sha1>::new(key, 0).unwrap_err(),
+			OtpError::InvalidDigits { got: 0 }
+		);
+		assert_eq!(
+			Hotp::<Sha1>::new(key, 11).unwrap_err(),
+			OtpError::InvalidDigits { got: 11 }
+		);
+		assert_eq!(
+			Hotp::<Sha1>::new(key, usize::MAX).unwrap_err(),
+			OtpError::InvalidDigits { got: usize::MAX }
+		);
+		let mut hotp = Hotp::<Sha1>::new(key, 9).unwrap();
+		assert_eq!(hotp.code().unwrap().to_string(), "284755224");
+		let mut hotp = Hotp::<Sha1>::new(key, 10).unwrap();
+		assert_eq!(hotp.code().unwrap().to_string(), "1284755224");
+	}
+
+	#[test]
+	fn hotp_code_at() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(3).unwrap();
+		assert_eq!(hotp.code_at(9).unwrap(), 520489);
+		hotp.code_at(999).unwrap();
+		assert_eq!(hotp.counter(), 3);
+		assert_eq!(hotp.code().unwrap(), 969429);
+	}
+
+	#[test]
+	fn hotp_debug_redacted() {
+		let key = b"\xde\xad\xbe\xef secret key bytes";
+		let mut hotp = Hotp::<Sha1>::new(key, 6).unwrap();
+		hotp.set_counter(42).unwrap();
+		let debug = format!("{:?}", hotp);
+		assert!(debug.contains("<redacted>"));
+		assert!(debug.contains("counter: 42"));
+		assert!(!debug.contains("secret key bytes"));
+		for byte in key {
+			assert!(!debug.contains(&format!("{:#x}", byte)));
+			assert!(!debug.contains(&format!("{:02x}", byte)));
+		}
+	}
+
+	#[test]
+	fn hotp_state_bytes() {
+		use crate::state::STATE_BYTES_LEN;
+
+		let key = b"12345678901234567890";
+		let mut hotp = Hotp::<Sha1>::new(key, 8).unwrap();
+		hotp.set_counter(7).unwrap();
+		let mut buf = [0u8; 16];
+		assert_eq!(hotp.to_state_bytes(&mut buf).unwrap(), STATE_BYTES_LEN);
+		let mut restored = Hotp::<Sha1>::from_state_bytes(key, &buf).unwrap();
+		assert_eq!(restored.counter(), 7);
+		assert_eq!(restored.code().unwrap(), hotp.code().unwrap());
+
+		assert_eq!(
+			hotp.to_state_bytes(&mut buf[..STATE_BYTES_LEN - 1]),
+			Err(OtpError::StateTooShort)
+		);
+		assert_eq!(
+			Hotp::<Sha1>::from_state_bytes(key, &buf[..STATE_BYTES_LEN - 1]).unwrap_err(),
+			OtpError::StateTooShort
+		);
+		assert_eq!(
+			Hotp::<Sha1>::from_state_bytes(key, &[]).unwrap_err(),
+			OtpError::StateTooShort
+		);
+		buf[0] = 0xff;
+		assert_eq!(
+			Hotp::<Sha1>::from_state_bytes(key, &buf).unwrap_err(),
+			OtpError::UnsupportedStateVersion { got: 0xff }
+		);
+	}
+
+	#[test]
+	fn hotp_builder() {
+		let mut hotp = Hotp::<Sha1>::builder()
+			.digits(6)
+			.counter(5)
+			.checksum(true)
+			.build(b"12345678901234567890")
+			.unwrap();
+		assert_eq!(hotp.counter(), 5);
+		assert!(hotp.checksum());
+		assert_eq!(hotp.code().unwrap(), 2546760);
+
+		assert_eq!(
+			Hotp::<Sha1>::builder()
+				.digits(11)
+				.build(b"12345678901234567890")
+				.unwrap_err(),
+			OtpError::InvalidDigits { got: 11 }
+		);
+		assert_eq!(
+			Hotp::<Sha1>::builder()
+				.truncation_offset(Some(16))
+				.build(b"12345678901234567890")
+				.unwrap_err(),
+			OtpError::InvalidLength
+		);
+	}
+
+	#[test]
+	fn hotp_base32() {
+		let mut hotp = Hotp::<Sha1>::new_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", 6).unwrap();
 		assert_eq!(hotp.code_increment().unwrap(), 755224);
 		assert_eq!(hotp.code_increment().unwrap(), 287082);
-		assert_eq!(hotp.code_increment().unwrap(), 359152);
-		assert_eq!(hotp.code_increment().unwrap(), 969429);
-		assert_eq!(hotp.code_increment().unwrap(), 338314);
-		assert_eq!(hotp.code_increment().unwrap(), 254676);
-		assert_eq!(hotp.code_increment().unwrap(), 287922);
-		assert_eq!(hotp.code_increment().unwrap(), 162583);
-		assert_eq!(hotp.code_increment().unwrap(), 399871);
-		assert_eq!(hotp.code_increment().unwrap(), 520489);
+		assert_eq!(
+			Hotp::<Sha1>::new_base32("not base32!", 6).unwrap_err(),
+			OtpError::InvalidBase32
+		);
+	}
+
+	#[test]
+	fn hotp_hex() {
+		let mut hotp =
+			Hotp::<Sha1>::new_hex("3132333435363738393031323334353637383930", 6).unwrap();
+		assert_eq!(hotp.code_increment().unwrap(), 755224);
+		assert_eq!(hotp.code_increment().unwrap(), 287082);
+		assert_eq!(
+			Hotp::<Sha1>::new_hex("0x31z2", 6).unwrap_err(),
+			OtpError::InvalidHex { position: 4 }
+		);
+	}
+
+	#[test]
+	fn hotp_minimum_key_length() {
+		assert_eq!(
+			Hotp::<Sha1>::new_strict(b"123456789012345", 6).unwrap_err(),
+			OtpError::KeyTooShort {
+				got: 15,
+				minimum: 16
+			}
+		);
+		assert!(Hotp::<Sha1>::new_strict(b"1234567890123456", 6).is_ok());
+		assert!(Hotp::<Sha1>::builder()
+			.enforce_minimum_key_length(true)
+			.build(b"1234567890123456")
+			.is_ok());
+		assert!(Hotp::<Sha1>::new(b"x", 6).is_ok());
+	}
+
+	#[test]
+	fn hotp_validate_full_window() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		// No match walks the entire window without touching the counter.
+		assert_eq!(hotp.validate(123456, 9), None);
+		assert_eq!(hotp.counter(), 0);
+		// The last position in the window still matches.
+		assert_eq!(hotp.validate(520489, 9), Some(9));
+		assert_eq!(hotp.counter(), 10);
+	}
+
+	#[test]
+	fn hotp_codes() {
+		let mut expected = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(42).unwrap();
+		let codes = hotp.codes(0..10).collect::<Result<Vec<_>, _>>().unwrap();
+		for code in codes {
+			assert_eq!(code, expected.code_increment().unwrap());
+		}
+		assert_eq!(hotp.counter(), 42);
+		assert_eq!(hotp.codes(5..5).count(), 0);
+		assert_eq!(
+			hotp.codes(u64::MAX - 3..u64::MAX)
+				.filter(Result::is_ok)
+				.count(),
+			3
+		);
 	}
 }
@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Validating codes from users who may have either an HOTP or a TOTP token,
+//! such as while migrating them from hardware tokens to an authenticator app.
+use crate::{hotp::Hotp, time::TimeSource, totp::Totp};
+use core::fmt::{self, Debug, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// The kind of token that a code matched, see [HybridValidator::validate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+	/// The code was for the [Hotp] token.
+	Hotp,
+	/// The code was for the [Totp] token.
+	Totp,
+}
+
+/// A validator that accepts codes from either an HOTP or a TOTP token belonging to the same
+/// user, which may have different secrets.
+///
+/// Only the token that a code matched is updated: the HOTP counter is advanced past it,
+/// or the TOTP time step is marked as used, while the other token is left untouched.
+///
+/// ```rust
+/// use otpee::{hybrid::{HybridValidator, TokenKind}, Hotp, Totp};
+/// use sha1::Sha1;
+///
+/// let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+/// hotp.set_window(2);
+/// let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// let mut validator = HybridValidator::new(hotp, totp);
+/// assert_eq!(validator.validate(94287082), Some(TokenKind::Totp));
+/// assert_eq!(validator.validate(359152), Some(TokenKind::Hotp));
+/// assert_eq!(validator.validate(359152), None);
+/// ```
+pub struct HybridValidator<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	hotp: Hotp<D>,
+	totp: Totp<D, C>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> HybridValidator<D, C> {
+	/// Creates a validator for the given tokens. HOTP codes are checked against the
+	/// look-ahead window of the HOTP instance, see [Hotp::set_window], and TOTP codes
+	/// against the skew of the TOTP instance.
+	#[inline]
+	pub fn new(hotp: Hotp<D>, totp: Totp<D, C>) -> Self {
+		Self { hotp, totp }
+	}
+
+	/// Returns the HOTP instance.
+	#[inline]
+	pub fn hotp(&self) -> &Hotp<D> {
+		&self.hotp
+	}
+
+	/// Returns the HOTP instance mutably, such as for resynchronizing it.
+	#[inline]
+	pub fn hotp_mut(&mut self) -> &mut Hotp<D> {
+		&mut self.hotp
+	}
+
+	/// Returns the TOTP instance.
+	#[inline]
+	pub fn totp(&self) -> &Totp<D, C> {
+		&self.totp
+	}
+
+	/// Returns the TOTP instance mutably.
+	#[inline]
+	pub fn totp_mut(&mut self) -> &mut Totp<D, C> {
+		&mut self.totp
+	}
+
+	/// Returns the HOTP and TOTP instances, such as for keeping only the TOTP one
+	/// once the user has finished migrating.
+	#[inline]
+	pub fn into_parts(self) -> (Hotp<D>, Totp<D, C>) {
+		(self.hotp, self.totp)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> HybridValidator<D, C> {
+	/// Validates the code against the TOTP token, and if it doesn't match,
+	/// against the HOTP token, returning which one it matched, if any.
+	///
+	/// The TOTP token is checked first, as its skew window is usually smaller than
+	/// the look-ahead window of the HOTP token.
+	pub fn validate(&mut self, code: u64) -> Option<TokenKind> {
+		if self.totp.validate_code(code) {
+			return Some(TokenKind::Totp);
+		}
+		let window = self.hotp.window();
+		self.hotp.validate(code, window).map(|_| TokenKind::Hotp)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Debug for HybridValidator<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("HybridValidator")
+			.field("hotp", &self.hotp)
+			.field("totp", &self.totp)
+			.finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{HybridValidator, TokenKind};
+	use crate::{hotp::Hotp, totp::Totp};
+	use sha1::Sha1;
+
+	fn validator() -> HybridValidator<Sha1> {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_window(3);
+		let totp = Totp::<Sha1>::new(b"abcdefghijabcdefghij", 6, 30, 1, || 59).unwrap();
+		HybridValidator::new(hotp, totp)
+	}
+
+	#[test]
+	fn hybrid_validate() {
+		let mut validator = validator();
+		let totp_code = *validator.totp_mut().code().unwrap();
+
+		assert_eq!(validator.validate(totp_code), Some(TokenKind::Totp));
+		assert_eq!(validator.totp().last_accepted_step(), Some(1));
+		assert_eq!(validator.hotp().counter(), 0);
+
+		assert_eq!(validator.validate(969429), Some(TokenKind::Hotp));
+		assert_eq!(validator.hotp().counter(), 4);
+		assert_eq!(validator.totp().last_accepted_step(), Some(1));
+
+		// Both codes are burned.
+		assert_eq!(validator.validate(totp_code), None);
+		assert_eq!(validator.validate(969429), None);
+		assert_eq!(validator.validate(338314), Some(TokenKind::Hotp));
+	}
+
+	#[test]
+	fn hybrid_no_cross_contamination() {
+		let mut validator = validator();
+		// A wrong code doesn't touch either token.
+		assert_eq!(validator.validate(0), None);
+		assert_eq!(validator.hotp().counter(), 0);
+		assert_eq!(validator.totp().last_accepted_step(), None);
+
+		// With the same secret, the TOTP code for time step 1 is the HOTP code for counter 1,
+		// which is only used up for the TOTP token, as it is checked first.
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let totp = Totp::<Sha1>::new(b"12345678901234567890", 6, 30, 0, || 59).unwrap();
+		let mut validator = HybridValidator::new(hotp, totp);
+		validator.hotp_mut().set_window(1);
+		assert_eq!(validator.validate(287082), Some(TokenKind::Totp));
+		assert_eq!(validator.hotp().counter(), 0);
+		assert_eq!(validator.validate(287082), Some(TokenKind::Hotp));
+		assert_eq!(validator.hotp().counter(), 2);
+		assert_eq!(validator.totp().last_accepted_step(), Some(1));
+	}
+}
@@ -0,0 +1,518 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! A collection of accounts, keyed by their issuer and label, that can be saved and loaded
+//! through a [Persistence] implementation.
+use crate::{
+	account::Account, error::OtpError, generator::OtpGenerator, hotp::Hotp, otp::Otp,
+	secret::Secret, time::TimeSource, totp::Totp,
+};
+use alloc::vec::Vec;
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+#[cfg(feature = "keyring-file")]
+use std::path::PathBuf;
+#[cfg(feature = "serde")]
+use {
+	crate::{
+		algorithm::{Algorithm, HashAlgorithm},
+		state::{HotpState, TotpState},
+	},
+	alloc::string::String,
+	serde::{Deserialize, Serialize},
+};
+
+/// The current version of the serialized keyring format.
+pub const KEYRING_VERSION: u32 = 1;
+
+/// A token stored in a [Keyring], which can be either a HOTP or a TOTP token.
+#[derive(Debug)]
+pub enum Token<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	/// A counter-based token.
+	Hotp(Hotp<D>),
+	/// A time-based token.
+	Totp(Totp<D, C>),
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> From<Hotp<D>> for Token<D, C> {
+	#[inline]
+	fn from(hotp: Hotp<D>) -> Self {
+		Token::Hotp(hotp)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> From<Totp<D, C>> for Token<D, C> {
+	#[inline]
+	fn from(totp: Totp<D, C>) -> Self {
+		Token::Totp(totp)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> OtpGenerator for Token<D, C> {
+	fn current_code(&mut self) -> Result<Otp, OtpError> {
+		match self {
+			Token::Hotp(hotp) => OtpGenerator::current_code(hotp),
+			Token::Totp(totp) => OtpGenerator::current_code(totp),
+		}
+	}
+
+	fn validate(&mut self, code: u64) -> bool {
+		match self {
+			Token::Hotp(hotp) => OtpGenerator::validate(hotp, code),
+			Token::Totp(totp) => OtpGenerator::validate(totp, code),
+		}
+	}
+
+	fn digits(&self) -> usize {
+		match self {
+			Token::Hotp(hotp) => OtpGenerator::digits(hotp),
+			Token::Totp(totp) => OtpGenerator::digits(totp),
+		}
+	}
+}
+
+/// Where a [Keyring] is saved to and loaded from, such as a file or a database row.
+///
+/// The keyring is handed over already serialized, so implementations only have to store bytes.
+pub trait Persistence {
+	/// Loads the serialized keyring, or returns [None] if none has been saved yet.
+	fn load(&mut self) -> Result<Option<Vec<u8>>, OtpError>;
+
+	/// Saves the serialized keyring, replacing the previous one.
+	fn save(&mut self, data: &[u8]) -> Result<(), OtpError>;
+}
+
+#[cfg(feature = "keyring-file")]
+/// Persists a keyring to a file.
+///
+/// The file is written through a temporary file next to it, which is then renamed over it,
+/// so that a crash while saving doesn't leave a truncated keyring behind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePersistence {
+	path: PathBuf,
+}
+
+#[cfg(feature = "keyring-file")]
+impl FilePersistence {
+	/// Creates a new file persistence, for the file at the given path.
+	pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+		Self { path: path.into() }
+	}
+}
+
+#[cfg(feature = "keyring-file")]
+impl Persistence for FilePersistence {
+	fn load(&mut self) -> Result<Option<Vec<u8>>, OtpError> {
+		match std::fs::read(&self.path) {
+			Ok(data) => Ok(Some(data)),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+			Err(err) => Err(OtpError::Io(err.kind())),
+		}
+	}
+
+	fn save(&mut self, data: &[u8]) -> Result<(), OtpError> {
+		let mut temp = self.path.clone().into_os_string();
+		temp.push(".tmp");
+		std::fs::write(&temp, data).map_err(|err| OtpError::Io(err.kind()))?;
+		std::fs::rename(&temp, &self.path).map_err(|err| OtpError::Io(err.kind()))
+	}
+}
+
+/// An account stored in a [Keyring], along with its secret, which the generators don't keep.
+#[derive(Debug)]
+struct Entry<D: Digest + BlockSizeUser + FixedOutputReset, C> {
+	account: Account<Token<D, C>>,
+	#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+	secret: Secret,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Entry<D, C> {
+	#[inline]
+	fn is(&self, issuer: Option<&str>, label: &str) -> bool {
+		self.account.issuer() == issuer && self.account.label() == label
+	}
+}
+
+/// A collection of accounts, each identified by its issuer and label.
+///
+/// As HOTP and TOTP instances don't retain their secret, it has to be given when adding
+/// an account, so that it can be saved along with the account.
+///
+/// ```rust
+/// use otpee::{keyring::Token, Account, Hotp, Keyring, Secret, Totp};
+/// use sha1::Sha1;
+///
+/// let secret = Secret::from(b"12345678901234567890");
+/// let mut keyring = Keyring::<Sha1>::new();
+/// let hotp = Hotp::new(&secret, 6).unwrap();
+/// keyring
+///     .add(Account::new(Token::from(hotp), "alice", Some("Example")), secret.clone())
+///     .unwrap();
+/// let totp = Totp::new(&secret, 8, 30, 1, (|| 59) as fn() -> u64).unwrap();
+/// keyring
+///     .add(Account::new(Token::from(totp), "bob", None::<&str>), secret)
+///     .unwrap();
+///
+/// let matches = keyring.find_by_code(94287082);
+/// assert_eq!(matches.len(), 1);
+/// assert_eq!(matches[0].label(), "bob");
+/// assert!(keyring.get_mut(Some("Example"), "alice").unwrap().validate_code(755224));
+/// ```
+#[derive(Debug)]
+pub struct Keyring<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	entries: Vec<Entry<D, C>>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Keyring<D, C> {
+	/// Creates a new, empty keyring.
+	#[inline]
+	pub fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+		}
+	}
+
+	/// Returns the number of accounts in the keyring.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Returns whether the keyring has no accounts.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Adds an account, along with the secret it was created with.
+	///
+	/// Returns [OtpError::DuplicateAccount] if there already is an account with the
+	/// same issuer and label.
+	pub fn add(&mut self, account: Account<Token<D, C>>, secret: Secret) -> Result<(), OtpError> {
+		if self
+			.entries
+			.iter()
+			.any(|entry| entry.is(account.issuer(), account.label()))
+		{
+			return Err(OtpError::DuplicateAccount);
+		}
+		self.entries.push(Entry { account, secret });
+		Ok(())
+	}
+
+	/// Removes the account with the given issuer and label, returning it.
+	pub fn remove(&mut self, issuer: Option<&str>, label: &str) -> Option<Account<Token<D, C>>> {
+		let index = self
+			.entries
+			.iter()
+			.position(|entry| entry.is(issuer, label))?;
+		Some(self.entries.remove(index).account)
+	}
+
+	/// Returns the account with the given issuer and label.
+	pub fn get(&self, issuer: Option<&str>, label: &str) -> Option<&Account<Token<D, C>>> {
+		self.entries
+			.iter()
+			.find(|entry| entry.is(issuer, label))
+			.map(|entry| &entry.account)
+	}
+
+	/// Returns the account with the given issuer and label, mutably.
+	///
+	/// Changing the issuer or label through this can make two accounts share them,
+	/// which will make [Keyring::load] reject the saved keyring.
+	pub fn get_mut(
+		&mut self,
+		issuer: Option<&str>,
+		label: &str,
+	) -> Option<&mut Account<Token<D, C>>> {
+		self.entries
+			.iter_mut()
+			.find(|entry| entry.is(issuer, label))
+			.map(|entry| &mut entry.account)
+	}
+
+	/// Returns an iterator over the accounts, in the order they were added.
+	pub fn iter(&self) -> impl Iterator<Item = &Account<Token<D, C>>> + '_ {
+		self.entries.iter().map(|entry| &entry.account)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> Keyring<D, C> {
+	/// Returns the accounts whose current code is the given one, for finding out which
+	/// account a code belongs to. Accounts that fail to generate a code are left out.
+	///
+	/// This only compares against the current codes, so it doesn't accept or consume the code.
+	pub fn find_by_code(&mut self, code: u64) -> Vec<&Account<Token<D, C>>> {
+		let mut matches = Vec::new();
+		for entry in self.entries.iter_mut() {
+			let current = entry.account.generator_mut().current_code();
+			if current.is_ok_and(|current| *current == code) {
+				matches.push(&entry.account);
+			}
+		}
+		matches
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Default for Keyring<D, C> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct StoredKeyring {
+	version: u32,
+	accounts: Vec<StoredAccount>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct StoredAccount {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	issuer: Option<String>,
+	label: String,
+	algorithm: String,
+	token: StoredToken,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum StoredToken {
+	Hotp(HotpState),
+	Totp(TotpState),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct StoredVersion {
+	version: u32,
+}
+
+#[cfg(feature = "serde")]
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, C: Clone> Keyring<D, C> {
+	/// Saves the keyring, including the secrets, as JSON.
+	pub fn save<P: Persistence>(&self, persistence: &mut P) -> Result<(), OtpError> {
+		let accounts = self
+			.entries
+			.iter()
+			.map(|entry| {
+				let token = match entry.account.generator() {
+					Token::Hotp(hotp) => StoredToken::Hotp(HotpState {
+						secret: Some(entry.secret.clone()),
+						..hotp.export_state()
+					}),
+					Token::Totp(totp) => StoredToken::Totp(TotpState {
+						secret: Some(entry.secret.clone()),
+						..totp.export_state()
+					}),
+				};
+				StoredAccount {
+					issuer: entry.account.issuer().map(String::from),
+					label: String::from(entry.account.label()),
+					algorithm: String::from(D::ALGORITHM.name()),
+					token,
+				}
+			})
+			.collect();
+		let data = serde_json::to_vec(&StoredKeyring {
+			version: KEYRING_VERSION,
+			accounts,
+		})
+		.map_err(|_| OtpError::InvalidKeyring)?;
+		persistence.save(&data)
+	}
+
+	/// Loads a keyring saved by [Keyring::save], giving the TOTP accounts the given time source.
+	/// Returns an empty keyring if none has been saved yet.
+	///
+	/// Returns [OtpError::UnsupportedKeyringVersion] if it was saved in a different version of
+	/// the format, and [OtpError::InvalidKeyring] if it is malformed, has accounts that share
+	/// an issuer and label, or was saved with a different hash function.
+	pub fn load<P: Persistence>(persistence: &mut P, time_source: C) -> Result<Self, OtpError> {
+		let Some(data) = persistence.load()? else {
+			return Ok(Self::new());
+		};
+		let version = serde_json::from_slice::<StoredVersion>(&data)
+			.map_err(|_| OtpError::InvalidKeyring)?
+			.version;
+		if version != KEYRING_VERSION {
+			return Err(OtpError::UnsupportedKeyringVersion { got: version });
+		}
+		let stored =
+			serde_json::from_slice::<StoredKeyring>(&data).map_err(|_| OtpError::InvalidKeyring)?;
+		let mut keyring = Self::new();
+		for account in stored.accounts {
+			if Algorithm::from_name(&account.algorithm) != Some(D::ALGORITHM) {
+				return Err(OtpError::InvalidKeyring);
+			}
+			let (token, secret) = match account.token {
+				StoredToken::Hotp(state) => (Token::Hotp(Hotp::from_state(&state)?), state.secret),
+				StoredToken::Totp(state) => (
+					Token::Totp(Totp::from_state(&state, time_source.clone())?),
+					state.secret,
+				),
+			};
+			let secret = secret.ok_or(OtpError::MissingSecret)?;
+			keyring
+				.add(Account::new(token, account.label, account.issuer), secret)
+				.map_err(|_| OtpError::InvalidKeyring)?;
+		}
+		Ok(keyring)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Keyring, Token};
+	use crate::{account::Account, error::OtpError, hotp::Hotp, secret::Secret, totp::Totp};
+	use sha1::Sha1;
+
+	fn keyring() -> Keyring<Sha1> {
+		let secret = Secret::from(b"12345678901234567890");
+		let mut keyring = Keyring::<Sha1>::new();
+		let hotp = Hotp::new(&secret, 6).unwrap();
+		keyring
+			.add(
+				Account::new(Token::from(hotp), "alice", Some("Example")),
+				secret.clone(),
+			)
+			.unwrap();
+		let totp = Totp::new(&secret, 8, 30, 1, (|| 59) as fn() -> u64).unwrap();
+		keyring
+			.add(Account::new(Token::from(totp), "bob", None::<&str>), secret)
+			.unwrap();
+		keyring
+	}
+
+	#[test]
+	fn keyring_accounts() {
+		let mut keyring = keyring();
+		assert_eq!(keyring.len(), 2);
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		assert_eq!(
+			keyring.add(
+				Account::new(Token::from(hotp), "alice", Some("Example")),
+				Secret::from(b"12345678901234567890"),
+			),
+			Err(OtpError::DuplicateAccount)
+		);
+		assert!(keyring.get(None, "alice").is_none());
+		assert_eq!(
+			keyring
+				.iter()
+				.map(|account| account.label())
+				.collect::<Vec<_>>(),
+			["alice", "bob"]
+		);
+
+		assert_eq!(keyring.find_by_code(755224)[0].label(), "alice");
+		assert_eq!(keyring.find_by_code(94287082)[0].label(), "bob");
+		assert!(keyring.find_by_code(123456).is_empty());
+
+		let alice = keyring.remove(Some("Example"), "alice").unwrap();
+		assert_eq!(alice.label(), "alice");
+		assert!(keyring.remove(Some("Example"), "alice").is_none());
+		assert_eq!(keyring.len(), 1);
+	}
+
+	#[cfg(all(feature = "serde", feature = "sha1", feature = "sha2"))]
+	mod persistence {
+		use super::keyring;
+		use crate::{
+			error::OtpError,
+			keyring::{Keyring, Persistence, Token},
+		};
+		use sha1::Sha1;
+		use sha2::Sha256;
+
+		#[derive(Default)]
+		struct Memory(Option<Vec<u8>>);
+
+		impl Persistence for Memory {
+			fn load(&mut self) -> Result<Option<Vec<u8>>, OtpError> {
+				Ok(self.0.clone())
+			}
+
+			fn save(&mut self, data: &[u8]) -> Result<(), OtpError> {
+				self.0 = Some(data.to_vec());
+				Ok(())
+			}
+		}
+
+		#[test]
+		fn keyring_round_trip() {
+			let mut memory = Memory::default();
+			assert!(Keyring::<Sha1>::load(&mut memory, || 59)
+				.unwrap()
+				.is_empty());
+
+			let mut keyring = keyring();
+			let alice = keyring.get_mut(Some("Example"), "alice").unwrap();
+			assert!(alice.validate_code(755224));
+			match keyring.get_mut(None, "bob").unwrap().generator_mut() {
+				Token::Totp(totp) => {
+					totp.set_enforce_single_use(false);
+					totp.set_strict_monotonic(true);
+					totp.set_grace_period(5);
+					totp.set_tolerance_seconds(45, 10);
+				}
+				Token::Hotp(_) => panic!("bob should have a TOTP token"),
+			}
+			keyring.save(&mut memory).unwrap();
+
+			let time = (|| 59) as fn() -> u64;
+			let mut loaded = Keyring::<Sha1>::load(&mut memory, time).unwrap();
+			assert_eq!(loaded.len(), 2);
+			match loaded
+				.get_mut(Some("Example"), "alice")
+				.unwrap()
+				.generator_mut()
+			{
+				Token::Hotp(hotp) => {
+					assert_eq!(hotp.counter(), 1);
+					assert_eq!(hotp.code().unwrap(), 287082);
+				}
+				Token::Totp(_) => panic!("alice should have a HOTP token"),
+			}
+			match loaded.get_mut(None, "bob").unwrap().generator_mut() {
+				Token::Totp(totp) => {
+					assert!(!totp.enforces_single_use());
+					assert!(totp.is_strict_monotonic());
+					assert_eq!(totp.grace_period(), Some(5));
+					assert_eq!(totp.tolerance_seconds(), Some((45, 10)));
+				}
+				Token::Hotp(_) => panic!("bob should have a TOTP token"),
+			}
+			assert_eq!(loaded.find_by_code(94287082)[0].label(), "bob");
+		}
+
+		#[test]
+		fn keyring_invalid() {
+			let mut memory = Memory::default();
+			keyring().save(&mut memory).unwrap();
+			assert_eq!(
+				Keyring::<Sha256>::load(&mut memory, || 59).unwrap_err(),
+				OtpError::InvalidKeyring
+			);
+
+			let data = String::from_utf8(memory.0.take().unwrap()).unwrap();
+			let mut memory = Memory(Some(data.replace("\"version\":1", "\"version\":2").into()));
+			assert_eq!(
+				Keyring::<Sha1>::load(&mut memory, || 59).unwrap_err(),
+				OtpError::UnsupportedKeyringVersion { got: 2 }
+			);
+
+			let data = data.replace(
+				"\"label\":\"bob\"",
+				"\"label\":\"alice\",\"issuer\":\"Example\"",
+			);
+			let mut memory = Memory(Some(data.into()));
+			assert_eq!(
+				Keyring::<Sha1>::load(&mut memory, || 59).unwrap_err(),
+				OtpError::InvalidKeyring
+			);
+		}
+	}
+}
@@ -2,13 +2,114 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+pub mod account;
+pub mod algorithm;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod audit;
+#[cfg(any(
+	feature = "backup-aegis",
+	feature = "backup-andotp",
+	feature = "backup-freeotp",
+	feature = "backup-keepass",
+	feature = "backup-twofas"
+))]
+pub mod backup;
+#[cfg(feature = "alloc")]
+mod base32;
+#[cfg(feature = "migration")]
+mod base64;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
+#[cfg(feature = "alloc")]
+pub mod enroll;
 pub mod error;
+pub mod fixed;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod generator;
+mod hex;
 pub mod hotp;
+pub mod hybrid;
+#[cfg(feature = "alloc")]
+pub mod keyring;
+pub mod mac;
+#[cfg(feature = "migration")]
+pub mod migration;
+#[cfg(feature = "motp")]
+pub mod motp;
+pub mod mutual;
 pub mod otp;
+pub mod preset;
+#[cfg(feature = "qr")]
+pub mod qr;
+#[cfg(feature = "recovery")]
+pub mod recovery;
+pub mod rotation;
+#[cfg(feature = "encryption")]
+pub mod seal;
+#[cfg(feature = "alloc")]
+pub mod secret;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "alloc")]
+pub mod sheet;
+pub mod state;
+#[cfg(feature = "sha1")]
+pub mod steam;
+pub mod throttle;
+pub mod time;
 pub mod totp;
+#[cfg(feature = "alloc")]
+pub mod uri;
+pub mod validation;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod vectors;
 
-pub use hotp::Hotp;
+#[cfg(feature = "alloc")]
+pub use account::Account;
+pub use algorithm::{Algorithm, HashAlgorithm};
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncTotp;
+pub use audit::AuditEvent;
+#[cfg(feature = "dynamic")]
+pub use dynamic::DynHotp;
+#[cfg(all(feature = "dynamic", feature = "alloc"))]
+pub use dynamic::DynTotp;
+pub use fixed::{HotpConst, TotpConst};
+#[cfg(feature = "arbitrary")]
+pub use fuzz::OtpConfig;
+pub use generator::OtpGenerator;
+pub use hotp::{Hotp, HotpBuilder, OverflowPolicy, PendingCode, VerifyOutcome};
+#[cfg(feature = "alloc")]
+pub use keyring::Keyring;
+#[cfg(feature = "motp")]
+pub use motp::{Motp, MotpCode};
+pub use mutual::MutualAuth;
 pub use otp::Otp;
-pub use totp::Totp;
+pub use preset::{Preset, PresetParams};
+#[cfg(feature = "qr")]
+pub use qr::QrImage;
+#[cfg(feature = "alloc")]
+pub use secret::Secret;
+#[cfg(feature = "std")]
+pub use shared::SharedTotp;
+#[cfg(feature = "serde")]
+pub use state::TotpConfig;
+#[cfg(feature = "alloc")]
+pub use state::{HotpState, TotpState};
+#[cfg(feature = "sha1")]
+pub use steam::{SteamCode, SteamTotp};
+#[cfg(feature = "async")]
+pub use time::AsyncTimeSource;
+pub use time::TimeSource;
+#[cfg(feature = "alloc")]
+pub use totp::BoxedTotp;
+pub use totp::{CodeWindow, Totp, TotpBuilder, TotpOptions};
+#[cfg(feature = "alloc")]
+pub use uri::{ParsedHotp, ParsedTotp};
+pub use validation::Validation;
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::error::OtpError;
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset, KeyInit};
+use hmac::{Mac, SimpleHmac};
+
+/// The largest MAC output supported, in bytes, which is that of HMAC-SHA-512.
+pub const MAX_OUTPUT_SIZE: usize = 64;
+
+/// A keyed MAC used to compute HOTP values, allowing the computation to be done elsewhere,
+/// such as by a hardware HMAC peripheral.
+///
+/// The default implementation is [Hmac], which computes the HMAC in software.
+///
+/// ```rust
+/// use otpee::{error::OtpError, mac::OtpMac, Hotp};
+/// use sha1::Sha1;
+///
+/// /// A MAC backed by some external engine.
+/// struct Engine;
+///
+/// impl OtpMac for Engine {
+///     fn output_size(&self) -> usize {
+///         20
+///     }
+///
+///     fn compute(&mut self, message: &[u8], out: &mut [u8]) -> Result<usize, OtpError> {
+///         // The RFC 4226 example HMAC-SHA-1 value, from section 5.4.
+///         let mac = [
+///             0x1f, 0x86, 0x98, 0x69, 0x0e, 0x02, 0xca, 0x16, 0x61, 0x85, 0x50, 0xef, 0x7f, 0x19,
+///             0xda, 0x8e, 0x94, 0x5b, 0x55, 0x5a,
+///         ];
+///         out[..20].copy_from_slice(&mac);
+///         Ok(20)
+///     }
+/// }
+///
+/// let mut hotp = Hotp::<Sha1, _>::with_mac(Engine, 6).unwrap();
+/// assert_eq!(hotp.code().unwrap(), 872921);
+/// ```
+pub trait OtpMac {
+	/// Returns the size of the MAC output, in bytes.
+	fn output_size(&self) -> usize;
+
+	/// Computes the MAC of the given message into the given buffer, which is
+	/// [MAX_OUTPUT_SIZE] bytes long, returning the number of bytes written.
+	///
+	/// The message is the moving factor, which is usually the 8-byte big-endian counter,
+	/// but can be longer, see [Hotp::code_for_factor](crate::Hotp::code_for_factor).
+	fn compute(&mut self, message: &[u8], out: &mut [u8]) -> Result<usize, OtpError>;
+}
+
+/// A software HMAC over the given hash function, used by [Hotp](crate::Hotp) by default.
+///
+/// With the `zeroize` feature enabled, this is overwritten when dropped,
+/// as [SimpleHmac] doesn't support zeroization by itself.
+#[derive(Clone)]
+pub struct Hmac<D: Digest + BlockSizeUser>(SimpleHmac<D>);
+
+impl<D: Digest + BlockSizeUser> Hmac<D> {
+	/// Creates a new HMAC, using the given bytes as the secret.
+	pub fn new_from_slice(key: &[u8]) -> Result<Self, OtpError> {
+		<SimpleHmac<D> as KeyInit>::new_from_slice(key)
+			.map(Self)
+			.map_err(|_| OtpError::InvalidLength)
+	}
+}
+
+impl<D: Digest + BlockSizeUser> From<SimpleHmac<D>> for Hmac<D> {
+	#[inline]
+	fn from(hmac: SimpleHmac<D>) -> Self {
+		Self(hmac)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> OtpMac for Hmac<D> {
+	#[inline]
+	fn output_size(&self) -> usize {
+		<D as Digest>::output_size()
+	}
+
+	fn compute(&mut self, message: &[u8], out: &mut [u8]) -> Result<usize, OtpError> {
+		let len = <D as Digest>::output_size();
+		let out = out.get_mut(..len).ok_or(OtpError::MacFailed)?;
+		self.0.update(message);
+		out.copy_from_slice(&self.0.finalize_fixed_reset());
+		Ok(len)
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl<D: Digest + BlockSizeUser> Drop for Hmac<D> {
+	fn drop(&mut self) {
+		// Replace the key-dependent state with one derived from an empty key.
+		// Volatile writes are used so the compiler can't elide this as a dead store.
+		if let Ok(blank) = <SimpleHmac<D> as KeyInit>::new_from_slice(&[]) {
+			// SAFETY: the old state is dropped exactly once, and immediately replaced with
+			// a valid one, so the field is never observed in an invalid state.
+			unsafe {
+				core::ptr::drop_in_place(&mut self.0);
+				core::ptr::write_volatile(&mut self.0, blank);
+			}
+			core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+		}
+	}
+}
@@ -0,0 +1,499 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Importing and exporting accounts in the format of Google Authenticator's "Export accounts"
+//! QR codes, which contain `otpauth-migration://offline?data=...` URIs.
+//!
+//! The data is a small protobuf message, which is decoded by hand.
+use crate::{
+	algorithm::Algorithm,
+	base64,
+	error::{MigrationError, OtpError},
+	secret::Secret,
+	uri::percent_decode,
+};
+use alloc::{string::String, vec::Vec};
+
+/// The type of an exported account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OtpType {
+	/// A counter-based account.
+	Hotp,
+	/// A time-based account, with a 30 second interval.
+	Totp,
+}
+
+/// An account exported from Google Authenticator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigratedAccount {
+	/// The secret.
+	pub secret: Secret,
+	/// The name of the account, which may be prefixed with the issuer.
+	pub name: String,
+	/// The issuer, if any.
+	pub issuer: Option<String>,
+	/// The hash algorithm.
+	pub algorithm: Algorithm,
+	/// The number of digits.
+	pub digits: usize,
+	/// Whether the account uses HOTP or TOTP.
+	pub otp_type: OtpType,
+	/// The counter, for HOTP accounts.
+	pub counter: u64,
+}
+
+/// The accounts in one `otpauth-migration://offline` URI, along with where it belongs in an export
+/// that was split across several URIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationBatch {
+	/// The accounts, as returned by [parse].
+	pub accounts: Vec<Result<MigratedAccount, MigrationError>>,
+	/// The version of the payload format.
+	pub version: u64,
+	/// The number of URIs in the export.
+	pub batch_size: u64,
+	/// The position of this URI in the export, starting at 0.
+	pub batch_index: u64,
+	/// An identifier shared by all the URIs in the export.
+	pub batch_id: u64,
+}
+
+/// Parses an `otpauth-migration://offline` URI, returning each of the accounts in it.
+///
+/// Accounts that can't be imported, such as ones using MD5, are returned as errors,
+/// without stopping the other accounts from being parsed. If the URI itself is invalid,
+/// [OtpError::Migration] is returned.
+///
+/// ```rust
+/// use otpee::{migration::{self, OtpType}, Algorithm};
+///
+/// let accounts = migration::parse(
+///     "otpauth-migration://offline?data=ChUKBmZvb2JhchIDYm9iIAIoAjABOCoQAQ%3D%3D",
+/// )
+/// .unwrap();
+/// let account = accounts[0].as_ref().unwrap();
+/// assert_eq!(account.name, "bob");
+/// assert_eq!(account.algorithm, Algorithm::Sha256);
+/// assert_eq!((account.otp_type, account.digits, account.counter), (OtpType::Hotp, 8, 42));
+/// ```
+pub fn parse(uri: &str) -> Result<Vec<Result<MigratedAccount, MigrationError>>, OtpError> {
+	parse_batch(uri).map(|batch| batch.accounts)
+}
+
+/// Parses an `otpauth-migration://offline` URI like [parse], including the batch metadata.
+pub fn parse_batch(uri: &str) -> Result<MigrationBatch, OtpError> {
+	const SCHEME: &str = "otpauth-migration://offline?";
+	if !uri
+		.get(..SCHEME.len())
+		.is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+	{
+		return Err(MigrationError::InvalidScheme.into());
+	}
+	let data = uri[SCHEME.len()..]
+		.split('&')
+		.find_map(|pair| pair.strip_prefix("data="))
+		.ok_or(MigrationError::InvalidEncoding)?;
+	// A `+` is part of the Base64 alphabet, not a space, even if it wasn't percent-encoded.
+	let data = percent_decode(data, false).map_err(|_| MigrationError::InvalidEncoding)?;
+	let payload = base64::decode(&data).ok_or(MigrationError::InvalidEncoding)?;
+
+	let mut batch = MigrationBatch {
+		accounts: Vec::new(),
+		version: 0,
+		batch_size: 1,
+		batch_index: 0,
+		batch_id: 0,
+	};
+	let mut reader = Reader(&payload);
+	while let Some((field, value)) = reader.field()? {
+		match (field, value) {
+			(1, Value::Bytes(entry)) => batch.accounts.push(parse_account(entry)),
+			(2, Value::Varint(value)) => batch.version = value,
+			(3, Value::Varint(value)) => batch.batch_size = value,
+			(4, Value::Varint(value)) => batch.batch_index = value,
+			(5, Value::Varint(value)) => batch.batch_id = value,
+			_ => {}
+		}
+	}
+	Ok(batch)
+}
+
+/// Creates `otpauth-migration://offline` URIs for the accounts, which Google Authenticator
+/// can import, such as by showing each of them as a QR code.
+///
+/// The accounts are split across as many URIs as needed to keep each payload, before it is
+/// encoded, within `max_payload_bytes`, with the batch metadata set so they can be imported
+/// together. An account that doesn't fit in a payload by itself gets a URI of its own.
+/// The payload is encoded as unpadded URL-safe Base64.
+///
+/// Returns [MigrationError::UnsupportedDigits] if an account has a number of digits
+/// other than 6 or 8, as the format can't represent it.
+pub fn export(
+	accounts: &[MigratedAccount],
+	max_payload_bytes: usize,
+) -> Result<Vec<String>, OtpError> {
+	// The batch metadata takes at most this many bytes, with 5 byte varints for the values.
+	const METADATA_LEN: usize = 4 * 6;
+	let mut batches: Vec<Vec<u8>> = Vec::new();
+	let mut current = Vec::new();
+	for account in accounts {
+		let mut entry = Vec::new();
+		write_account(&mut entry, account)?;
+		let mut field = Vec::new();
+		write_bytes(&mut field, 1, &entry);
+		if !current.is_empty() && current.len() + field.len() + METADATA_LEN > max_payload_bytes {
+			batches.push(core::mem::take(&mut current));
+		}
+		current.extend_from_slice(&field);
+	}
+	if !current.is_empty() || batches.is_empty() {
+		batches.push(current);
+	}
+
+	// The identifier only has to be shared by the URIs of one export, so a hash will do.
+	let batch_id = batches
+		.iter()
+		.flatten()
+		.fold(0x811c_9dc5_u32, |hash, &byte| {
+			(hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+		}) & 0x7fff_ffff;
+	let batch_size = batches.len() as u64;
+	Ok(batches
+		.into_iter()
+		.enumerate()
+		.map(|(index, mut payload)| {
+			write_varint_field(&mut payload, 2, 1);
+			write_varint_field(&mut payload, 3, batch_size);
+			write_varint_field(&mut payload, 4, index as u64);
+			write_varint_field(&mut payload, 5, u64::from(batch_id));
+			let mut uri = String::from("otpauth-migration://offline?data=");
+			uri.push_str(&base64::encode_url_safe(&payload));
+			uri
+		})
+		.collect())
+}
+
+/// Writes an `OtpParameters` message.
+fn write_account(out: &mut Vec<u8>, account: &MigratedAccount) -> Result<(), MigrationError> {
+	let digits = match account.digits {
+		6 => 1,
+		8 => 2,
+		got => return Err(MigrationError::UnsupportedDigits { got: got as u64 }),
+	};
+	let algorithm = match account.algorithm {
+		Algorithm::Sha1 => 1,
+		Algorithm::Sha256 => 2,
+		Algorithm::Sha512 => 3,
+	};
+	write_bytes(out, 1, account.secret.as_bytes());
+	write_bytes(out, 2, account.name.as_bytes());
+	if let Some(issuer) = &account.issuer {
+		write_bytes(out, 3, issuer.as_bytes());
+	}
+	write_varint_field(out, 4, algorithm);
+	write_varint_field(out, 5, digits);
+	match account.otp_type {
+		OtpType::Hotp => {
+			write_varint_field(out, 6, 1);
+			write_varint_field(out, 7, account.counter);
+		}
+		OtpType::Totp => write_varint_field(out, 6, 2),
+	}
+	Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		out.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+	out.push(value as u8);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+	write_varint(out, field << 3);
+	write_varint(out, value);
+}
+
+fn write_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+	write_varint(out, field << 3 | 2);
+	write_varint(out, bytes.len() as u64);
+	out.extend_from_slice(bytes);
+}
+
+/// Parses an `OtpParameters` message.
+fn parse_account(entry: &[u8]) -> Result<MigratedAccount, MigrationError> {
+	let mut secret = Vec::new();
+	let mut name = String::new();
+	let mut issuer = String::new();
+	let (mut algorithm, mut digits, mut otp_type, mut counter) = (0, 0, 0, 0);
+	let mut reader = Reader(entry);
+	while let Some((field, value)) = reader.field()? {
+		match (field, value) {
+			(1, Value::Bytes(bytes)) => secret = bytes.to_vec(),
+			(2, Value::Bytes(bytes)) => name = string(bytes)?,
+			(3, Value::Bytes(bytes)) => issuer = string(bytes)?,
+			(4, Value::Varint(value)) => algorithm = value,
+			(5, Value::Varint(value)) => digits = value,
+			(6, Value::Varint(value)) => otp_type = value,
+			(7, Value::Varint(value)) => counter = value,
+			_ => {}
+		}
+	}
+	// Unspecified values are treated as the defaults, like Google Authenticator does.
+	let algorithm = match algorithm {
+		0 | 1 => Algorithm::Sha1,
+		2 => Algorithm::Sha256,
+		3 => Algorithm::Sha512,
+		got => return Err(MigrationError::UnsupportedAlgorithm { got }),
+	};
+	let digits = match digits {
+		0 | 1 => 6,
+		2 => 8,
+		got => return Err(MigrationError::UnsupportedDigits { got }),
+	};
+	let otp_type = match otp_type {
+		1 => OtpType::Hotp,
+		0 | 2 => OtpType::Totp,
+		got => return Err(MigrationError::UnsupportedType { got }),
+	};
+	if secret.is_empty() {
+		return Err(MigrationError::Malformed);
+	}
+	Ok(MigratedAccount {
+		secret: Secret::from(secret),
+		name,
+		issuer: Some(issuer).filter(|issuer| !issuer.is_empty()),
+		algorithm,
+		digits,
+		otp_type,
+		counter,
+	})
+}
+
+fn string(bytes: &[u8]) -> Result<String, MigrationError> {
+	String::from_utf8(bytes.to_vec()).map_err(|_| MigrationError::Malformed)
+}
+
+/// The value of a protobuf field.
+enum Value<'a> {
+	Varint(u64),
+	Bytes(&'a [u8]),
+	/// A fixed-size value, which isn't used by migration payloads.
+	Fixed,
+}
+
+/// Reads the fields of a protobuf message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+	fn field(&mut self) -> Result<Option<(u64, Value<'a>)>, MigrationError> {
+		if self.0.is_empty() {
+			return Ok(None);
+		}
+		let key = self.varint()?;
+		let value = match key & 7 {
+			0 => Value::Varint(self.varint()?),
+			1 => self.skip(8)?,
+			2 => {
+				let len = usize::try_from(self.varint()?).map_err(|_| MigrationError::Malformed)?;
+				Value::Bytes(self.take(len)?)
+			}
+			5 => self.skip(4)?,
+			_ => return Err(MigrationError::Malformed),
+		};
+		Ok(Some((key >> 3, value)))
+	}
+
+	fn varint(&mut self) -> Result<u64, MigrationError> {
+		let mut value = 0;
+		for shift in (0..64).step_by(7) {
+			let (&byte, rest) = self.0.split_first().ok_or(MigrationError::Malformed)?;
+			self.0 = rest;
+			value |= u64::from(byte & 0x7f) << shift;
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+		Err(MigrationError::Malformed)
+	}
+
+	fn take(&mut self, len: usize) -> Result<&'a [u8], MigrationError> {
+		if len > self.0.len() {
+			return Err(MigrationError::Malformed);
+		}
+		let (bytes, rest) = self.0.split_at(len);
+		self.0 = rest;
+		Ok(bytes)
+	}
+
+	fn skip(&mut self, len: usize) -> Result<Value<'a>, MigrationError> {
+		self.take(len).map(|_| Value::Fixed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{export, parse, parse_batch, MigratedAccount, OtpType};
+	use crate::{
+		algorithm::Algorithm,
+		error::{MigrationError, OtpError},
+		secret::Secret,
+	};
+	use alloc::{format, vec::Vec};
+
+	/// One TOTP and one HOTP account, in a batch of one.
+	const FIXTURE: &str = "otpauth-migration://offline?data=CkAKFDEyMzQ1Njc4OTAxMjM0NTY3ODkwEhlFe\
+		GFtcGxlOmFsaWNlQGV4YW1wbGUuY29tGgdFeGFtcGxlIAEoATACChUKBmZvb2JhchIDYm9iIAIoAjABOCoQARgBIAA\
+		olZrvOg%3D%3D";
+
+	#[test]
+	fn migration_parse() {
+		let accounts = parse(FIXTURE).unwrap();
+		assert_eq!(
+			accounts,
+			[
+				Ok(MigratedAccount {
+					secret: Secret::from(b"12345678901234567890"),
+					name: "Example:alice@example.com".into(),
+					issuer: Some("Example".into()),
+					algorithm: Algorithm::Sha1,
+					digits: 6,
+					otp_type: OtpType::Totp,
+					counter: 0,
+				}),
+				Ok(MigratedAccount {
+					secret: Secret::from(b"foobar"),
+					name: "bob".into(),
+					issuer: None,
+					algorithm: Algorithm::Sha256,
+					digits: 8,
+					otp_type: OtpType::Hotp,
+					counter: 42,
+				}),
+			]
+		);
+	}
+
+	#[test]
+	fn migration_unsupported_accounts() {
+		// MD5, seven digits, and an account with every value unspecified.
+		let accounts = parse(
+			"otpauth-migration://offline?data=ChMKBmZvb2JhchIDbWQ1IAQoATACChUKBmZvb2JhchIFc2V2ZW4gAS\
+			 gDMAIKEgoGZm9vYmFyEgJvayAAKAAwABAB",
+		)
+		.unwrap();
+		assert_eq!(accounts.len(), 3);
+		assert_eq!(
+			accounts[0],
+			Err(MigrationError::UnsupportedAlgorithm { got: 4 })
+		);
+		assert_eq!(
+			accounts[1],
+			Err(MigrationError::UnsupportedDigits { got: 3 })
+		);
+		let account = accounts[2].as_ref().unwrap();
+		assert_eq!(account.name, "ok");
+		assert_eq!(
+			(account.algorithm, account.digits, account.otp_type),
+			(Algorithm::Sha1, 6, OtpType::Totp)
+		);
+	}
+
+	#[test]
+	fn migration_malformed() {
+		let cases = [
+			(
+				"otpauth://totp/alice?secret=MZXW6YQ",
+				MigrationError::InvalidScheme,
+			),
+			// A multibyte character across the end of the scheme.
+			(
+				"otpauth-migration://offlineé?data=CgU",
+				MigrationError::InvalidScheme,
+			),
+			(
+				"otpauth-migration://offline?foo=bar",
+				MigrationError::InvalidEncoding,
+			),
+			(
+				"otpauth-migration://offline?data=Zm9v!",
+				MigrationError::InvalidEncoding,
+			),
+			// A length-delimited field that is longer than the payload.
+			(
+				"otpauth-migration://offline?data=CgU",
+				MigrationError::Malformed,
+			),
+		];
+		for (uri, err) in cases {
+			assert_eq!(parse(uri), Err(OtpError::Migration(err)), "{}", uri);
+		}
+	}
+
+	#[test]
+	fn migration_export_round_trip() {
+		let accounts = parse(FIXTURE)
+			.unwrap()
+			.into_iter()
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		let uris = export(&accounts, 1000).unwrap();
+		assert_eq!(uris.len(), 1);
+		let batch = parse_batch(&uris[0]).unwrap();
+		assert_eq!(
+			(batch.version, batch.batch_size, batch.batch_index),
+			(1, 1, 0)
+		);
+		assert_eq!(
+			batch.accounts.into_iter().collect::<Result<Vec<_>, _>>(),
+			Ok(accounts)
+		);
+		assert_eq!(
+			export(&[], 1000).unwrap(),
+			["otpauth-migration://offline?data=EAEYASAAKMW78gg"]
+		);
+	}
+
+	#[test]
+	fn migration_export_batches() {
+		let accounts = (0..20)
+			.map(|i| MigratedAccount {
+				secret: Secret::from(format!("secret number {}", i).as_bytes()),
+				name: format!("user{}@example.com", i),
+				issuer: Some("Example".into()),
+				algorithm: Algorithm::Sha1,
+				digits: 6,
+				otp_type: if i % 2 == 0 {
+					OtpType::Totp
+				} else {
+					OtpType::Hotp
+				},
+				counter: if i % 2 == 0 { 0 } else { i },
+			})
+			.collect::<Vec<_>>();
+		let uris = export(&accounts, 200).unwrap();
+		assert!(uris.len() > 1);
+		let mut imported = Vec::new();
+		let mut batch_id = None;
+		for (index, uri) in uris.iter().enumerate() {
+			let data = uri
+				.strip_prefix("otpauth-migration://offline?data=")
+				.unwrap();
+			assert!(!data.contains(['+', '/', '=']));
+			let batch = parse_batch(uri).unwrap();
+			assert_eq!(batch.batch_size, uris.len() as u64);
+			assert_eq!(batch.batch_index, index as u64);
+			assert_eq!(*batch_id.get_or_insert(batch.batch_id), batch.batch_id);
+			imported.extend(batch.accounts.into_iter().map(Result::unwrap));
+		}
+		assert_eq!(imported, accounts);
+
+		let mut account = accounts[0].clone();
+		account.digits = 7;
+		assert_eq!(
+			export(&[account], 200),
+			Err(OtpError::Migration(MigrationError::UnsupportedDigits {
+				got: 7
+			}))
+		);
+	}
+}
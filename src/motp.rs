@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, hotp::find_match, secret::Secret, time::TimeSource};
+use core::fmt::{self, Debug, Display, Formatter};
+use md5::{Digest, Md5};
+use subtle::ConstantTimeEq;
+
+/// The number of seconds each mOTP code is valid for.
+pub const MOTP_INTERVAL: u64 = 10;
+
+/// The number of characters in an mOTP code.
+pub const MOTP_CODE_LENGTH: usize = 6;
+
+/// An mOTP code, made of 6 lowercase hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MotpCode([u8; MOTP_CODE_LENGTH]);
+
+impl MotpCode {
+	/// Returns the code as a string.
+	#[inline]
+	pub fn as_str(&self) -> &str {
+		// The code is always made of ASCII hex digits.
+		core::str::from_utf8(&self.0).unwrap_or_default()
+	}
+}
+
+impl Display for MotpCode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// A Mobile-OTP (mOTP) generator, for legacy systems that don't support TOTP.
+///
+/// Codes are the first 6 hex digits of the MD5 hash of the number of 10 second steps since
+/// the unix epoch, the secret and the PIN, concatenated as text.
+/// Like [Totp](crate::Totp), it takes a [TimeSource] to get the current time.
+///
+/// MD5 is broken, and mOTP has no protection against replaying a code,
+/// so it should only be used where it is required for compatibility.
+///
+/// ```rust
+/// use otpee::Motp;
+///
+/// let mut motp = Motp::new("0000000000000000", "1234", || 59).unwrap();
+/// assert_eq!(motp.code().unwrap().as_str(), "d25c01");
+/// assert!(motp.validate_code("D25C01", 3));
+/// ```
+pub struct Motp<C = fn() -> u64> {
+	secret: Secret,
+	pin: Secret,
+	time_source: C,
+}
+
+impl<C> Motp<C> {
+	/// Creates a new instance with the given secret, which must be made of hex digits,
+	/// and the given PIN.
+	pub fn new(secret_hex: &str, pin: &str, time_source: C) -> Result<Self, OtpError> {
+		if secret_hex.is_empty() {
+			return Err(OtpError::MissingSecret);
+		}
+		if let Some(position) = secret_hex.bytes().position(|c| !c.is_ascii_hexdigit()) {
+			return Err(OtpError::InvalidHex { position });
+		}
+		Ok(Self {
+			secret: Secret::from(secret_hex.as_bytes()),
+			pin: Secret::from(pin.as_bytes()),
+			time_source,
+		})
+	}
+
+	/// Calculate the mOTP code for the given time, represented as seconds from the unix epoch.
+	pub fn code_at_time(&self, time: u64) -> MotpCode {
+		self.code_at_step(time / MOTP_INTERVAL)
+	}
+
+	/// Replaces the source of the current time. See [TimeSource].
+	#[inline]
+	pub fn set_time_source(&mut self, time_source: C) {
+		self.time_source = time_source;
+	}
+
+	fn code_at_step(&self, step: u64) -> MotpCode {
+		let mut buf = [0; 20];
+		let hash = Md5::new()
+			.chain_update(decimal(step, &mut buf))
+			.chain_update(self.secret.as_bytes())
+			.chain_update(self.pin.as_bytes())
+			.finalize();
+		let mut code = [0; MOTP_CODE_LENGTH];
+		for (i, c) in code.iter_mut().enumerate() {
+			let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+			*c = b"0123456789abcdef"[nibble as usize];
+		}
+		MotpCode(code)
+	}
+}
+
+impl<C: TimeSource> Motp<C> {
+	/// Calculate the mOTP code for the current time.
+	pub fn code(&mut self) -> Result<MotpCode, OtpError> {
+		let time = self.time_source.unix_seconds()?;
+		Ok(self.code_at_time(time))
+	}
+
+	/// Validates a code typed by a user as being valid for the current time, accepting codes for
+	/// up to `skew` 10 second steps before or after the current one.
+	///
+	/// The code is case-insensitive, and compared in constant time.
+	pub fn validate_code(&mut self, code: &str, skew: usize) -> bool {
+		let Ok(code) = <[u8; MOTP_CODE_LENGTH]>::try_from(code.as_bytes()) else {
+			return false;
+		};
+		let Ok(time) = self.time_source.unix_seconds() else {
+			return false;
+		};
+		let code = code.map(|c| c.to_ascii_lowercase());
+		let step = time / MOTP_INTERVAL;
+		let skew = skew as u64;
+		let steps = step.saturating_sub(skew)..=step.saturating_add(skew);
+		find_match(steps, |step| self.code_at_step(step).0.ct_eq(&code)).is_some()
+	}
+}
+
+impl<C> Debug for Motp<C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Motp").finish_non_exhaustive()
+	}
+}
+
+/// Writes the number as decimal digits into the buffer, returning the part that was written.
+fn decimal(mut value: u64, buf: &mut [u8; 20]) -> &[u8] {
+	let mut start = buf.len();
+	loop {
+		start -= 1;
+		buf[start] = b'0' + (value % 10) as u8;
+		value /= 10;
+		if value == 0 {
+			break;
+		}
+	}
+	&buf[start..]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decimal, Motp};
+	use crate::error::OtpError;
+
+	#[test]
+	fn motp_code_vectors() {
+		let motp = Motp::new("0000000000000000", "1234", || 0).unwrap();
+		for (time, expected) in [
+			(0, "6816ad"),
+			(59, "d25c01"),
+			(1111111109, "dca196"),
+			(1234567890, "fd20a5"),
+			(2000000000, "0f77ed"),
+		] {
+			assert_eq!(motp.code_at_time(time).as_str(), expected);
+		}
+	}
+
+	#[test]
+	fn motp_validate() {
+		let mut motp = Motp::new("0000000000000000", "1234", || 1234567890).unwrap();
+		assert_eq!(motp.code().unwrap().as_str(), "fd20a5");
+		assert!(motp.validate_code("fd20a5", 0));
+		assert!(motp.validate_code("FD20A5", 0));
+		assert!(!motp.validate_code("fd20a", 0));
+		assert!(!motp.validate_code("fd20a6", 3));
+		let earlier = motp.code_at_time(1234567890 - 30);
+		assert!(!motp.validate_code(earlier.as_str(), 2));
+		assert!(motp.validate_code(earlier.as_str(), 3));
+	}
+
+	#[test]
+	fn motp_invalid_secret() {
+		assert_eq!(
+			Motp::new("", "1234", || 0).unwrap_err(),
+			OtpError::MissingSecret
+		);
+		assert_eq!(
+			Motp::new("00g0", "1234", || 0).unwrap_err(),
+			OtpError::InvalidHex { position: 2 }
+		);
+	}
+
+	#[test]
+	fn motp_decimal() {
+		let mut buf = [0; 20];
+		assert_eq!(decimal(0, &mut buf), b"0");
+		assert_eq!(decimal(123456789, &mut buf), b"123456789");
+		assert_eq!(decimal(u64::MAX, &mut buf), b"18446744073709551615");
+	}
+}
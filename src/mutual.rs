@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{
+	error::OtpError,
+	hotp::Hotp,
+	mac::{Hmac, OtpMac},
+	otp::Otp,
+};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// Mutual authentication over a pair of HOTP counters: the client proves itself with
+/// the code for a counter, and the server responds with the code for the counter after it.
+///
+/// Both sides wrap their own [Hotp] instance with the shared secret. The tokens returned along
+/// the way make sure the steps happen in order: the server can only respond to a verified
+/// client code, and the client can only check a response to a code it issued.
+///
+/// ```rust
+/// use otpee::{Hotp, MutualAuth};
+/// use sha1::Sha1;
+///
+/// let key = b"12345678901234567890";
+/// let mut client = MutualAuth::new(Hotp::<Sha1>::new(key, 6).unwrap());
+/// let mut server = MutualAuth::new(Hotp::<Sha1>::new(key, 6).unwrap());
+///
+/// let (code, pending) = client.client_code().unwrap();
+/// let token = server.verify_client(*code, 2).unwrap();
+/// let response = server.server_response(token).unwrap();
+/// assert!(client.verify_server(pending, *response));
+/// ```
+#[derive(Debug)]
+pub struct MutualAuth<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac = Hmac<D>> {
+	hotp: Hotp<D, M>,
+}
+
+/// Proof that a client code was verified, needed to issue the server's response.
+#[must_use]
+#[derive(Debug)]
+pub struct RespondToken {
+	counter: u64,
+}
+
+/// A client code that was issued, needed to verify the server's response to it.
+#[must_use]
+#[derive(Debug)]
+pub struct AwaitingResponse {
+	counter: u64,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac> MutualAuth<D, M> {
+	/// Wraps the given HOTP instance.
+	#[inline]
+	pub fn new(hotp: Hotp<D, M>) -> Self {
+		Self { hotp }
+	}
+
+	/// Returns the wrapped HOTP instance.
+	#[inline]
+	pub fn into_inner(self) -> Hotp<D, M> {
+		self.hotp
+	}
+
+	/// Returns the current counter value.
+	#[inline]
+	pub fn counter(&self) -> u64 {
+		self.hotp.counter()
+	}
+
+	/// Issues the client code for the current counter, advancing the counter past both it
+	/// and the counter of the expected response, so neither can be used again.
+	pub fn client_code(&mut self) -> Result<(Otp, AwaitingResponse), OtpError> {
+		let counter = self.hotp.counter();
+		let response = counter.checked_add(1).ok_or(OtpError::CounterOverflow)?;
+		let code = self.hotp.code_at(counter)?;
+		self.hotp.advance_past(response)?;
+		Ok((code, AwaitingResponse { counter: response }))
+	}
+
+	/// Verifies a client code against the current counter and the next `window` counters,
+	/// like [Hotp::validate], returning a token for responding to it.
+	///
+	/// On success, the counter is advanced past both the matched counter and
+	/// the counter of the response, so the client code can't be replayed.
+	pub fn verify_client(&mut self, code: u64, window: usize) -> Option<RespondToken> {
+		let matched = self.hotp.validate(code, window)?;
+		let response = matched.checked_add(1)?;
+		self.hotp.advance_past(response).ok()?;
+		Some(RespondToken { counter: response })
+	}
+
+	/// Calculates the server's response to a verified client code,
+	/// which is the code for the counter after the one it matched.
+	#[inline]
+	pub fn server_response(&mut self, token: RespondToken) -> Result<Otp, OtpError> {
+		self.hotp.code_at(token.counter)
+	}
+
+	/// Verifies the server's response to an issued client code.
+	/// Only the code for the counter after the client code is accepted.
+	pub fn verify_server(&mut self, pending: AwaitingResponse, code: u64) -> bool {
+		self.hotp.find_counter([pending.counter], code).is_some()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::MutualAuth;
+	use crate::hotp::Hotp;
+	use sha1::Sha1;
+
+	fn pair() -> (MutualAuth<Sha1>, MutualAuth<Sha1>) {
+		let key = b"12345678901234567890";
+		(
+			MutualAuth::new(Hotp::new(key, 6).unwrap()),
+			MutualAuth::new(Hotp::new(key, 6).unwrap()),
+		)
+	}
+
+	#[test]
+	fn mutual_handshake() {
+		let (mut client, mut server) = pair();
+		for _ in 0..2 {
+			let (code, pending) = client.client_code().unwrap();
+			let token = server.verify_client(*code, 2).unwrap();
+			let response = server.server_response(token).unwrap();
+			assert!(client.verify_server(pending, *response));
+		}
+		assert_eq!(client.counter(), 4);
+		assert_eq!(server.counter(), 4);
+		// The second handshake used counters 2 and 3.
+		let (code, pending) = client.client_code().unwrap();
+		assert_eq!(code, 338314);
+		assert!(!client.verify_server(pending, 338314));
+	}
+
+	#[test]
+	fn mutual_replayed_client_code() {
+		let (mut client, mut server) = pair();
+		let (code, _) = client.client_code().unwrap();
+		let token = server.verify_client(*code, 2);
+		assert!(token.is_some());
+		assert!(server.verify_client(*code, 2).is_none());
+		// The response counter can't be used as a client code either.
+		let (response, _) = MutualAuth::<Sha1>::new(
+			Hotp::builder()
+				.counter(1)
+				.build(b"12345678901234567890")
+				.unwrap(),
+		)
+		.client_code()
+		.unwrap();
+		assert!(server.verify_client(*response, 2).is_none());
+	}
+}
@@ -1,26 +1,73 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
-use alloc::fmt::{self, Display, Formatter};
+use crate::hotp::MAX_DIGITS;
 use constant_time_eq::constant_time_eq;
-use core::{cmp::PartialEq, convert::AsRef, ops::Deref};
+use core::{
+	cmp::PartialEq,
+	convert::AsRef,
+	fmt::{self, Display, Formatter},
+	ops::Deref,
+};
+
+/// The maximum length of a code, which is [MAX_DIGITS] plus the checksum digit.
+pub(crate) const MAX_LENGTH: usize = MAX_DIGITS + 1;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 /// A one-time password.
 /// Can just be treated like a normal number, while also having a
 /// [Display] implementation that shows it padded with zeroes.
 pub struct Otp {
-	code: u32,
+	code: u64,
 	length: usize,
 }
 
 impl Otp {
 	#[inline]
-	pub(crate) fn new(code: u32, length: usize) -> Self {
+	pub(crate) fn new(code: u64, length: usize) -> Self {
 		Self { code, length }
 	}
+
+	/// Returns the number of digits this code is displayed with,
+	/// including the checksum digit, if there is one.
+	#[inline]
+	pub fn length(&self) -> usize {
+		self.length
+	}
+
+	/// Writes the code as zero-padded ASCII digits into the given buffer,
+	/// returning the part that was written.
+	pub(crate) fn write_digits<'a>(&self, buf: &'a mut [u8; MAX_LENGTH]) -> &'a [u8] {
+		let digits = &mut buf[..self.length.min(MAX_LENGTH)];
+		let mut code = self.code;
+		for digit in digits.iter_mut().rev() {
+			*digit = b'0' + (code % 10) as u8;
+			code /= 10;
+		}
+		digits
+	}
+}
+
+/// Collects the digits of a code typed by a user into the given buffer,
+/// skipping any ASCII whitespace and dashes, and returning the digits collected.
+///
+/// Returns `None` if the code contains any other characters, or is too long to be valid.
+pub(crate) fn parse_digits<'a>(input: &str, buf: &'a mut [u8; MAX_LENGTH]) -> Option<&'a [u8]> {
+	let mut len = 0;
+	for byte in input.bytes() {
+		match byte {
+			b'0'..=b'9' => {
+				*buf.get_mut(len)? = byte;
+				len += 1;
+			}
+			b'-' => {}
+			byte if byte.is_ascii_whitespace() => {}
+			_ => return None,
+		}
+	}
+	Some(&buf[..len])
 }
 
 impl Deref for Otp {
-	type Target = u32;
+	type Target = u64;
 
 	#[inline]
 	fn deref(&self) -> &Self::Target {
@@ -28,9 +75,9 @@ impl Deref for Otp {
 	}
 }
 
-impl AsRef<u32> for Otp {
+impl AsRef<u64> for Otp {
 	#[inline]
-	fn as_ref(&self) -> &u32 {
+	fn as_ref(&self) -> &u64 {
 		&self.code
 	}
 }
@@ -38,33 +85,46 @@ impl AsRef<u32> for Otp {
 impl PartialEq<u32> for Otp {
 	#[inline]
 	fn eq(&self, other: &u32) -> bool {
-		constant_time_eq(&self.to_ne_bytes(), &other.to_ne_bytes())
+		constant_time_eq(&self.to_ne_bytes(), &u64::from(*other).to_ne_bytes())
 	}
 }
 
 impl Display for Otp {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-		#[cfg(feature = "std")]
-		return write!(f, "{:0width$}", self.code, width = self.length);
+		write!(f, "{:0width$}", self.code, width = self.length)
+	}
+}
 
-		#[cfg(not(feature = "std"))]
-		{
-			use alloc::{
-				string::{String, ToString},
-				vec::Vec,
-			};
+#[cfg(feature = "arbitrary")]
+/// Generates codes of 1 to [MAX_DIGITS] + 1 digits, counting the
+/// checksum digit, which always fit in their length.
+impl<'a> arbitrary::Arbitrary<'a> for Otp {
+	fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+		let length = u.int_in_range(1..=MAX_LENGTH)?;
+		let code = u.int_in_range(0..=10_u64.pow(length as u32) - 1)?;
+		Ok(Self::new(code, length))
+	}
+}
 
-			let mut out = core::iter::repeat('0')
-				.take(self.length)
-				.collect::<Vec<char>>();
-			let out_len = out.len();
-			let code = self.code.to_string().chars().collect::<Vec<char>>();
-			let code_length = code.len();
-			assert!(code_length <= out_len);
-			let start = out_len - code_length;
-			out[start..].copy_from_slice(&code);
+#[cfg(test)]
+mod tests {
+	use super::{parse_digits, Otp, MAX_LENGTH};
 
-			return f.write_str(&out.into_iter().collect::<String>());
-		}
+	#[test]
+	fn otp_write_digits() {
+		let mut buf = [0; MAX_LENGTH];
+		assert_eq!(Otp::new(12345, 7).write_digits(&mut buf), b"0012345");
+		assert_eq!(Otp::new(7081804, 8).write_digits(&mut buf), b"07081804");
+	}
+
+	#[test]
+	fn otp_parse_digits() {
+		let mut buf = [0; MAX_LENGTH];
+		assert_eq!(parse_digits("123 456", &mut buf), Some(&b"123456"[..]));
+		assert_eq!(parse_digits(" 012-345\r\n", &mut buf), Some(&b"012345"[..]));
+		assert_eq!(parse_digits("", &mut buf), Some(&b""[..]));
+		assert_eq!(parse_digits("12345a", &mut buf), None);
+		assert_eq!(parse_digits("+123456", &mut buf), None);
+		assert_eq!(parse_digits("123456789012", &mut buf), None);
 	}
 }
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Commonly used combinations of TOTP settings.
+use crate::algorithm::Algorithm;
+
+/// A commonly used combination of TOTP settings, see [TotpBuilder::preset](crate::TotpBuilder::preset).
+///
+/// More presets may be added in the future, so matching on this should include a wildcard arm.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Preset {
+	/// The defaults from RFC 6238, and what most authenticator apps expect:
+	/// 6 digits, an interval of 30 seconds and SHA-1.
+	#[default]
+	Rfc6238Default,
+	/// 8 digits, an interval of 30 seconds and SHA-256, as required by some enterprise identity providers.
+	EightDigitSha256,
+	/// 8 digits, an interval of 30 seconds and SHA-512.
+	EightDigitSha512,
+	/// 7 digits, an interval of 10 seconds and SHA-1, as used by Authy.
+	SevenDigit10s,
+}
+
+/// The settings of a [Preset].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PresetParams {
+	/// The number of digits in codes.
+	pub digits: usize,
+	/// The length of a time step, in seconds.
+	pub interval: u64,
+	/// The number of time steps before and after the current one that are accepted.
+	pub skew: usize,
+	/// The hash algorithm that should be used.
+	pub algorithm: Algorithm,
+}
+
+impl Preset {
+	/// Returns the settings of the preset.
+	pub fn params(self) -> PresetParams {
+		let (digits, interval, algorithm) = match self {
+			Preset::Rfc6238Default => (6, 30, Algorithm::Sha1),
+			Preset::EightDigitSha256 => (8, 30, Algorithm::Sha256),
+			Preset::EightDigitSha512 => (8, 30, Algorithm::Sha512),
+			Preset::SevenDigit10s => (7, 10, Algorithm::Sha1),
+		};
+		PresetParams {
+			digits,
+			interval,
+			skew: 1,
+			algorithm,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Preset, PresetParams};
+	use crate::algorithm::Algorithm;
+
+	#[test]
+	fn preset_params() {
+		let params = |digits, interval, algorithm| PresetParams {
+			digits,
+			interval,
+			skew: 1,
+			algorithm,
+		};
+		assert_eq!(
+			Preset::Rfc6238Default.params(),
+			params(6, 30, Algorithm::Sha1)
+		);
+		assert_eq!(
+			Preset::EightDigitSha256.params(),
+			params(8, 30, Algorithm::Sha256)
+		);
+		assert_eq!(
+			Preset::EightDigitSha512.params(),
+			params(8, 30, Algorithm::Sha512)
+		);
+		assert_eq!(
+			Preset::SevenDigit10s.params(),
+			params(7, 10, Algorithm::Sha1)
+		);
+		assert_eq!(Preset::default(), Preset::Rfc6238Default);
+	}
+}
@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::error::OtpError;
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+use qrcode::{Color, EcLevel, QrCode};
+
+/// How much of a QR code can be damaged while still being readable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorCorrection {
+	/// About 7% of the code can be restored.
+	Low,
+	/// About 15% of the code can be restored.
+	#[default]
+	Medium,
+	/// About 25% of the code can be restored.
+	Quartile,
+	/// About 30% of the code can be restored.
+	High,
+}
+
+impl From<ErrorCorrection> for EcLevel {
+	fn from(level: ErrorCorrection) -> Self {
+		match level {
+			ErrorCorrection::Low => EcLevel::L,
+			ErrorCorrection::Medium => EcLevel::M,
+			ErrorCorrection::Quartile => EcLevel::Q,
+			ErrorCorrection::High => EcLevel::H,
+		}
+	}
+}
+
+/// The settings for generating a QR code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QrOptions {
+	/// The error correction level, which defaults to [ErrorCorrection::Medium].
+	pub error_correction: ErrorCorrection,
+	/// The number of light modules around the code, which defaults to the 4 the standard requires.
+	pub quiet_zone: usize,
+}
+
+impl Default for QrOptions {
+	fn default() -> Self {
+		Self {
+			error_correction: ErrorCorrection::default(),
+			quiet_zone: 4,
+		}
+	}
+}
+
+/// A QR code, such as one for a provisioning URI from
+/// [Account::provisioning_qr](crate::Account::provisioning_qr).
+///
+/// ```rust
+/// use otpee::{Account, Totp};
+/// use sha1::Sha1;
+///
+/// let totp = Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, || 59).unwrap();
+/// let account = Account::new(totp, "alice@example.com", Some("Example"));
+/// let qr = account.provisioning_qr(b"12345678901234567890").unwrap();
+/// assert!(qr.to_svg().starts_with("<svg"));
+/// println!("{}", qr.to_unicode());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrImage {
+	width: usize,
+	modules: Vec<bool>,
+	quiet_zone: usize,
+}
+
+impl QrImage {
+	/// Encodes the data as a QR code, with the given options.
+	///
+	/// Returns [OtpError::QrFailed] if the data doesn't fit in a QR code.
+	pub fn new<A: AsRef<[u8]>>(data: A, options: QrOptions) -> Result<Self, OtpError> {
+		let code = QrCode::with_error_correction_level(data, options.error_correction.into())
+			.map_err(|_| OtpError::QrFailed)?;
+		Ok(Self {
+			width: code.width(),
+			modules: code
+				.into_colors()
+				.into_iter()
+				.map(|color| color == Color::Dark)
+				.collect(),
+			quiet_zone: options.quiet_zone,
+		})
+	}
+
+	/// Returns the width and height in modules, including the quiet zone.
+	#[inline]
+	pub fn size(&self) -> usize {
+		self.width + 2 * self.quiet_zone
+	}
+
+	/// Returns whether the module at the given position is dark, counting from the top left
+	/// corner of the quiet zone. Positions outside of the code are light.
+	pub fn is_dark(&self, x: usize, y: usize) -> bool {
+		match (
+			x.checked_sub(self.quiet_zone),
+			y.checked_sub(self.quiet_zone),
+		) {
+			(Some(x), Some(y)) if x < self.width && y < self.width => {
+				self.modules[y * self.width + x]
+			}
+			_ => false,
+		}
+	}
+
+	/// Renders the code as an SVG image, with one unit per module, so it can be scaled freely.
+	pub fn to_svg(&self) -> String {
+		let size = self.size();
+		let mut svg = String::new();
+		// Writing to a string can't fail.
+		let _ = write!(
+			svg,
+			"<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {0}\" \
+			 shape-rendering=\"crispEdges\"><rect width=\"{0}\" height=\"{0}\" fill=\"#fff\"/>\
+			 <path fill=\"#000\" d=\"",
+			size
+		);
+		for y in 0..size {
+			for x in (0..size).filter(|&x| self.is_dark(x, y)) {
+				let _ = write!(svg, "M{},{}h1v1h-1z", x, y);
+			}
+		}
+		svg.push_str("\"/></svg>");
+		svg
+	}
+
+	/// Renders the code as lines of unicode block characters, two modules per character,
+	/// for showing it in a terminal. Dark modules are drawn as blocks.
+	pub fn to_unicode(&self) -> String {
+		let size = self.size();
+		let mut out = String::new();
+		for y in (0..size).step_by(2) {
+			for x in 0..size {
+				out.push(match (self.is_dark(x, y), self.is_dark(x, y + 1)) {
+					(true, true) => '█',
+					(true, false) => '▀',
+					(false, true) => '▄',
+					(false, false) => ' ',
+				});
+			}
+			out.push('\n');
+		}
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ErrorCorrection, QrImage, QrOptions};
+	use crate::{account::Account, error::OtpError, totp::Totp};
+	use alloc::{vec, vec::Vec};
+	use sha1::Sha1;
+
+	/// Decodes the QR code, scaled up so the decoder can find it.
+	fn decode(qr: &QrImage) -> String {
+		const SCALE: usize = 4;
+		let size = qr.size() * SCALE;
+		let mut image = rqrr::PreparedImage::prepare_from_greyscale(size, size, |x, y| {
+			if qr.is_dark(x / SCALE, y / SCALE) {
+				0
+			} else {
+				255
+			}
+		});
+		let grids = image.detect_grids();
+		assert_eq!(grids.len(), 1);
+		grids[0].decode().unwrap().1
+	}
+
+	#[test]
+	fn qr_provisioning_round_trip() {
+		let secret = b"12345678901234567890";
+		let totp = Totp::<Sha1, _>::new(secret, 8, 30, 1, || 59).unwrap();
+		let account = Account::new(totp, "alice@example.com", Some("Example Co"));
+		let qr = account.provisioning_qr(secret).unwrap();
+		let uri = decode(&qr);
+		assert_eq!(uri, account.to_uri(secret));
+		let mut totp = Totp::<Sha1, _>::from_uri(&uri, || 59).unwrap();
+		assert_eq!(totp.code().unwrap(), 94287082);
+
+		let options = QrOptions {
+			error_correction: ErrorCorrection::High,
+			quiet_zone: 2,
+		};
+		let qr = account.provisioning_qr_with(secret, options).unwrap();
+		assert_eq!(decode(&qr), uri);
+	}
+
+	#[test]
+	fn qr_rendering() {
+		let options = QrOptions {
+			error_correction: ErrorCorrection::Low,
+			quiet_zone: 1,
+		};
+		let qr = QrImage::new("otpauth", options).unwrap();
+		// Version 1 codes are 21 modules wide, with a finder pattern at the top left.
+		assert_eq!(qr.size(), 23);
+		assert!(!qr.is_dark(0, 0));
+		assert!(qr.is_dark(1, 1));
+		assert!(!qr.is_dark(22, 22));
+		assert!(!qr.is_dark(100, 1));
+
+		let svg = qr.to_svg();
+		assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 23 23\""));
+		assert!(svg.contains("M1,1h1v1h-1z"));
+		assert!(svg.ends_with("\"/></svg>"));
+
+		let unicode = qr.to_unicode();
+		let lines = unicode.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 12);
+		assert!(lines.iter().all(|line| line.chars().count() == 23));
+		assert!(lines[0].starts_with(" ▄▄▄▄▄▄▄ "));
+	}
+
+	#[test]
+	fn qr_too_long() {
+		let data = vec![b'a'; 8000];
+		assert_eq!(
+			QrImage::new(data, QrOptions::default()),
+			Err(OtpError::QrFailed)
+		);
+	}
+}
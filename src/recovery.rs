@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Single-use recovery codes, which are handed out alongside a TOTP token
+//! for when the user loses access to it.
+use crate::error::OtpError;
+use alloc::{string::String, vec, vec::Vec};
+use core::{
+	fmt::{self, Debug, Formatter},
+	marker::PhantomData,
+};
+use digest::Digest;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The characters recovery codes are made of, which is the Base32 alphabet,
+/// so that the easily confused `0`, `1` and `8` can't appear.
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The length of the random salt that codes are hashed with, in bytes.
+const SALT_LEN: usize = 16;
+
+/// The layout of generated recovery codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecoveryFormat {
+	/// The number of groups in each code.
+	pub groups: usize,
+	/// The number of characters in each group.
+	pub group_len: usize,
+}
+
+impl Default for RecoveryFormat {
+	/// Two groups of four characters, such as `ABCD-EFGH`, which is 40 bits of entropy.
+	fn default() -> Self {
+		Self {
+			groups: 2,
+			group_len: 4,
+		}
+	}
+}
+
+/// A set of single-use recovery codes, stored as salted hashes made with the given hash function.
+///
+/// Only the hashes are kept, and serialized, so the codes themselves have to be shown to the
+/// user when they're generated. Each code can only be used once.
+///
+/// ```rust
+/// use otpee::recovery::{RecoveryCodes, RecoveryFormat};
+/// use sha2::Sha256;
+///
+/// let (mut recovery, codes) =
+///     RecoveryCodes::<Sha256>::generate(10, RecoveryFormat::default()).unwrap();
+/// assert_eq!(codes.len(), 10);
+/// assert!(recovery.verify_and_consume(&codes[3].to_lowercase()));
+/// assert!(!recovery.verify_and_consume(&codes[3]));
+/// assert_eq!(recovery.remaining(), 9);
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+pub struct RecoveryCodes<D: Digest> {
+	salt: Vec<u8>,
+	hashes: Vec<Vec<u8>>,
+	#[cfg_attr(feature = "serde", serde(skip))]
+	_digest: PhantomData<fn() -> D>,
+}
+
+impl<D: Digest> RecoveryCodes<D> {
+	/// Generates the given number of random recovery codes in the given format, returning
+	/// them along with the set of their hashes. Groups are separated by dashes.
+	///
+	/// Returns [OtpError::InvalidRecoveryFormat] if the format has no groups, or empty ones.
+	pub fn generate(count: usize, format: RecoveryFormat) -> Result<(Self, Vec<String>), OtpError> {
+		if format.groups == 0 || format.group_len == 0 {
+			return Err(OtpError::InvalidRecoveryFormat);
+		}
+		let mut salt = vec![0; SALT_LEN];
+		getrandom::getrandom(&mut salt).map_err(|_| OtpError::RandomFailed)?;
+		let mut random = vec![0; format.groups * format.group_len];
+		let mut codes = Vec::with_capacity(count);
+		let mut hashes = Vec::with_capacity(count);
+		for _ in 0..count {
+			getrandom::getrandom(&mut random).map_err(|_| OtpError::RandomFailed)?;
+			let mut code = String::with_capacity(format.groups * (format.group_len + 1));
+			for (i, byte) in random.iter().enumerate() {
+				if i > 0 && i % format.group_len == 0 {
+					code.push('-');
+				}
+				// The alphabet has 32 characters, so this doesn't bias any of them.
+				code.push(ALPHABET[usize::from(byte & 0x1F)] as char);
+			}
+			hashes.push(hash::<D>(&salt, &normalize(&code)));
+			codes.push(code);
+		}
+		Ok((
+			Self {
+				salt,
+				hashes,
+				_digest: PhantomData,
+			},
+			codes,
+		))
+	}
+
+	/// Checks whether the input is one of the remaining codes, and if so, consumes it,
+	/// so that it won't be accepted again.
+	///
+	/// Case, dashes and whitespace are ignored, and the input is compared against every
+	/// remaining code in constant time.
+	pub fn verify_and_consume(&mut self, input: &str) -> bool {
+		let hash = hash::<D>(&self.salt, &normalize(input));
+		let mut found = Choice::from(0);
+		let mut index = 0u64;
+		for (i, candidate) in self.hashes.iter().enumerate() {
+			let matches = candidate.ct_eq(&hash) & !found;
+			index = u64::conditional_select(&index, &(i as u64), matches);
+			found |= matches;
+		}
+		if bool::from(found) {
+			self.hashes.swap_remove(index as usize);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Returns the number of codes that haven't been used yet.
+	#[inline]
+	pub fn remaining(&self) -> usize {
+		self.hashes.len()
+	}
+}
+
+// These are implemented by hand, as deriving them would require the hash function to implement them.
+impl<D: Digest> Clone for RecoveryCodes<D> {
+	fn clone(&self) -> Self {
+		Self {
+			salt: self.salt.clone(),
+			hashes: self.hashes.clone(),
+			_digest: PhantomData,
+		}
+	}
+}
+
+impl<D: Digest> PartialEq for RecoveryCodes<D> {
+	fn eq(&self, other: &Self) -> bool {
+		self.salt == other.salt && self.hashes == other.hashes
+	}
+}
+
+impl<D: Digest> Eq for RecoveryCodes<D> {}
+
+impl<D: Digest> Debug for RecoveryCodes<D> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RecoveryCodes")
+			.field("remaining", &self.remaining())
+			.finish_non_exhaustive()
+	}
+}
+
+/// Uppercases the code, and removes any dashes and whitespace.
+fn normalize(input: &str) -> Vec<u8> {
+	input
+		.bytes()
+		.filter(|c| *c != b'-' && !c.is_ascii_whitespace())
+		.map(|c| c.to_ascii_uppercase())
+		.collect()
+}
+
+fn hash<D: Digest>(salt: &[u8], code: &[u8]) -> Vec<u8> {
+	D::new()
+		.chain_update(salt)
+		.chain_update(code)
+		.finalize()
+		.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{RecoveryCodes, RecoveryFormat};
+	use crate::error::OtpError;
+	use sha2::Sha256;
+
+	#[test]
+	fn recovery_generate() {
+		let format = RecoveryFormat {
+			groups: 3,
+			group_len: 5,
+		};
+		let (recovery, codes) = RecoveryCodes::<Sha256>::generate(10, format).unwrap();
+		assert_eq!(recovery.remaining(), 10);
+		assert_eq!(codes.len(), 10);
+		for code in &codes {
+			assert_eq!(code.len(), 17);
+			assert_eq!(code.matches('-').count(), 2);
+			assert!(code
+				.bytes()
+				.all(|c| c == b'-' || super::ALPHABET.contains(&c)));
+		}
+		assert_ne!(codes[0], codes[1]);
+		assert!(matches!(
+			RecoveryCodes::<Sha256>::generate(
+				1,
+				RecoveryFormat {
+					groups: 0,
+					group_len: 4
+				}
+			),
+			Err(OtpError::InvalidRecoveryFormat)
+		));
+	}
+
+	#[test]
+	fn recovery_consume() {
+		let (mut recovery, codes) =
+			RecoveryCodes::<Sha256>::generate(10, RecoveryFormat::default()).unwrap();
+		assert!(!recovery.verify_and_consume("AAAA-AAAA"));
+		let messy = format!(" {} ", codes[4].to_lowercase().replace('-', " - "));
+		assert!(recovery.verify_and_consume(&messy));
+		assert!(!recovery.verify_and_consume(&codes[4]));
+		assert_eq!(recovery.remaining(), 9);
+		assert!(codes
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| *i != 4)
+			.all(|(_, code)| recovery.verify_and_consume(code)));
+		assert_eq!(recovery.remaining(), 0);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn recovery_serde() {
+		let (mut recovery, codes) =
+			RecoveryCodes::<Sha256>::generate(4, RecoveryFormat::default()).unwrap();
+		assert!(recovery.verify_and_consume(&codes[0]));
+		let json = serde_json::to_string(&recovery).unwrap();
+		for code in &codes {
+			assert!(!json.contains(code.as_str()));
+		}
+
+		let mut restored: RecoveryCodes<Sha256> = serde_json::from_str(&json).unwrap();
+		assert_eq!(restored, recovery);
+		assert_eq!(restored.remaining(), 3);
+		assert!(!restored.verify_and_consume(&codes[0]));
+		assert!(restored.verify_and_consume(&codes[1]));
+	}
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, time::TimeSource, totp::Totp, validation::Validation};
+use core::fmt::{self, Debug, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// Which secret a code matched, as reported by [RotatingValidator::validate_code].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SecretMatch {
+	/// The code matched the current secret.
+	Primary,
+	/// The code matched the previous secret, so the user should re-enroll.
+	Previous,
+}
+
+/// Validates TOTP codes against both the current secret and the previous one,
+/// for while users are moving over to a rotated secret.
+///
+/// Both TOTP instances get a clone of the same time source, which is only read once
+/// per validation, so they always agree on the current time.
+///
+/// ```rust
+/// use otpee::rotation::{RotatingValidator, SecretMatch};
+/// use sha1::Sha1;
+///
+/// let mut validator = RotatingValidator::<Sha1>::new(
+///     b"new secret never used before",
+///     Some(b"12345678901234567890"),
+///     8,
+///     30,
+///     1,
+///     || 59,
+/// )
+/// .unwrap();
+/// assert_eq!(validator.validate_code(94287082), Some(SecretMatch::Previous));
+/// validator.expire_previous();
+/// assert!(!validator.has_previous());
+/// ```
+pub struct RotatingValidator<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	primary: Totp<D, C>,
+	previous: Option<Totp<D, C>>,
+	time_source: C,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource + Clone> RotatingValidator<D, C> {
+	/// Creates a new validator for the given current and previous secrets,
+	/// which share all the other settings. See [Totp::new] for the parameters.
+	pub fn new<
+		A: AsRef<[u8]>,
+		B: AsRef<[u8]>,
+		L: Into<Option<usize>> + Copy,
+		I: Into<Option<u64>> + Copy,
+		S: Into<Option<usize>> + Copy,
+	>(
+		primary: A,
+		previous: Option<B>,
+		length: L,
+		interval: I,
+		skew: S,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		let build = |key: &[u8]| Totp::new(key, length, interval, skew, time_source.clone());
+		Ok(Self {
+			primary: build(primary.as_ref())?,
+			previous: previous.map(|key| build(key.as_ref())).transpose()?,
+			time_source,
+		})
+	}
+
+	/// Returns the TOTP instance for the current secret.
+	#[inline]
+	pub fn primary(&mut self) -> &mut Totp<D, C> {
+		&mut self.primary
+	}
+
+	/// Returns whether the previous secret is still accepted.
+	#[inline]
+	pub fn has_previous(&self) -> bool {
+		self.previous.is_some()
+	}
+
+	/// Stops accepting codes for the previous secret.
+	#[inline]
+	pub fn expire_previous(&mut self) {
+		self.previous = None;
+	}
+
+	/// Validates the code against both secrets, returning which one it matched, if any.
+	/// See [Totp::validate_code].
+	///
+	/// The code is always checked against both secrets, so the time taken doesn't reveal
+	/// which one matched.
+	pub fn validate_code(&mut self, code: u64) -> Option<SecretMatch> {
+		let millis = self.time_source.unix_millis_wide();
+		let primary = self.primary.validate_code_at_reported_time(code, millis);
+		let previous = self
+			.previous
+			.as_mut()
+			.map(|previous| previous.validate_code_at_reported_time(code, millis));
+		match (primary, previous) {
+			(Validation::Valid { .. }, _) => Some(SecretMatch::Primary),
+			(_, Some(Validation::Valid { .. })) => Some(SecretMatch::Previous),
+			_ => None,
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Debug for RotatingValidator<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RotatingValidator")
+			.field("primary", &self.primary)
+			.field("previous", &self.previous)
+			.finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{RotatingValidator, SecretMatch};
+	use alloc::rc::Rc;
+	use core::cell::Cell;
+	use sha1::Sha1;
+
+	#[test]
+	fn rotation_validate_code() {
+		let now = Rc::new(Cell::new(59));
+		let clock = now.clone();
+		let mut validator = RotatingValidator::<Sha1, _>::new(
+			b"12345678901234567890",
+			Some(b"abcdefghijklmnopqrst"),
+			8,
+			30,
+			1,
+			move || clock.get(),
+		)
+		.unwrap();
+		let mut previous_totp =
+			crate::Totp::<Sha1>::new(b"abcdefghijklmnopqrst", 8, 30, 1, || 59).unwrap();
+		let previous = *previous_totp.code().unwrap();
+		assert_eq!(
+			validator.validate_code(94287082),
+			Some(SecretMatch::Primary)
+		);
+		assert_eq!(
+			validator.validate_code(previous),
+			Some(SecretMatch::Previous)
+		);
+		assert_eq!(validator.validate_code(12345678), None);
+		// Each secret keeps its own replay protection.
+		assert_eq!(validator.validate_code(previous), None);
+
+		// Once expired, the same validator rejects codes for the previous secret,
+		// even for a time step that hasn't been used yet.
+		validator.expire_previous();
+		now.set(89);
+		let unused = *previous_totp.code_at_time(89).unwrap();
+		assert_eq!(validator.validate_code(unused), None);
+		let next = *validator.primary().code_at_time(89).unwrap();
+		assert_eq!(validator.validate_code(next), Some(SecretMatch::Primary));
+	}
+
+	#[test]
+	fn rotation_send() {
+		fn assert_send<T: Send>() {}
+		assert_send::<RotatingValidator<Sha1>>();
+	}
+}
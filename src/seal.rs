@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{
+	error::OtpError,
+	hotp::OverflowPolicy,
+	secret::Secret,
+	state::{self, HotpState, TotpState, STATE_BYTES_LEN},
+};
+use alloc::vec::Vec;
+use chacha20poly1305::{
+	aead::{Aead, KeyInit, Payload},
+	ChaCha20Poly1305, Nonce,
+};
+use core::time::Duration;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// The current version of the sealed state format.
+///
+/// Version 1 consists of the version byte, a random 12-byte nonce, and the binary state,
+/// encrypted and authenticated with ChaCha20-Poly1305. The version byte, along with the kind
+/// of state, is authenticated as associated data.
+pub const SEALED_VERSION: u8 = 1;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+impl HotpState {
+	/// Encrypts the state, including the secret, with the given 256-bit key, for storage.
+	/// See [SEALED_VERSION] for the format.
+	///
+	/// Returns [OtpError::RandomFailed] if a nonce couldn't be generated.
+	///
+	/// ```rust
+	/// use otpee::{Hotp, HotpState, Secret};
+	/// use sha1::Sha1;
+	///
+	/// let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+	/// let mut state = hotp.export_state();
+	/// state.secret = Some(Secret::from(b"12345678901234567890"));
+	///
+	/// let key = [42; 32];
+	/// let sealed = state.seal(&key).unwrap();
+	/// assert_eq!(HotpState::open(&key, &sealed).unwrap(), state);
+	/// ```
+	pub fn seal(&self, key: &[u8; 32]) -> Result<Vec<u8>, OtpError> {
+		let mut plaintext = Vec::new();
+		push_snapshot(&mut plaintext, self.counter, self.digits)?;
+		plaintext.push(u8::from(self.checksum));
+		push_offset(&mut plaintext, self.truncation_offset);
+		plaintext.extend_from_slice(&(self.window as u64).to_be_bytes());
+		plaintext.push(match self.overflow_policy {
+			OverflowPolicy::Error => 0,
+			OverflowPolicy::Wrap => 1,
+			OverflowPolicy::Saturate => 2,
+		});
+		plaintext.extend_from_slice(&self.minimum_counter.to_be_bytes());
+		push_secret(&mut plaintext, self.secret.as_ref());
+		seal(key, b'H', plaintext)
+	}
+
+	/// Decrypts a state sealed by [HotpState::seal] with the given key.
+	///
+	/// Returns [OtpError::DecryptionFailed] if the key is wrong, the sealed state was
+	/// truncated or tampered with, or it holds an unknown overflow policy,
+	/// and [OtpError::UnsupportedStateVersion] if it was sealed in an unknown format.
+	pub fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Self, OtpError> {
+		let plaintext = open(key, b'H', sealed)?;
+		let mut reader = Reader(&plaintext);
+		let (counter, digits) = reader.snapshot()?;
+		Ok(Self {
+			counter,
+			digits,
+			checksum: reader.u8()? != 0,
+			truncation_offset: reader.offset()?,
+			window: reader.u64()? as usize,
+			overflow_policy: match reader.u8()? {
+				0 => OverflowPolicy::Error,
+				1 => OverflowPolicy::Wrap,
+				2 => OverflowPolicy::Saturate,
+				_ => return Err(OtpError::DecryptionFailed),
+			},
+			minimum_counter: reader.u64()?,
+			secret: reader.secret()?,
+		})
+	}
+}
+
+impl TotpState {
+	/// Encrypts the state, including the secret, with the given 256-bit key, for storage.
+	/// See [HotpState::seal].
+	pub fn seal(&self, key: &[u8; 32]) -> Result<Vec<u8>, OtpError> {
+		let mut plaintext = Vec::new();
+		push_snapshot(
+			&mut plaintext,
+			self.last_accepted_step.unwrap_or(0),
+			self.digits,
+		)?;
+		plaintext.push(u8::from(self.last_accepted_step.is_some()));
+		plaintext.push(u8::from(self.checksum));
+		push_offset(&mut plaintext, self.truncation_offset);
+		plaintext.extend_from_slice(&self.interval.as_secs().to_be_bytes());
+		plaintext.extend_from_slice(&self.interval.subsec_nanos().to_be_bytes());
+		plaintext.extend_from_slice(&self.epoch.to_be_bytes());
+		plaintext.extend_from_slice(&(self.skew as u64).to_be_bytes());
+		plaintext.extend_from_slice(&self.drift_offset.to_be_bytes());
+		plaintext.extend_from_slice(&self.max_drift_offset.to_be_bytes());
+		plaintext.push(u8::from(self.learn_drift));
+		plaintext.push(u8::from(self.single_use));
+		plaintext.push(u8::from(self.strict_monotonic));
+		push_optional_u64(&mut plaintext, self.grace_period);
+		plaintext.push(u8::from(self.tolerance.is_some()));
+		let (behind, ahead) = self.tolerance.unwrap_or_default();
+		plaintext.extend_from_slice(&behind.to_be_bytes());
+		plaintext.extend_from_slice(&ahead.to_be_bytes());
+		push_secret(&mut plaintext, self.secret.as_ref());
+		seal(key, b'T', plaintext)
+	}
+
+	/// Decrypts a state sealed by [TotpState::seal] with the given key.
+	/// See [HotpState::open].
+	pub fn open(key: &[u8; 32], sealed: &[u8]) -> Result<Self, OtpError> {
+		let plaintext = open(key, b'T', sealed)?;
+		let mut reader = Reader(&plaintext);
+		let (step, digits) = reader.snapshot()?;
+		Ok(Self {
+			last_accepted_step: (reader.u8()? != 0).then_some(step),
+			digits,
+			checksum: reader.u8()? != 0,
+			truncation_offset: reader.offset()?,
+			interval: reader.duration()?,
+			epoch: reader.u64()?,
+			skew: reader.u64()? as usize,
+			drift_offset: reader.u64()? as i64,
+			max_drift_offset: reader.u64()?,
+			learn_drift: reader.u8()? != 0,
+			single_use: reader.u8()? != 0,
+			strict_monotonic: reader.u8()? != 0,
+			grace_period: reader.optional_u64()?,
+			tolerance: {
+				let present = reader.u8()? != 0;
+				let tolerance = (reader.u64()?, reader.u64()?);
+				present.then_some(tolerance)
+			},
+			secret: reader.secret()?,
+		})
+	}
+}
+
+/// Encrypts the plaintext. With the `zeroize` feature enabled, it is wiped afterwards,
+/// as it contains the secret.
+#[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+fn seal(key: &[u8; 32], kind: u8, mut plaintext: Vec<u8>) -> Result<Vec<u8>, OtpError> {
+	let mut nonce = [0; NONCE_LEN];
+	getrandom::getrandom(&mut nonce).map_err(|_| OtpError::RandomFailed)?;
+	let cipher = ChaCha20Poly1305::new(key.into());
+	let payload = Payload {
+		msg: &plaintext,
+		aad: &[SEALED_VERSION, kind],
+	};
+	let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), payload);
+	#[cfg(feature = "zeroize")]
+	plaintext.zeroize();
+	let ciphertext = ciphertext.map_err(|_| OtpError::InvalidLength)?;
+	let mut sealed = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+	sealed.push(SEALED_VERSION);
+	sealed.extend_from_slice(&nonce);
+	sealed.extend_from_slice(&ciphertext);
+	Ok(sealed)
+}
+
+/// Decrypts and authenticates a sealed state of the given kind.
+fn open(key: &[u8; 32], kind: u8, sealed: &[u8]) -> Result<Plaintext, OtpError> {
+	match sealed.first() {
+		None => return Err(OtpError::DecryptionFailed),
+		Some(&SEALED_VERSION) => {}
+		Some(&got) => return Err(OtpError::UnsupportedStateVersion { got }),
+	}
+	if sealed.len() < 1 + NONCE_LEN + TAG_LEN {
+		return Err(OtpError::DecryptionFailed);
+	}
+	let (nonce, ciphertext) = sealed[1..].split_at(NONCE_LEN);
+	let cipher = ChaCha20Poly1305::new(key.into());
+	let payload = Payload {
+		msg: ciphertext,
+		aad: &[SEALED_VERSION, kind],
+	};
+	cipher
+		.decrypt(Nonce::from_slice(nonce), payload)
+		.map(Plaintext)
+		.map_err(|_| OtpError::DecryptionFailed)
+}
+
+/// A decrypted state. With the `zeroize` feature enabled, it is wiped when dropped.
+struct Plaintext(Vec<u8>);
+
+#[cfg(feature = "zeroize")]
+impl Drop for Plaintext {
+	fn drop(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+impl core::ops::Deref for Plaintext {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+fn push_snapshot(buf: &mut Vec<u8>, counter: u64, digits: usize) -> Result<(), OtpError> {
+	let mut snapshot = [0; STATE_BYTES_LEN];
+	state::encode(counter, digits, &mut snapshot)?;
+	buf.extend_from_slice(&snapshot);
+	Ok(())
+}
+
+fn push_offset(buf: &mut Vec<u8>, offset: Option<u8>) {
+	buf.extend_from_slice(&[u8::from(offset.is_some()), offset.unwrap_or(0)]);
+}
+
+fn push_optional_u64(buf: &mut Vec<u8>, value: Option<u64>) {
+	buf.push(u8::from(value.is_some()));
+	buf.extend_from_slice(&value.unwrap_or(0).to_be_bytes());
+}
+
+fn push_secret(buf: &mut Vec<u8>, secret: Option<&Secret>) {
+	buf.push(u8::from(secret.is_some()));
+	if let Some(secret) = secret {
+		buf.extend_from_slice(secret.as_bytes());
+	}
+}
+
+/// Reads the fields of a decrypted state in order.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8], OtpError> {
+		if self.0.len() < len {
+			return Err(OtpError::StateTooShort);
+		}
+		let (taken, rest) = self.0.split_at(len);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	fn snapshot(&mut self) -> Result<(u64, usize), OtpError> {
+		state::decode(self.take(STATE_BYTES_LEN)?)
+	}
+
+	fn u8(&mut self) -> Result<u8, OtpError> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn u64(&mut self) -> Result<u64, OtpError> {
+		let mut bytes = [0; 8];
+		bytes.copy_from_slice(self.take(8)?);
+		Ok(u64::from_be_bytes(bytes))
+	}
+
+	fn duration(&mut self) -> Result<Duration, OtpError> {
+		let secs = self.u64()?;
+		let mut nanos = [0; 4];
+		nanos.copy_from_slice(self.take(4)?);
+		Ok(Duration::new(secs, u32::from_be_bytes(nanos)))
+	}
+
+	fn offset(&mut self) -> Result<Option<u8>, OtpError> {
+		let present = self.u8()? != 0;
+		let offset = self.u8()?;
+		Ok(present.then_some(offset))
+	}
+
+	fn optional_u64(&mut self) -> Result<Option<u64>, OtpError> {
+		let present = self.u8()? != 0;
+		let value = self.u64()?;
+		Ok(present.then_some(value))
+	}
+
+	fn secret(&mut self) -> Result<Option<Secret>, OtpError> {
+		let present = self.u8()? != 0;
+		let secret = core::mem::take(&mut self.0);
+		Ok(present.then(|| Secret::from(secret)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{error::OtpError, Hotp, HotpState, Secret, Totp, TotpState};
+	use sha1::Sha1;
+
+	const KEY: [u8; 32] = [7; 32];
+
+	fn hotp_state() -> HotpState {
+		let mut hotp = Hotp::<Sha1>::builder()
+			.digits(8)
+			.checksum(true)
+			.window(3)
+			.build(b"12345678901234567890")
+			.unwrap();
+		hotp.set_counter(42).unwrap();
+		let mut state = hotp.export_state();
+		state.secret = Some(Secret::from(b"12345678901234567890"));
+		state
+	}
+
+	#[test]
+	fn seal_round_trip() {
+		let state = hotp_state();
+		let sealed = state.seal(&KEY).unwrap();
+		assert_eq!(HotpState::open(&KEY, &sealed).unwrap(), state);
+		// The nonce is random, so sealing again gives a different result.
+		assert_ne!(state.seal(&KEY).unwrap(), sealed);
+
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert!(totp.validate_code(94287082));
+		totp.set_max_drift_offset(20);
+		totp.set_drift_offset(-12);
+		totp.auto_learn_drift(true);
+		totp.set_enforce_single_use(false);
+		totp.set_strict_monotonic(true);
+		totp.set_grace_period(5);
+		totp.set_tolerance_seconds(45, 10);
+		let state = totp.export_state();
+		let sealed = state.seal(&KEY).unwrap();
+		let opened = TotpState::open(&KEY, &sealed).unwrap();
+		assert_eq!(opened, state);
+		assert_eq!(opened.last_accepted_step, Some(1));
+		assert_eq!(opened.drift_offset, -12);
+		assert_eq!(opened.max_drift_offset, 20);
+		assert!(opened.learn_drift);
+		assert!(!opened.single_use && opened.strict_monotonic);
+		assert_eq!(opened.grace_period, Some(5));
+		assert_eq!(opened.tolerance, Some((45, 10)));
+		assert!(opened.secret.is_none());
+	}
+
+	#[test]
+	fn seal_wrong_key() {
+		let sealed = hotp_state().seal(&KEY).unwrap();
+		assert_eq!(
+			HotpState::open(&[8; 32], &sealed),
+			Err(OtpError::DecryptionFailed)
+		);
+		// A sealed HOTP state can't be opened as a TOTP state either.
+		assert_eq!(
+			TotpState::open(&KEY, &sealed),
+			Err(OtpError::DecryptionFailed)
+		);
+	}
+
+	#[test]
+	fn seal_tampered() {
+		let sealed = hotp_state().seal(&KEY).unwrap();
+		for i in 1..sealed.len() {
+			let mut tampered = sealed.clone();
+			tampered[i] ^= 1;
+			assert_eq!(
+				HotpState::open(&KEY, &tampered),
+				Err(OtpError::DecryptionFailed)
+			);
+		}
+		let mut tampered = sealed.clone();
+		tampered[0] = 2;
+		assert_eq!(
+			HotpState::open(&KEY, &tampered),
+			Err(OtpError::UnsupportedStateVersion { got: 2 })
+		);
+		assert_eq!(
+			HotpState::open(&KEY, &sealed[..sealed.len() - 1]),
+			Err(OtpError::DecryptionFailed)
+		);
+		assert_eq!(HotpState::open(&KEY, &[]), Err(OtpError::DecryptionFailed));
+	}
+
+	#[test]
+	fn seal_unknown_overflow_policy() {
+		let state = hotp_state();
+		let mut plaintext = Vec::new();
+		super::push_snapshot(&mut plaintext, state.counter, state.digits).unwrap();
+		plaintext.push(u8::from(state.checksum));
+		super::push_offset(&mut plaintext, state.truncation_offset);
+		plaintext.extend_from_slice(&(state.window as u64).to_be_bytes());
+		plaintext.push(3);
+		plaintext.extend_from_slice(&state.minimum_counter.to_be_bytes());
+		super::push_secret(&mut plaintext, state.secret.as_ref());
+		let sealed = super::seal(&KEY, b'H', plaintext).unwrap();
+		assert_eq!(
+			HotpState::open(&KEY, &sealed),
+			Err(OtpError::DecryptionFailed)
+		);
+	}
+}
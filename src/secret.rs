@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{base32, error::OtpError, hex};
+use alloc::vec::Vec;
+use core::{
+	convert::AsRef,
+	fmt::{self, Debug, Formatter},
+};
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A shared secret, which can be used anywhere a key is accepted.
+///
+/// With the `zeroize` feature enabled, the secret is wiped from memory when dropped.
+///
+/// ```rust
+/// use otpee::{Hotp, Secret};
+/// use sha1::Sha1;
+///
+/// let secret = Secret::from(b"12345678901234567890");
+/// let mut hotp = Hotp::<Sha1>::new(&secret, 6).unwrap();
+/// assert_eq!(hotp.code().unwrap(), 755224);
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(Vec<u8>);
+
+impl Secret {
+	/// Decodes a secret from RFC 4648 Base32, as used by most provisioning flows.
+	///
+	/// Padding is optional, decoding is case-insensitive,
+	/// and any spaces or dashes are ignored.
+	pub fn from_base32(input: &str) -> Result<Self, OtpError> {
+		base32::decode(input).map(Self)
+	}
+
+	/// Decodes a secret from hex, as used by the RFC test vectors.
+	///
+	/// Decoding is case-insensitive, an optional `0x` prefix is allowed,
+	/// and any whitespace is ignored.
+	pub fn from_hex(input: &str) -> Result<Self, OtpError> {
+		hex::decode(input).map(Self)
+	}
+
+	#[cfg(feature = "getrandom")]
+	/// Generates a cryptographically random secret of the given length, in bytes.
+	pub fn generate(len: usize) -> Result<Self, OtpError> {
+		let mut bytes = alloc::vec![0; len];
+		getrandom::getrandom(&mut bytes).map_err(|_| OtpError::RandomFailed)?;
+		Ok(Self(bytes))
+	}
+
+	#[cfg(feature = "getrandom")]
+	/// Generates a cryptographically random secret with the recommended length for the
+	/// given hash function, which is its output size: 20 bytes for SHA-1, 32 for SHA-256,
+	/// and 64 for SHA-512.
+	pub fn generate_for<D: digest::Digest>() -> Result<Self, OtpError> {
+		Self::generate(<D as digest::Digest>::output_size())
+	}
+
+	/// Returns the raw bytes of the secret.
+	#[inline]
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	/// Returns the length of the secret, in bytes.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Returns whether the secret is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl From<Vec<u8>> for Secret {
+	#[inline]
+	fn from(bytes: Vec<u8>) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<&[u8]> for Secret {
+	#[inline]
+	fn from(bytes: &[u8]) -> Self {
+		Self(bytes.to_vec())
+	}
+}
+
+impl<const N: usize> From<&[u8; N]> for Secret {
+	#[inline]
+	fn from(bytes: &[u8; N]) -> Self {
+		Self(bytes.to_vec())
+	}
+}
+
+impl AsRef<[u8]> for Secret {
+	#[inline]
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl Debug for Secret {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("Secret(<redacted>)")
+	}
+}
+
+/// Secrets are serialized as raw bytes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Secret {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_bytes(&self.0)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Secret {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct SecretVisitor;
+
+		impl<'de> serde::de::Visitor<'de> for SecretVisitor {
+			type Value = Secret;
+
+			fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+				f.write_str("a byte array")
+			}
+
+			fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Secret, E> {
+				Ok(Secret::from(bytes))
+			}
+
+			fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<Secret, E> {
+				Ok(Secret::from(bytes))
+			}
+
+			fn visit_seq<A: serde::de::SeqAccess<'de>>(
+				self,
+				mut seq: A,
+			) -> Result<Secret, A::Error> {
+				let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+				while let Some(byte) = seq.next_element()? {
+					bytes.push(byte);
+				}
+				Ok(Secret::from(bytes))
+			}
+		}
+
+		deserializer.deserialize_bytes(SecretVisitor)
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Secret {
+	#[inline]
+	fn zeroize(&mut self) {
+		self.0.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Secret {
+	#[inline]
+	fn drop(&mut self) {
+		self.zeroize();
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Secret {}
+
+#[cfg(test)]
+mod tests {
+	use super::Secret;
+	use crate::Hotp;
+	use sha1::Sha1;
+
+	#[test]
+	fn secret_hotp() {
+		let secret = Secret::from(b"12345678901234567890");
+		let mut hotp = Hotp::<Sha1>::new(&secret, 6).unwrap();
+		assert_eq!(hotp.code_increment().unwrap(), 755224);
+		assert_eq!(hotp.code_increment().unwrap(), 287082);
+		assert_eq!(hotp.code_increment().unwrap(), 359152);
+	}
+
+	#[test]
+	fn secret_base32() {
+		let secret = Secret::from_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+		assert_eq!(secret.as_bytes(), b"12345678901234567890");
+	}
+
+	#[test]
+	fn secret_hex() {
+		let secret = Secret::from_hex("3132333435363738393031323334353637383930").unwrap();
+		assert_eq!(secret.as_bytes(), b"12345678901234567890");
+	}
+
+	#[cfg(feature = "getrandom")]
+	#[test]
+	fn secret_generate() {
+		use sha2::{Sha256, Sha512};
+
+		assert_eq!(Secret::generate(16).unwrap().len(), 16);
+		assert_eq!(Secret::generate_for::<Sha1>().unwrap().len(), 20);
+		assert_eq!(Secret::generate_for::<Sha256>().unwrap().len(), 32);
+		assert_eq!(Secret::generate_for::<Sha512>().unwrap().len(), 64);
+		assert_ne!(Secret::generate(20).unwrap(), Secret::generate(20).unwrap());
+	}
+
+	#[cfg(feature = "zeroize")]
+	#[test]
+	fn secret_zeroize() {
+		use zeroize::{Zeroize, ZeroizeOnDrop};
+
+		fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+		assert_zeroize_on_drop::<Secret>();
+
+		let mut secret = Secret::from(b"12345678901234567890");
+		secret.zeroize();
+		assert!(secret.is_empty());
+	}
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, otp::Otp, time::TimeSource, totp::Totp, validation::Validation};
+use core::fmt::{self, Debug, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A [Totp] instance that can be shared between threads, such as the request handlers
+/// of a web server, with methods that only need `&self`.
+///
+/// Cloning it is cheap, and the clones share the same instance, including its
+/// replay protection. Each call holds an internal lock while it runs, so when the same
+/// code is validated from several threads at once, only one of them accepts it.
+///
+/// ```rust
+/// use otpee::{SharedTotp, Totp};
+/// use sha1::Sha1;
+///
+/// let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// let shared = SharedTotp::new(totp);
+/// let handler = shared.clone();
+/// let accepted = std::thread::spawn(move || handler.validate_code(94287082))
+///     .join()
+///     .unwrap();
+/// assert!(accepted);
+/// assert!(!shared.validate_code(94287082));
+/// ```
+pub struct SharedTotp<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	inner: Arc<Mutex<Totp<D, C>>>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> SharedTotp<D, C> {
+	/// Wraps the TOTP instance, so that it can be shared.
+	pub fn new(totp: Totp<D, C>) -> Self {
+		Self {
+			inner: Arc::new(Mutex::new(totp)),
+		}
+	}
+
+	/// Runs the closure with exclusive access to the TOTP instance,
+	/// for anything that isn't exposed by this wrapper, such as changing its settings.
+	pub fn with<R, F: FnOnce(&mut Totp<D, C>) -> R>(&self, f: F) -> R {
+		f(&mut self.lock())
+	}
+
+	/// Locks the instance. A panic in another thread can't leave it half-updated,
+	/// so if that happened while the lock was held, the instance is still used.
+	fn lock(&self) -> MutexGuard<'_, Totp<D, C>> {
+		self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> SharedTotp<D, C> {
+	/// Calculates the current OTP value, see [Totp::code].
+	#[inline]
+	pub fn code(&self) -> Result<Otp, OtpError> {
+		self.lock().code()
+	}
+
+	/// Validates the code, see [Totp::validate_code].
+	#[inline]
+	pub fn validate_code(&self, code: u64) -> bool {
+		self.lock().validate_code(code)
+	}
+
+	/// Validates the code, returning a detailed result, see [Totp::validate_code_detailed].
+	#[inline]
+	pub fn validate_code_detailed(&self, code: u64) -> Validation {
+		self.lock().validate_code_detailed(code)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Clone for SharedTotp<D, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Self {
+			inner: Arc::clone(&self.inner),
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Debug for SharedTotp<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		// Locking here could deadlock when formatting it from inside of SharedTotp::with.
+		match self.inner.try_lock() {
+			Ok(totp) => f.debug_tuple("SharedTotp").field(&*totp).finish(),
+			Err(_) => f.write_str("SharedTotp(<locked>)"),
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> From<Totp<D, C>> for SharedTotp<D, C> {
+	#[inline]
+	fn from(totp: Totp<D, C>) -> Self {
+		Self::new(totp)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::SharedTotp;
+	use crate::{totp::Totp, validation::Validation};
+	use sha1::Sha1;
+	use std::{
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc, Barrier,
+		},
+		thread,
+	};
+
+	#[test]
+	fn shared_totp_send_sync() {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<SharedTotp<Sha1>>();
+	}
+
+	#[test]
+	fn shared_totp_single_winner() {
+		const THREADS: usize = 8;
+		for _ in 0..50 {
+			let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+			let shared = SharedTotp::new(totp);
+			let barrier = Arc::new(Barrier::new(THREADS));
+			let winners = Arc::new(AtomicUsize::new(0));
+			let handles = (0..THREADS)
+				.map(|_| {
+					let (shared, barrier, winners) =
+						(shared.clone(), barrier.clone(), winners.clone());
+					thread::spawn(move || {
+						barrier.wait();
+						if shared.validate_code(94287082) {
+							winners.fetch_add(1, Ordering::SeqCst);
+						}
+					})
+				})
+				.collect::<Vec<_>>();
+			for handle in handles {
+				handle.join().unwrap();
+			}
+			assert_eq!(winners.load(Ordering::SeqCst), 1);
+			assert_eq!(
+				shared.validate_code_detailed(94287082),
+				Validation::Replayed
+			);
+		}
+	}
+
+	#[test]
+	fn shared_totp_with() {
+		let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		let shared = SharedTotp::from(totp);
+		assert_eq!(shared.code().unwrap(), 94287082);
+		shared.with(|totp| totp.set_skew(0));
+		assert_eq!(shared.with(|totp| totp.skew()), 0);
+	}
+}
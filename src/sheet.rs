@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Sheets of codes generated ahead of time, such as for printing and handing out
+//! as an offline fallback.
+use crate::{error::OtpError, hotp::Hotp, mac::OtpMac, otp::Otp, totp::Totp};
+use alloc::{
+	string::{String, ToString},
+	vec::Vec,
+};
+use core::fmt::{self, Display, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// A code on a [Sheet].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SheetEntry {
+	/// The counter of the code for HOTP sheets, or the time it is for, in seconds from the
+	/// unix epoch, for TOTP sheets.
+	pub index: u64,
+	/// The code, zero-padded to the number of digits.
+	pub code: String,
+}
+
+/// A list of codes generated ahead of time, see [generate_hotp_sheet] and [generate_totp_sheet].
+///
+/// Formatting it with [Display] renders it as a text table with fixed-width columns.
+///
+/// ```rust
+/// use otpee::{sheet, Hotp};
+/// use sha1::Sha1;
+///
+/// let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+/// let sheet = sheet::generate_hotp_sheet(&mut hotp, 8, 3);
+/// let table = sheet.to_string();
+/// assert_eq!(
+///     table.lines().collect::<Vec<_>>(),
+///     [
+///         "Counter | Code  ",
+///         "--------+-------",
+///         "      8 | 399871",
+///         "      9 | 520489",
+///         "     10 | 403154",
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet {
+	/// The codes, in the order they were requested in.
+	pub entries: Vec<SheetEntry>,
+	/// The error that stopped generating codes early, if any,
+	/// in which case the sheet only holds the codes before it.
+	pub error: Option<OtpError>,
+	heading: &'static str,
+}
+
+impl Sheet {
+	/// Returns whether all the requested codes were generated.
+	#[inline]
+	pub fn is_complete(&self) -> bool {
+		self.error.is_none()
+	}
+}
+
+impl Display for Sheet {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let index_width = self
+			.entries
+			.iter()
+			.map(|entry| digit_count(entry.index))
+			.chain([self.heading.len()])
+			.max()
+			.unwrap_or_default();
+		let code_width = self
+			.entries
+			.iter()
+			.map(|entry| entry.code.len())
+			.chain(["Code".len()])
+			.max()
+			.unwrap_or_default();
+		writeln!(
+			f,
+			"{:>index_width$} | {:<code_width$}",
+			self.heading, "Code"
+		)?;
+		writeln!(f, "{:->index_width$}-+-{:-<code_width$}", "", "")?;
+		for entry in &self.entries {
+			writeln!(
+				f,
+				"{:>index_width$} | {:<code_width$}",
+				entry.index, entry.code
+			)?;
+		}
+		Ok(())
+	}
+}
+
+fn digit_count(n: u64) -> usize {
+	n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+/// Generates the HOTP codes for `count` counters, starting at `start`,
+/// without changing the counter of the HOTP instance.
+///
+/// If the counters would go past [u64::MAX], the sheet stops at it, with
+/// [OtpError::CounterOverflow] as its error.
+pub fn generate_hotp_sheet<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac>(
+	hotp: &mut Hotp<D, M>,
+	start: u64,
+	count: usize,
+) -> Sheet {
+	let counters = (0..count as u64).map(|offset| start.checked_add(offset));
+	generate(counters, "Counter", |counter| match counter {
+		Some(counter) => hotp.code_at(counter).map(|code| (counter, code)),
+		None => Err(OtpError::CounterOverflow),
+	})
+}
+
+/// Generates the TOTP codes for the given times, in seconds from the unix epoch,
+/// such as the start of each shift that a code is needed for.
+pub fn generate_totp_sheet<D: Digest + BlockSizeUser + FixedOutputReset, C>(
+	totp: &mut Totp<D, C>,
+	times: &[u64],
+) -> Sheet {
+	generate(times.iter().copied(), "Time", |time| {
+		totp.code_at_time(time).map(|code| (time, code))
+	})
+}
+
+fn generate<T, I: Iterator<Item = T>, F: FnMut(T) -> Result<(u64, Otp), OtpError>>(
+	items: I,
+	heading: &'static str,
+	mut code: F,
+) -> Sheet {
+	let mut sheet = Sheet {
+		entries: Vec::with_capacity(items.size_hint().0),
+		error: None,
+		heading,
+	};
+	for item in items {
+		match code(item) {
+			Ok((index, code)) => sheet.entries.push(SheetEntry {
+				index,
+				code: code.to_string(),
+			}),
+			Err(err) => {
+				sheet.error = Some(err);
+				break;
+			}
+		}
+	}
+	sheet
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{generate_hotp_sheet, generate_totp_sheet};
+	use crate::{error::OtpError, Hotp, Totp};
+	use sha1::Sha1;
+
+	#[test]
+	fn hotp_sheet() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		hotp.set_counter(3).unwrap();
+		let sheet = generate_hotp_sheet(&mut hotp, 5, 100);
+		assert!(sheet.is_complete());
+		assert_eq!(sheet.entries.len(), 100);
+		assert_eq!(hotp.counter(), 3);
+		for entry in &sheet.entries {
+			assert_eq!(entry.code, hotp.code_at(entry.index).unwrap().to_string());
+			assert_eq!(entry.code.len(), 6);
+		}
+		let table = sheet.to_string();
+		let widths = table.lines().map(str::len).collect::<Vec<_>>();
+		assert!(widths
+			.iter()
+			.all(|&width| width == "Counter | 123456".len()));
+	}
+
+	#[test]
+	fn hotp_sheet_overflow() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let sheet = generate_hotp_sheet(&mut hotp, u64::MAX - 1, 5);
+		assert_eq!(sheet.error, Some(OtpError::CounterOverflow));
+		assert_eq!(
+			sheet
+				.entries
+				.iter()
+				.map(|entry| entry.index)
+				.collect::<Vec<_>>(),
+			[u64::MAX - 1, u64::MAX]
+		);
+	}
+
+	#[test]
+	fn totp_sheet() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 0).unwrap();
+		let times = [
+			59,
+			1111111109,
+			1111111111,
+			1234567890,
+			2000000000,
+			20000000000,
+		];
+		let sheet = generate_totp_sheet(&mut totp, &times);
+		assert!(sheet.is_complete());
+		for (entry, time) in sheet.entries.iter().zip(times) {
+			assert_eq!(entry.index, time);
+			assert_eq!(entry.code, totp.code_at_time(time).unwrap().to_string());
+		}
+		assert_eq!(sheet.entries[0].code, "94287082");
+		assert_eq!(sheet.entries[1].code, "07081804");
+		let table = sheet.to_string();
+		let mut lines = table.lines();
+		assert_eq!(lines.next(), Some("       Time | Code    "));
+		assert_eq!(lines.next(), Some("------------+---------"));
+		assert_eq!(lines.next(), Some("         59 | 94287082"));
+		assert!(lines.all(|line| line.len() == "20000000000 | 12345678".len()));
+	}
+}
@@ -0,0 +1,482 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::error::OtpError;
+#[cfg(feature = "serde")]
+use {
+	crate::algorithm::Algorithm,
+	serde::{Deserialize, Serialize},
+};
+#[cfg(feature = "alloc")]
+use {
+	crate::{hotp::OverflowPolicy, secret::Secret},
+	core::time::Duration,
+};
+
+/// The current version of the binary state snapshot format.
+pub const STATE_VERSION: u8 = 1;
+
+/// The length of a binary state snapshot, in bytes.
+///
+/// Version 1 of the format consists of the version byte,
+/// the counter as a big-endian u64, and the digit length as a single byte.
+pub const STATE_BYTES_LEN: usize = 10;
+
+/// Writes a binary state snapshot into the given buffer, returning the number of bytes written.
+pub(crate) fn encode(counter: u64, digits: usize, buf: &mut [u8]) -> Result<usize, OtpError> {
+	let buf = buf
+		.get_mut(..STATE_BYTES_LEN)
+		.ok_or(OtpError::StateTooShort)?;
+	buf[0] = STATE_VERSION;
+	buf[1..9].copy_from_slice(&counter.to_be_bytes());
+	buf[9] = digits as u8;
+	Ok(STATE_BYTES_LEN)
+}
+
+/// Reads a binary state snapshot, returning the counter and the digit length.
+pub(crate) fn decode(buf: &[u8]) -> Result<(u64, usize), OtpError> {
+	match buf.first() {
+		None => return Err(OtpError::StateTooShort),
+		Some(&STATE_VERSION) => {}
+		Some(&got) => return Err(OtpError::UnsupportedStateVersion { got }),
+	}
+	let buf = buf.get(..STATE_BYTES_LEN).ok_or(OtpError::StateTooShort)?;
+	let mut counter = [0u8; 8];
+	counter.copy_from_slice(&buf[1..9]);
+	Ok((u64::from_be_bytes(counter), buf[9] as usize))
+}
+
+/// The persistable state of a [Hotp](crate::Hotp) instance.
+///
+/// HOTP instances don't retain their raw secret, so [Hotp::export_state](crate::Hotp::export_state)
+/// leaves it empty. It has to be supplied deliberately, either by setting it here, or by passing it
+/// to [Hotp::from_state_with_key](crate::Hotp::from_state_with_key) for secrets stored elsewhere.
+///
+/// ```rust
+/// use otpee::{Hotp, Secret};
+/// use sha1::Sha1;
+///
+/// let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+/// hotp.increment_counter().unwrap();
+/// let mut state = hotp.export_state();
+/// state.secret = Some(Secret::from(b"12345678901234567890"));
+///
+/// let mut restored = Hotp::<Sha1>::from_state(&state).unwrap();
+/// assert_eq!(restored.counter(), 1);
+/// assert_eq!(restored.code().unwrap(), 287082);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HotpState {
+	/// The secret, if it is stored alongside the rest of the state.
+	#[cfg_attr(
+		feature = "serde",
+		serde(default, skip_serializing_if = "Option::is_none")
+	)]
+	pub secret: Option<Secret>,
+	/// The counter value.
+	pub counter: u64,
+	/// The number of digits in generated codes, not counting the checksum digit.
+	#[cfg_attr(
+		feature = "serde",
+		serde(deserialize_with = "serde_impl::deserialize_digits")
+	)]
+	pub digits: usize,
+	/// Whether a checksum digit is appended to generated codes.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub checksum: bool,
+	/// The fixed truncation offset, if one is set.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub truncation_offset: Option<u8>,
+	/// The look-ahead window used when validating.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub window: usize,
+	/// The policy followed when the counter would be advanced past [u64::MAX].
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub overflow_policy: OverflowPolicy,
+	/// The lowest counter that a code can still be accepted for.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub minimum_counter: u64,
+}
+
+/// The persistable state of a [Totp](crate::Totp) instance.
+///
+/// Like [HotpState], the secret is left empty by [Totp::export_state](crate::Totp::export_state),
+/// and the time source isn't included, so it has to be given again when restoring.
+///
+/// ```rust
+/// use otpee::{Secret, Totp};
+/// use sha1::Sha1;
+///
+/// let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// assert!(totp.validate_code(94287082));
+/// let mut state = totp.export_state();
+/// state.secret = Some(Secret::from(b"12345678901234567890"));
+///
+/// let mut restored = Totp::<Sha1>::from_state(&state, || 59).unwrap();
+/// assert_eq!(restored.last_accepted_step(), Some(1));
+/// assert!(!restored.validate_code(94287082));
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TotpState {
+	/// The secret, if it is stored alongside the rest of the state.
+	#[cfg_attr(
+		feature = "serde",
+		serde(default, skip_serializing_if = "Option::is_none")
+	)]
+	pub secret: Option<Secret>,
+	/// The number of digits in generated codes, not counting the checksum digit.
+	#[cfg_attr(
+		feature = "serde",
+		serde(deserialize_with = "serde_impl::deserialize_digits")
+	)]
+	pub digits: usize,
+	/// Whether a checksum digit is appended to generated codes.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub checksum: bool,
+	/// The fixed truncation offset, if one is set.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub truncation_offset: Option<u8>,
+	/// The length of a time step.
+	///
+	/// When deserializing, a plain number of seconds is also accepted.
+	#[cfg_attr(
+		feature = "serde",
+		serde(deserialize_with = "serde_impl::deserialize_interval")
+	)]
+	pub interval: Duration,
+	/// The time that the first time step starts at, in seconds from the unix epoch.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub epoch: u64,
+	/// The number of time steps before and after the current one that are accepted.
+	pub skew: usize,
+	/// The last time step that a code was accepted for, if any.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub last_accepted_step: Option<u64>,
+	/// The drift offset of the token, in time steps.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub drift_offset: i64,
+	/// The largest drift offset that can be set or learned, in time steps.
+	#[cfg_attr(
+		feature = "serde",
+		serde(default = "serde_impl::default_max_drift_offset")
+	)]
+	pub max_drift_offset: u64,
+	/// Whether the drift offset is updated by successful validations.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub learn_drift: bool,
+	/// Whether each code can only be used once.
+	#[cfg_attr(feature = "serde", serde(default = "serde_impl::default_true"))]
+	pub single_use: bool,
+	/// Whether codes for time steps before the last accepted one are always rejected.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub strict_monotonic: bool,
+	/// The grace period for codes of the previous time step, in seconds, if one is set.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub grace_period: Option<u64>,
+	/// How old and how far in the future codes can be, in seconds, if set.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub tolerance: Option<(u64, u64)>,
+}
+
+/// The configuration of a [Totp](crate::Totp) instance, for storing it as JSON alongside other
+/// settings, such as in a database.
+///
+/// Unlike [TotpState], it only holds the settings shared with the user's authenticator,
+/// without the replay protection state. The secret is serialized as Base32, and can be left
+/// out with [TotpConfig::without_secret], to be given to
+/// [Totp::from_config_with_key](crate::Totp::from_config_with_key) instead.
+///
+/// Deserializing it rejects the same settings that [TotpBuilder](crate::TotpBuilder) does.
+///
+/// ```rust
+/// # #[cfg(feature = "sha1")]
+/// # {
+/// use otpee::{state::TotpConfig, Totp};
+/// use sha1::Sha1;
+///
+/// let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// let config = totp.to_config(b"12345678901234567890");
+/// let json = serde_json::to_string(&config).unwrap();
+/// assert_eq!(
+///     json,
+///     r#"{"secret":"GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ","digits":8,"period":30,"skew":1,"algorithm":"SHA1"}"#
+/// );
+///
+/// let config: TotpConfig = serde_json::from_str(&json).unwrap();
+/// let mut restored = Totp::<Sha1>::from_config(&config, || 59).unwrap();
+/// assert_eq!(restored.code().unwrap(), 94287082);
+/// # }
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpConfig {
+	/// The secret, if it is stored alongside the rest of the configuration.
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		with = "serde_impl::base32_secret"
+	)]
+	pub secret: Option<Secret>,
+	/// The number of digits in generated codes.
+	#[serde(deserialize_with = "serde_impl::deserialize_digits")]
+	pub digits: usize,
+	/// The length of a time step, in seconds.
+	#[serde(deserialize_with = "serde_impl::deserialize_period")]
+	pub period: u64,
+	/// The number of time steps before and after the current one that are accepted.
+	pub skew: usize,
+	/// The hash algorithm, by the name used in provisioning URIs.
+	#[serde(with = "serde_impl::algorithm_name")]
+	pub algorithm: Algorithm,
+	/// The time that the first time step starts at, in seconds from the unix epoch.
+	#[serde(default, skip_serializing_if = "serde_impl::is_zero")]
+	pub epoch: u64,
+}
+
+#[cfg(feature = "serde")]
+impl TotpConfig {
+	/// Returns the configuration with the secret left out, such as for storing the secret elsewhere.
+	#[inline]
+	pub fn without_secret(self) -> Self {
+		Self {
+			secret: None,
+			..self
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+	use crate::{error::OtpError, hotp::MAX_DIGITS};
+	use core::time::Duration;
+	use serde::{de::Error, Deserialize, Deserializer};
+
+	/// An interval, either as a [Duration], or a number of seconds.
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Interval {
+		Seconds(u64),
+		Duration(Duration),
+	}
+
+	pub(super) fn deserialize_interval<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Duration, D::Error> {
+		Ok(match Interval::deserialize(deserializer)? {
+			Interval::Seconds(seconds) => Duration::from_secs(seconds),
+			Interval::Duration(duration) => duration,
+		})
+	}
+
+	pub(super) fn deserialize_digits<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<usize, D::Error> {
+		let digits = usize::deserialize(deserializer)?;
+		if !(1..=MAX_DIGITS).contains(&digits) {
+			return Err(D::Error::custom(OtpError::InvalidDigits { got: digits }));
+		}
+		Ok(digits)
+	}
+
+	pub(super) fn deserialize_period<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<u64, D::Error> {
+		match u64::deserialize(deserializer)? {
+			0 => Err(D::Error::custom(OtpError::InvalidInterval)),
+			period => Ok(period),
+		}
+	}
+
+	#[inline]
+	pub(super) fn default_max_drift_offset() -> u64 {
+		crate::totp::DEFAULT_MAX_DRIFT_OFFSET
+	}
+
+	#[inline]
+	pub(super) fn default_true() -> bool {
+		true
+	}
+
+	#[inline]
+	pub(super) fn is_zero(value: &u64) -> bool {
+		*value == 0
+	}
+
+	/// Serializes a secret as a Base32 string.
+	pub(super) mod base32_secret {
+		use crate::{base32, secret::Secret};
+		use alloc::string::String;
+		use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+		pub fn serialize<S: Serializer>(
+			secret: &Option<Secret>,
+			serializer: S,
+		) -> Result<S::Ok, S::Error> {
+			match secret {
+				Some(secret) => serializer.serialize_str(&base32::encode(secret.as_bytes())),
+				None => serializer.serialize_none(),
+			}
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(
+			deserializer: D,
+		) -> Result<Option<Secret>, D::Error> {
+			Option::<String>::deserialize(deserializer)?
+				.map(|secret| Secret::from_base32(&secret).map_err(D::Error::custom))
+				.transpose()
+		}
+	}
+
+	/// Serializes an algorithm by its name, as used in provisioning URIs.
+	pub(super) mod algorithm_name {
+		use crate::algorithm::Algorithm;
+		use alloc::string::String;
+		use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+		pub fn serialize<S: Serializer>(
+			algorithm: &Algorithm,
+			serializer: S,
+		) -> Result<S::Ok, S::Error> {
+			serializer.serialize_str(algorithm.name())
+		}
+
+		pub fn deserialize<'de, D: Deserializer<'de>>(
+			deserializer: D,
+		) -> Result<Algorithm, D::Error> {
+			let name = String::deserialize(deserializer)?;
+			Algorithm::from_name(&name)
+				.ok_or_else(|| D::Error::custom(format_args!("unsupported algorithm `{}`", name)))
+		}
+	}
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+	use super::{HotpState, TotpConfig, TotpState};
+	use crate::{Hotp, Secret};
+	use core::time::Duration;
+	use sha1::Sha1;
+
+	#[test]
+	fn hotp_state_round_trip() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_counter(4).unwrap();
+		let mut state = hotp.export_state();
+		state.secret = Some(Secret::from(b"12345678901234567890"));
+
+		let json = serde_json::to_string(&state).unwrap();
+		let state: HotpState = serde_json::from_str(&json).unwrap();
+		let mut restored = Hotp::<Sha1>::from_state(&state).unwrap();
+		assert_eq!(restored.counter(), hotp.counter());
+		assert_eq!(
+			restored.code_increment().unwrap(),
+			hotp.code_increment().unwrap()
+		);
+		assert_eq!(restored.counter(), 5);
+	}
+
+	#[test]
+	fn hotp_state_without_secret() {
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let json = serde_json::to_string(&hotp.export_state()).unwrap();
+		assert!(!json.contains("secret"));
+		let state: HotpState = serde_json::from_str(&json).unwrap();
+		assert!(Hotp::<Sha1>::from_state(&state).is_err());
+		let mut restored =
+			Hotp::<Sha1>::from_state_with_key(b"12345678901234567890", &state).unwrap();
+		assert_eq!(restored.code().unwrap(), 755224);
+	}
+
+	#[test]
+	fn hotp_state_invalid_digits() {
+		let json = r#"{"counter":0,"digits":11}"#;
+		assert!(serde_json::from_str::<HotpState>(json).is_err());
+	}
+
+	#[test]
+	fn totp_state_interval() {
+		let json = r#"{"digits":6,"interval":30,"skew":1}"#;
+		let state = serde_json::from_str::<TotpState>(json).unwrap();
+		assert_eq!(state.interval, Duration::from_secs(30));
+		assert_eq!(state.max_drift_offset, 10);
+		assert!(!state.learn_drift);
+		assert!(state.single_use);
+		assert!(!state.strict_monotonic);
+		assert_eq!((state.grace_period, state.tolerance), (None, None));
+		let json = r#"{"digits":6,"interval":{"secs":0,"nanos":500000000},"skew":1}"#;
+		let state = serde_json::from_str::<TotpState>(json).unwrap();
+		assert_eq!(state.interval, Duration::from_millis(500));
+		let json = serde_json::to_string(&state).unwrap();
+		assert_eq!(serde_json::from_str::<TotpState>(&json).unwrap(), state);
+	}
+
+	#[cfg(feature = "sha1")]
+	#[test]
+	fn totp_config_round_trip() {
+		use crate::{algorithm::Algorithm, error::OtpError, Totp};
+
+		let key = b"12345678901234567890";
+		let totp = Totp::<Sha1>::builder()
+			.digits(8)
+			.interval(60)
+			.skew(2)
+			.epoch(30)
+			.time_source(|| 1111111109)
+			.build(key)
+			.unwrap();
+		let config = totp.to_config(key);
+		assert_eq!(config.algorithm, Algorithm::Sha1);
+		let json = serde_json::to_string(&config).unwrap();
+		assert!(json.contains(r#""secret":"GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ""#));
+		let restored: TotpConfig = serde_json::from_str(&json).unwrap();
+		assert_eq!(restored, config);
+		let mut restored = Totp::<Sha1>::from_config(&restored, || 1111111109).unwrap();
+		assert_eq!(restored.to_config(key), config);
+		assert_eq!(restored.code().unwrap(), totp.clone().code().unwrap());
+
+		let json = serde_json::to_string(&config.clone().without_secret()).unwrap();
+		assert!(!json.contains("secret"));
+		let stripped: TotpConfig = serde_json::from_str(&json).unwrap();
+		assert_eq!(
+			Totp::<Sha1>::from_config(&stripped, || 0).err(),
+			Some(OtpError::MissingSecret)
+		);
+		assert!(Totp::<Sha1>::from_config_with_key(key, &stripped, || 0).is_ok());
+
+		let zero_period = TotpConfig {
+			period: 0,
+			..config.clone()
+		};
+		assert_eq!(
+			Totp::<Sha1>::from_config(&zero_period, || 0).err(),
+			Some(OtpError::InvalidInterval)
+		);
+
+		let sha256 = TotpConfig {
+			algorithm: Algorithm::Sha256,
+			..config
+		};
+		assert_eq!(
+			Totp::<Sha1>::from_config(&sha256, || 0).err(),
+			Some(OtpError::AlgorithmMismatch {
+				expected: Algorithm::Sha1,
+				got: Algorithm::Sha256
+			})
+		);
+	}
+
+	#[test]
+	fn totp_config_invalid() {
+		let parse = |json| serde_json::from_str::<TotpConfig>(json);
+		assert!(parse(r#"{"digits":6,"period":30,"skew":1,"algorithm":"sha256"}"#).is_ok());
+		assert!(parse(r#"{"digits":6,"period":0,"skew":1,"algorithm":"SHA1"}"#).is_err());
+		assert!(parse(r#"{"digits":0,"period":30,"skew":1,"algorithm":"SHA1"}"#).is_err());
+		assert!(parse(r#"{"digits":6,"period":30,"skew":1,"algorithm":"MD5"}"#).is_err());
+		assert!(parse(
+			r#"{"secret":"not base32!","digits":6,"period":30,"skew":1,"algorithm":"SHA1"}"#
+		)
+		.is_err());
+	}
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{error::OtpError, time::TimeSource, totp::Totp};
+use core::fmt::{self, Display, Formatter};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+/// The characters Steam Guard codes are made of.
+pub const STEAM_ALPHABET: &[u8; 26] = b"23456789BCDFGHJKMNPQRTVWXY";
+
+/// The number of characters in a Steam Guard code.
+pub const STEAM_CODE_LENGTH: usize = 5;
+
+/// A Steam Guard code, made of 5 characters from [STEAM_ALPHABET].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SteamCode([u8; STEAM_CODE_LENGTH]);
+
+impl SteamCode {
+	/// Maps a 31-bit value, such as one returned by [Hotp::raw_code](crate::Hotp::raw_code),
+	/// to a Steam Guard code, taking each character from the remainder of dividing by 26.
+	pub fn from_raw(mut value: u32) -> Self {
+		let mut code = [0; STEAM_CODE_LENGTH];
+		for c in &mut code {
+			*c = STEAM_ALPHABET[(value % 26) as usize];
+			value /= 26;
+		}
+		Self(code)
+	}
+
+	/// Returns the code as a string.
+	#[inline]
+	pub fn as_str(&self) -> &str {
+		// The code is always made of ASCII characters from the alphabet.
+		core::str::from_utf8(&self.0).unwrap_or_default()
+	}
+}
+
+impl Display for SteamCode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// A generator for Steam Guard codes, which are SHA-1 TOTP codes with a 30 second interval,
+/// mapped to 5 characters instead of decimal digits.
+///
+/// ```rust
+/// use otpee::steam::SteamTotp;
+///
+/// let mut steam = SteamTotp::new(b"12345678901234567890", || 59).unwrap();
+/// assert_eq!(steam.code().unwrap().as_str(), "PV9M4");
+/// assert!(steam.validate_code("pv9m4"));
+/// ```
+#[derive(Debug)]
+pub struct SteamTotp<C> {
+	totp: Totp<Sha1, C>,
+}
+
+impl<C: TimeSource> SteamTotp<C> {
+	/// Creates a new instance with the given shared secret, as used by Steam,
+	/// accepting codes one time step before or after the current one.
+	pub fn new<A: AsRef<[u8]>>(key: A, time_source: C) -> Result<Self, OtpError> {
+		Totp::new(key, None, 30, 1, time_source).map(Self::from_totp)
+	}
+
+	/// Wraps the given TOTP instance, using its interval, skew and other settings.
+	/// Its code length is ignored.
+	#[inline]
+	pub fn from_totp(totp: Totp<Sha1, C>) -> Self {
+		Self { totp }
+	}
+
+	/// Returns a reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner(&self) -> &Totp<Sha1, C> {
+		&self.totp
+	}
+
+	/// Returns a mutable reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner_mut(&mut self) -> &mut Totp<Sha1, C> {
+		&mut self.totp
+	}
+
+	/// Unwraps the TOTP instance.
+	#[inline]
+	pub fn into_inner(self) -> Totp<Sha1, C> {
+		self.totp
+	}
+
+	/// Calculate the Steam Guard code for the current time.
+	pub fn code(&mut self) -> Result<SteamCode, OtpError> {
+		self.totp.raw_code().map(SteamCode::from_raw)
+	}
+
+	/// Validates a code typed by a user as being valid for the current time,
+	/// taking the skew value into account like [Totp::validate_code].
+	///
+	/// The code is case-insensitive, and compared in constant time.
+	pub fn validate_code(&mut self, code: &str) -> bool {
+		let Ok(code) = <[u8; STEAM_CODE_LENGTH]>::try_from(code.as_bytes()) else {
+			return false;
+		};
+		let code = code.map(|c| c.to_ascii_uppercase());
+		self.totp
+			.validate_raw(|value| SteamCode::from_raw(value).0.ct_eq(&code))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{SteamCode, SteamTotp};
+	use alloc::string::ToString;
+
+	#[test]
+	fn steam_code_vectors() {
+		for (time, expected) in [
+			(59, "PV9M4"),
+			(1111111109, "PY4YB"),
+			(1234567890, "VHHQY"),
+			(2000000000, "9N776"),
+		] {
+			let mut steam = SteamTotp::new(b"12345678901234567890", move || time).unwrap();
+			let code = steam.code().unwrap();
+			assert_eq!(code.as_str(), expected);
+			assert_eq!(code.to_string(), expected);
+		}
+	}
+
+	#[test]
+	fn steam_code_from_raw() {
+		assert_eq!(SteamCode::from_raw(0).as_str(), "22222");
+		assert_eq!(SteamCode::from_raw(25 + 26).as_str(), "Y3222");
+	}
+
+	#[test]
+	fn steam_validate() {
+		let mut steam = SteamTotp::new(b"12345678901234567890", || 1111111109).unwrap();
+		assert!(!steam.validate_code("PY4Y"));
+		assert!(!steam.validate_code("PY4YC"));
+		assert!(!steam.validate_code("PY4YBB"));
+		assert!(steam.validate_code("py4yB"));
+		// The code can only be used once.
+		assert!(!steam.validate_code("PY4YB"));
+	}
+}
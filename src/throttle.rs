@@ -0,0 +1,482 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{
+	error::{OtpError, TimeError},
+	hotp::Hotp,
+	mac::OtpMac,
+	time::TimeSource,
+	totp::Totp,
+};
+use core::fmt::{self, Display, Formatter};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// An error from a [ThrottledValidator], on top of the code being wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleError {
+	/// Too many attempts have failed, so the code was not checked at all.
+	/// If `retry_after` is set, validation can be attempted again at that time,
+	/// otherwise the validator stays locked until it is [reset](ThrottledValidator::reset).
+	Throttled { retry_after: Option<u64> },
+	/// The code was wrong.
+	BadCode { remaining_attempts: u32 },
+	/// The time source for the backoff couldn't report the time, so the code was not checked.
+	TimeUnavailable(TimeError),
+}
+
+impl Display for ThrottleError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ThrottleError::Throttled {
+				retry_after: Some(retry_after),
+			} => write!(
+				f,
+				"too many failed attempts, try again after {}",
+				retry_after
+			),
+			ThrottleError::Throttled { retry_after: None } => {
+				f.write_str("too many failed attempts, validation is locked")
+			}
+			ThrottleError::BadCode { remaining_attempts } => write!(
+				f,
+				"the code is invalid, {} attempts remaining",
+				remaining_attempts
+			),
+			ThrottleError::TimeUnavailable(err) => write!(f, "the time is unavailable: {}", err),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl Error for ThrottleError {}
+
+/// A wrapper around a [Hotp] or [Totp] instance that throttles validation attempts,
+/// as required by RFC 4226 section 7.3.
+///
+/// After `max_attempts` consecutive failures, the validator locks until it is reset.
+/// Optionally, an exponential backoff can be enabled, where every failure doubles the
+/// time that has to pass before the next attempt is allowed.
+///
+/// ```rust
+/// use otpee::{throttle::{ThrottleError, ThrottledValidator}, Hotp};
+/// use sha1::Sha1;
+///
+/// let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+/// let mut validator = ThrottledValidator::new(hotp, 3);
+/// assert_eq!(validator.validate(123456, 2), Err(ThrottleError::BadCode { remaining_attempts: 2 }));
+/// assert_eq!(validator.validate(287082, 2), Ok(1));
+/// ```
+pub struct ThrottledValidator<V, T = fn() -> u64> {
+	inner: V,
+	max_attempts: u32,
+	failures: u32,
+	backoff: Option<Backoff<T>>,
+}
+
+struct Backoff<T> {
+	base: u64,
+	next_attempt: u64,
+	time_source: T,
+}
+
+impl<V> ThrottledValidator<V> {
+	/// Wraps the given validator, locking it after `max_attempts` consecutive failures.
+	pub fn new(inner: V, max_attempts: u32) -> Self {
+		Self {
+			inner,
+			max_attempts,
+			failures: 0,
+			backoff: None,
+		}
+	}
+}
+
+impl<V, T> ThrottledValidator<V, T> {
+	/// Enables exponential backoff: after the Nth consecutive failure, no attempts are allowed
+	/// until `base * 2^(N - 1)` seconds have passed, using the given time source to get the
+	/// current time.
+	///
+	/// If the time source can't report the time, attempts fail with
+	/// [ThrottleError::TimeUnavailable].
+	pub fn with_backoff<U: TimeSource>(
+		self,
+		base: u64,
+		time_source: U,
+	) -> ThrottledValidator<V, U> {
+		ThrottledValidator {
+			inner: self.inner,
+			max_attempts: self.max_attempts,
+			failures: self.failures,
+			backoff: Some(Backoff {
+				base,
+				next_attempt: 0,
+				time_source,
+			}),
+		}
+	}
+
+	/// Returns the number of consecutive failed attempts.
+	#[inline]
+	pub fn failures(&self) -> u32 {
+		self.failures
+	}
+
+	/// Clears the failure count, unlocking the validator.
+	pub fn reset(&mut self) {
+		self.failures = 0;
+		if let Some(backoff) = &mut self.backoff {
+			backoff.next_attempt = 0;
+		}
+	}
+
+	/// Returns a reference to the wrapped validator.
+	#[inline]
+	pub fn inner(&self) -> &V {
+		&self.inner
+	}
+
+	/// Returns a mutable reference to the wrapped validator.
+	#[inline]
+	pub fn inner_mut(&mut self) -> &mut V {
+		&mut self.inner
+	}
+
+	/// Unwraps the validator, discarding the throttling state.
+	#[inline]
+	pub fn into_inner(self) -> V {
+		self.inner
+	}
+}
+
+impl<V, T: TimeSource> ThrottledValidator<V, T> {
+	/// Runs the given validation function, unless too many attempts have failed.
+	/// The function should return the counter or time step that matched, if any.
+	pub fn attempt<F: FnOnce(&mut V) -> Option<u64>>(
+		&mut self,
+		validate: F,
+	) -> Result<u64, ThrottleError> {
+		if self.failures >= self.max_attempts {
+			return Err(ThrottleError::Throttled { retry_after: None });
+		}
+		let now = match &mut self.backoff {
+			Some(backoff) => {
+				let now = backoff
+					.time_source
+					.unix_seconds()
+					.map_err(ThrottleError::TimeUnavailable)?;
+				if now < backoff.next_attempt {
+					return Err(ThrottleError::Throttled {
+						retry_after: Some(backoff.next_attempt),
+					});
+				}
+				now
+			}
+			None => 0,
+		};
+		match validate(&mut self.inner) {
+			Some(matched) => {
+				self.reset();
+				Ok(matched)
+			}
+			None => {
+				self.failures += 1;
+				if let Some(backoff) = &mut self.backoff {
+					let delay = backoff
+						.base
+						.saturating_mul(1_u64.checked_shl(self.failures - 1).unwrap_or(u64::MAX));
+					backoff.next_attempt = now.saturating_add(delay);
+				}
+				Err(ThrottleError::BadCode {
+					remaining_attempts: self.max_attempts - self.failures,
+				})
+			}
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, M: OtpMac, T: TimeSource>
+	ThrottledValidator<Hotp<D, M>, T>
+{
+	/// Validates the code using [Hotp::validate], with the given look-ahead window.
+	#[inline]
+	pub fn validate(&mut self, code: u64, window: usize) -> Result<u64, ThrottleError> {
+		self.attempt(|hotp| hotp.validate(code, window))
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource, T: TimeSource>
+	ThrottledValidator<Totp<D, C>, T>
+{
+	/// Validates the code for the current time using the TOTP instance's skew,
+	/// returning the time step that matched.
+	#[inline]
+	pub fn validate_code(&mut self, code: u64) -> Result<u64, ThrottleError> {
+		self.attempt(|totp| totp.validate_code_at(code))
+	}
+}
+
+/// A wrapper around a [Totp] instance that limits the number of validation attempts
+/// per time step, using the instance's own time source.
+///
+/// Once `max_attempts` attempts were made in the current time step, further attempts are
+/// rejected with [OtpError::RateLimited] until the next one starts. By default, only failed
+/// attempts count towards the limit, see [RateLimitedTotp::count_successes].
+///
+/// ```rust
+/// use otpee::{error::OtpError, throttle::RateLimitedTotp, Totp};
+/// use sha1::Sha1;
+///
+/// let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+/// let mut limited = RateLimitedTotp::new(totp, 2);
+/// assert_eq!(limited.validate_code(12345678), Ok(false));
+/// assert_eq!(limited.validate_code(12345678), Ok(false));
+/// assert_eq!(
+///     limited.validate_code(94287082),
+///     Err(OtpError::RateLimited { retry_at: 60 })
+/// );
+/// ```
+pub struct RateLimitedTotp<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
+	totp: Totp<D, C>,
+	max_attempts: u32,
+	count_successes: bool,
+	step: u64,
+	attempts: u32,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> RateLimitedTotp<D, C> {
+	/// Wraps the TOTP instance, allowing `max_attempts` attempts per time step.
+	pub fn new(totp: Totp<D, C>, max_attempts: u32) -> Self {
+		Self {
+			totp,
+			max_attempts,
+			count_successes: false,
+			step: 0,
+			attempts: 0,
+		}
+	}
+
+	/// Sets whether successful attempts count towards the limit as well, not just failed ones.
+	#[inline]
+	pub fn count_successes(mut self, count_successes: bool) -> Self {
+		self.count_successes = count_successes;
+		self
+	}
+
+	/// Returns the number of attempts counted in the time step of the last attempt.
+	#[inline]
+	pub fn attempts(&self) -> u32 {
+		self.attempts
+	}
+
+	/// Clears the attempt count, allowing attempts again in the current time step.
+	#[inline]
+	pub fn reset(&mut self) {
+		self.attempts = 0;
+	}
+
+	/// Returns a reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner(&self) -> &Totp<D, C> {
+		&self.totp
+	}
+
+	/// Returns a mutable reference to the wrapped TOTP instance.
+	#[inline]
+	pub fn inner_mut(&mut self) -> &mut Totp<D, C> {
+		&mut self.totp
+	}
+
+	/// Unwraps the TOTP instance, discarding the rate limiting state.
+	#[inline]
+	pub fn into_inner(self) -> Totp<D, C> {
+		self.totp
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> RateLimitedTotp<D, C> {
+	/// Validates the code for the current time, see [Totp::validate_code].
+	///
+	/// Returns [OtpError::RateLimited] without checking the code if the limit for the current
+	/// time step was reached, with the time the next time step starts at, in seconds from the
+	/// unix epoch. Returns [OtpError::TimeUnavailable] if the time source couldn't report the time.
+	pub fn validate_code(&mut self, code: u64) -> Result<bool, OtpError> {
+		let step = self.totp.counter()?;
+		if step != self.step {
+			self.step = step;
+			self.attempts = 0;
+		}
+		if self.attempts >= self.max_attempts {
+			return Err(OtpError::RateLimited {
+				retry_at: self.totp.valid_until()?,
+			});
+		}
+		let valid = self.totp.validate_code(code);
+		if !valid || self.count_successes {
+			self.attempts += 1;
+		}
+		Ok(valid)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{RateLimitedTotp, ThrottleError, ThrottledValidator};
+	use crate::{error::OtpError, Hotp, Totp};
+	use alloc::rc::Rc;
+	use core::cell::Cell;
+	use sha1::Sha1;
+
+	#[test]
+	fn throttle_lockout() {
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut validator = ThrottledValidator::new(hotp, 5);
+		for code in 0..4 {
+			assert_eq!(
+				validator.validate(code, 0),
+				Err(ThrottleError::BadCode {
+					remaining_attempts: 4 - code as u32
+				})
+			);
+		}
+		// A successful validation resets the failure count.
+		assert_eq!(validator.validate(755224, 0), Ok(0));
+		assert_eq!(validator.failures(), 0);
+
+		for code in 0..5 {
+			assert!(matches!(
+				validator.validate(code, 0),
+				Err(ThrottleError::BadCode { .. })
+			));
+		}
+		// Even the right code is not accepted once locked.
+		assert_eq!(
+			validator.validate(287082, 0),
+			Err(ThrottleError::Throttled { retry_after: None })
+		);
+		assert_eq!(validator.inner().counter(), 1);
+		validator.reset();
+		assert_eq!(validator.validate(287082, 0), Ok(1));
+	}
+
+	#[test]
+	fn throttle_backoff() {
+		let now = Rc::new(Cell::new(59));
+		let time = now.clone();
+		let totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 59).unwrap();
+		let mut validator = ThrottledValidator::new(totp, 10).with_backoff(2, move || time.get());
+		assert!(validator.validate_code(0).is_err());
+		assert_eq!(
+			validator.validate_code(94287082),
+			Err(ThrottleError::Throttled {
+				retry_after: Some(61)
+			})
+		);
+		now.set(61);
+		assert!(validator.validate_code(0).is_err());
+		now.set(64);
+		assert_eq!(
+			validator.validate_code(94287082),
+			Err(ThrottleError::Throttled {
+				retry_after: Some(65)
+			})
+		);
+		now.set(65);
+		assert_eq!(validator.validate_code(94287082), Ok(1));
+		assert!(validator.validate_code(0).is_err());
+		assert_eq!(
+			validator.validate_code(94287082),
+			Err(ThrottleError::Throttled {
+				retry_after: Some(67)
+			})
+		);
+	}
+
+	#[test]
+	fn throttle_backoff_time_source() {
+		use crate::{
+			error::TimeError,
+			time::{Millis, TimeSource},
+		};
+
+		struct Unset;
+
+		impl TimeSource for Unset {
+			fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+				Err(TimeError::NotSet)
+			}
+		}
+
+		let hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut validator = ThrottledValidator::new(hotp, 10).with_backoff(2, Unset);
+		// Without the time, the backoff can't be enforced, so the code isn't checked.
+		assert_eq!(
+			validator.validate(755224, 0),
+			Err(ThrottleError::TimeUnavailable(TimeError::NotSet))
+		);
+		assert_eq!(validator.failures(), 0);
+
+		// Millisecond time sources are read in seconds.
+		let hotp = validator.into_inner();
+		let mut validator = ThrottledValidator::new(hotp, 10).with_backoff(2, Millis(|| 59_999));
+		assert!(validator.validate(0, 0).is_err());
+		assert_eq!(
+			validator.validate(755224, 0),
+			Err(ThrottleError::Throttled {
+				retry_after: Some(61)
+			})
+		);
+	}
+
+	#[test]
+	fn throttle_send() {
+		fn assert_send<T: Send>() {}
+		assert_send::<ThrottledValidator<Hotp<Sha1>>>();
+	}
+
+	#[test]
+	fn rate_limited_totp() {
+		let time = Rc::new(Cell::new(59));
+		let clock = time.clone();
+		let totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, move || clock.get()).unwrap();
+		let mut limited = RateLimitedTotp::new(totp, 3);
+		for _ in 0..3 {
+			assert_eq!(limited.validate_code(12345678), Ok(false));
+		}
+		assert_eq!(limited.attempts(), 3);
+		// The right code isn't checked once the limit is reached.
+		assert_eq!(
+			limited.validate_code(94287082),
+			Err(OtpError::RateLimited { retry_at: 60 })
+		);
+
+		// The limit resets when the next time step starts.
+		time.set(60);
+		assert_eq!(limited.validate_code(12345678), Ok(false));
+		assert_eq!(limited.attempts(), 1);
+		let code = *limited.inner_mut().code().unwrap();
+		assert_eq!(limited.validate_code(code), Ok(true));
+		assert_eq!(limited.attempts(), 1);
+	}
+
+	#[test]
+	fn rate_limited_totp_successes() {
+		let time = Rc::new(Cell::new(59));
+		let clock = time.clone();
+		let totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, move || clock.get()).unwrap();
+		let mut limited = RateLimitedTotp::new(totp, 2).count_successes(true);
+		assert_eq!(limited.validate_code(94287082), Ok(true));
+		// The code was already used, so this fails, and reaches the limit.
+		assert_eq!(limited.validate_code(94287082), Ok(false));
+		assert_eq!(
+			limited.validate_code(94287082),
+			Err(OtpError::RateLimited { retry_at: 60 })
+		);
+
+		time.set(90);
+		assert_eq!(limited.validate_code(12345678), Ok(false));
+		limited.reset();
+		assert_eq!(limited.attempts(), 0);
+	}
+}
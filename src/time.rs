@@ -0,0 +1,492 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+#[cfg(any(feature = "std", feature = "chrono", feature = "time"))]
+use crate::error::OtpError;
+use crate::error::TimeError;
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "ntp")]
+mod sntp;
+#[cfg(feature = "ntp")]
+pub use sntp::SntpSource;
+
+/// A source of the current time, for [Totp](crate::Totp).
+///
+/// This is implemented for any `FnMut() -> u64` closure returning seconds from the unix epoch,
+/// so most callers can just pass a closure. Wrap a closure returning milliseconds in [Millis]
+/// to allow time steps shorter than a second.
+///
+/// Clocks that can fail, such as an RTC that hasn't been set yet, can implement this directly,
+/// and [Totp](crate::Totp) reports the error as
+/// [TimeUnavailable](crate::error::OtpError::TimeUnavailable).
+pub trait TimeSource {
+	/// Returns the current time, in seconds from the unix epoch.
+	///
+	/// This takes `&mut self`, so that time sources can hold state,
+	/// such as a driver for a hardware clock.
+	fn unix_seconds(&mut self) -> Result<u64, TimeError>;
+
+	/// Returns the current time, in milliseconds from the unix epoch.
+	///
+	/// By default, this is [TimeSource::unix_seconds] converted to milliseconds,
+	/// so it only has a precision of one second. Times that don't fit in milliseconds
+	/// return [TimeError::OutOfRange].
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		self.unix_seconds()?
+			.checked_mul(1000)
+			.ok_or(TimeError::OutOfRange)
+	}
+
+	/// Returns the current time, in milliseconds from the unix epoch, as a [u128],
+	/// which is what [Totp](crate::Totp) reads.
+	///
+	/// By default, this is [TimeSource::unix_millis]. Time sources with a precision of one
+	/// second can return [TimeSource::unix_seconds] converted to milliseconds instead,
+	/// as closures do, so that times too far in the future for milliseconds in a [u64]
+	/// give the same time step as [Totp::code_at_time](crate::Totp::code_at_time).
+	#[inline]
+	fn unix_millis_wide(&mut self) -> Result<u128, TimeError> {
+		self.unix_millis().map(u128::from)
+	}
+}
+
+impl<F: FnMut() -> u64> TimeSource for F {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		Ok(self())
+	}
+
+	#[inline]
+	fn unix_millis_wide(&mut self) -> Result<u128, TimeError> {
+		Ok(u128::from(self()) * 1000)
+	}
+}
+
+#[cfg(feature = "async")]
+/// An asynchronous source of the current time, for [AsyncTotp](crate::AsyncTotp),
+/// such as a time service behind an async API.
+///
+/// This is implemented for any `FnMut()` closure returning a future
+/// that resolves to seconds from the unix epoch.
+pub trait AsyncTimeSource {
+	/// Returns the current time, in seconds from the unix epoch.
+	fn unix_seconds(&mut self) -> impl Future<Output = Result<u64, TimeError>>;
+
+	/// Returns the current time, in milliseconds from the unix epoch.
+	///
+	/// By default, this is [AsyncTimeSource::unix_seconds] converted to milliseconds,
+	/// so it only has a precision of one second. Times that don't fit in milliseconds
+	/// return [TimeError::OutOfRange].
+	#[inline]
+	fn unix_millis(&mut self) -> impl Future<Output = Result<u64, TimeError>> {
+		async {
+			self.unix_seconds()
+				.await?
+				.checked_mul(1000)
+				.ok_or(TimeError::OutOfRange)
+		}
+	}
+
+	/// Returns the current time, in milliseconds from the unix epoch, as a [u128],
+	/// like [TimeSource::unix_millis_wide].
+	#[inline]
+	fn unix_millis_wide(&mut self) -> impl Future<Output = Result<u128, TimeError>> {
+		async { self.unix_millis().await.map(u128::from) }
+	}
+}
+
+#[cfg(feature = "async")]
+impl<F: FnMut() -> Fut, Fut: Future<Output = u64>> AsyncTimeSource for F {
+	#[inline]
+	fn unix_seconds(&mut self) -> impl Future<Output = Result<u64, TimeError>> {
+		let now = self();
+		async { Ok(now.await) }
+	}
+
+	#[inline]
+	fn unix_millis_wide(&mut self) -> impl Future<Output = Result<u128, TimeError>> {
+		let now = self();
+		async { Ok(u128::from(now.await) * 1000) }
+	}
+}
+
+/// A time source wrapping a closure that returns milliseconds from the unix epoch.
+///
+/// ```rust
+/// use otpee::{time::Millis, Totp};
+/// use core::time::Duration;
+/// use sha1::Sha1;
+///
+/// let mut totp = Totp::<Sha1>::builder()
+///     .interval_duration(Duration::from_millis(500))
+///     .time_source(Millis(|| 1_250))
+///     .build(b"12345678901234567890")
+///     .unwrap();
+/// assert_eq!(totp.counter(), Ok(2));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Millis<F>(pub F);
+
+impl<F: FnMut() -> u64> TimeSource for Millis<F> {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		Ok((self.0)() / 1000)
+	}
+
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		Ok((self.0)())
+	}
+}
+
+#[cfg(feature = "std")]
+/// A time source using the [SystemTime], with millisecond precision.
+///
+/// If the system clock is set to before the unix epoch, reading the time fails with
+/// [TimeError::BeforeEpoch]. Wrap it in [ClampToEpoch] to use the epoch instead.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl SystemClock {
+	/// Returns the current time, in milliseconds from the unix epoch,
+	/// or [OtpError::TimeBeforeEpoch] if the system clock is set to before it.
+	#[inline]
+	pub fn try_now_millis(&self) -> Result<u64, OtpError> {
+		millis_since_epoch(SystemTime::now())
+	}
+}
+
+#[cfg(feature = "std")]
+impl TimeSource for SystemClock {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		self.unix_millis().map(|millis| millis / 1000)
+	}
+
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		self.try_now_millis().map_err(|_| TimeError::BeforeEpoch)
+	}
+}
+
+/// A time source wrapping another one, that reports the unix epoch instead of failing with
+/// [TimeError::BeforeEpoch], for clocks that may briefly be set to before it.
+///
+/// Any other error is passed through.
+///
+/// ```rust
+/// # #[cfg(feature = "std")]
+/// # {
+/// use otpee::time::{ClampToEpoch, SystemClock};
+/// use otpee::Totp;
+/// use sha1::Sha1;
+///
+/// let totp = Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, ClampToEpoch(SystemClock));
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ClampToEpoch<C>(pub C);
+
+impl<C: TimeSource> TimeSource for ClampToEpoch<C> {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		clamp_to_epoch(self.0.unix_seconds())
+	}
+
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		clamp_to_epoch(self.0.unix_millis())
+	}
+
+	#[inline]
+	fn unix_millis_wide(&mut self) -> Result<u128, TimeError> {
+		clamp_to_epoch(self.0.unix_millis_wide())
+	}
+}
+
+#[inline]
+fn clamp_to_epoch<T: Default>(time: Result<T, TimeError>) -> Result<T, TimeError> {
+	match time {
+		Err(TimeError::BeforeEpoch) => Ok(T::default()),
+		time => time,
+	}
+}
+
+#[cfg(feature = "std")]
+/// A time source that reads the [SystemTime] once, and then advances it using the monotonic
+/// [Instant], so that the wall clock jumping, such as when it is corrected by NTP or changed
+/// by the user, doesn't make validation windows jump along with it.
+///
+/// The anchored time drifts along with the monotonic clock, so it should be
+/// [resynced](MonotonicAnchor::resync) now and then, such as after the wall clock was corrected.
+/// A maximum divergence can be set to detect when that is needed.
+///
+/// ```rust
+/// use otpee::time::MonotonicAnchor;
+/// use std::time::{Duration, Instant, SystemTime};
+///
+/// let mut anchor = MonotonicAnchor::new()
+///     .unwrap()
+///     .with_max_divergence(Duration::from_secs(5));
+/// assert!(anchor.check_divergence().is_ok());
+///
+/// // Anchoring to a wall clock that is an hour ahead makes it diverge.
+/// let ahead = SystemTime::now() + Duration::from_secs(3600);
+/// let mut anchor = MonotonicAnchor::from_parts(Instant::now(), ahead)
+///     .unwrap()
+///     .with_max_divergence(Duration::from_secs(5));
+/// assert!(anchor.check_divergence().is_err());
+/// anchor.resync().unwrap();
+/// assert!(anchor.check_divergence().is_ok());
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct MonotonicAnchor {
+	instant: Instant,
+	wall_millis: u64,
+	max_divergence: Option<Duration>,
+}
+
+#[cfg(feature = "std")]
+impl MonotonicAnchor {
+	/// Anchors to the current time.
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the system clock is set to before the unix epoch.
+	#[inline]
+	pub fn new() -> Result<Self, OtpError> {
+		Self::from_parts(Instant::now(), SystemTime::now())
+	}
+
+	/// Anchors to the given wall clock time, as read at the given instant.
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the time is before the unix epoch.
+	pub fn from_parts(instant: Instant, wall_time: SystemTime) -> Result<Self, OtpError> {
+		Ok(Self {
+			instant,
+			wall_millis: millis_since_epoch(wall_time)?,
+			max_divergence: None,
+		})
+	}
+
+	/// Sets how far the wall clock and the anchored time may disagree, after which
+	/// reading the time fails with [TimeError::Diverged] until it is [resynced](MonotonicAnchor::resync).
+	#[inline]
+	pub fn with_max_divergence(mut self, max_divergence: Duration) -> Self {
+		self.max_divergence = Some(max_divergence);
+		self
+	}
+
+	/// Anchors to the current time again.
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the system clock is set to before the unix epoch,
+	/// in which case the previous anchor is kept.
+	pub fn resync(&mut self) -> Result<(), OtpError> {
+		let instant = Instant::now();
+		self.wall_millis = millis_since_epoch(SystemTime::now())?;
+		self.instant = instant;
+		Ok(())
+	}
+
+	/// Returns the anchored time, in milliseconds from the unix epoch.
+	pub fn now_millis(&self) -> u64 {
+		let elapsed = u64::try_from(self.instant.elapsed().as_millis()).unwrap_or(u64::MAX);
+		self.wall_millis.saturating_add(elapsed)
+	}
+
+	/// Returns how far the wall clock and the anchored time disagree, in either direction.
+	/// A wall clock set to before the unix epoch counts as being at it.
+	pub fn divergence(&self) -> Duration {
+		let wall = millis_since_epoch(SystemTime::now()).unwrap_or(0);
+		Duration::from_millis(wall.abs_diff(self.now_millis()))
+	}
+
+	/// Returns [TimeError::Diverged] if the wall clock and the anchored time disagree by more
+	/// than the maximum divergence, if one is set.
+	pub fn check_divergence(&self) -> Result<(), TimeError> {
+		match self.max_divergence {
+			Some(max) if self.divergence() > max => Err(TimeError::Diverged),
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl TimeSource for MonotonicAnchor {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		self.unix_millis().map(|millis| millis / 1000)
+	}
+
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		self.check_divergence()?;
+		Ok(self.now_millis())
+	}
+}
+
+#[cfg(feature = "alloc")]
+impl TimeSource for alloc::boxed::Box<dyn TimeSource> {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		(**self).unix_seconds()
+	}
+
+	#[inline]
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		(**self).unix_millis()
+	}
+
+	#[inline]
+	fn unix_millis_wide(&mut self) -> Result<u128, TimeError> {
+		(**self).unix_millis_wide()
+	}
+}
+
+#[cfg(feature = "std")]
+/// Returns the current time from the [SystemTime], in seconds from the unix epoch,
+/// clamped to 0 if the system clock is set to before it.
+pub(crate) fn system_time() -> u64 {
+	SystemClock.try_now_millis().unwrap_or(0) / 1000
+}
+
+#[cfg(feature = "std")]
+/// Returns the given time, in milliseconds from the unix epoch,
+/// or [OtpError::TimeBeforeEpoch] if it is before it.
+pub(crate) fn millis_since_epoch(time: SystemTime) -> Result<u64, OtpError> {
+	let millis = time
+		.duration_since(UNIX_EPOCH)
+		.map_err(|_| OtpError::TimeBeforeEpoch)?
+		.as_millis();
+	Ok(u64::try_from(millis).unwrap_or(u64::MAX))
+}
+
+#[cfg(all(feature = "chrono", feature = "std"))]
+/// Returns the current time from [Utc::now](chrono::Utc::now), in seconds from the unix epoch,
+/// clamped to 0 if the system clock is set to before it.
+pub(crate) fn chrono_time() -> u64 {
+	u64::try_from(chrono::Utc::now().timestamp()).unwrap_or(0)
+}
+
+#[cfg(feature = "chrono")]
+/// Returns the given [DateTime](chrono::DateTime), in milliseconds from the unix epoch,
+/// or [OtpError::TimeBeforeEpoch] if it is before it.
+pub(crate) fn datetime_millis(time: chrono::DateTime<chrono::Utc>) -> Result<u64, OtpError> {
+	u64::try_from(time.timestamp_millis()).map_err(|_| OtpError::TimeBeforeEpoch)
+}
+
+#[cfg(all(feature = "time", feature = "std"))]
+/// Returns the current time from [OffsetDateTime::now_utc](::time::OffsetDateTime::now_utc),
+/// in seconds from the unix epoch, clamped to 0 if the system clock is set to before it.
+pub(crate) fn offsetdatetime_time() -> u64 {
+	u64::try_from(::time::OffsetDateTime::now_utc().unix_timestamp()).unwrap_or(0)
+}
+
+#[cfg(feature = "time")]
+/// Returns the given [OffsetDateTime](::time::OffsetDateTime), in milliseconds from the unix epoch,
+/// or [OtpError::TimeBeforeEpoch] if it is before it.
+pub(crate) fn offsetdatetime_millis(time: ::time::OffsetDateTime) -> Result<u64, OtpError> {
+	let millis = time.unix_timestamp_nanos().div_euclid(1_000_000);
+	u64::try_from(millis).map_err(|_| OtpError::TimeBeforeEpoch)
+}
+
+#[cfg(feature = "wasm")]
+/// Returns the current time from [Date.now](js_sys::Date::now), in seconds from the unix epoch,
+/// clamped to 0 if the clock is set to before it.
+pub(crate) fn js_date_time() -> u64 {
+	// Float to integer casts saturate, and turn NaN into 0.
+	(js_sys::Date::now() / 1000.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Millis, TimeSource};
+
+	#[test]
+	fn time_source_precision() {
+		let mut seconds = || 59;
+		assert_eq!(seconds.unix_seconds(), Ok(59));
+		assert_eq!(seconds.unix_millis(), Ok(59_000));
+		let mut millis = Millis(|| 59_999);
+		assert_eq!(millis.unix_seconds(), Ok(59));
+		assert_eq!(millis.unix_millis(), Ok(59_999));
+	}
+
+	#[test]
+	fn clamp_to_epoch() {
+		use super::ClampToEpoch;
+		use crate::error::TimeError;
+
+		struct Failing(TimeError);
+
+		impl TimeSource for Failing {
+			fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+				Err(self.0)
+			}
+		}
+
+		assert_eq!(
+			Failing(TimeError::BeforeEpoch).unix_millis(),
+			Err(TimeError::BeforeEpoch)
+		);
+		assert_eq!(
+			ClampToEpoch(Failing(TimeError::BeforeEpoch)).unix_millis(),
+			Ok(0)
+		);
+		assert_eq!(
+			ClampToEpoch(Failing(TimeError::BeforeEpoch)).unix_seconds(),
+			Ok(0)
+		);
+		assert_eq!(
+			ClampToEpoch(Failing(TimeError::NotSet)).unix_millis(),
+			Err(TimeError::NotSet)
+		);
+		assert_eq!(ClampToEpoch(|| 59).unix_millis(), Ok(59_000));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn time_before_epoch() {
+		use super::millis_since_epoch;
+		use crate::error::OtpError;
+		use std::time::{Duration, UNIX_EPOCH};
+
+		assert_eq!(
+			millis_since_epoch(UNIX_EPOCH - Duration::from_secs(10)),
+			Err(OtpError::TimeBeforeEpoch)
+		);
+		assert_eq!(
+			millis_since_epoch(UNIX_EPOCH + Duration::from_millis(1_500)),
+			Ok(1_500)
+		);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn monotonic_anchor() {
+		use super::MonotonicAnchor;
+		use crate::error::TimeError;
+		use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+		// Anchored 90 seconds ago at a wall clock time of 59 seconds, so the wall clock is
+		// now far away from the anchored time, which only advanced by the elapsed time.
+		let instant = Instant::now() - Duration::from_secs(90);
+		let mut anchor =
+			MonotonicAnchor::from_parts(instant, UNIX_EPOCH + Duration::from_secs(59)).unwrap();
+		let seconds = anchor.unix_seconds().unwrap();
+		assert!((149..151).contains(&seconds));
+		assert!(anchor.divergence() > Duration::from_secs(3600));
+
+		let mut anchor = anchor.with_max_divergence(Duration::from_secs(60));
+		assert_eq!(anchor.unix_millis(), Err(TimeError::Diverged));
+		anchor.resync().unwrap();
+		assert!(anchor.check_divergence().is_ok());
+		let wall = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+		assert!(anchor.unix_seconds().unwrap().abs_diff(wall) <= 1);
+	}
+}
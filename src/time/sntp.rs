@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! A time source that gets the time from an NTP server, using SNTP (RFC 4330).
+use super::{MonotonicAnchor, SystemClock, TimeSource};
+use crate::error::TimeError;
+use alloc::string::{String, ToString};
+use std::{
+	io::{self, ErrorKind},
+	net::{SocketAddr, ToSocketAddrs, UdpSocket},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The port NTP servers listen on.
+const NTP_PORT: u16 = 123;
+
+/// The length of an NTP packet without any extensions, in bytes.
+const PACKET_LEN: usize = 48;
+
+/// The number of seconds between the NTP epoch, 1900-01-01, and the unix epoch.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// A time source that queries an NTP server, and advances the time it reported with the
+/// monotonic clock in between queries, like a [MonotonicAnchor].
+///
+/// The server is queried when the time is first read, and again once the refresh interval
+/// has passed since the last query. Each query is a single blocking UDP exchange, which gives up
+/// after the timeout. If a query fails, the time from the last successful query keeps being used,
+/// or the [SystemTime] if there hasn't been one, and the error is available from
+/// [SntpSource::last_sync_error].
+///
+/// ```rust,no_run
+/// use otpee::{time::SntpSource, Totp};
+/// use sha1::Sha1;
+/// use std::time::Duration;
+///
+/// let ntp = SntpSource::new("pool.ntp.org", Duration::from_secs(3600));
+/// let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, ntp).unwrap();
+/// let code = totp.code().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SntpSource {
+	server: String,
+	refresh: Duration,
+	timeout: Duration,
+	anchor: Option<MonotonicAnchor>,
+	last_attempt: Option<Instant>,
+	last_error: Option<io::Error>,
+}
+
+impl SntpSource {
+	/// Creates a time source querying the given server, which is a host name or IP address,
+	/// optionally followed by a port, every `refresh`. Queries time out after a second by default.
+	///
+	/// The server isn't contacted until the time is first read.
+	pub fn new(server: &str, refresh: Duration) -> Self {
+		Self {
+			server: String::from(server),
+			refresh,
+			timeout: Duration::from_secs(1),
+			anchor: None,
+			last_attempt: None,
+			last_error: None,
+		}
+	}
+
+	/// Sets how long to wait for the server to respond to a query.
+	#[inline]
+	pub fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Returns the error that made the last query fail, if it did.
+	#[inline]
+	pub fn last_sync_error(&self) -> Option<&io::Error> {
+		self.last_error.as_ref()
+	}
+
+	/// Returns whether a query has succeeded, so that the time is from the server,
+	/// rather than the [SystemTime].
+	#[inline]
+	pub fn is_synced(&self) -> bool {
+		self.anchor.is_some()
+	}
+
+	/// Queries the server now, regardless of the refresh interval.
+	///
+	/// If the query fails, the error is returned, and also kept for [SntpSource::last_sync_error].
+	pub fn sync(&mut self) -> io::Result<()> {
+		self.last_attempt = Some(Instant::now());
+		match query(&self.server, self.timeout) {
+			Ok(anchor) => {
+				self.anchor = Some(anchor);
+				self.last_error = None;
+				Ok(())
+			}
+			Err(err) => {
+				let copy = io::Error::new(err.kind(), err.to_string());
+				self.last_error = Some(err);
+				Err(copy)
+			}
+		}
+	}
+}
+
+impl TimeSource for SntpSource {
+	#[inline]
+	fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+		self.unix_millis().map(|millis| millis / 1000)
+	}
+
+	fn unix_millis(&mut self) -> Result<u64, TimeError> {
+		let due = self
+			.last_attempt
+			.is_none_or(|last| last.elapsed() >= self.refresh);
+		if due {
+			// Failures are kept for last_sync_error, and the previous time is used instead.
+			let _ = self.sync();
+		}
+		match &self.anchor {
+			Some(anchor) => Ok(anchor.now_millis()),
+			None => SystemClock.unix_millis(),
+		}
+	}
+}
+
+/// Sends a single query to the server, returning the time it reported, anchored to when it did.
+fn query(server: &str, timeout: Duration) -> io::Result<MonotonicAnchor> {
+	let addr = resolve(server)?;
+	let local: SocketAddr = if addr.is_ipv4() {
+		([0, 0, 0, 0], 0).into()
+	} else {
+		([0u16; 8], 0).into()
+	};
+	let socket = UdpSocket::bind(local)?;
+	socket.set_read_timeout(Some(timeout))?;
+	socket.connect(addr)?;
+
+	// Version 4, client mode. The transmit timestamp is only used to match up the response,
+	// as recommended by RFC 4330 section 5, so the local time doesn't have to be accurate.
+	let mut request = [0u8; PACKET_LEN];
+	request[0] = 0b00_100_011;
+	let cookie = to_timestamp(SystemTime::now());
+	request[40..48].copy_from_slice(&cookie.to_be_bytes());
+
+	let sent = Instant::now();
+	socket.send(&request)?;
+	let mut response = [0u8; PACKET_LEN];
+	let len = socket.recv(&mut response).map_err(|err| match err.kind() {
+		// Timeouts are reported as WouldBlock on some platforms.
+		ErrorKind::WouldBlock => ErrorKind::TimedOut.into(),
+		_ => err,
+	})?;
+	let round_trip = sent.elapsed();
+	let received = Instant::now();
+
+	let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message);
+	if len < PACKET_LEN {
+		return Err(invalid("the NTP response is too short"));
+	}
+	let mode = response[0] & 0b111;
+	let leap = response[0] >> 6;
+	if mode != 4 && mode != 5 {
+		return Err(invalid("the NTP response isn't from a server"));
+	}
+	if response[1] == 0 || leap == 3 {
+		return Err(invalid(
+			"the NTP server is unsynchronized, or refused the query",
+		));
+	}
+	if read_timestamp(&response, 24) != cookie {
+		return Err(invalid("the NTP response doesn't match the query"));
+	}
+	let receive = timestamp_nanos(read_timestamp(&response, 32));
+	let transmit = timestamp_nanos(read_timestamp(&response, 40));
+
+	// The time the server took to respond isn't part of the network delay,
+	// which is assumed to be the same both ways.
+	let processing = transmit.saturating_sub(receive);
+	let delay = round_trip.as_nanos().saturating_sub(processing) / 2;
+	let now = transmit.saturating_add(delay);
+	let now = u64::try_from(now).map_err(|_| invalid("the NTP server reported an invalid time"))?;
+	MonotonicAnchor::from_parts(received, UNIX_EPOCH + Duration::from_nanos(now))
+		.map_err(|_| invalid("the NTP server reported a time before the unix epoch"))
+}
+
+/// Resolves the server, using the NTP port if the server doesn't include one.
+fn resolve(server: &str) -> io::Result<SocketAddr> {
+	let mut addrs = match server.to_socket_addrs() {
+		Ok(addrs) => addrs,
+		Err(_) => (server, NTP_PORT).to_socket_addrs()?,
+	};
+	addrs
+		.next()
+		.ok_or_else(|| io::Error::new(ErrorKind::NotFound, "the NTP server has no address"))
+}
+
+fn read_timestamp(packet: &[u8; PACKET_LEN], offset: usize) -> u64 {
+	let mut bytes = [0u8; 8];
+	bytes.copy_from_slice(&packet[offset..offset + 8]);
+	u64::from_be_bytes(bytes)
+}
+
+/// Converts an NTP timestamp, as seconds since 1900 in the upper 32 bits and a fraction of a
+/// second in the lower ones, into nanoseconds from the unix epoch.
+///
+/// Timestamps that would be before the unix epoch are taken to be from after the seconds
+/// wrapped around in 2036.
+fn timestamp_nanos(timestamp: u64) -> u128 {
+	let mut seconds = timestamp >> 32;
+	if seconds < NTP_UNIX_OFFSET {
+		seconds += 1 << 32;
+	}
+	let fraction = (u128::from(timestamp & 0xFFFF_FFFF) * 1_000_000_000) >> 32;
+	u128::from(seconds - NTP_UNIX_OFFSET) * 1_000_000_000 + fraction
+}
+
+/// Converts a time into an NTP timestamp, the inverse of [timestamp_nanos].
+fn to_timestamp(time: SystemTime) -> u64 {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	let seconds = (since_epoch.as_secs() + NTP_UNIX_OFFSET) & 0xFFFF_FFFF;
+	let fraction = (u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000;
+	(seconds << 32) | fraction
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{timestamp_nanos, to_timestamp, SntpSource, PACKET_LEN};
+	use crate::time::TimeSource;
+	use std::{
+		io::ErrorKind,
+		net::UdpSocket,
+		thread,
+		time::{Duration, SystemTime, UNIX_EPOCH},
+	};
+
+	/// Starts a server on a local port that answers the given number of queries,
+	/// reporting the given time, and stops afterwards.
+	fn mock_server(unix_time: u64, stratum: u8, queries: usize) -> String {
+		let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let addr = socket.local_addr().unwrap().to_string();
+		thread::spawn(move || {
+			let timestamp = to_timestamp(UNIX_EPOCH + Duration::from_secs(unix_time));
+			for _ in 0..queries {
+				let mut request = [0u8; PACKET_LEN];
+				let (_, client) = socket.recv_from(&mut request).unwrap();
+				assert_eq!(request[0] & 0b111, 3);
+				let mut response = [0u8; PACKET_LEN];
+				response[0] = 0b00_100_100;
+				response[1] = stratum;
+				response[24..32].copy_from_slice(&request[40..48]);
+				response[32..40].copy_from_slice(&timestamp.to_be_bytes());
+				response[40..48].copy_from_slice(&timestamp.to_be_bytes());
+				socket.send_to(&response, client).unwrap();
+			}
+		});
+		addr
+	}
+
+	#[test]
+	fn sntp_timestamps() {
+		let time = UNIX_EPOCH + Duration::from_millis(1_111_111_109_500);
+		assert_eq!(
+			timestamp_nanos(to_timestamp(time)),
+			1_111_111_109_500_000_000
+		);
+		// 2040, after the NTP seconds wrapped around.
+		let time = UNIX_EPOCH + Duration::from_secs(2_208_988_800);
+		assert_eq!(
+			to_timestamp(time) >> 32,
+			2_208_988_800 + 2_208_988_800 - (1 << 32)
+		);
+		assert_eq!(
+			timestamp_nanos(to_timestamp(time)),
+			2_208_988_800_000_000_000
+		);
+	}
+
+	#[test]
+	fn sntp_sync() {
+		let server = mock_server(1_111_111_109, 1, 1);
+		let mut ntp = SntpSource::new(&server, Duration::from_secs(3600));
+		assert!(!ntp.is_synced());
+		let seconds = ntp.unix_seconds().unwrap();
+		assert!((1_111_111_109..1_111_111_111).contains(&seconds));
+		assert!(ntp.is_synced());
+		assert!(ntp.last_sync_error().is_none());
+
+		// The server has stopped, so syncing again fails, and the previous time keeps being used.
+		let mut ntp = ntp.with_timeout(Duration::from_millis(100));
+		assert!(ntp.sync().is_err());
+		assert!(ntp.last_sync_error().is_some());
+		let seconds = ntp.unix_seconds().unwrap();
+		assert!((1_111_111_109..1_111_111_111).contains(&seconds));
+	}
+
+	#[test]
+	fn sntp_fallback() {
+		// A server that never answers.
+		let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+		let server = silent.local_addr().unwrap().to_string();
+		let mut ntp = SntpSource::new(&server, Duration::from_secs(3600))
+			.with_timeout(Duration::from_millis(100));
+		let system = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_secs();
+		assert!(ntp.unix_seconds().unwrap().abs_diff(system) <= 1);
+		assert!(!ntp.is_synced());
+		assert_eq!(ntp.last_sync_error().unwrap().kind(), ErrorKind::TimedOut);
+
+		// A server that refuses the query with a kiss-o'-death packet.
+		let server = mock_server(1_111_111_109, 0, 1);
+		let mut ntp = SntpSource::new(&server, Duration::from_secs(3600));
+		assert_eq!(ntp.sync().unwrap_err().kind(), ErrorKind::InvalidData);
+		assert!(!ntp.is_synced());
+	}
+}
@@ -1,12 +1,49 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
+#[cfg(any(
+	feature = "std",
+	feature = "chrono",
+	feature = "time",
+	feature = "wasm"
+))]
+use crate::time;
 use crate::{
-	hotp::Hotp,
+	audit::AuditEvent,
+	error::TimeError,
+	generator::OtpGenerator,
+	hex,
+	hotp::{Hotp, HotpBuilder},
+	mac::Hmac,
+	otp,
+	preset::Preset,
+	time::TimeSource,
+	validation::Validation,
 	{error::OtpError, otp::Otp},
 };
-use alloc::boxed::Box;
+#[cfg(feature = "time")]
+use ::time::OffsetDateTime;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use core::{
+	fmt::{self, Debug, Formatter},
+	ops::RangeInclusive,
+	time::Duration,
+};
 use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
 #[cfg(feature = "std")]
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::SystemTime;
+#[cfg(feature = "sha1")]
+use subtle::Choice;
+#[cfg(feature = "serde")]
+use {
+	crate::state::TotpConfig,
+	serde::{Deserialize, Serialize},
+};
+#[cfg(feature = "alloc")]
+use {
+	crate::{algorithm::HashAlgorithm, secret::Secret, state::TotpState, uri},
+	alloc::{boxed::Box, string::String},
+	core::fmt::Write,
+};
 
 /// A Time-based One-Time Password (TOTP) generator.
 ///
@@ -15,6 +52,13 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// time, allowing it to work on both modern platforms and minimalist embedded
 /// platforms, as long as there is some way to get the current time.
 ///
+/// The time source can be any `FnMut() -> u64` closure returning seconds from the unix epoch,
+/// or any other [TimeSource], such as one with millisecond precision for sub-second intervals.
+/// It is stored inline, so with the default plain function pointer, no allocator is needed.
+/// Closures that capture state have their own type, so use `Totp::<D, _>` to infer it,
+/// or [BoxedTotp] to erase it. The instance is [Send] and [Sync] whenever the time source is,
+/// which includes the default one.
+///
 /// TOTP also allows for a "skew" value, which will allow the previous N
 /// or next N codes to be accepted, to account for possible time desynchronization
 /// between the client and server.
@@ -29,14 +73,23 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// let otp = totp_sha1.code().unwrap();
 /// println!("The code for the current time is: {}", otp);
 /// ```
-pub struct Totp<D: Digest + BlockSizeUser + FixedOutputReset> {
+pub struct Totp<D: Digest + BlockSizeUser + FixedOutputReset, C = fn() -> u64> {
 	hotp: Hotp<D>,
-	interval: u64,
+	interval: Duration,
+	epoch: u64,
 	skew: usize,
-	time_callback: Box<dyn Fn() -> u64>,
+	single_use: bool,
+	strict_monotonic: bool,
+	last_accepted_step: Option<u64>,
+	drift_offset: i64,
+	max_drift_offset: u64,
+	learn_drift: bool,
+	grace_period: Option<u64>,
+	tolerance: Option<(u64, u64)>,
+	time_source: C,
 }
 
-impl<D: Digest + BlockSizeUser + FixedOutputReset> Totp<D> {
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Totp<D, C> {
 	/// Creates a new HOTP instance, using the given bytes as the secret,
 	/// the given length, the given skew value, and the given time callback.
 	pub fn new<
@@ -44,136 +97,2935 @@ impl<D: Digest + BlockSizeUser + FixedOutputReset> Totp<D> {
 		L: Into<Option<usize>>,
 		I: Into<Option<u64>>,
 		S: Into<Option<usize>>,
-		C: Fn() -> u64 + 'static,
 	>(
 		key: A,
 		length: L,
 		interval: I,
 		skew: S,
-		time_callback: C,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		TotpBuilder::new()
+			.digits(length.into().unwrap_or(6))
+			.interval(interval.into().unwrap_or(30))
+			.skew(skew.into().unwrap_or(1))
+			.time_source(time_source)
+			.build(key)
+	}
+
+	/// Creates a new TOTP instance from an existing HOTP instance, reusing its secret and
+	/// settings, such as the number of digits, with the given interval in seconds, skew value,
+	/// and time callback. The counter of the HOTP instance is kept, but isn't used.
+	///
+	/// Returns [OtpError::InvalidInterval] if the interval is zero.
+	pub fn from_hotp(
+		hotp: Hotp<D>,
+		interval: u64,
+		skew: usize,
+		time_source: C,
 	) -> Result<Self, OtpError> {
-		let interval = interval.into().unwrap_or(30);
-		let skew = skew.into().unwrap_or(1);
-		Hotp::new(key, length).map(|hotp| Totp {
+		if interval == 0 {
+			return Err(OtpError::InvalidInterval);
+		}
+		Ok(Self {
 			hotp,
-			interval,
+			interval: Duration::from_secs(interval),
+			epoch: 0,
 			skew,
-			time_callback: Box::new(time_callback),
+			single_use: true,
+			strict_monotonic: false,
+			last_accepted_step: None,
+			drift_offset: 0,
+			max_drift_offset: DEFAULT_MAX_DRIFT_OFFSET,
+			learn_drift: false,
+			grace_period: None,
+			tolerance: None,
+			time_source,
 		})
 	}
 
-	#[cfg(feature = "std")]
-	/// Creates a new TOTP instance, using the given bytes as the secret,
-	/// the given length, and the [SystemTime](std::time::SystemTime) to determine the current time.
-	pub fn new_from_system_time<
-		A: AsRef<[u8]>,
-		L: Into<Option<usize>>,
-		I: Into<Option<u64>>,
-		S: Into<Option<usize>>,
-	>(
-		key: A,
+	/// Returns the HOTP instance that generates the codes. Its counter isn't used.
+	#[inline]
+	pub fn hotp(&self) -> &Hotp<D> {
+		&self.hotp
+	}
+
+	/// Returns a mutable reference to the HOTP instance that generates the codes,
+	/// such as for changing its number of digits or checksum setting.
+	///
+	/// Changing its counter has no effect, as the time step is used instead.
+	#[inline]
+	pub fn hotp_mut(&mut self) -> &mut Hotp<D> {
+		&mut self.hotp
+	}
+
+	/// Returns the HOTP instance that generates the codes, dropping the time source
+	/// and the TOTP settings. Its counter is whatever it was before it was wrapped.
+	#[inline]
+	pub fn into_hotp(self) -> Hotp<D> {
+		self.hotp
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Creates a new TOTP instance, using the given Base32 string as the secret.
+	/// See [Secret::from_base32] for the accepted format, and [Totp::new] for the other parameters.
+	pub fn new_base32<L: Into<Option<usize>>, I: Into<Option<u64>>, S: Into<Option<usize>>>(
+		key: &str,
+		length: L,
+		interval: I,
+		skew: S,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		Self::new(
+			Secret::from_base32(key)?,
+			length,
+			interval,
+			skew,
+			time_source,
+		)
+	}
+
+	/// Creates a new TOTP instance, using the given hex string as the secret.
+	/// See [Secret::from_hex] for the accepted format, and [Totp::new] for the other parameters.
+	pub fn new_hex<L: Into<Option<usize>>, I: Into<Option<u64>>, S: Into<Option<usize>>>(
+		key: &str,
 		length: L,
 		interval: I,
 		skew: S,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		hex::with_decoded(key, |key| {
+			Self::new(key, length, interval, skew, time_source)
+		})
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Restores a TOTP instance from a previously exported state, using the secret stored in it,
+	/// and the given time callback.
+	///
+	/// Returns [OtpError::MissingSecret] if the state has no secret.
+	pub fn from_state(state: &TotpState, time_source: C) -> Result<Self, OtpError> {
+		let secret = state.secret.as_ref().ok_or(OtpError::MissingSecret)?;
+		Self::from_state_with_key(secret, state, time_source)
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Restores a TOTP instance from a previously exported state, using the given bytes as
+	/// the secret, and the given time callback. Any secret stored in the state is ignored.
+	pub fn from_state_with_key<A: AsRef<[u8]>>(
+		key: A,
+		state: &TotpState,
+		time_source: C,
 	) -> Result<Self, OtpError> {
-		Self::new(key, length, interval, skew, time_callback)
+		let mut totp = TotpBuilder::new()
+			.digits(state.digits)
+			.checksum(state.checksum)
+			.truncation_offset(state.truncation_offset)
+			.interval_duration(state.interval)
+			.epoch(state.epoch)
+			.skew(state.skew)
+			.time_source(time_source)
+			.build(key)?;
+		totp.last_accepted_step = state.last_accepted_step;
+		totp.max_drift_offset = state.max_drift_offset;
+		totp.learn_drift = state.learn_drift;
+		totp.set_drift_offset(state.drift_offset);
+		totp.single_use = state.single_use;
+		totp.strict_monotonic = state.strict_monotonic;
+		totp.grace_period = state.grace_period;
+		totp.tolerance = state.tolerance;
+		Ok(totp)
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Exports the state of this instance, so it can be persisted and restored later.
+	/// The secret is not included, see [TotpState].
+	pub fn export_state(&self) -> TotpState {
+		TotpState {
+			secret: None,
+			digits: self.hotp.digits(),
+			checksum: self.hotp.checksum(),
+			truncation_offset: self.hotp.truncation_offset(),
+			interval: self.interval,
+			epoch: self.epoch,
+			skew: self.skew,
+			last_accepted_step: self.last_accepted_step,
+			drift_offset: self.drift_offset,
+			max_drift_offset: self.max_drift_offset,
+			learn_drift: self.learn_drift,
+			single_use: self.single_use,
+			strict_monotonic: self.strict_monotonic,
+			grace_period: self.grace_period,
+			tolerance: self.tolerance,
+		}
+	}
+
+	/// Returns the number of digits in generated codes, not counting the checksum digit.
+	#[inline]
+	pub fn digits(&self) -> usize {
+		self.hotp.digits()
+	}
+
+	/// Returns the length of a time step.
+	#[inline]
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Sets the length of each time step, in seconds.
+	/// See [Totp::set_interval_duration].
+	#[inline]
+	pub fn set_interval(&mut self, interval: u64) -> Result<(), OtpError> {
+		self.set_interval_duration(Duration::from_secs(interval))
+	}
+
+	/// Sets the length of each time step, returning [OtpError::InvalidInterval] if it is zero.
+	///
+	/// The last accepted time step is converted to the one containing its end, so codes for
+	/// times that were already used stay rejected. The drift offset is reset, as it was measured
+	/// in time steps of the old length.
+	pub fn set_interval_duration(&mut self, interval: Duration) -> Result<(), OtpError> {
+		if interval.is_zero() {
+			return Err(OtpError::InvalidInterval);
+		}
+		if let Some(step) = self.last_accepted_step {
+			let end = (u128::from(step) + 1).saturating_mul(self.interval.as_nanos());
+			let step = (end - 1) / interval.as_nanos();
+			self.last_accepted_step = Some(u64::try_from(step).unwrap_or(u64::MAX));
+		}
+		self.interval = interval;
+		self.drift_offset = 0;
+		Ok(())
+	}
+
+	/// Returns the time that the first time step starts at, in seconds from the unix epoch.
+	#[inline]
+	pub fn epoch(&self) -> u64 {
+		self.epoch
+	}
+
+	/// Returns the number of time steps before and after the current one that are accepted when validating.
+	#[inline]
+	pub fn skew(&self) -> usize {
+		self.skew
+	}
+
+	/// Sets the number of time steps before and after the current one that are accepted when validating.
+	#[inline]
+	pub fn set_skew(&mut self, skew: usize) {
+		self.skew = skew;
+	}
+
+	/// Returns the grace period for codes of the previous time step, in seconds, if one is set.
+	/// See [Totp::set_grace_period].
+	#[inline]
+	pub fn grace_period(&self) -> Option<u64> {
+		self.grace_period
+	}
+
+	/// Sets a grace period, in seconds, during which the code of the previous time step is still
+	/// accepted after a new time step starts, or clears it with `None`.
+	///
+	/// When set, it takes precedence over the skew for earlier time steps: only the previous one
+	/// is accepted, and only during the first `grace` seconds of the current one. The skew still
+	/// controls how many later time steps are accepted.
+	///
+	/// ```rust
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 62).unwrap();
+	/// totp.set_grace_period(5);
+	/// // The code for the previous time step, 2 seconds into the current one.
+	/// assert!(totp.validate_code(94287082));
+	/// ```
+	#[inline]
+	pub fn set_grace_period<G: Into<Option<u64>>>(&mut self, grace: G) {
+		self.grace_period = grace.into();
+	}
+
+	/// Returns how old and how far in the future codes can be, in seconds, if set.
+	/// See [Totp::set_tolerance_seconds].
+	#[inline]
+	pub fn tolerance_seconds(&self) -> Option<(u64, u64)> {
+		self.tolerance
+	}
+
+	/// Sets how old and how far in the future codes can be, in seconds, for tolerances that
+	/// aren't a whole number of time steps. This takes precedence over the skew and grace period.
+	///
+	/// A code for an earlier time step is accepted if its time step started at most `behind`
+	/// seconds ago, and one for a later time step if it starts at most `ahead` seconds from now.
+	/// The code for the current time step is always accepted.
+	///
+	/// ```rust
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 70).unwrap();
+	/// totp.set_tolerance_seconds(45, 0);
+	/// // The code for the previous time step, which started 40 seconds ago.
+	/// assert!(totp.validate_code(94287082));
+	/// ```
+	#[inline]
+	pub fn set_tolerance_seconds(&mut self, behind: u64, ahead: u64) {
+		self.tolerance = Some((behind, ahead));
+	}
+
+	/// Clears the tolerance set with [Totp::set_tolerance_seconds],
+	/// so that the skew and grace period are used again.
+	#[inline]
+	pub fn clear_tolerance_seconds(&mut self) {
+		self.tolerance = None;
+	}
+
+	/// Returns the time step for the given time, represented as seconds from the unix epoch.
+	#[inline]
+	fn counter_at(&self, time: u64) -> Result<u64, OtpError> {
+		self.step_at_millis(u128::from(time) * 1000)
+	}
+
+	/// Returns the time step for the given time, represented as milliseconds from the unix epoch.
+	#[inline]
+	pub(crate) fn counter_at_millis(&self, millis: u64) -> Result<u64, OtpError> {
+		self.step_at_millis(u128::from(millis))
 	}
 
-	fn counter(&self) -> u64 {
-		(*self.time_callback)() / self.interval
+	/// Returns the time step for the given time, represented as milliseconds from the unix epoch,
+	/// which is wider than [u64], so that no time in seconds is out of range.
+	/// Times before the epoch of this instance are in the first time step.
+	///
+	/// Returns [OtpError::TimeOutOfRange] if the time step doesn't fit in a [u64],
+	/// which can only happen with intervals shorter than a second.
+	fn step_at_millis(&self, millis: u128) -> Result<u64, OtpError> {
+		let elapsed = millis.saturating_sub(u128::from(self.epoch) * 1000);
+		let step = elapsed * NANOS_PER_MILLI / self.interval.as_nanos();
+		u64::try_from(step).map_err(|_| OtpError::TimeOutOfRange)
 	}
 
 	/// Calculate the OTP value for the given time, represented as seconds from the unix epoch.
+	///
+	/// Returns [OtpError::TimeOutOfRange] if the time step for it doesn't fit in a [u64].
 	pub fn code_at_time(&mut self, time: u64) -> Result<Otp, OtpError> {
-		self.hotp.set_counter(time / self.interval);
-		self.hotp.code()
+		let counter = self.counter_at(time)?;
+		self.hotp.code_at(counter)
+	}
+
+	/// Calculate the OTP value for the given time, represented as milliseconds from the unix epoch.
+	///
+	/// Returns [OtpError::TimeOutOfRange] if the time step for it doesn't fit in a [u64].
+	pub fn code_at_millis(&mut self, millis: u64) -> Result<Otp, OtpError> {
+		let counter = self.counter_at_millis(millis)?;
+		self.hotp.code_at(counter)
 	}
 
 	#[cfg(feature = "std")]
 	/// Calculate the OTP value for the given [SystemTime](std::time::SystemTime).
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the time is before the unix epoch.
 	pub fn code_at_system_time(&mut self, system_time: SystemTime) -> Result<Otp, OtpError> {
-		self.code_at_time(
-			system_time
-				.duration_since(UNIX_EPOCH)
-				.expect("time went backwards")
-				.as_secs(),
-		)
+		self.code_at_millis(time::millis_since_epoch(system_time)?)
+	}
+
+	#[cfg(feature = "chrono")]
+	/// Calculate the OTP value for the given [DateTime].
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the time is before the unix epoch.
+	pub fn code_at_datetime(&mut self, datetime: DateTime<Utc>) -> Result<Otp, OtpError> {
+		self.code_at_millis(time::datetime_millis(datetime)?)
+	}
+
+	#[cfg(feature = "chrono")]
+	/// Validates the code like [Totp::validate_code], for the given [DateTime]
+	/// rather than the current time. No code is valid for a time before the unix epoch.
+	pub fn validate_code_at_datetime(&mut self, code: u64, datetime: DateTime<Utc>) -> bool {
+		match time::datetime_millis(datetime) {
+			Ok(millis) => self
+				.validate_code_at_millis(code, u128::from(millis))
+				.is_valid(),
+			Err(_) => false,
+		}
+	}
+
+	#[cfg(feature = "time")]
+	/// Calculate the OTP value for the given [OffsetDateTime].
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the time is before the unix epoch.
+	pub fn code_at_offsetdatetime(&mut self, datetime: OffsetDateTime) -> Result<Otp, OtpError> {
+		self.code_at_millis(time::offsetdatetime_millis(datetime)?)
+	}
+
+	#[cfg(feature = "time")]
+	/// Validates the code like [Totp::validate_code], for the given [OffsetDateTime]
+	/// rather than the current time. No code is valid for a time before the unix epoch.
+	pub fn validate_code_at_offsetdatetime(&mut self, code: u64, datetime: OffsetDateTime) -> bool {
+		match time::offsetdatetime_millis(datetime) {
+			Ok(millis) => self
+				.validate_code_at_millis(code, u128::from(millis))
+				.is_valid(),
+			Err(_) => false,
+		}
+	}
+
+	/// Returns an iterator over the OTP values for the time step of the given time,
+	/// represented as seconds from the unix epoch, as well as the given number of steps
+	/// before and after it.
+	pub fn codes_around(
+		&mut self,
+		now: u64,
+		steps_before: u64,
+		steps_after: u64,
+	) -> impl Iterator<Item = Result<Otp, OtpError>> + '_ {
+		let counter = self.counter_at(now);
+		// If the time is out of range, only the error is returned.
+		let error = counter.clone().err().map(Err);
+		let steps = counter
+			.map(|counter| {
+				counter.saturating_sub(steps_before)..=counter.saturating_add(steps_after)
+			})
+			.into_iter()
+			.flatten();
+		error
+			.into_iter()
+			.chain(steps.map(move |step| self.hotp.code_at(step)))
+	}
+
+	/// Fills the slice with the OTP values for consecutive time steps, starting at that of the
+	/// given time, represented as seconds from the unix epoch. See [Hotp::codes_into].
+	#[inline]
+	pub fn codes_into(&mut self, time: u64, out: &mut [Otp]) -> Result<(), OtpError> {
+		let counter = self.counter_at(time)?;
+		self.hotp.codes_into(counter, out)
+	}
+
+	/// Validates the code like [Totp::validate_code], for the given time, represented as seconds
+	/// from the unix epoch, rather than the current one, such as a time reported by an offline device.
+	///
+	/// The skew window is applied around the given time, and codes that were already used
+	/// are rejected, just like for the current time.
+	#[inline]
+	pub fn validate_code_at_time(&mut self, code: u64, time: u64) -> bool {
+		self.validate_code_at_time_detailed(code, time).is_valid()
+	}
+
+	/// Validates the code like [Totp::validate_code_at_time], returning a detailed result
+	/// like [Totp::validate_code_detailed], including the time step that matched.
+	pub fn validate_code_at_time_detailed(&mut self, code: u64, time: u64) -> Validation {
+		self.validate_code_at_millis(code, u128::from(time) * 1000)
+	}
+
+	/// Validates the code like [Totp::validate_code_detailed], for the time reported by a time source,
+	/// represented as milliseconds from the unix epoch, rejecting it if there was no time to report.
+	pub(crate) fn validate_code_at_reported_time(
+		&mut self,
+		code: u64,
+		millis: Result<u128, TimeError>,
+	) -> Validation {
+		match millis {
+			Ok(millis) => self.validate_code_at_millis(code, millis),
+			Err(_) => {
+				self.hotp.audit(AuditEvent::ValidationFailed);
+				Validation::Invalid
+			}
+		}
+	}
+
+	/// Validates the code like [Totp::validate_code_detailed], for the given time,
+	/// represented as milliseconds from the unix epoch, rather than the current one.
+	fn validate_code_at_millis(&mut self, code: u64, millis: u128) -> Validation {
+		let Ok((counter, behind, ahead)) = self.validation_window_at_millis(millis) else {
+			self.hotp.audit(AuditEvent::ValidationFailed);
+			return Validation::Invalid;
+		};
+		let steps = self.skew_steps(counter, behind, ahead);
+		let matched = self.hotp.find_counter(steps, code);
+		match self.accept(counter, matched) {
+			Some(step) => Validation::Valid {
+				step,
+				drift: drift(step, counter),
+			},
+			None if self.single_use => {
+				let last_accepted = self.last_accepted_step;
+				let used = self
+					.window(counter, behind, ahead)
+					.filter(|&step| last_accepted.is_some_and(|last| step <= last));
+				match self.hotp.find_counter(used, code) {
+					Some(_) => Validation::Replayed,
+					None => Validation::Invalid,
+				}
+			}
+			None => Validation::Invalid,
+		}
+	}
+
+	/// Replaces the source of the current time. See [TimeSource].
+	#[inline]
+	pub fn set_time_source(&mut self, time_source: C) {
+		self.time_source = time_source;
+	}
+
+	#[cfg(feature = "async")]
+	/// Returns the source of the current time.
+	#[inline]
+	pub(crate) fn time_source_mut(&mut self) -> &mut C {
+		&mut self.time_source
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Registers a hook that is invoked for every code generated, and every validation attempt,
+	/// such as for audit logging. See [AuditEvent] for what is reported.
+	///
+	/// Only one hook can be registered at a time; registering another replaces it.
+	#[inline]
+	pub fn set_audit_hook<F: FnMut(AuditEvent) + Send + Sync + 'static>(&mut self, f: F) {
+		self.hotp.set_audit_hook(f);
+	}
+
+	/// Returns whether each code can only be used once. See [Totp::set_enforce_single_use].
+	#[inline]
+	pub fn enforces_single_use(&self) -> bool {
+		self.single_use
+	}
+
+	/// Sets whether each code can only be used once, as required by RFC 6238 section 5.2.
+	/// This is enabled by default.
+	///
+	/// When enabled, once a code is accepted, codes for its time step and any earlier ones
+	/// are rejected, even if they are within the skew window. When disabled, the last accepted
+	/// time step is still recorded, but codes can be reused until they leave the skew window.
+	#[inline]
+	pub fn set_enforce_single_use(&mut self, enforce: bool) {
+		self.single_use = enforce;
+	}
+
+	/// Returns whether codes for time steps before the last accepted one are always rejected.
+	/// See [Totp::set_strict_monotonic].
+	#[inline]
+	pub fn is_strict_monotonic(&self) -> bool {
+		self.strict_monotonic
+	}
+
+	/// Sets whether codes for time steps before the last accepted one are rejected, even if they
+	/// were never used, so that codes are only ever accepted in order, such as when several
+	/// servers validate codes for the same user. This is disabled by default.
+	///
+	/// While single use is enforced, earlier time steps are already rejected, so this only makes
+	/// a difference when it is disabled: codes for the last accepted time step can then still
+	/// be reused, but not ones for earlier time steps. The last accepted time step can be
+	/// persisted with [Totp::last_accepted_step] and [Totp::restore_last_accepted_step].
+	///
+	/// ```rust
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+	/// totp.set_enforce_single_use(false);
+	/// totp.set_strict_monotonic(true);
+	/// totp.restore_last_accepted_step(2);
+	/// // The code for time step 1, which was never used.
+	/// assert!(!totp.validate_code(94287082));
+	/// ```
+	#[inline]
+	pub fn set_strict_monotonic(&mut self, strict: bool) {
+		self.strict_monotonic = strict;
+	}
+
+	/// Returns the highest time step that a code was accepted for, if any.
+	/// Codes for it, and any earlier time steps, are rejected while single use is enforced.
+	#[inline]
+	pub fn last_accepted_step(&self) -> Option<u64> {
+		self.last_accepted_step
+	}
+
+	/// Restores the highest time step that a code was accepted for, such as after a restart,
+	/// so that codes that were already used keep being rejected.
+	#[inline]
+	pub fn restore_last_accepted_step(&mut self, step: u64) {
+		self.last_accepted_step = Some(step);
+	}
+
+	/// Returns the drift offset, in time steps, that is applied to the current time step
+	/// when validating codes and generating them for the current time. See [Totp::set_drift_offset].
+	#[inline]
+	pub fn drift_offset(&self) -> i64 {
+		self.drift_offset
+	}
+
+	/// Sets the drift offset, in time steps, to compensate for a token whose clock is known to be
+	/// ahead, for a positive offset, or behind, for a negative one, as described in RFC 6238
+	/// section 6. The skew window is centered on the current time step moved by the offset.
+	///
+	/// The offset is clamped to [Totp::max_drift_offset].
+	pub fn set_drift_offset(&mut self, offset: i64) {
+		let max = i64::try_from(self.max_drift_offset).unwrap_or(i64::MAX);
+		self.drift_offset = offset.clamp(-max, max);
+	}
+
+	/// Returns the largest drift offset that can be set or learned, in time steps.
+	#[inline]
+	pub fn max_drift_offset(&self) -> u64 {
+		self.max_drift_offset
+	}
+
+	/// Sets the largest drift offset that can be set or learned, in time steps, so that a
+	/// compromised token can't move the window arbitrarily far. This is 10 time steps by default.
+	/// The current offset is clamped to the new maximum.
+	pub fn set_max_drift_offset(&mut self, max: u64) {
+		self.max_drift_offset = max;
+		self.set_drift_offset(self.drift_offset);
+	}
+
+	/// Returns whether the drift offset is updated by successful validations.
+	/// See [Totp::auto_learn_drift].
+	#[inline]
+	pub fn learns_drift(&self) -> bool {
+		self.learn_drift
+	}
+
+	/// Sets whether the drift offset is updated by successful validations. When enabled, a code
+	/// that matches a time step other than the expected one moves the offset by the difference,
+	/// so that a slowly drifting token keeps being accepted without widening the skew window.
+	/// This is disabled by default.
+	#[inline]
+	pub fn auto_learn_drift(&mut self, learn: bool) {
+		self.learn_drift = learn;
+	}
+
+	/// Returns the given time step moved by the drift offset. Like times before the epoch,
+	/// moving it before the first time step saturates, but moving it past [u64::MAX]
+	/// returns [OtpError::TimeOutOfRange].
+	fn apply_drift(&self, step: u64) -> Result<u64, OtpError> {
+		if self.drift_offset >= 0 {
+			step.checked_add(self.drift_offset.unsigned_abs())
+				.ok_or(OtpError::TimeOutOfRange)
+		} else {
+			Ok(step.saturating_sub(self.drift_offset.unsigned_abs()))
+		}
+	}
+
+	/// Returns the time that the given time step starts at, in nanoseconds from the unix epoch.
+	#[inline]
+	fn step_start(&self, step: u64) -> u128 {
+		let offset = u128::from(step).saturating_mul(self.interval.as_nanos());
+		(u128::from(self.epoch) * NANOS_PER_SEC).saturating_add(offset)
+	}
+
+	/// Returns the expected time step for the given time, represented as milliseconds
+	/// from the unix epoch, with the drift offset applied.
+	#[inline]
+	pub(crate) fn expected_step_at_millis(&self, millis: u128) -> Result<u64, OtpError> {
+		self.apply_drift(self.step_at_millis(millis)?)
+	}
+
+	/// Records the matched time step, if any, as the last accepted one, learning the drift
+	/// if enabled, and reporting the outcome to the audit hook.
+	fn accept(&mut self, counter: u64, matched: Option<u64>) -> Option<u64> {
+		match matched {
+			Some(step) => {
+				self.last_accepted_step = self.last_accepted_step.max(Some(step));
+				if self.learn_drift {
+					self.set_drift_offset(self.drift_offset.saturating_add(drift(step, counter)));
+				}
+				self.hotp.audit(AuditEvent::ValidationSucceeded {
+					counter: step,
+					drift: drift(step, counter),
+				});
+			}
+			None => self.hotp.audit(AuditEvent::ValidationFailed),
+		}
+		matched
+	}
+
+	/// Returns the expected time step for the given time, represented as milliseconds from the
+	/// unix epoch, along with how many earlier and later time steps are accepted at that time,
+	/// which is the skew, unless a grace period or tolerance is set.
+	fn validation_window_at_millis(&self, millis: u128) -> Result<(u64, u64, u64), OtpError> {
+		let elapsed = millis.saturating_sub(u128::from(self.epoch) * 1000);
+		let interval = self.interval.as_nanos();
+		let into_step = elapsed * NANOS_PER_MILLI % interval;
+		let skew = saturating_u64(self.skew);
+		let (behind, ahead) = match (self.tolerance, self.grace_period) {
+			(Some((behind, ahead)), _) => {
+				// The time step k steps back started k intervals plus the time into the current
+				// one ago, and the one k steps ahead starts k intervals minus it from now.
+				let behind = (u128::from(behind) * NANOS_PER_SEC)
+					.checked_sub(into_step)
+					.map_or(0, |behind| behind / interval);
+				let ahead = (u128::from(ahead) * NANOS_PER_SEC + into_step) / interval;
+				(saturating_u64(behind), saturating_u64(ahead))
+			}
+			(None, Some(grace)) => (
+				u64::from(into_step < u128::from(grace) * NANOS_PER_SEC),
+				skew,
+			),
+			(None, None) => (skew, skew),
+		};
+		let counter = self.apply_drift(self.step_at_millis(millis)?)?;
+		Ok((counter, behind, ahead))
+	}
+
+	/// Returns the time steps in the window around the given time step, from `behind` steps
+	/// before it to `ahead` steps after it, closest first, skipping any at or before the last
+	/// accepted one if single use is enforced, or any before it in strict monotonic mode.
+	fn skew_steps(&self, counter: u64, behind: u64, ahead: u64) -> impl Iterator<Item = u64> {
+		let last_accepted = self
+			.last_accepted_step
+			.filter(|_| self.single_use || self.strict_monotonic);
+		let reuse_last = !self.single_use;
+		// Distances past time step 0 or u64::MAX have no steps, so they aren't iterated over,
+		// which matters for huge skews near either end.
+		let (behind, ahead) = (behind.min(counter), ahead.min(u64::MAX - counter));
+		let around = (1..=behind.max(ahead)).flat_map(move |distance| {
+			[
+				counter.checked_sub(distance).filter(|_| distance <= behind),
+				counter.checked_add(distance).filter(|_| distance <= ahead),
+			]
+		});
+		core::iter::once(Some(counter))
+			.chain(around)
+			.flatten()
+			.filter(move |&step| {
+				last_accepted.is_none_or(|last| step > last || (reuse_last && step == last))
+			})
+	}
+
+	/// Returns all the time steps in the window around the given time step,
+	/// from `behind` steps before it to `ahead` steps after it.
+	fn window(&self, counter: u64, behind: u64, ahead: u64) -> RangeInclusive<u64> {
+		counter.saturating_sub(behind)..=counter.saturating_add(ahead)
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> Totp<D, C> {
+	/// Returns the time step for the current time, as reported by the time source,
+	/// with millisecond precision if the time source provides it.
+	///
+	/// Returns [OtpError::TimeUnavailable] if the time source couldn't report the time.
+	#[inline]
+	pub fn counter(&mut self) -> Result<u64, OtpError> {
+		let millis = self.time_source.unix_millis_wide()?;
+		self.expected_step_at_millis(millis)
+	}
+
+	/// Returns the number of seconds until the current code expires, rounded up.
+	/// Exactly on the boundary of a time step, this is the full interval.
+	pub fn seconds_remaining(&mut self) -> Result<u64, OtpError> {
+		let (now, next) = self.step_boundary()?;
+		Ok(saturating_secs((next - now).div_ceil(NANOS_PER_SEC)))
+	}
+
+	/// Returns the time that the current code expires at, in seconds from the unix epoch,
+	/// which is when the next time step starts.
+	pub fn valid_until(&mut self) -> Result<u64, OtpError> {
+		let (_, next) = self.step_boundary()?;
+		Ok(saturating_secs(next.div_ceil(NANOS_PER_SEC)))
+	}
+
+	/// Returns how far into the current time step the current time is, from 0.0 at the start
+	/// of the time step, up to 1.0 when the code expires, such as for drawing a progress bar.
+	pub fn progress(&mut self) -> Result<f32, OtpError> {
+		let (now, next) = self.step_boundary()?;
+		let interval = self.interval.as_nanos();
+		let elapsed = interval.saturating_sub(next - now);
+		Ok((elapsed as f64 / interval as f64) as f32)
+	}
+
+	/// Returns the current time, and the time the next time step starts at,
+	/// both in nanoseconds from the unix epoch.
+	fn step_boundary(&mut self) -> Result<(u128, u128), OtpError> {
+		let millis = self.time_source.unix_millis_wide()?;
+		let now = millis * NANOS_PER_MILLI;
+		let epoch = u128::from(self.epoch) * NANOS_PER_SEC;
+		let step = u128::from(self.step_at_millis(millis)?);
+		let next = epoch + (step + 1) * self.interval.as_nanos();
+		Ok((now, next.max(now)))
 	}
 
 	/// Calculate the OTP value for the current time.
+	///
+	/// Returns [OtpError::TimeUnavailable] if the time source couldn't report the time.
 	pub fn code(&mut self) -> Result<Otp, OtpError> {
-		let counter = self.counter();
-		self.code_at_time(counter)
+		let counter = self.counter()?;
+		self.hotp.code_at(counter)
+	}
+
+	/// Calculate the OTP value for the current time, along with the window of time it is valid in.
+	///
+	/// With a drift offset, the window is still that of the current time step,
+	/// like [Totp::valid_until], while the step is the one the code is for.
+	pub fn code_with_validity(&mut self) -> Result<(Otp, CodeWindow), OtpError> {
+		let (current, step) = self.current_steps()?;
+		let code = self.hotp.code_at(step)?;
+		let start = self.step_start(current);
+		let end = start.saturating_add(self.interval.as_nanos());
+		let window = CodeWindow {
+			step,
+			not_before: saturating_secs(start / NANOS_PER_SEC),
+			not_after: saturating_secs(end / NANOS_PER_SEC),
+		};
+		Ok((code, window))
+	}
+
+	/// Returns the time steps in the skew window around the current one, along with their codes,
+	/// which are all the codes that [Totp::validate_code] would currently accept,
+	/// ignoring any that were already used. The codes are calculated lazily.
+	///
+	/// The window is cut short near time step 0 and [u64::MAX], and the iterator stops early
+	/// if a code can't be calculated.
+	///
+	/// ```rust
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+	/// let steps: Vec<u64> = totp.window_codes().unwrap().map(|(step, _)| step).collect();
+	/// assert_eq!(steps, [0, 1, 2]);
+	/// ```
+	pub fn window_codes(&mut self) -> Result<impl Iterator<Item = (u64, Otp)> + '_, OtpError> {
+		let millis = self.time_source.unix_millis_wide()?;
+		let (counter, behind, ahead) = self.validation_window_at_millis(millis)?;
+		let steps = self.window(counter, behind, ahead);
+		Ok(steps.map_while(move |step| self.hotp.code_at(step).ok().map(|code| (step, code))))
+	}
+
+	#[cfg(feature = "alloc")]
+	/// Formats the codes returned by [Totp::window_codes] on a single line, such as for logging,
+	/// as `step: code` pairs separated by commas.
+	pub fn window_codes_string(&mut self) -> Result<String, OtpError> {
+		let mut out = String::new();
+		for (step, code) in self.window_codes()? {
+			if !out.is_empty() {
+				out.push_str(", ");
+			}
+			let _ = write!(out, "{}: {}", step, code);
+		}
+		Ok(out)
+	}
+
+	/// Returns the codes for the current time step and up to `n - 1` after it, along with the
+	/// time each one becomes the current code at, in seconds from the unix epoch, such as for
+	/// helping a user whose clock is off. The codes are calculated lazily.
+	///
+	/// This doesn't affect which codes are accepted. The iterator stops early at [u64::MAX],
+	/// or if a code can't be calculated.
+	///
+	/// ```rust
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+	/// let preview: Vec<_> = totp.preview(3).unwrap().map(|(time, _)| time).collect();
+	/// assert_eq!(preview, [30, 60, 90]);
+	/// ```
+	pub fn preview(&mut self, n: usize) -> Result<impl Iterator<Item = (u64, Otp)> + '_, OtpError> {
+		let (current, expected) = self.current_steps()?;
+		let steps = (0..n as u64).map_while(move |offset| {
+			Some((current.checked_add(offset)?, expected.checked_add(offset)?))
+		});
+		Ok(self.timed_codes(steps))
+	}
+
+	/// Returns the codes for up to `n` time steps before the current one, most recent first,
+	/// along with the time each one became the current code at, in seconds from the unix epoch,
+	/// like [Totp::preview]. The iterator stops early at time step 0.
+	pub fn history(&mut self, n: usize) -> Result<impl Iterator<Item = (u64, Otp)> + '_, OtpError> {
+		let (current, expected) = self.current_steps()?;
+		let steps = (1..=n as u64).map_while(move |offset| {
+			Some((current.checked_sub(offset)?, expected.checked_sub(offset)?))
+		});
+		Ok(self.timed_codes(steps))
+	}
+
+	/// Returns the time step for the current time, both without and with the drift offset applied.
+	fn current_steps(&mut self) -> Result<(u64, u64), OtpError> {
+		let millis = self.time_source.unix_millis_wide()?;
+		let current = self.step_at_millis(millis)?;
+		Ok((current, self.apply_drift(current)?))
+	}
+
+	/// Calculates the codes lazily, given pairs of the time step that each one is shown during,
+	/// and the time step it is for, along with the time the former starts at,
+	/// in seconds from the unix epoch.
+	fn timed_codes<'a, I: Iterator<Item = (u64, u64)> + 'a>(
+		&'a mut self,
+		steps: I,
+	) -> impl Iterator<Item = (u64, Otp)> + 'a {
+		steps.map_while(move |(shown, step)| {
+			let start = saturating_secs(self.step_start(shown) / NANOS_PER_SEC);
+			self.hotp.code_at(step).ok().map(|code| (start, code))
+		})
 	}
 
 	/// Validates the code as being valid for the current time.
 	/// This takes the skew value into account, which also allows the previous N or next N codes to be accepted.
-	pub fn validate_code(&mut self, code: u32) -> bool {
-		let counter = self.counter();
-		for value in
-			counter.saturating_sub(self.skew as u64)..=counter.saturating_add(self.skew as u64)
-		{
-			self.hotp.set_counter(value);
-			if self.hotp.code().map(|c| c == code).unwrap_or(false) {
-				return true;
+	/// The time source is sampled once per call.
+	///
+	/// Every time step in the skew window is checked, regardless of whether an earlier one matched,
+	/// so the time taken doesn't reveal which step matched, or whether any did.
+	///
+	/// Once a code is accepted, codes for its time step and any earlier ones are rejected,
+	/// so that each code can only be used once, as required by RFC 6238 section 5.2.
+	/// See [Totp::set_enforce_single_use].
+	///
+	/// If the time source couldn't report the time, the code is rejected.
+	pub fn validate_code(&mut self, code: u64) -> bool {
+		self.validate_code_detailed(code).is_valid()
+	}
+
+	/// Validates the code like [Totp::validate_code], returning the time step that it matched.
+	///
+	/// If the code matches more than one time step in the skew window, the one closest to the
+	/// current time step is returned, preferring the earlier one when two are equally close.
+	pub fn validate_code_at(&mut self, code: u64) -> Option<u64> {
+		match self.validate_code_detailed(code) {
+			Validation::Valid { step, .. } => Some(step),
+			_ => None,
+		}
+	}
+
+	/// Validates the code like [Totp::validate_code], returning how many time steps the matched
+	/// code is from the current one, which is negative if the client's clock is behind.
+	///
+	/// Like [Totp::validate_code_at], the match closest to the current time step is preferred.
+	pub fn validate_with_drift(&mut self, code: u64) -> Option<i64> {
+		match self.validate_code_detailed(code) {
+			Validation::Valid { drift, .. } => Some(drift),
+			_ => None,
+		}
+	}
+
+	/// Validates the code like [Totp::validate_code], returning a detailed result.
+	///
+	/// If the code doesn't match, but is the code for a time step in the skew window that
+	/// was already accepted, or is older than the one that was, [Validation::Replayed] is returned.
+	pub fn validate_code_detailed(&mut self, code: u64) -> Validation {
+		let millis = self.time_source.unix_millis_wide();
+		self.validate_code_at_reported_time(code, millis)
+	}
+
+	/// Validates several codes at once, such as the current and previous codes pasted together,
+	/// returning the index of the first one that is valid for the current time,
+	/// along with the time step it matched.
+	///
+	/// The codes for the skew window are calculated once, and every code is compared against
+	/// each of them in constant time. Only the matched time step is marked as used.
+	pub fn validate_any(&mut self, codes: &[u64]) -> Option<(usize, u64)> {
+		let (counter, behind, ahead) = self.validation_window()?;
+		let steps = self.skew_steps(counter, behind, ahead);
+		let matched = self.hotp.find_counter_any(steps, codes);
+		self.accept(counter, matched.map(|(_, step)| step))?;
+		matched
+	}
+
+	/// Validates a code typed by a user as being valid for the current time,
+	/// taking the skew value into account like [Totp::validate_code].
+	///
+	/// Any ASCII whitespace and dashes are ignored, so "942 870 82" and "94287082\n" are accepted.
+	/// The code must have exactly as many digits as generated codes, including any leading zeroes,
+	/// and it is compared against the zero-padded form of each code.
+	pub fn validate_str(&mut self, code: &str) -> bool {
+		let Some((counter, behind, ahead)) = self.validation_window() else {
+			return false;
+		};
+		let mut buf = [0; otp::MAX_LENGTH];
+		let matched = match otp::parse_digits(code, &mut buf) {
+			Some(digits) => {
+				let steps = self.skew_steps(counter, behind, ahead);
+				self.hotp.find_counter_digits(steps, digits)
+			}
+			None => None,
+		};
+		self.accept(counter, matched).is_some()
+	}
+
+	#[cfg(feature = "sha1")]
+	/// Calculates the 31-bit value for the current time step, like [Hotp::raw_code],
+	/// for codes that map it differently than the usual decimal digits.
+	pub(crate) fn raw_code(&mut self) -> Result<u32, OtpError> {
+		let counter = self.counter()?;
+		self.hotp.raw_code_at(counter)
+	}
+
+	#[cfg(feature = "sha1")]
+	/// Validates a code against the 31-bit values for the time steps in the skew window,
+	/// using the given constant-time comparison, like [Totp::validate_str].
+	pub(crate) fn validate_raw<F: FnMut(u32) -> Choice>(&mut self, is_match: F) -> bool {
+		let Some((counter, behind, ahead)) = self.validation_window() else {
+			return false;
+		};
+		let steps = self.skew_steps(counter, behind, ahead);
+		let matched = self.hotp.find_counter_raw(steps, is_match);
+		self.accept(counter, matched).is_some()
+	}
+
+	/// Returns the time step for the current time to validate a code against, along with how
+	/// many earlier and later time steps are accepted, reporting a failed validation attempt
+	/// if the time source couldn't report the time, or it is out of range.
+	fn validation_window(&mut self) -> Option<(u64, u64, u64)> {
+		let window = self
+			.time_source
+			.unix_millis_wide()
+			.map_err(OtpError::from)
+			.and_then(|millis| self.validation_window_at_millis(millis));
+		match window {
+			Ok(window) => Some(window),
+			Err(_) => {
+				self.hotp.audit(AuditEvent::ValidationFailed);
+				None
 			}
 		}
-		false
 	}
 }
 
-#[cfg(feature = "std")]
-fn time_callback() -> u64 {
-	SystemTime::now()
-		.duration_since(UNIX_EPOCH)
-		.expect("time went backwards")
-		.as_secs()
-}
+#[cfg(feature = "alloc")]
+impl<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, C> Totp<D, C> {
+	/// Creates a new TOTP instance from an `otpauth://totp` provisioning URI, with the default
+	/// skew of 1. See [ParsedTotp](crate::uri::ParsedTotp) for parsing URIs without knowing the
+	/// algorithm first, or for keeping the label and issuer.
+	///
+	/// Returns [OtpError::UriParse] if the URI is invalid, or names a different algorithm.
+	pub fn from_uri(uri: &str, time_source: C) -> Result<Self, OtpError> {
+		uri::ParsedTotp::parse(uri)?.build(time_source)
+	}
 
-#[cfg(test)]
-mod tests {
-	use super::Totp;
-	use sha1::Sha1;
-	use sha2::{Sha256, Sha512};
+	/// Creates an `otpauth://totp` provisioning URI for this TOTP instance, such as for
+	/// showing it as a QR code. The secret has to be given, as it isn't retained.
+	///
+	/// Like [Hotp::to_uri], the label is prefixed with the issuer if one is given.
+	/// The period is the interval rounded down to whole seconds, as URIs can't carry
+	/// shorter ones, and the epoch and skew aren't included.
+	///
+	/// ```rust
+	/// # #[cfg(feature = "sha1")]
+	/// # {
+	/// use otpee::Totp;
+	/// use sha1::Sha1;
+	///
+	/// let totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 60, 1, || 59).unwrap();
+	/// assert_eq!(
+	///     totp.to_uri(b"12345678901234567890", "alice@example.com", Some("Example Co")),
+	///     "otpauth://totp/Example%20Co:alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+	///      &issuer=Example%20Co&algorithm=SHA1&digits=8&period=60"
+	/// );
+	/// # }
+	/// ```
+	pub fn to_uri(&self, secret: &[u8], label: &str, issuer: Option<&str>) -> String {
+		uri::build(&uri::UriParameters {
+			kind: "totp",
+			secret,
+			label,
+			issuer,
+			algorithm: D::ALGORITHM,
+			digits: self.hotp.digits(),
+			counter: None,
+			period: Some(self.interval.as_secs()),
+		})
+	}
 
-	#[test]
-	fn totp_sha1() {
-		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 0).unwrap();
-		assert_eq!(totp.code_at_time(59).unwrap(), 94287082);
-		assert_eq!(totp.code_at_time(1111111109).unwrap(), 7081804);
-		assert_eq!(totp.code_at_time(1111111111).unwrap(), 14050471);
-		assert_eq!(totp.code_at_time(1234567890).unwrap(), 89005924);
-		assert_eq!(totp.code_at_time(2000000000).unwrap(), 69279037);
-		assert_eq!(totp.code_at_time(20000000000).unwrap(), 65353130);
+	#[cfg(feature = "serde")]
+	/// Creates a new TOTP instance from a stored configuration, with the given time source.
+	///
+	/// Returns [OtpError::MissingSecret] if the configuration doesn't include the secret,
+	/// and [OtpError::AlgorithmMismatch] if it names a different algorithm.
+	pub fn from_config(config: &TotpConfig, time_source: C) -> Result<Self, OtpError> {
+		let secret = config.secret.as_ref().ok_or(OtpError::MissingSecret)?;
+		Self::from_config_with_key(secret, config, time_source)
 	}
 
-	#[test]
-	fn totp_sha256() {
-		let mut totp =
-			Totp::<Sha256>::new(b"12345678901234567890123456789012", 8, 30, 0, || 0).unwrap();
-		assert_eq!(totp.code_at_time(59).unwrap(), 46119246);
-		assert_eq!(totp.code_at_time(1111111109).unwrap(), 68084774);
-		assert_eq!(totp.code_at_time(1111111111).unwrap(), 67062674);
-		assert_eq!(totp.code_at_time(1234567890).unwrap(), 91819424);
-		assert_eq!(totp.code_at_time(2000000000).unwrap(), 90698825);
-		assert_eq!(totp.code_at_time(20000000000).unwrap(), 77737706);
+	#[cfg(feature = "serde")]
+	/// Creates a new TOTP instance from a stored configuration, using the given bytes as the
+	/// secret, and the given time source. Any secret stored in the configuration is ignored.
+	///
+	/// Returns [OtpError::AlgorithmMismatch] if the configuration names a different algorithm.
+	pub fn from_config_with_key<A: AsRef<[u8]>>(
+		key: A,
+		config: &TotpConfig,
+		time_source: C,
+	) -> Result<Self, OtpError> {
+		if config.algorithm != D::ALGORITHM {
+			return Err(OtpError::AlgorithmMismatch {
+				expected: D::ALGORITHM,
+				got: config.algorithm,
+			});
+		}
+		TotpBuilder::new()
+			.digits(config.digits)
+			.interval(config.period)
+			.skew(config.skew)
+			.epoch(config.epoch)
+			.time_source(time_source)
+			.build(key)
 	}
 
-	#[test]
-	fn totp_sha512() {
-		let mut totp = Totp::<Sha512>::new(
-			b"1234567890123456789012345678901234567890123456789012345678901234",
-			8,
-			30,
-			0,
-			|| 0,
-		)
-		.unwrap();
-		assert_eq!(totp.code_at_time(59).unwrap(), 90693936);
-		assert_eq!(totp.code_at_time(1111111109).unwrap(), 25091201);
-		assert_eq!(totp.code_at_time(1111111111).unwrap(), 99943326);
-		assert_eq!(totp.code_at_time(1234567890).unwrap(), 93441116);
-		assert_eq!(totp.code_at_time(2000000000).unwrap(), 38618901);
-		assert_eq!(totp.code_at_time(20000000000).unwrap(), 47863826);
+	#[cfg(feature = "serde")]
+	/// Returns the configuration of this instance, including the given secret, as it isn't retained.
+	/// Use [TotpConfig::without_secret] to leave it out.
+	///
+	/// Like [Totp::to_uri], the period is the interval rounded down to whole seconds.
+	pub fn to_config(&self, secret: &[u8]) -> TotpConfig {
+		TotpConfig {
+			secret: Some(Secret::from(secret)),
+			digits: self.hotp.digits(),
+			period: self.interval.as_secs(),
+			skew: self.skew,
+			algorithm: D::ALGORITHM,
+			epoch: self.epoch,
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> Totp<D> {
+	#[cfg(feature = "std")]
+	/// Creates a new TOTP instance, using the given bytes as the secret,
+	/// the given length, and the [SystemTime](std::time::SystemTime) to determine the current time.
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the system clock is set to before the unix epoch.
+	/// If it is set back later, the time is clamped to 0. The time only has a precision of one
+	/// second; pass [SystemClock](crate::time::SystemClock) to [Totp::new] for milliseconds.
+	pub fn new_from_system_time<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+	) -> Result<Self, OtpError> {
+		time::SystemClock.try_now_millis()?;
+		Self::new(key, length, interval, skew, time::system_time)
+	}
+
+	#[cfg(all(feature = "chrono", feature = "std"))]
+	/// Creates a new TOTP instance like [Totp::new_from_system_time],
+	/// using [Utc::now] to determine the current time.
+	pub fn new_with_chrono_clock<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+	) -> Result<Self, OtpError> {
+		time::datetime_millis(Utc::now())?;
+		Self::new(key, length, interval, skew, time::chrono_time)
+	}
+
+	#[cfg(all(feature = "time", feature = "std"))]
+	/// Creates a new TOTP instance like [Totp::new_from_system_time],
+	/// using [OffsetDateTime::now_utc] to determine the current time.
+	pub fn new_with_offsetdatetime_clock<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+	) -> Result<Self, OtpError> {
+		time::offsetdatetime_millis(OffsetDateTime::now_utc())?;
+		Self::new(key, length, interval, skew, time::offsetdatetime_time)
+	}
+
+	#[cfg(feature = "wasm")]
+	/// Creates a new TOTP instance, using the given bytes as the secret, the given length,
+	/// and JavaScript's [Date.now](js_sys::Date::now) to determine the current time,
+	/// for WebAssembly targets running in a browser, where `SystemTime` is unavailable.
+	///
+	/// The time only has a precision of one second, and is clamped to 0 if the clock
+	/// is set to before the unix epoch.
+	pub fn new_from_js_date<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+	) -> Result<Self, OtpError> {
+		Self::new(key, length, interval, skew, time::js_date_time)
+	}
+
+	/// Creates a builder for configuring a new TOTP instance.
+	#[inline]
+	pub fn builder() -> TotpBuilder<D> {
+		TotpBuilder::new()
+	}
+
+	#[cfg(feature = "getrandom")]
+	/// Creates a new TOTP instance with the default settings and a freshly generated secret,
+	/// returning both, so the secret can be shown to the user.
+	/// See [TotpBuilder::provision].
+	#[inline]
+	pub fn provision() -> Result<(Self, Secret), OtpError> {
+		Self::builder().provision()
+	}
+}
+
+#[cfg(feature = "std")]
+impl<D: Digest + BlockSizeUser + FixedOutputReset> Totp<D, time::MonotonicAnchor> {
+	/// Creates a new TOTP instance like [Totp::new_from_system_time], anchored to the current
+	/// time with a [MonotonicAnchor](time::MonotonicAnchor), so that the system clock jumping
+	/// doesn't affect it.
+	///
+	/// Returns [OtpError::TimeBeforeEpoch] if the system clock is set to before the unix epoch.
+	pub fn new_with_anchor<
+		A: AsRef<[u8]>,
+		L: Into<Option<usize>>,
+		I: Into<Option<u64>>,
+		S: Into<Option<usize>>,
+	>(
+		key: A,
+		length: L,
+		interval: I,
+		skew: S,
+	) -> Result<Self, OtpError> {
+		Self::new(key, length, interval, skew, time::MonotonicAnchor::new()?)
+	}
+
+	/// Returns the anchor, such as to [resync](time::MonotonicAnchor::resync) it.
+	#[inline]
+	pub fn anchor_mut(&mut self) -> &mut time::MonotonicAnchor {
+		&mut self.time_source
+	}
+}
+
+/// A [Totp] instance with a boxed time source, for when the type of the time source
+/// needs to be erased, such as to store instances with different time sources together.
+#[cfg(feature = "alloc")]
+pub type BoxedTotp<D> = Totp<D, Box<dyn TimeSource>>;
+
+/// The inner [Hotp] instance is printed with its key material redacted.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> Debug for Totp<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Totp")
+			.field("hotp", &self.hotp)
+			.field("interval", &self.interval)
+			.field("epoch", &self.epoch)
+			.field("skew", &self.skew)
+			.field("single_use", &self.single_use)
+			.field("strict_monotonic", &self.strict_monotonic)
+			.field("last_accepted_step", &self.last_accepted_step)
+			.field("drift_offset", &self.drift_offset)
+			.field("max_drift_offset", &self.max_drift_offset)
+			.field("learn_drift", &self.learn_drift)
+			.field("grace_period", &self.grace_period)
+			.field("tolerance", &self.tolerance)
+			.field("time_source", &format_args!("<time source>"))
+			.finish()
+	}
+}
+
+/// Clones get a copy of the time source of the original instance, and like [Hotp], they don't
+/// include its audit hook.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: Clone> Clone for Totp<D, C>
+where
+	Hmac<D>: Clone,
+{
+	fn clone(&self) -> Self {
+		Totp {
+			hotp: self.hotp.clone(),
+			interval: self.interval,
+			epoch: self.epoch,
+			skew: self.skew,
+			single_use: self.single_use,
+			strict_monotonic: self.strict_monotonic,
+			last_accepted_step: self.last_accepted_step,
+			drift_offset: self.drift_offset,
+			max_drift_offset: self.max_drift_offset,
+			learn_drift: self.learn_drift,
+			grace_period: self.grace_period,
+			tolerance: self.tolerance,
+			time_source: self.time_source.clone(),
+		}
+	}
+}
+
+/// Validation uses the skew window of the TOTP instance.
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C: TimeSource> OtpGenerator for Totp<D, C> {
+	#[inline]
+	fn current_code(&mut self) -> Result<Otp, OtpError> {
+		self.code()
+	}
+
+	#[inline]
+	fn validate(&mut self, code: u64) -> bool {
+		self.validate_code(code)
+	}
+
+	#[inline]
+	fn digits(&self) -> usize {
+		Totp::digits(self)
+	}
+}
+
+/// The window of time that a TOTP code is valid in, as returned by [Totp::code_with_validity].
+///
+/// The times are in seconds from the unix epoch, rounded down for intervals that aren't
+/// a whole number of seconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CodeWindow {
+	/// The time step of the code.
+	pub step: u64,
+	/// The time that the code becomes valid at.
+	pub not_before: u64,
+	/// The time that the code expires at, which is when the next time step starts.
+	pub not_after: u64,
+}
+
+/// A builder for [Totp] instances.
+///
+/// When the `std` feature is enabled, the [SystemTime] is used
+/// to determine the current time, unless another time source is given.
+/// Otherwise, a time source must be given, or building will fail with [OtpError::MissingTimeSource].
+///
+/// ```rust
+/// use otpee::Totp;
+/// use sha1::Sha1;
+///
+/// let mut totp = Totp::<Sha1>::builder()
+///     .digits(8)
+///     .interval(30)
+///     .skew(1)
+///     .time_source(|| 59)
+///     .build(b"12345678901234567890")
+///     .unwrap();
+/// assert_eq!(totp.code().unwrap(), 94287082);
+/// ```
+pub struct TotpBuilder<D, C = fn() -> u64> {
+	hotp: HotpBuilder<D>,
+	interval: Duration,
+	epoch: u64,
+	skew: usize,
+	time_source: Option<C>,
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> TotpBuilder<D> {
+	/// Creates a new builder, with the default of 6 digits, an interval of 30 seconds,
+	/// and a skew of 1.
+	pub fn new() -> Self {
+		Self {
+			hotp: HotpBuilder::new(),
+			interval: Duration::from_secs(30),
+			epoch: 0,
+			skew: 1,
+			#[cfg(feature = "std")]
+			time_source: Some(time::system_time),
+			#[cfg(not(feature = "std"))]
+			time_source: None,
+		}
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset, C> TotpBuilder<D, C> {
+	/// Sets the number of digits in generated codes, which must be between 1 and
+	/// [MAX_DIGITS](crate::hotp::MAX_DIGITS).
+	#[inline]
+	pub fn digits(mut self, digits: usize) -> Self {
+		self.hotp = self.hotp.digits(digits);
+		self
+	}
+
+	/// Sets whether a checksum digit is appended to generated codes.
+	/// See [Hotp::set_checksum].
+	#[inline]
+	pub fn checksum(mut self, checksum: bool) -> Self {
+		self.hotp = self.hotp.checksum(checksum);
+		self
+	}
+
+	/// Sets a fixed truncation offset.
+	/// See [Hotp::set_truncation_offset].
+	#[inline]
+	pub fn truncation_offset(mut self, offset: Option<u8>) -> Self {
+		self.hotp = self.hotp.truncation_offset(offset);
+		self
+	}
+
+	/// Sets whether secrets shorter than [MIN_KEY_LENGTH](crate::hotp::MIN_KEY_LENGTH)
+	/// should be rejected.
+	/// See [HotpBuilder::enforce_minimum_key_length].
+	#[inline]
+	pub fn enforce_minimum_key_length(mut self, enforce: bool) -> Self {
+		self.hotp = self.hotp.enforce_minimum_key_length(enforce);
+		self
+	}
+
+	/// Sets the length of each time step, in seconds.
+	#[inline]
+	pub fn interval(self, interval: u64) -> Self {
+		self.interval_duration(Duration::from_secs(interval))
+	}
+
+	/// Sets the length of each time step.
+	///
+	/// Intervals that aren't a whole number of seconds need a [TimeSource]
+	/// with millisecond precision, such as [Millis](crate::time::Millis).
+	#[inline]
+	pub fn interval_duration(mut self, interval: Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+
+	/// Sets the time that the first time step starts at, in seconds from the unix epoch,
+	/// which is T0 in RFC 6238. This defaults to 0.
+	///
+	/// Provisioning URIs have no way to carry it, so it has to be configured separately
+	/// on both sides when it isn't 0.
+	#[inline]
+	pub fn epoch(mut self, t0: u64) -> Self {
+		self.epoch = t0;
+		self
+	}
+
+	/// Sets how many time steps before and after the current one are accepted when validating.
+	#[inline]
+	pub fn skew(mut self, skew: usize) -> Self {
+		self.skew = skew;
+		self
+	}
+
+	/// Sets the number of digits, the interval and the skew to those of the preset,
+	/// which later calls can override.
+	///
+	/// The hash function is chosen by the type parameter, so it isn't changed;
+	/// see [PresetParams::algorithm](crate::preset::PresetParams::algorithm) for the one to use.
+	#[inline]
+	pub fn preset(self, preset: Preset) -> Self {
+		let params = preset.params();
+		self.digits(params.digits)
+			.interval(params.interval)
+			.skew(params.skew)
+	}
+
+	/// Sets the source of the current time, such as a closure returning seconds
+	/// from the unix epoch. See [TimeSource], or `AsyncTimeSource` for building an `AsyncTotp`.
+	#[inline]
+	pub fn time_source<T>(self, time_source: T) -> TotpBuilder<D, T> {
+		TotpBuilder {
+			hotp: self.hotp,
+			interval: self.interval,
+			epoch: self.epoch,
+			skew: self.skew,
+			time_source: Some(time_source),
+		}
+	}
+
+	/// Builds the TOTP instance, using the given bytes as the secret.
+	///
+	/// Returns [OtpError::InvalidInterval] if the interval is zero.
+	pub fn build<A: AsRef<[u8]>>(self, key: A) -> Result<Totp<D, C>, OtpError> {
+		let time_source = self.time_source.ok_or(OtpError::MissingTimeSource)?;
+		if self.interval.is_zero() {
+			return Err(OtpError::InvalidInterval);
+		}
+		Ok(Totp {
+			hotp: self.hotp.build(key)?,
+			interval: self.interval,
+			epoch: self.epoch,
+			skew: self.skew,
+			single_use: true,
+			strict_monotonic: false,
+			last_accepted_step: None,
+			drift_offset: 0,
+			max_drift_offset: DEFAULT_MAX_DRIFT_OFFSET,
+			learn_drift: false,
+			grace_period: None,
+			tolerance: None,
+			time_source,
+		})
+	}
+
+	#[cfg(feature = "getrandom")]
+	/// Builds the TOTP instance with a freshly generated secret of the recommended length for
+	/// the hash function, returning both, so the secret can be shown to the user.
+	pub fn provision(self) -> Result<(Totp<D, C>, Secret), OtpError> {
+		let secret = Secret::generate_for::<D>()?;
+		self.build(&secret).map(|totp| (totp, secret))
+	}
+}
+
+impl<D: Digest + BlockSizeUser + FixedOutputReset> Default for TotpBuilder<D> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<D, C> Debug for TotpBuilder<D, C> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_struct("TotpBuilder")
+			.field("hotp", &self.hotp)
+			.field("interval", &self.interval)
+			.field("epoch", &self.epoch)
+			.field("skew", &self.skew)
+			.field(
+				"time_source",
+				&self.time_source.as_ref().map(|_| "<time source>"),
+			)
+			.finish()
+	}
+}
+
+/// The settings used by the one-shot [generate] and [verify] functions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TotpOptions {
+	/// The number of digits in codes, 6 by default.
+	pub digits: usize,
+	/// The length of a time step in seconds, 30 by default.
+	pub interval: u64,
+	/// The number of time steps before and after the current one that are accepted, 1 by default.
+	pub skew: usize,
+}
+
+impl Default for TotpOptions {
+	fn default() -> Self {
+		Self {
+			digits: 6,
+			interval: 30,
+			skew: 1,
+		}
+	}
+}
+
+/// Creates a TOTP instance with the given options, fixed at the given time.
+fn one_shot<D: Digest + BlockSizeUser + FixedOutputReset>(
+	secret: &[u8],
+	unix_time: u64,
+	opts: &TotpOptions,
+) -> Result<Totp<D, impl Fn() -> u64>, OtpError> {
+	TotpBuilder::<D>::new()
+		.digits(opts.digits)
+		.interval(opts.interval)
+		.skew(opts.skew)
+		.time_source(move || unix_time)
+		.build(secret)
+}
+
+/// Calculates the TOTP code for the given time, in seconds from the unix epoch,
+/// without keeping a [Totp] instance around.
+///
+/// ```rust
+/// use otpee::totp::{self, TotpOptions};
+/// use sha1::Sha1;
+///
+/// let opts = TotpOptions {
+///     digits: 8,
+///     ..TotpOptions::default()
+/// };
+/// let code = totp::generate::<Sha1>(b"12345678901234567890", 59, &opts).unwrap();
+/// assert_eq!(code, 94287082);
+/// ```
+pub fn generate<D: Digest + BlockSizeUser + FixedOutputReset>(
+	secret: &[u8],
+	unix_time: u64,
+	opts: &TotpOptions,
+) -> Result<Otp, OtpError> {
+	one_shot::<D>(secret, unix_time, opts)?.code()
+}
+
+/// Validates the code for the given time, in seconds from the unix epoch, accepting the time
+/// steps within the skew, like [Totp::validate_code], without keeping a [Totp] instance around.
+///
+/// Every time step in the skew window is checked, so the time taken doesn't reveal which one
+/// matched. As nothing is remembered between calls, codes aren't rejected when they're reused,
+/// which the caller has to handle if needed.
+///
+/// ```rust
+/// use otpee::totp::{self, TotpOptions};
+/// use sha1::Sha1;
+///
+/// let opts = TotpOptions {
+///     digits: 8,
+///     ..TotpOptions::default()
+/// };
+/// assert!(totp::verify::<Sha1>(b"12345678901234567890", 94287082, 70, &opts));
+/// assert!(!totp::verify::<Sha1>(b"12345678901234567890", 94287082, 120, &opts));
+/// ```
+pub fn verify<D: Digest + BlockSizeUser + FixedOutputReset>(
+	secret: &[u8],
+	code: u64,
+	unix_time: u64,
+	opts: &TotpOptions,
+) -> bool {
+	one_shot::<D>(secret, unix_time, opts).is_ok_and(|mut totp| totp.validate_code(code))
+}
+
+/// The default maximum drift offset, in time steps.
+pub(crate) const DEFAULT_MAX_DRIFT_OFFSET: u64 = 10;
+
+const NANOS_PER_MILLI: u128 = 1_000_000;
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+fn saturating_secs(secs: u128) -> u64 {
+	saturating_u64(secs)
+}
+
+/// Converts the value to a [u64], saturating if it doesn't fit, such as a skew of [usize::MAX]
+/// on a target where it is wider.
+fn saturating_u64<T: TryInto<u64>>(value: T) -> u64 {
+	value.try_into().unwrap_or(u64::MAX)
+}
+
+/// Returns how many time steps the matched step is after the expected one.
+/// This is exact for any step in the skew window, even near the ends of the range of `u64`.
+fn drift(step: u64, counter: u64) -> i64 {
+	step.wrapping_sub(counter) as i64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Totp, TotpBuilder};
+	use crate::{
+		error::{OtpError, TimeError},
+		hotp::Hotp,
+		otp::Otp,
+		preset::Preset,
+		time::{Millis, TimeSource},
+		validation::Validation,
+		vectors::{TOTP_SHA1, TOTP_SHA256, TOTP_SHA512},
+	};
+	use alloc::{rc::Rc, vec::Vec};
+	use core::{cell::Cell, time::Duration};
+	use sha1::Sha1;
+	use sha2::{Sha256, Sha512};
+
+	#[test]
+	fn totp_sha1() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 0).unwrap();
+		for vector in TOTP_SHA1 {
+			assert_eq!(
+				*totp.code_at_time(vector.moving_factor).unwrap(),
+				vector.code
+			);
+		}
+	}
+
+	#[test]
+	fn totp_sha256() {
+		let mut totp =
+			Totp::<Sha256>::new(b"12345678901234567890123456789012", 8, 30, 0, || 0).unwrap();
+		for vector in TOTP_SHA256 {
+			assert_eq!(
+				*totp.code_at_time(vector.moving_factor).unwrap(),
+				vector.code
+			);
+		}
+	}
+
+	#[test]
+	fn totp_far_future_time_source() {
+		// A time in seconds goes through the same path as Totp::code_at_time,
+		// even though it doesn't fit in milliseconds.
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 6, 30, 1, || u64::MAX).unwrap();
+		assert_eq!(totp.counter(), Ok(u64::MAX / 30));
+		let code = totp.code().unwrap();
+		assert_eq!(code, totp.code_at_time(u64::MAX).unwrap());
+		assert_eq!(totp.valid_until(), Ok(u64::MAX));
+		assert!(totp.validate_code(*code));
+		assert_eq!(totp.last_accepted_step(), Some(u64::MAX / 30));
+
+		// Time sources that only report seconds through TimeSource::unix_seconds still
+		// have to fit in milliseconds.
+		struct Seconds(u64);
+
+		impl TimeSource for Seconds {
+			fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+				Ok(self.0)
+			}
+		}
+
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, Seconds(u64::MAX)).unwrap();
+		assert_eq!(
+			totp.code(),
+			Err(OtpError::TimeUnavailable(TimeError::OutOfRange))
+		);
+		assert!(!totp.validate_code(287082));
+		assert!(!totp.validate_str("287082"));
+		assert_eq!(totp.validate_any(&[287082]), None);
+		assert_eq!(totp.last_accepted_step(), None);
+
+		// The largest time that fits in milliseconds still works.
+		totp.set_time_source(Seconds(u64::MAX / 1000));
+		let code = totp.code().unwrap();
+		assert!(totp.validate_code(*code));
+	}
+
+	#[test]
+	fn totp_far_future_times() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 6).unwrap();
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 6, 30, 1, || 0).unwrap();
+		// Times in seconds aren't saturated when converted to milliseconds.
+		assert_eq!(
+			totp.code_at_time(u64::MAX).unwrap(),
+			hotp.code_at(u64::MAX / 30).unwrap()
+		);
+		assert_ne!(
+			totp.code_at_time(u64::MAX).unwrap(),
+			totp.code_at_time(u64::MAX / 1000 + 30).unwrap()
+		);
+
+		// With an interval of 1 second, the last time step is u64::MAX,
+		// and the skew window is cut short after it.
+		totp.set_interval(1).unwrap();
+		let last = *hotp.code_at(u64::MAX).unwrap();
+		let before = *hotp.code_at(u64::MAX - 1).unwrap();
+		assert_eq!(*totp.code_at_time(u64::MAX).unwrap(), last);
+		assert_eq!(
+			totp.validate_code_at_time_detailed(before, u64::MAX),
+			Validation::Valid {
+				step: u64::MAX - 1,
+				drift: -1
+			}
+		);
+		assert_eq!(
+			totp.validate_code_at_time_detailed(last, u64::MAX),
+			Validation::Valid {
+				step: u64::MAX,
+				drift: 0
+			}
+		);
+		assert_eq!(
+			totp.validate_code_at_time_detailed(last, u64::MAX),
+			Validation::Replayed
+		);
+
+		// A skew of usize::MAX saturates, rather than wrapping.
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 6, 1, usize::MAX, || {
+			u64::MAX / 1000
+		})
+		.unwrap();
+		let steps = totp.window_codes().unwrap().map(|(step, _)| step);
+		assert!(steps.take(3).eq([0, 1, 2]));
+	}
+
+	#[test]
+	fn totp_time_out_of_range() {
+		let mut totp = TotpBuilder::<Sha1>::new()
+			.interval_duration(Duration::from_millis(1))
+			.time_source(Millis(|| u64::MAX))
+			.build(b"12345678901234567890")
+			.unwrap();
+		// The time step for the last millisecond is u64::MAX, but no time step fits
+		// for a time in seconds that far in the future.
+		assert_eq!(totp.counter(), Ok(u64::MAX));
+		let mut codes = [totp.code().unwrap(); 2];
+		assert_eq!(totp.code_at_time(u64::MAX), Err(OtpError::TimeOutOfRange));
+		assert_eq!(
+			totp.validate_code_at_time_detailed(0, u64::MAX),
+			Validation::Invalid
+		);
+		assert_eq!(
+			totp.codes_into(u64::MAX, &mut codes),
+			Err(OtpError::TimeOutOfRange)
+		);
+		assert_eq!(
+			totp.codes_around(u64::MAX, 1, 1).collect::<Vec<_>>(),
+			[Err(OtpError::TimeOutOfRange)]
+		);
+
+		// Moving the last time step forward doesn't saturate either.
+		totp.set_drift_offset(1);
+		assert_eq!(totp.counter(), Err(OtpError::TimeOutOfRange));
+		assert_eq!(totp.code(), Err(OtpError::TimeOutOfRange));
+		assert!(!totp.validate_code(0));
+	}
+
+	#[cfg(not(target_arch = "wasm32"))]
+	proptest::proptest! {
+		#[test]
+		fn totp_extreme_inputs_dont_panic(
+			time: u64,
+			interval in 1..=u64::MAX,
+			sub_second: bool,
+			skew in 0..4usize,
+		) {
+			let interval = if sub_second {
+				Duration::from_nanos(interval)
+			} else {
+				Duration::from_secs(interval)
+			};
+			let mut totp = TotpBuilder::<Sha1>::new()
+				.interval_duration(interval)
+				.skew(skew)
+				.time_source(move || time)
+				.build(b"12345678901234567890")
+				.unwrap();
+			if let Ok(code) = totp.code_at_time(time) {
+				proptest::prop_assert!(totp.validate_code_at_time(*code, time));
+			}
+			let _ = totp.code_at_millis(time);
+			let _ = totp.counter();
+			let _ = totp.code_with_validity();
+			let _ = totp.seconds_remaining();
+			let _ = totp.valid_until();
+			let _ = totp.progress();
+			let _ = totp.validate_code(0);
+			if let Ok(codes) = totp.window_codes() {
+				codes.for_each(drop);
+			}
+			if let Ok(codes) = totp.preview(2) {
+				codes.for_each(drop);
+			}
+			if let Ok(codes) = totp.history(2) {
+				codes.for_each(drop);
+			}
+			totp.set_skew(usize::MAX);
+			let _ = totp.window_codes().map(|mut codes| codes.next());
+		}
+	}
+
+	#[test]
+	fn totp_grace_period() {
+		let time = Rc::new(Cell::new(62));
+		let clock = time.clone();
+		let new = || {
+			let clock = clock.clone();
+			let mut totp =
+				Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 2, move || clock.get())
+					.unwrap();
+			totp.set_grace_period(5);
+			totp
+		};
+		let codes = (0..5)
+			.map(|step| *new().code_at_time(step * 30).unwrap())
+			.collect::<Vec<_>>();
+
+		// 2 seconds into the time step, the previous step's code is accepted,
+		// but not any earlier ones, even though the skew would allow them.
+		assert_eq!(
+			new().validate_code_detailed(codes[1]),
+			Validation::Valid { step: 1, drift: -1 }
+		);
+		assert!(!new().validate_code(codes[0]));
+
+		// 10 seconds into it, the grace period is over.
+		time.set(70);
+		assert!(!new().validate_code(codes[1]));
+		assert!(new().validate_code(codes[2]));
+		// Later time steps are still accepted within the skew.
+		assert!(new().validate_code(codes[4]));
+
+		let mut totp = new();
+		totp.set_grace_period(None);
+		assert_eq!(totp.grace_period(), None);
+		assert!(totp.validate_code(codes[0]));
+	}
+
+	#[test]
+	fn totp_strict_monotonic() {
+		let time = Rc::new(Cell::new(59));
+		let clock = time.clone();
+		let new = |strict| {
+			let clock = clock.clone();
+			let mut totp =
+				Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, move || clock.get())
+					.unwrap();
+			totp.set_enforce_single_use(false);
+			totp.set_strict_monotonic(strict);
+			totp
+		};
+		let codes = (0..3)
+			.map(|step| *new(false).code_at_time(step * 30).unwrap())
+			.collect::<Vec<_>>();
+
+		for strict in [false, true] {
+			let mut totp = new(strict);
+			assert_eq!(totp.is_strict_monotonic(), strict);
+			assert_eq!(
+				totp.validate_code_detailed(codes[2]),
+				Validation::Valid { step: 2, drift: 1 }
+			);
+			assert_eq!(totp.last_accepted_step(), Some(2));
+			// The code for the previous time step was never used, and is within the skew.
+			assert_eq!(totp.validate_code(codes[1]), !strict);
+			// Without single use, the last accepted code can still be reused.
+			assert!(totp.validate_code(codes[2]));
+		}
+
+		// The last accepted time step can be restored, such as after a restart.
+		time.set(60);
+		let mut totp = new(true);
+		totp.restore_last_accepted_step(2);
+		assert!(!totp.validate_code(codes[1]));
+		assert!(totp.validate_code(codes[2]));
+		totp.set_strict_monotonic(false);
+		assert!(totp.validate_code(codes[1]));
+	}
+
+	#[test]
+	fn totp_tolerance_seconds() {
+		let time = Rc::new(Cell::new(60));
+		let clock = time.clone();
+		let new = || {
+			let clock = clock.clone();
+			let mut totp =
+				Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 2, move || clock.get())
+					.unwrap();
+			totp.set_tolerance_seconds(45, 10);
+			totp
+		};
+		assert_eq!(new().tolerance_seconds(), Some((45, 10)));
+		let codes = (0..5)
+			.map(|step| *new().code_at_time(step * 30).unwrap())
+			.collect::<Vec<_>>();
+
+		// At the start of time step 2, the previous one started 30 seconds ago,
+		// and the one before it 60 seconds ago, which is too old even though the skew allows it.
+		assert_eq!(
+			new().validate_code_detailed(codes[1]),
+			Validation::Valid { step: 1, drift: -1 }
+		);
+		assert!(!new().validate_code(codes[0]));
+		assert!(new().validate_code(codes[2]));
+		assert!(!new().validate_code(codes[3]));
+
+		// 15 seconds into it, the previous time step started exactly 45 seconds ago.
+		time.set(75);
+		assert!(new().validate_code(codes[1]));
+		time.set(76);
+		assert!(!new().validate_code(codes[1]));
+		assert!(new().validate_code(codes[2]));
+
+		// The next time step is accepted from 10 seconds before it starts.
+		time.set(79);
+		assert!(!new().validate_code(codes[3]));
+		time.set(80);
+		assert!(new().validate_code(codes[3]));
+		assert!(!new().validate_code(codes[4]));
+		assert_eq!(
+			new()
+				.window_codes()
+				.unwrap()
+				.map(|(step, _)| step)
+				.collect::<Vec<_>>(),
+			[2, 3]
+		);
+
+		let mut totp = new();
+		totp.clear_tolerance_seconds();
+		assert_eq!(totp.tolerance_seconds(), None);
+		assert!(totp.validate_code(codes[0]));
+	}
+
+	#[test]
+	fn totp_validate_code_at_time() {
+		let new = || {
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || -> u64 {
+				unreachable!("the time source shouldn't be used")
+			})
+			.unwrap()
+		};
+		for vector in TOTP_SHA1 {
+			let (code, time) = (vector.code, vector.moving_factor);
+			let step = time / 30;
+			assert!(new().validate_code_at_time(code, time));
+			assert_eq!(
+				new().validate_code_at_time_detailed(code, time + 30),
+				Validation::Valid { step, drift: -1 }
+			);
+			assert_eq!(
+				new().validate_code_at_time_detailed(code, time - 30),
+				Validation::Valid { step, drift: 1 }
+			);
+			assert!(!new().validate_code_at_time(code, time + 60));
+
+			let mut totp = new();
+			assert!(totp.validate_code_at_time(code, time));
+			assert_eq!(
+				totp.validate_code_at_time_detailed(code, time),
+				Validation::Replayed
+			);
+		}
+	}
+
+	#[test]
+	fn totp_one_shot() {
+		let opts = super::TotpOptions {
+			digits: 8,
+			..Default::default()
+		};
+		for (vectors, check) in [
+			(
+				TOTP_SHA1,
+				super::verify::<Sha1> as fn(&[u8], u64, u64, &_) -> bool,
+			),
+			(TOTP_SHA256, super::verify::<Sha256>),
+			(TOTP_SHA512, super::verify::<Sha512>),
+		] {
+			for vector in vectors {
+				let code = vector.code;
+				let time = vector.moving_factor;
+				assert!(check(vector.secret, code, time, &opts));
+				assert!(check(vector.secret, code, time + 30, &opts));
+				assert!(!check(vector.secret, code, time + 60, &opts));
+				assert!(!check(vector.secret, code ^ 1, time, &opts));
+			}
+		}
+		for vector in TOTP_SHA512 {
+			let code = super::generate::<Sha512>(vector.secret, vector.moving_factor, &opts);
+			assert_eq!(*code.unwrap(), vector.code);
+		}
+
+		let opts = super::TotpOptions {
+			interval: 0,
+			..opts
+		};
+		assert_eq!(
+			super::generate::<Sha1>(TOTP_SHA1[0].secret, 59, &opts),
+			Err(OtpError::InvalidInterval)
+		);
+		assert!(!super::verify::<Sha1>(
+			TOTP_SHA1[0].secret,
+			94287082,
+			59,
+			&opts
+		));
+	}
+
+	#[test]
+	fn totp_hotp_conversion() {
+		let mut hotp = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_counter(5).unwrap();
+		let time = Rc::new(Cell::new(0));
+		let mut totp = hotp
+			.into_totp(30, 0, {
+				let time = time.clone();
+				move || time.get()
+			})
+			.unwrap();
+		let mut reference = Hotp::<Sha1>::new(b"12345678901234567890", 8).unwrap();
+		for step in 0..10 {
+			time.set(step * 30 + 29);
+			assert_eq!(totp.counter().unwrap(), step);
+			assert_eq!(totp.code().unwrap(), reference.code_at(step).unwrap());
+		}
+		time.set(59);
+		assert_eq!(totp.code().unwrap(), 94287082);
+
+		let mut hotp = totp.into_hotp();
+		assert_eq!(hotp.counter(), 5);
+		assert_eq!(hotp.digits(), 8);
+		assert_eq!(hotp.code().unwrap(), reference.code_at(5).unwrap());
+		assert_eq!(
+			hotp.into_totp(0, 1, || 59).unwrap_err(),
+			OtpError::InvalidInterval
+		);
+	}
+
+	#[test]
+	fn totp_custom_hotp() {
+		use hmac::{Mac, SimpleHmac};
+
+		let mac = <SimpleHmac<Sha1> as Mac>::new_from_slice(b"12345678901234567890").unwrap();
+		let hotp = Hotp::with_hasher(mac, 8).unwrap();
+		let mut totp = Totp::from_hotp(hotp, 30, 1, || 0).unwrap();
+		for vector in TOTP_SHA1 {
+			assert_eq!(
+				*totp.code_at_time(vector.moving_factor).unwrap(),
+				vector.code
+			);
+		}
+		assert_eq!(totp.hotp().digits(), 8);
+
+		totp.hotp_mut().set_checksum(true);
+		assert!(totp.hotp().checksum());
+		assert_eq!(*totp.code_at_time(59).unwrap() / 10, 94287082);
+	}
+
+	#[test]
+	fn totp_sha256_ten_digits() {
+		let mut totp =
+			Totp::<Sha256>::new(b"12345678901234567890123456789012", 10, 30, 0, || 0).unwrap();
+		let vectors = [
+			(59, "0746119246"),
+			(1111111109, "1568084774"),
+			(1111111111, "1167062674"),
+			(1234567890, "0091819424"),
+			(2000000000, "1790698825"),
+			(20000000000, "0777737706"),
+		];
+		for (time, expected) in vectors {
+			let otp = totp.code_at_time(time).unwrap();
+			assert_eq!(otp.to_string(), expected);
+			assert_eq!(*otp, expected.parse::<u64>().unwrap());
+		}
+		let mut totp = Totp::<Sha256>::new(b"12345678901234567890123456789012", 10, 30, 0, || {
+			1111111109
+		})
+		.unwrap();
+		assert!(totp.validate_code(1568084774));
+	}
+
+	#[test]
+	fn totp_sha512() {
+		let mut totp = Totp::<Sha512>::new(
+			b"1234567890123456789012345678901234567890123456789012345678901234",
+			8,
+			30,
+			0,
+			|| 0,
+		)
+		.unwrap();
+		for vector in TOTP_SHA512 {
+			assert_eq!(
+				*totp.code_at_time(vector.moving_factor).unwrap(),
+				vector.code
+			);
+		}
+	}
+
+	#[test]
+	fn totp_code_uses_time_source() {
+		let vectors = [
+			(59, 94287082),
+			(1111111109, 7081804),
+			(1111111111, 14050471),
+			(1234567890, 89005924),
+			(2000000000, 69279037),
+			(20000000000, 65353130),
+		];
+		for (time, expected) in vectors {
+			let mut totp =
+				Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, move || time).unwrap();
+			assert_eq!(totp.code().unwrap(), expected);
+			assert_eq!(totp.code().unwrap(), totp.code_at_time(time).unwrap());
+			assert!(totp.validate_code(expected.into()));
+		}
+	}
+
+	#[cfg(feature = "chrono")]
+	#[test]
+	fn totp_chrono() {
+		use chrono::DateTime;
+
+		let mut totp = Totp::<Sha1>::new(TOTP_SHA1[0].secret, 8, 30, 0, || 0).unwrap();
+		for vector in TOTP_SHA1 {
+			let datetime = DateTime::from_timestamp(vector.moving_factor as i64, 0).unwrap();
+			assert_eq!(*totp.code_at_datetime(datetime).unwrap(), vector.code);
+			assert!(totp.validate_code_at_datetime(vector.code, datetime));
+		}
+		let before_epoch = DateTime::from_timestamp(-1, 0).unwrap();
+		assert_eq!(
+			totp.code_at_datetime(before_epoch),
+			Err(OtpError::TimeBeforeEpoch)
+		);
+		assert!(!totp.validate_code_at_datetime(94287082, before_epoch));
+		let mut clock = Totp::<Sha1>::new_with_chrono_clock(TOTP_SHA1[0].secret, 8, 30, 0).unwrap();
+		assert!(clock.counter().unwrap() > 0);
+	}
+
+	#[cfg(feature = "time")]
+	#[test]
+	fn totp_offsetdatetime() {
+		use ::time::{Duration, OffsetDateTime};
+
+		let mut totp = Totp::<Sha1>::new(TOTP_SHA1[0].secret, 8, 30, 0, || 0).unwrap();
+		for vector in TOTP_SHA1 {
+			let datetime =
+				OffsetDateTime::from_unix_timestamp(vector.moving_factor as i64).unwrap();
+			assert_eq!(*totp.code_at_offsetdatetime(datetime).unwrap(), vector.code);
+			assert!(totp.validate_code_at_offsetdatetime(vector.code, datetime));
+		}
+		let before_epoch = OffsetDateTime::UNIX_EPOCH - Duration::milliseconds(1);
+		assert_eq!(
+			totp.code_at_offsetdatetime(before_epoch),
+			Err(OtpError::TimeBeforeEpoch)
+		);
+		assert!(!totp.validate_code_at_offsetdatetime(94287082, before_epoch));
+		let mut clock =
+			Totp::<Sha1>::new_with_offsetdatetime_clock(TOTP_SHA1[0].secret, 8, 30, 0).unwrap();
+		assert!(clock.counter().unwrap() > 0);
+	}
+
+	#[test]
+	fn totp_time_unavailable() {
+		use crate::{error::TimeError, time::TimeSource};
+
+		/// A clock that fails the first time it's read, like an RTC that hasn't been set.
+		struct Unset(bool);
+
+		impl TimeSource for Unset {
+			fn unix_seconds(&mut self) -> Result<u64, TimeError> {
+				if core::mem::replace(&mut self.0, true) {
+					Ok(59)
+				} else {
+					Err(TimeError::NotSet)
+				}
+			}
+		}
+
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, Unset(false)).unwrap();
+		assert_eq!(
+			totp.code(),
+			Err(OtpError::TimeUnavailable(TimeError::NotSet))
+		);
+		assert_eq!(totp.code().unwrap(), 94287082);
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, Unset(false)).unwrap();
+		assert!(!totp.validate_code(94287082));
+		assert!(totp.validate_code(94287082));
+	}
+
+	#[test]
+	fn totp_send_sync() {
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<Totp<Sha1>>();
+		assert_send_sync::<Hotp<Sha256>>();
+	}
+
+	#[test]
+	fn totp_stateful_time_source() {
+		// Each sample of the clock advances it by one time step.
+		let mut time = 59;
+		let clock = move || {
+			let now = time;
+			time += 30;
+			now
+		};
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, clock).unwrap();
+		assert_eq!(totp.code().unwrap(), 94287082);
+		let next = Hotp::<Sha1>::new(b"12345678901234567890", 8)
+			.unwrap()
+			.code_at(2)
+			.unwrap();
+		assert!(totp.validate_code(*next));
+		assert_eq!(totp.counter().unwrap(), 3);
+	}
+
+	#[test]
+	fn totp_validate_str() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 59).unwrap();
+		assert!(totp.validate_str("94287082"));
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 59).unwrap();
+		assert!(!totp.validate_str("094287082"));
+		assert!(!totp.validate_str("4287082"));
+		assert!(!totp.validate_str("94287O82"));
+		assert!(totp.validate_str("9428 7082\r\n"));
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 1111111109).unwrap();
+		assert!(!totp.validate_str("7081804"));
+		assert!(totp.validate_str("07081804"));
+	}
+
+	#[test]
+	fn totp_replay_protection() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert_eq!(totp.last_accepted_step(), None);
+		assert!(totp.validate_code(94287082));
+		assert_eq!(totp.last_accepted_step(), Some(1));
+		assert!(!totp.validate_code(94287082));
+		assert!(!totp.validate_str("94287082"));
+		// The code for the previous step is within the skew window, but is now too old.
+		let previous = *totp.code_at_time(29).unwrap();
+		assert!(!totp.validate_code(previous));
+		// The code for the next step can still be used.
+		let next = *totp.code_at_time(60).unwrap();
+		assert!(totp.validate_code(next));
+		assert_eq!(totp.last_accepted_step(), Some(2));
+	}
+
+	#[test]
+	fn totp_enforce_single_use() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert!(totp.enforces_single_use());
+		assert!(totp.validate_code(94287082));
+		assert_eq!(totp.validate_code_detailed(94287082), Validation::Replayed);
+
+		totp.set_enforce_single_use(false);
+		assert!(totp.validate_code(94287082));
+		let previous = *totp.code_at_time(29).unwrap();
+		assert!(totp.validate_code(previous));
+		// The highest accepted step is kept, so enforcing again rejects both codes.
+		assert_eq!(totp.last_accepted_step(), Some(1));
+		totp.set_enforce_single_use(true);
+		assert!(!totp.validate_code(94287082));
+		assert!(!totp.validate_code(previous));
+
+		let mut restored = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		restored.restore_last_accepted_step(1);
+		assert_eq!(
+			restored.validate_code_detailed(94287082),
+			Validation::Replayed
+		);
+		let next = *restored.code_at_time(60).unwrap();
+		assert!(restored.validate_code(next));
+		assert_eq!(restored.last_accepted_step(), Some(2));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn totp_audit_hook() {
+		use crate::audit::AuditEvent;
+		use std::sync::{Arc, Mutex};
+
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		let sink = events.clone();
+		totp.set_audit_hook(move |event| sink.lock().unwrap().push(event));
+		let previous = *totp.code_at_time(29).unwrap();
+		assert!(totp.validate_code(previous));
+		assert!(!totp.validate_code(previous));
+		assert_eq!(
+			*events.lock().unwrap(),
+			[
+				AuditEvent::CodeGenerated { counter: 0 },
+				AuditEvent::ValidationSucceeded {
+					counter: 0,
+					drift: -1
+				},
+				AuditEvent::ValidationFailed,
+			]
+		);
+	}
+
+	#[test]
+	fn totp_codes_into() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 0).unwrap();
+		let mut codes = [totp.code().unwrap(); 4];
+		totp.codes_into(1111111109, &mut codes).unwrap();
+		for (offset, code) in (0..).zip(codes) {
+			assert_eq!(code, totp.code_at_time(1111111109 + offset * 30).unwrap());
+		}
+		assert_eq!(codes[0], 7081804);
+		assert_eq!(codes[1], 14050471);
+	}
+
+	#[test]
+	fn totp_validate_code_detailed() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		let next = *totp.code_at_time(60).unwrap();
+		let previous = *totp.code_at_time(29).unwrap();
+		assert_eq!(
+			totp.validate_code_detailed(94287082),
+			Validation::Valid { step: 1, drift: 0 }
+		);
+		assert_eq!(totp.validate_code_detailed(94287082), Validation::Replayed);
+		// Codes for steps before the accepted one can't be used anymore either.
+		assert_eq!(totp.validate_code_detailed(previous), Validation::Replayed);
+		assert_eq!(
+			totp.validate_code_detailed(next),
+			Validation::Valid { step: 2, drift: 1 }
+		);
+		assert_eq!(totp.validate_code_detailed(12345678), Validation::Invalid);
+	}
+
+	#[test]
+	fn totp_builder_getters() {
+		let totp = Totp::<Sha1>::builder()
+			.digits(8)
+			.interval(60)
+			.skew(2)
+			.time_source(|| 0)
+			.build(b"12345678901234567890")
+			.unwrap();
+		assert_eq!(totp.digits(), 8);
+		assert_eq!(totp.interval(), Duration::from_secs(60));
+		assert_eq!(totp.skew(), 2);
+		assert_eq!(totp.epoch(), 0);
+	}
+
+	#[test]
+	fn totp_epoch() {
+		let mut totp = Totp::<Sha1>::builder()
+			.digits(8)
+			.epoch(1111111100)
+			.skew(0)
+			.time_source(|| 1111111109)
+			.build(b"12345678901234567890")
+			.unwrap();
+		let step_zero = Hotp::<Sha1>::new(b"12345678901234567890", 8)
+			.unwrap()
+			.code_at(0)
+			.unwrap();
+		assert_eq!(totp.epoch(), 1111111100);
+		assert_eq!(totp.counter().unwrap(), 0);
+		assert_eq!(totp.code_at_time(1111111109).unwrap(), step_zero);
+		// Times before the epoch are in the first time step.
+		assert_eq!(totp.code_at_time(0).unwrap(), step_zero);
+		// 30 seconds after the epoch is the same step as 59 seconds after the unix epoch.
+		assert_eq!(*totp.code_at_time(1111111130).unwrap(), TOTP_SHA1[0].code);
+		assert!(totp.validate_code(*step_zero));
+		let mut restored = Totp::<Sha1>::from_state_with_key(
+			b"12345678901234567890",
+			&totp.export_state(),
+			|| 1111111130,
+		)
+		.unwrap();
+		assert_eq!(restored.counter().unwrap(), 1);
+	}
+
+	#[test]
+	fn totp_millisecond_interval() {
+		let now = Rc::new(Cell::new(1_250));
+		let clock = now.clone();
+		let mut totp = Totp::<Sha1>::builder()
+			.digits(8)
+			.interval_duration(Duration::from_millis(500))
+			.skew(0)
+			.time_source(Millis(move || clock.get()))
+			.build(b"12345678901234567890")
+			.unwrap();
+		let hotp = |counter| {
+			Hotp::<Sha1>::new(b"12345678901234567890", 8)
+				.unwrap()
+				.code_at(counter)
+				.unwrap()
+		};
+		assert_eq!(totp.interval(), Duration::from_millis(500));
+		assert_eq!(totp.counter().unwrap(), 2);
+		assert_eq!(totp.code().unwrap(), hotp(2));
+		assert_eq!(totp.code_at_millis(1_499).unwrap(), hotp(2));
+		assert_eq!(totp.code_at_millis(1_500).unwrap(), hotp(3));
+		// Whole seconds are still accepted, at 2 steps per second.
+		assert_eq!(totp.code_at_time(59).unwrap(), hotp(118));
+		assert!(totp.validate_code(*hotp(2)));
+		now.set(1_600);
+		assert!(!totp.validate_code(*hotp(2)));
+		assert!(totp.validate_code(*hotp(3)));
+
+		// A millisecond clock gives the same results for whole-second intervals.
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, Millis(|| 59_999)).unwrap();
+		assert_eq!(totp.counter().unwrap(), 1);
+		assert_eq!(*totp.code().unwrap(), TOTP_SHA1[0].code);
+	}
+
+	#[test]
+	fn totp_seconds_remaining() {
+		let now = Rc::new(Cell::new(59));
+		let clock = now.clone();
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 6, 30, 1, move || clock.get()).unwrap();
+		for (time, remaining, until, progress) in [
+			(59, 1, 60, 29.0 / 30.0),
+			(60, 30, 90, 0.0),
+			(61, 29, 90, 1.0 / 30.0),
+		] {
+			now.set(time);
+			assert_eq!(totp.seconds_remaining().unwrap(), remaining);
+			assert_eq!(totp.valid_until().unwrap(), until);
+			assert!((totp.progress().unwrap() - progress).abs() < f32::EPSILON);
+		}
+
+		let mut totp = Totp::<Sha1>::builder()
+			.interval_duration(Duration::from_millis(500))
+			.time_source(Millis(|| 1_250))
+			.build(b"12345678901234567890")
+			.unwrap();
+		assert_eq!(totp.seconds_remaining().unwrap(), 1);
+		assert_eq!(totp.valid_until().unwrap(), 2);
+		assert!((totp.progress().unwrap() - 0.5).abs() < f32::EPSILON);
+	}
+
+	#[test]
+	fn totp_code_with_validity() {
+		let now = Rc::new(Cell::new(59));
+		let clock = now.clone();
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, move || clock.get()).unwrap();
+		for (time, step, not_before) in
+			[(59, 1, 30), (60, 2, 60), (1111111109, 37037036, 1111111080)]
+		{
+			now.set(time);
+			let (code, window) = totp.code_with_validity().unwrap();
+			assert_eq!(code, totp.code_at_time(time).unwrap());
+			assert_eq!(window.step, step);
+			assert_eq!(window.not_before, not_before);
+			assert_eq!(window.not_after - window.not_before, 30);
+		}
+		let (code, _) = totp.code_with_validity().unwrap();
+		assert_eq!(*code, TOTP_SHA1[1].code);
+
+		// With a drift offset, the code is for another step, but the window is the current one.
+		now.set(59);
+		totp.set_drift_offset(-1);
+		let (code, window) = totp.code_with_validity().unwrap();
+		assert_eq!(code, totp.code_at_time(29).unwrap());
+		assert_eq!(window.step, 0);
+		assert_eq!(window.not_before, 30);
+		assert_eq!(window.not_after, totp.valid_until().unwrap());
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn totp_code_at_system_time() {
+		use std::time::{SystemTime, UNIX_EPOCH};
+
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 0).unwrap();
+		let time = UNIX_EPOCH + Duration::from_secs(59);
+		assert_eq!(*totp.code_at_system_time(time).unwrap(), TOTP_SHA1[0].code);
+		let before: SystemTime = UNIX_EPOCH - Duration::from_secs(10);
+		assert_eq!(
+			totp.code_at_system_time(before),
+			Err(OtpError::TimeBeforeEpoch)
+		);
+	}
+
+	#[test]
+	fn totp_clone() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert!(totp.validate_code(94287082));
+		let mut clone = totp.clone();
+		assert_eq!(clone.code().unwrap(), totp.code().unwrap());
+		assert_eq!(clone.last_accepted_step(), Some(1));
+		assert!(!clone.validate_code(94287082));
+
+		clone.set_time_source(|| 1111111109);
+		assert_eq!(*totp.code().unwrap(), TOTP_SHA1[0].code);
+		assert_eq!(*clone.code().unwrap(), TOTP_SHA1[1].code);
+	}
+
+	#[test]
+	fn totp_debug() {
+		let key = b"\xde\xad\xbe\xef secret key bytes";
+		let totp = Totp::<Sha1>::new(key, 8, 45, 1, || 59).unwrap();
+		let debug = format!("{:?}", totp);
+		assert!(debug.contains("interval: 45s"));
+		assert!(debug.contains("digits: 8"));
+		assert!(debug.contains("<redacted>"));
+		assert!(debug.contains("<time source>"));
+		assert!(!debug.contains("secret key bytes"));
+		for byte in key {
+			assert!(!debug.contains(&format!("{:#x}", byte)));
+			assert!(!debug.contains(&format!("{:02x}", byte)));
+		}
+	}
+
+	#[test]
+	fn totp_invalid_digits() {
+		assert_eq!(
+			Totp::<Sha1>::new(b"12345678901234567890", 0, 30, 0, || 0).err(),
+			Some(OtpError::InvalidDigits { got: 0 })
+		);
+	}
+
+	#[test]
+	fn totp_leaves_counter_untouched() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert_eq!(totp.code_at_time(1234567890).unwrap(), 89005924);
+		assert!(totp.validate_code(94287082));
+		assert!(!totp.validate_code(89005924));
+		assert_eq!(totp.hotp.counter(), 0);
+	}
+
+	#[test]
+	fn totp_builder() {
+		let mut totp = Totp::<Sha256>::builder()
+			.digits(8)
+			.skew(0)
+			.time_source(|| 1111111109)
+			.build(b"12345678901234567890123456789012")
+			.unwrap();
+		assert_eq!(totp.code().unwrap(), 68084774);
+		assert!(totp.validate_code(68084774));
+		assert!(Totp::<Sha1>::builder()
+			.digits(11)
+			.build(b"12345678901234567890")
+			.is_err());
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn totp_anchor() {
+		use crate::time::MonotonicAnchor;
+		use std::time::{Instant, UNIX_EPOCH};
+
+		let mut totp = Totp::<Sha1, _>::new_with_anchor(b"12345678901234567890", 8, 30, 0).unwrap();
+		assert!(totp.code().is_ok());
+		// The time step follows the time elapsed since anchoring, not the wall clock.
+		let anchor = MonotonicAnchor::from_parts(
+			Instant::now() - Duration::from_secs(30),
+			UNIX_EPOCH + Duration::from_secs(5),
+		)
+		.unwrap();
+		totp.set_time_source(anchor);
+		assert_eq!(totp.counter(), Ok(1));
+		assert!(totp.validate_code(94287082));
+		totp.anchor_mut().resync().unwrap();
+		assert!(totp.counter().unwrap() > 1);
+	}
+
+	#[test]
+	fn totp_builder_preset() {
+		let build = |builder: TotpBuilder<Sha256>| {
+			builder
+				.time_source(|| 1111111109)
+				.build(b"12345678901234567890123456789012")
+				.unwrap()
+		};
+		let mut preset = build(Totp::builder().preset(Preset::EightDigitSha256));
+		let mut manual = build(Totp::builder().digits(8).interval(30).skew(1));
+		assert_eq!(preset.digits(), manual.digits());
+		assert_eq!(preset.interval(), manual.interval());
+		assert_eq!(preset.skew(), manual.skew());
+		assert_eq!(preset.code().unwrap(), manual.code().unwrap());
+		assert_eq!(preset.code().unwrap(), 68084774);
+
+		// Later calls override the preset.
+		let totp = build(Totp::builder().preset(Preset::SevenDigit10s).skew(3));
+		assert_eq!(totp.digits(), 7);
+		assert_eq!(totp.interval(), Duration::from_secs(10));
+		assert_eq!(totp.skew(), 3);
+	}
+
+	#[test]
+	fn totp_base32() {
+		let mut totp =
+			Totp::<Sha1>::new_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", 8, 30, 0, || 0).unwrap();
+		assert_eq!(totp.code_at_time(59).unwrap(), 94287082);
+		assert_eq!(totp.code_at_time(1111111109).unwrap(), 7081804);
+		assert_eq!(totp.code_at_time(1111111111).unwrap(), 14050471);
+		assert_eq!(totp.code_at_time(1234567890).unwrap(), 89005924);
+		assert_eq!(totp.code_at_time(2000000000).unwrap(), 69279037);
+		assert_eq!(totp.code_at_time(20000000000).unwrap(), 65353130);
+	}
+
+	#[test]
+	fn totp_hex() {
+		let mut totp = Totp::<Sha256>::new_hex(
+			"3132333435363738393031323334353637383930313233343536373839303132",
+			8,
+			30,
+			0,
+			|| 0,
+		)
+		.unwrap();
+		assert_eq!(totp.code_at_time(59).unwrap(), 46119246);
+		assert_eq!(totp.code_at_time(1111111109).unwrap(), 68084774);
+	}
+
+	#[cfg(feature = "getrandom")]
+	#[test]
+	fn totp_provision() {
+		let (mut totp, secret) = Totp::<Sha256>::builder()
+			.time_source(|| 59)
+			.provision()
+			.unwrap();
+		assert_eq!(secret.len(), 32);
+		let mut expected = Totp::<Sha256>::new(&secret, 6, 30, 1, || 59).unwrap();
+		assert_eq!(totp.code().unwrap(), expected.code().unwrap());
+	}
+
+	#[test]
+	fn totp_validate_full_window() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 2, || 1111111109).unwrap();
+		assert!(!totp.validate_code(12345678));
+		assert_eq!(totp.validate_code_at(7081804), Some(1111111109 / 30));
+		assert_eq!(totp.validate_code_at(14050471), Some(1111111111 / 30));
+		let ahead = *totp.code_at_time(1111111109 + 60).unwrap();
+		assert_eq!(totp.validate_code_at(ahead), Some(1111111109 / 30 + 2));
+		let outside = *totp.code_at_time(1111111109 + 90).unwrap();
+		assert_eq!(totp.validate_code_at(outside), None);
+	}
+
+	#[test]
+	fn totp_validate_code_at_previous_step() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 1111111109).unwrap();
+		let counter = totp.counter().unwrap();
+		let previous = *totp.code_at_time(1111111109 - 30).unwrap();
+		assert_eq!(totp.validate_code_at(previous), Some(counter - 1));
+		assert_eq!(totp.validate_code_at(previous), None);
+	}
+
+	#[test]
+	fn totp_validate_with_drift() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 1111111109).unwrap();
+		totp.set_enforce_single_use(false);
+		for (time, drift) in [(1111111109, 0), (1111111109 - 30, -1), (1111111109 + 30, 1)] {
+			let code = *totp.code_at_time(time).unwrap();
+			assert_eq!(totp.validate_with_drift(code), Some(drift));
+		}
+		assert_eq!(totp.validate_with_drift(0), None);
+
+		// Time steps on either side of i64::MAX still give an exact drift.
+		let now = 1 << 63;
+		let mut totp = Totp::<Sha1>::builder()
+			.interval_duration(Duration::from_millis(1))
+			.time_source(Millis(move || now))
+			.build(b"12345678901234567890")
+			.unwrap();
+		let previous = *totp.code_at_millis(now - 1).unwrap();
+		assert_eq!(totp.validate_with_drift(previous), Some(-1));
+	}
+
+	#[test]
+	fn totp_drift_offset() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 1, || 1111111109).unwrap();
+		assert_eq!(totp.drift_offset(), 0);
+		totp.set_drift_offset(-2);
+		assert_eq!(totp.counter().unwrap(), 1111111109 / 30 - 2);
+		let behind = *totp.code_at_time(1111111109 - 90).unwrap();
+		assert_eq!(totp.validate_with_drift(behind), Some(-1));
+		// Without learning, the offset stays put.
+		assert_eq!(totp.drift_offset(), -2);
+		totp.set_drift_offset(1_000);
+		assert_eq!(totp.drift_offset(), 10);
+		totp.set_max_drift_offset(3);
+		assert_eq!(totp.drift_offset(), 3);
+
+		// A larger maximum is restored before the offset, so it isn't clamped to the default.
+		#[cfg(feature = "alloc")]
+		{
+			totp.set_max_drift_offset(20);
+			totp.set_drift_offset(15);
+			totp.auto_learn_drift(true);
+			let restored = Totp::<Sha1>::from_state_with_key(
+				b"12345678901234567890",
+				&totp.export_state(),
+				|| 1111111109,
+			)
+			.unwrap();
+			assert_eq!(restored.max_drift_offset(), 20);
+			assert_eq!(restored.drift_offset(), 15);
+			assert!(restored.learns_drift());
+		}
+	}
+
+	#[test]
+	fn totp_learn_drift() {
+		// The token falls behind by 10 more seconds every time it's used, up to 90 seconds.
+		let now = Rc::new(Cell::new(0));
+		let new = || {
+			let clock = now.clone();
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, move || clock.get())
+		};
+		let mut learning = new().unwrap();
+		learning.auto_learn_drift(true);
+		let mut fixed = new().unwrap();
+		let mut fixed_failed = false;
+		for (i, lag) in (0..=90).step_by(10).enumerate() {
+			now.set(30 * (1_000 + 100 * i as u64));
+			let code = *learning.code_at_time(now.get() - lag).unwrap();
+			assert!(learning.validate_code(code), "lag of {} seconds", lag);
+			fixed_failed |= !fixed.validate_code(code);
+		}
+		assert_eq!(learning.drift_offset(), -3);
+		assert!(fixed_failed);
+	}
+
+	#[test]
+	fn totp_validate_code_at_closest_step() {
+		// With a single digit, codes for different steps in the window collide.
+		let new = || Totp::<Sha1>::new(b"12345678901234567890", 1, 30, 3, || 300).unwrap();
+		let mut totp = new();
+		let counter = totp.counter().unwrap();
+		let steps = counter - 3..=counter + 3;
+		let codes: Vec<(u64, u64)> = steps
+			.map(|step| (step, *totp.code_at_time(step * 30).unwrap()))
+			.collect();
+		for &(_, code) in &codes {
+			let closest = codes
+				.iter()
+				.filter(|&&(_, other)| other == code)
+				.map(|&(step, _)| step)
+				.min_by_key(|&step| (step.abs_diff(counter), step));
+			assert_eq!(new().validate_code_at(code), closest);
+		}
+	}
+
+	#[test]
+	fn totp_codes_around() {
+		let mut totp = Totp::<Sha1>::new(b"12345678901234567890", 8, 30, 0, || 0).unwrap();
+		let codes = totp
+			.codes_around(1111111109, 1, 2)
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		assert_eq!(codes.len(), 4);
+		assert_eq!(codes[1], 7081804);
+		assert_eq!(codes[2], 14050471);
+		assert_eq!(codes[0], totp.code_at_time(1111111109 - 30).unwrap());
+		assert_eq!(codes[3], totp.code_at_time(1111111109 + 60).unwrap());
+		assert_eq!(totp.codes_around(59, 5, 0).count(), 2);
+	}
+
+	#[test]
+	fn totp_zero_interval() {
+		assert_eq!(
+			Totp::<Sha1, _>::new(b"12345678901234567890", 6, 0, 1, || 59).unwrap_err(),
+			OtpError::InvalidInterval
+		);
+		assert_eq!(
+			Totp::<Sha1>::builder()
+				.interval_duration(Duration::ZERO)
+				.time_source(|| 59)
+				.build(b"12345678901234567890")
+				.unwrap_err(),
+			OtpError::InvalidInterval
+		);
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert_eq!(totp.set_interval(0), Err(OtpError::InvalidInterval));
+		assert_eq!(totp.interval(), Duration::from_secs(30));
+		assert_eq!(totp.code().unwrap(), 94287082);
+	}
+
+	#[test]
+	fn totp_set_interval() {
+		let now = Rc::new(Cell::new(59));
+		let time = now.clone();
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, move || time.get()).unwrap();
+		assert!(totp.validate_code(94287082));
+		totp.set_interval(10).unwrap();
+		assert_eq!(totp.interval(), Duration::from_secs(10));
+		assert_eq!(totp.counter(), Ok(5));
+		// Step 1 of 30 seconds ended at 59, which is in step 5 of 10 seconds.
+		assert_eq!(totp.last_accepted_step(), Some(5));
+		totp.set_interval(60).unwrap();
+		assert_eq!(totp.last_accepted_step(), Some(0));
+		assert_eq!(totp.counter(), Ok(0));
+		now.set(60);
+		assert_eq!(totp.counter(), Ok(1));
+		let code = *totp.code_at_time(60).unwrap();
+		assert!(totp.validate_code(code));
+	}
+
+	#[test]
+	fn totp_set_skew() {
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 0, || 89).unwrap();
+		assert!(!totp.validate_code(94287082));
+		totp.set_skew(2);
+		assert_eq!(totp.skew(), 2);
+		assert!(totp.validate_code(94287082));
+		let code = *totp.code_at_time(120).unwrap();
+		totp.set_skew(0);
+		assert!(!totp.validate_code(code));
+	}
+
+	#[test]
+	fn totp_validate_any() {
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		let before = *totp.code_at_time(29).unwrap();
+		let after = *totp.code_at_time(60).unwrap();
+		assert_eq!(totp.validate_any(&[]), None);
+		assert_eq!(totp.validate_any(&[1, 2, 3]), None);
+		assert_eq!(totp.validate_any(&[1, before, 94287082]), Some((1, 0)));
+		// Only the matched step is used up, so the later codes are still valid.
+		assert_eq!(totp.validate_any(&[before, 94287082]), Some((1, 1)));
+		assert_eq!(totp.validate_any(&[after, after]), Some((0, 2)));
+		assert_eq!(totp.validate_any(&[after, 94287082]), None);
+
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert_eq!(
+			totp.validate_any(&[94287082, 94287082, before]),
+			Some((0, 1))
+		);
+		assert_eq!(totp.last_accepted_step(), Some(1));
+	}
+
+	#[test]
+	fn totp_preview_history() {
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 1111111109).unwrap();
+		let codes = |iter: &mut dyn Iterator<Item = (u64, Otp)>| {
+			iter.map(|(time, code)| (time, *code)).collect::<Vec<_>>()
+		};
+		let third = *totp.code_at_time(1111111140).unwrap();
+		assert_eq!(
+			codes(&mut totp.preview(3).unwrap()),
+			[
+				(1111111080, 7081804),
+				(1111111110, 14050471),
+				(1111111140, third)
+			]
+		);
+		let previous =
+			[1111111050, 1111111020].map(|time| (time, *totp.code_at_time(time).unwrap()));
+		assert_eq!(codes(&mut totp.history(2).unwrap()), previous);
+		// Neither counts as using the codes.
+		assert_eq!(totp.last_accepted_step(), None);
+		assert!(totp.validate_code(7081804));
+
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 1, || 59).unwrap();
+		assert_eq!(codes(&mut totp.history(5).unwrap()), [(0, 84755224)]);
+		assert_eq!(totp.preview(0).unwrap().count(), 0);
+
+		// With a drift offset, the codes are shifted, but the times are still those of the
+		// actual time steps.
+		totp.set_drift_offset(1);
+		let shifted = [60, 90].map(|time| *totp.code_at_time(time).unwrap());
+		assert_eq!(
+			codes(&mut totp.preview(2).unwrap()),
+			[(30, shifted[0]), (60, shifted[1])]
+		);
+		assert_eq!(codes(&mut totp.history(5).unwrap()), [(0, 94287082)]);
+	}
+
+	#[test]
+	fn totp_window_codes() {
+		let mut totp =
+			Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 2, || 1111111109).unwrap();
+		let codes = totp.window_codes().unwrap().collect::<Vec<_>>();
+		assert_eq!(codes.len(), 5);
+		for (i, &(step, code)) in codes.iter().enumerate() {
+			let time = 1111111109 - 60 + 30 * i as u64;
+			assert_eq!(step, totp.counter_at(time).unwrap());
+			assert_eq!(code, totp.code_at_time(time).unwrap());
+		}
+		assert_eq!(codes[2].1, 7081804);
+		// Listing the codes doesn't count as using them.
+		assert!(totp.validate_code(7081804));
+		assert_eq!(totp.window_codes().unwrap().count(), 5);
+
+		let mut totp = Totp::<Sha1, _>::new(b"12345678901234567890", 8, 30, 2, || 59).unwrap();
+		let steps = totp
+			.window_codes()
+			.unwrap()
+			.map(|(step, _)| step)
+			.collect::<Vec<_>>();
+		assert_eq!(steps, [0, 1, 2, 3]);
+		assert_eq!(
+			totp.window_codes_string().unwrap(),
+			format!(
+				"0: {}, 1: 94287082, 2: {}, 3: {}",
+				totp.code_at_time(0).unwrap(),
+				totp.code_at_time(60).unwrap(),
+				totp.code_at_time(90).unwrap()
+			)
+		);
+	}
+
+	#[test]
+	fn otp_generator_objects() {
+		use crate::{Hotp, OtpGenerator};
+		use alloc::{boxed::Box, vec, vec::Vec};
+
+		let mut generators: Vec<Box<dyn OtpGenerator>> = vec![
+			Box::new(
+				Hotp::<Sha1>::builder()
+					.window(2)
+					.build(b"12345678901234567890")
+					.unwrap(),
+			),
+			Box::new(
+				Totp::<Sha512>::new(
+					b"1234567890123456789012345678901234567890123456789012345678901234",
+					8,
+					30,
+					1,
+					|| 1111111109,
+				)
+				.unwrap(),
+			),
+		];
+		assert_eq!(generators[0].digits(), 6);
+		assert_eq!(generators[1].digits(), 8);
+		assert_eq!(generators[0].current_code().unwrap(), 755224);
+		assert_eq!(generators[1].current_code().unwrap(), 25091201);
+		// The HOTP window accepts a code two counters ahead, then refuses to replay it.
+		assert!(generators[0].validate(359152));
+		assert!(!generators[0].validate(359152));
+		assert_eq!(generators[0].current_code().unwrap(), 969429);
+		assert!(generators[1].validate(25091201));
+		assert!(!generators[1].validate(755224));
 	}
 }
@@ -0,0 +1,650 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use crate::{
+	algorithm::{Algorithm, HashAlgorithm},
+	base32,
+	error::{OtpError, UriError},
+	hotp::{Hotp, MAX_DIGITS},
+	secret::Secret,
+	totp::Totp,
+};
+#[cfg(feature = "dynamic")]
+use crate::{
+	dynamic::{DynHotp, DynTotp},
+	time::TimeSource,
+};
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, str::FromStr};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// The settings of a HOTP token, parsed from an `otpauth://hotp` provisioning URI.
+///
+/// As the algorithm is only known once the URI is parsed, this can either be built into a
+/// [Hotp] for a specific hash function with [ParsedHotp::build], or, with the `dynamic` feature,
+/// into a [DynHotp] for whichever one the URI names.
+///
+/// ```rust
+/// use otpee::{uri::ParsedHotp, Algorithm};
+///
+/// let parsed = ParsedHotp::parse(
+///     "otpauth://hotp/Example:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=5&digits=8",
+/// )
+/// .unwrap();
+/// assert_eq!(parsed.issuer.as_deref(), Some("Example"));
+/// assert_eq!(parsed.label, "alice");
+/// assert_eq!(parsed.algorithm, Algorithm::Sha1);
+/// assert_eq!((parsed.digits, parsed.counter), (8, 5));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedHotp {
+	/// The secret.
+	pub secret: Secret,
+	/// The label, usually the account name, without the issuer prefix.
+	pub label: String,
+	/// The issuer, from either the `issuer` parameter or the label prefix.
+	pub issuer: Option<String>,
+	/// The hash algorithm, which defaults to SHA-1.
+	pub algorithm: Algorithm,
+	/// The number of digits, which defaults to 6.
+	pub digits: usize,
+	/// The initial counter value.
+	pub counter: u64,
+}
+
+impl ParsedHotp {
+	/// Parses an `otpauth://hotp` provisioning URI.
+	///
+	/// Returns [OtpError::UriParse] if the URI is malformed, is for a TOTP token,
+	/// or is missing the secret or counter.
+	pub fn parse(uri: &str) -> Result<Self, OtpError> {
+		let uri = parse(uri)?;
+		if uri.kind != "hotp" {
+			return Err(UriError::WrongType.into());
+		}
+		Ok(Self {
+			secret: uri.secret,
+			label: uri.label,
+			issuer: uri.issuer,
+			algorithm: uri.algorithm,
+			digits: uri.digits,
+			counter: uri.counter.ok_or(UriError::MissingCounter)?,
+		})
+	}
+
+	/// Builds a HOTP instance with the parsed settings, using the given hash function.
+	///
+	/// Returns [UriError::AlgorithmMismatch] if the URI names a different algorithm.
+	pub fn build<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm>(
+		&self,
+	) -> Result<Hotp<D>, OtpError> {
+		if D::ALGORITHM != self.algorithm {
+			return Err(UriError::AlgorithmMismatch.into());
+		}
+		Hotp::builder()
+			.digits(self.digits)
+			.counter(self.counter)
+			.build(&self.secret)
+	}
+
+	#[cfg(feature = "dynamic")]
+	/// Builds a HOTP instance with the parsed settings, using whichever algorithm the URI names.
+	pub fn build_dynamic(&self) -> Result<DynHotp, OtpError> {
+		let mut hotp = DynHotp::new(self.algorithm, &self.secret, self.digits)?;
+		hotp.set_counter(self.counter)?;
+		Ok(hotp)
+	}
+}
+
+impl FromStr for ParsedHotp {
+	type Err = OtpError;
+
+	#[inline]
+	fn from_str(uri: &str) -> Result<Self, Self::Err> {
+		Self::parse(uri)
+	}
+}
+
+/// The settings of a TOTP token, parsed from an `otpauth://totp` provisioning URI.
+///
+/// Like [ParsedHotp], this can be built into a [Totp] for a specific hash function with
+/// [ParsedTotp::build], or, with the `dynamic` feature, into a [DynTotp].
+///
+/// ```rust
+/// use otpee::{uri::ParsedTotp, Algorithm};
+///
+/// let parsed = ParsedTotp::parse(
+///     "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+///      &issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+/// )
+/// .unwrap();
+/// assert_eq!(parsed.issuer.as_deref(), Some("ACME Co"));
+/// assert_eq!(parsed.label, "john.doe@email.com");
+/// assert_eq!(parsed.algorithm, Algorithm::Sha256);
+/// assert_eq!((parsed.digits, parsed.period), (8, 60));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedTotp {
+	/// The secret.
+	pub secret: Secret,
+	/// The label, usually the account name, without the issuer prefix.
+	pub label: String,
+	/// The issuer, from either the `issuer` parameter or the label prefix.
+	pub issuer: Option<String>,
+	/// The hash algorithm, which defaults to SHA-1.
+	pub algorithm: Algorithm,
+	/// The number of digits, which defaults to 6.
+	pub digits: usize,
+	/// The length of a time step in seconds, which defaults to 30.
+	pub period: u64,
+}
+
+impl ParsedTotp {
+	/// Parses an `otpauth://totp` provisioning URI.
+	///
+	/// Returns [OtpError::UriParse] if the URI is malformed, is for a HOTP token,
+	/// or is missing the secret.
+	pub fn parse(uri: &str) -> Result<Self, OtpError> {
+		let uri = parse(uri)?;
+		if uri.kind != "totp" {
+			return Err(UriError::WrongType.into());
+		}
+		Ok(Self {
+			secret: uri.secret,
+			label: uri.label,
+			issuer: uri.issuer,
+			algorithm: uri.algorithm,
+			digits: uri.digits,
+			period: uri.period.unwrap_or(30),
+		})
+	}
+
+	/// Builds a TOTP instance with the parsed settings, using the given hash function
+	/// and time source, and the default skew of 1.
+	///
+	/// Returns [UriError::AlgorithmMismatch] if the URI names a different algorithm.
+	pub fn build<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm, C>(
+		&self,
+		time_source: C,
+	) -> Result<Totp<D, C>, OtpError> {
+		if D::ALGORITHM != self.algorithm {
+			return Err(UriError::AlgorithmMismatch.into());
+		}
+		Totp::new(&self.secret, self.digits, self.period, None, time_source)
+	}
+
+	#[cfg(feature = "dynamic")]
+	/// Builds a TOTP instance with the parsed settings, using whichever algorithm the URI names,
+	/// and the default skew of 1.
+	pub fn build_dynamic<C: TimeSource + 'static>(
+		&self,
+		time_source: C,
+	) -> Result<DynTotp, OtpError> {
+		DynTotp::new(
+			self.algorithm,
+			&self.secret,
+			self.digits,
+			self.period,
+			None,
+			time_source,
+		)
+	}
+}
+
+impl FromStr for ParsedTotp {
+	type Err = OtpError;
+
+	#[inline]
+	fn from_str(uri: &str) -> Result<Self, Self::Err> {
+		Self::parse(uri)
+	}
+}
+
+/// The parameters of a provisioning URI, as described by the Key Uri Format used by
+/// Google Authenticator and most other authenticator apps.
+pub(crate) struct UriParameters<'a> {
+	pub kind: &'a str,
+	pub secret: &'a [u8],
+	pub label: &'a str,
+	pub issuer: Option<&'a str>,
+	pub algorithm: Algorithm,
+	pub digits: usize,
+	pub counter: Option<u64>,
+	pub period: Option<u64>,
+}
+
+/// Builds an `otpauth://` provisioning URI.
+///
+/// The label is prefixed with the issuer, if there is one, as recommended for
+/// compatibility with older apps that ignore the `issuer` parameter.
+pub(crate) fn build(params: &UriParameters<'_>) -> String {
+	let mut uri = String::from("otpauth://");
+	uri.push_str(params.kind);
+	uri.push('/');
+	if let Some(issuer) = params.issuer {
+		percent_encode(&mut uri, issuer);
+		uri.push(':');
+	}
+	percent_encode(&mut uri, params.label);
+	uri.push_str("?secret=");
+	uri.push_str(&base32::encode(params.secret));
+	if let Some(issuer) = params.issuer {
+		uri.push_str("&issuer=");
+		percent_encode(&mut uri, issuer);
+	}
+	// Writing to a string can't fail.
+	let _ = write!(
+		uri,
+		"&algorithm={}&digits={}",
+		params.algorithm, params.digits
+	);
+	if let Some(counter) = params.counter {
+		let _ = write!(uri, "&counter={}", counter);
+	}
+	if let Some(period) = params.period {
+		let _ = write!(uri, "&period={}", period);
+	}
+	uri
+}
+
+/// A parsed provisioning URI, of either type.
+pub(crate) struct ParsedUri {
+	pub kind: String,
+	pub secret: Secret,
+	pub label: String,
+	pub issuer: Option<String>,
+	pub algorithm: Algorithm,
+	pub digits: usize,
+	pub counter: Option<u64>,
+	pub period: Option<u64>,
+}
+
+/// Parses an `otpauth://` provisioning URI, applying the defaults for any missing parameters.
+/// The type is lowercased, but otherwise not checked.
+pub(crate) fn parse(uri: &str) -> Result<ParsedUri, UriError> {
+	const SCHEME: &str = "otpauth://";
+	if !uri
+		.get(..SCHEME.len())
+		.is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+	{
+		return Err(UriError::InvalidScheme);
+	}
+	let rest = &uri[SCHEME.len()..];
+	let (kind, rest) = rest.split_once('/').ok_or(UriError::InvalidScheme)?;
+	let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+	// A literal colon separates the issuer prefix, so that an encoded one can be part of it,
+	// but some apps encode the separator too.
+	let (mut issuer, label) = match label.split_once(':') {
+		Some((issuer, account)) => (
+			Some(percent_decode(issuer, false)?),
+			percent_decode(account, false)?,
+		),
+		None => {
+			let label = percent_decode(label, false)?;
+			match label.split_once(':') {
+				Some((issuer, account)) => (Some(issuer.into()), account.into()),
+				None => (None, label),
+			}
+		}
+	};
+	let label = String::from(label.trim_start());
+	let mut secret = None;
+	let mut algorithm = Algorithm::default();
+	let mut digits = 6;
+	let mut counter = None;
+	let mut period = None;
+	for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+		let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+		let value = percent_decode(value, true)?;
+		match key {
+			"secret" => {
+				secret = Some(Secret::from_base32(&value).map_err(|_| UriError::InvalidSecret)?)
+			}
+			"issuer" => issuer = Some(value),
+			"algorithm" => {
+				algorithm = Algorithm::from_name(&value).ok_or(UriError::InvalidAlgorithm)?
+			}
+			"digits" => {
+				digits = value
+					.parse()
+					.ok()
+					.filter(|digits| (1..=MAX_DIGITS).contains(digits))
+					.ok_or(UriError::InvalidDigits)?
+			}
+			"counter" => counter = Some(value.parse().map_err(|_| UriError::InvalidCounter)?),
+			"period" => {
+				period = Some(
+					value
+						.parse()
+						.ok()
+						.filter(|&period| period > 0)
+						.ok_or(UriError::InvalidPeriod)?,
+				)
+			}
+			// Unknown parameters are ignored, as some apps add their own.
+			_ => {}
+		}
+	}
+	Ok(ParsedUri {
+		kind: kind.to_ascii_lowercase(),
+		secret: secret
+			.filter(|secret| !secret.is_empty())
+			.ok_or(UriError::MissingSecret)?,
+		label,
+		issuer,
+		algorithm,
+		digits,
+		counter,
+		period,
+	})
+}
+
+/// Decodes percent-encoding, and in parameter values, `+` as a space.
+pub(crate) fn percent_decode(input: &str, query: bool) -> Result<String, UriError> {
+	let mut out = Vec::with_capacity(input.len());
+	let mut bytes = input.bytes();
+	while let Some(byte) = bytes.next() {
+		match byte {
+			b'%' => {
+				let high = bytes.next().and_then(hex_value);
+				let low = bytes.next().and_then(hex_value);
+				match (high, low) {
+					(Some(high), Some(low)) => out.push(high << 4 | low),
+					_ => return Err(UriError::InvalidEncoding),
+				}
+			}
+			b'+' if query => out.push(b' '),
+			_ => out.push(byte),
+		}
+	}
+	String::from_utf8(out).map_err(|_| UriError::InvalidEncoding)
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+	(c as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Percent-encodes a label or parameter value, leaving only unreserved characters and `@` as they are.
+/// Colons are encoded too, as they separate the issuer from the account name in labels.
+fn percent_encode(out: &mut String, input: &str) {
+	for byte in input.bytes() {
+		match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => {
+				out.push(byte as char)
+			}
+			_ => {
+				let _ = write!(out, "%{:02X}", byte);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ParsedHotp, ParsedTotp};
+	use crate::{
+		algorithm::Algorithm,
+		error::{OtpError, UriError},
+		secret::Secret,
+	};
+
+	#[test]
+	fn uri_parse_hotp() {
+		let parsed = ParsedHotp::parse(
+			"otpauth://hotp/Caf%C3%A9%20Corp:%20alice@example.com\
+			 ?secret=gezdgnbvgy3tqojqgezdgnbvgy3tqojq&counter=5&digits=8&algorithm=sha256",
+		)
+		.unwrap();
+		assert_eq!(
+			parsed,
+			ParsedHotp {
+				secret: Secret::from(b"12345678901234567890"),
+				label: "alice@example.com".into(),
+				issuer: Some("Café Corp".into()),
+				algorithm: Algorithm::Sha256,
+				digits: 8,
+				counter: 5,
+			}
+		);
+		// The issuer parameter takes precedence, and missing parameters use the defaults.
+		let parsed: ParsedHotp =
+			"otpauth://HOTP/Old:bob?issuer=New+Corp&secret=MZXW6YQ&counter=0&foo=bar"
+				.parse()
+				.unwrap();
+		assert_eq!(parsed.issuer.as_deref(), Some("New Corp"));
+		assert_eq!(parsed.label, "bob");
+		assert_eq!(parsed.algorithm, Algorithm::Sha1);
+		assert_eq!(parsed.digits, 6);
+		assert_eq!(parsed.secret.as_bytes(), b"foob");
+	}
+
+	#[test]
+	fn uri_parse_malformed() {
+		let cases = [
+			(
+				"https://hotp/alice?secret=MZXW6YQ&counter=0",
+				UriError::InvalidScheme,
+			),
+			("otpauth://totp/alice?secret=MZXW6YQ", UriError::WrongType),
+			("otpauth://hotp/alice?counter=0", UriError::MissingSecret),
+			(
+				"otpauth://hotp/alice?secret=&counter=0",
+				UriError::MissingSecret,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ1&counter=0",
+				UriError::InvalidSecret,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ",
+				UriError::MissingCounter,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ&counter=-1",
+				UriError::InvalidCounter,
+			),
+			// A multibyte character across the end of the scheme.
+			(
+				"otpauth:/é/x?secret=GEZDGNBV&counter=0",
+				UriError::InvalidScheme,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ&counter=0&algorithm=MD5",
+				UriError::InvalidAlgorithm,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ&counter=0&digits=12",
+				UriError::InvalidDigits,
+			),
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ&counter=0&digits=0",
+				UriError::InvalidDigits,
+			),
+			(
+				"otpauth://hotp/al%2ice?secret=MZXW6YQ&counter=0",
+				UriError::InvalidEncoding,
+			),
+			(
+				"otpauth://hotp/%FF?secret=MZXW6YQ&counter=0",
+				UriError::InvalidEncoding,
+			),
+		];
+		for (uri, err) in cases {
+			assert_eq!(
+				ParsedHotp::parse(uri),
+				Err(OtpError::UriParse(err)),
+				"{}",
+				uri
+			);
+		}
+	}
+
+	#[test]
+	fn uri_parse_totp() {
+		// From Google's Key Uri Format documentation.
+		let parsed = ParsedTotp::parse(
+			"otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+		)
+		.unwrap();
+		assert_eq!(
+			parsed,
+			ParsedTotp {
+				secret: Secret::from(b"Hello!\xde\xad\xbe\xef"),
+				label: "alice@google.com".into(),
+				issuer: Some("Example".into()),
+				algorithm: Algorithm::Sha1,
+				digits: 6,
+				period: 30,
+			}
+		);
+		let parsed = ParsedTotp::parse(
+			"otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+			 &issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30",
+		)
+		.unwrap();
+		assert_eq!(parsed.issuer.as_deref(), Some("ACME Co"));
+		assert_eq!(parsed.label, "john.doe@email.com");
+		assert_eq!(parsed.secret.len(), 20);
+		// GitHub's format, with the issuer only in the label prefix of the second one.
+		let parsed: ParsedTotp = "otpauth://totp/GitHub:octocat?secret=MZXW6YTBOI&issuer=GitHub"
+			.parse()
+			.unwrap();
+		assert_eq!(parsed.issuer.as_deref(), Some("GitHub"));
+		assert_eq!(parsed.label, "octocat");
+		let parsed = ParsedTotp::parse("otpauth://totp/GitHub:octocat?secret=MZXW6YTBOI").unwrap();
+		assert_eq!(parsed.issuer.as_deref(), Some("GitHub"));
+		assert_eq!(parsed.secret.as_bytes(), b"foobar");
+		// Padded and lowercase secrets are accepted, and the period can be changed.
+		let parsed = ParsedTotp::parse(
+			"otpauth://totp/alice?secret=mzxw6yq%3D&algorithm=SHA512&digits=8&period=60",
+		)
+		.unwrap();
+		assert_eq!(parsed.issuer, None);
+		assert_eq!(parsed.secret.as_bytes(), b"foob");
+		assert_eq!(parsed.algorithm, Algorithm::Sha512);
+		assert_eq!((parsed.digits, parsed.period), (8, 60));
+		assert_eq!(
+			ParsedTotp::parse("otpauth://totp/alice?secret=MZXW6YQ=")
+				.unwrap()
+				.secret
+				.as_bytes(),
+			b"foob"
+		);
+	}
+
+	#[test]
+	fn uri_parse_totp_malformed() {
+		let cases = [
+			(
+				"otpauth://hotp/alice?secret=MZXW6YQ&counter=0",
+				UriError::WrongType,
+			),
+			(
+				"otpauth:/totp/alice?secret=MZXW6YQ",
+				UriError::InvalidScheme,
+			),
+			(
+				"otpauth://totp/alice?issuer=Example",
+				UriError::MissingSecret,
+			),
+			(
+				"otpauth://totp/alice?secret=MZXW6YQ&period=",
+				UriError::InvalidPeriod,
+			),
+			(
+				"otpauth://totp/alice?secret=MZXW6YQ&period=0",
+				UriError::InvalidPeriod,
+			),
+			(
+				"otpauth://totp/alice?secret=MZXW6YQ&period=30s",
+				UriError::InvalidPeriod,
+			),
+		];
+		for (uri, err) in cases {
+			assert_eq!(
+				ParsedTotp::parse(uri),
+				Err(OtpError::UriParse(err)),
+				"{}",
+				uri
+			);
+		}
+	}
+
+	#[cfg(all(feature = "sha1", feature = "sha2"))]
+	#[test]
+	fn uri_totp_build() {
+		use crate::totp::Totp;
+		use sha1::Sha1;
+
+		let uri = "otpauth://totp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8";
+		let mut totp = Totp::<Sha1, _>::from_uri(uri, || 59).unwrap();
+		assert_eq!(totp.code().unwrap(), 94287082);
+		assert_eq!(totp.skew(), 1);
+		let parsed = ParsedTotp::parse(uri).unwrap();
+		assert_eq!(
+			parsed.build::<sha2::Sha256, _>(|| 59).err(),
+			Some(OtpError::UriParse(UriError::AlgorithmMismatch))
+		);
+	}
+
+	#[cfg(all(feature = "sha1", feature = "sha2"))]
+	#[test]
+	fn uri_totp_round_trip() {
+		use crate::totp::Totp;
+		use sha1::Sha1;
+		use sha2::Sha256;
+
+		// As imported by Google Authenticator and Aegis, with the defaults included.
+		let totp = Totp::<Sha1, _>::new(b"Hello!\xde\xad\xbe\xef", 6, 30, 1, || 59).unwrap();
+		assert_eq!(
+			totp.to_uri(
+				b"Hello!\xde\xad\xbe\xef",
+				"alice@google.com",
+				Some("Example")
+			),
+			"otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example\
+			 &algorithm=SHA1&digits=6&period=30"
+		);
+		assert_eq!(
+			totp.to_uri(b"foob", "alice", None),
+			"otpauth://totp/alice?secret=MZXW6YQ&algorithm=SHA1&digits=6&period=30"
+		);
+
+		let mut totp = Totp::<Sha256, _>::new(b"12345678901234567890", 8, 60, 1, || 59).unwrap();
+		let uri = totp.to_uri(b"12345678901234567890", "bob: work", Some("Zürich Bank"));
+		assert_eq!(
+			uri,
+			"otpauth://totp/Z%C3%BCrich%20Bank:bob%3A%20work?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+			 &issuer=Z%C3%BCrich%20Bank&algorithm=SHA256&digits=8&period=60"
+		);
+		let parsed = ParsedTotp::parse(&uri).unwrap();
+		assert_eq!(parsed.label, "bob: work");
+		assert_eq!(parsed.issuer.as_deref(), Some("Zürich Bank"));
+		assert_eq!((parsed.digits, parsed.period), (8, 60));
+		let mut restored = Totp::<Sha256, _>::from_uri(&uri, || 59).unwrap();
+		assert_eq!(restored.interval(), totp.interval());
+		assert_eq!(restored.code().unwrap(), totp.code().unwrap());
+	}
+
+	#[cfg(all(feature = "sha1", feature = "sha2"))]
+	#[test]
+	fn uri_round_trip() {
+		use crate::hotp::Hotp;
+		use sha1::Sha1;
+		use sha2::Sha512;
+
+		let mut hotp = Hotp::<Sha512>::new(b"12345678901234567890", 8).unwrap();
+		hotp.set_counter(42).unwrap();
+		let uri = hotp.to_uri(b"12345678901234567890", "alice:work", Some("Café Corp"));
+		let parsed = ParsedHotp::parse(&uri).unwrap();
+		assert_eq!(parsed.issuer.as_deref(), Some("Café Corp"));
+		assert_eq!(parsed.label, "alice:work");
+		let mut restored = parsed.build::<Sha512>().unwrap();
+		assert_eq!(restored.counter(), 42);
+		assert_eq!(restored.code().unwrap(), hotp.code().unwrap());
+		assert_eq!(
+			parsed.build::<Sha1>().err(),
+			Some(OtpError::UriParse(UriError::AlgorithmMismatch))
+		);
+		let mut restored = Hotp::<Sha512>::from_uri(&uri).unwrap();
+		assert_eq!(restored.code().unwrap(), hotp.code().unwrap());
+	}
+}
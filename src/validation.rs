@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/// The detailed result of validating a code, as returned by
+/// [Hotp::validate_detailed](crate::Hotp::validate_detailed) and
+/// [Totp::validate_code_detailed](crate::Totp::validate_code_detailed).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Validation {
+	/// The code was accepted.
+	Valid {
+		/// The counter, or time step, that the code matched.
+		step: u64,
+		/// How far the matched step was from the expected one: for HOTP, how far ahead of the
+		/// stored counter it was, and for TOTP, how many steps before or after the current one.
+		drift: i64,
+	},
+	/// The code was valid, but has already been used, so it was rejected.
+	Replayed,
+	/// The code was rejected.
+	Invalid,
+}
+
+impl Validation {
+	/// Returns whether the code was accepted.
+	#[inline]
+	pub fn is_valid(&self) -> bool {
+		matches!(self, Validation::Valid { .. })
+	}
+}
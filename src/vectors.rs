@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! The test vectors from [RFC 4226](https://www.rfc-editor.org/rfc/rfc4226#appendix-D)
+//! and [RFC 6238](https://www.rfc-editor.org/rfc/rfc6238#appendix-B), for checking
+//! other implementations, such as custom hash functions or MACs, against.
+
+use crate::{
+	algorithm::{Algorithm, HashAlgorithm},
+	error::OtpError,
+	hotp::Hotp,
+};
+use digest::{core_api::BlockSizeUser, Digest, FixedOutputReset};
+
+/// The time step of the TOTP test vectors, in seconds.
+pub const TOTP_INTERVAL: u64 = 30;
+
+/// A single test vector.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Vector {
+	/// The shared secret.
+	pub secret: &'static [u8],
+	/// The counter for HOTP, or the Unix time in seconds for TOTP.
+	pub moving_factor: u64,
+	/// The expected code.
+	pub code: u64,
+	/// The number of digits in the code.
+	pub digits: usize,
+}
+
+const SHA1_SECRET: &[u8] = b"12345678901234567890";
+const SHA256_SECRET: &[u8] = b"12345678901234567890123456789012";
+const SHA512_SECRET: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+const fn vector(secret: &'static [u8], moving_factor: u64, code: u64, digits: usize) -> Vector {
+	Vector {
+		secret,
+		moving_factor,
+		code,
+		digits,
+	}
+}
+
+/// The HOTP HMAC-SHA1 test vectors from RFC 4226, for counters 0 through 9.
+pub const HOTP_SHA1: &[Vector] = &[
+	vector(SHA1_SECRET, 0, 755224, 6),
+	vector(SHA1_SECRET, 1, 287082, 6),
+	vector(SHA1_SECRET, 2, 359152, 6),
+	vector(SHA1_SECRET, 3, 969429, 6),
+	vector(SHA1_SECRET, 4, 338314, 6),
+	vector(SHA1_SECRET, 5, 254676, 6),
+	vector(SHA1_SECRET, 6, 287922, 6),
+	vector(SHA1_SECRET, 7, 162583, 6),
+	vector(SHA1_SECRET, 8, 399871, 6),
+	vector(SHA1_SECRET, 9, 520489, 6),
+];
+
+/// The TOTP HMAC-SHA1 test vectors from RFC 6238.
+pub const TOTP_SHA1: &[Vector] = &[
+	vector(SHA1_SECRET, 59, 94287082, 8),
+	vector(SHA1_SECRET, 1111111109, 7081804, 8),
+	vector(SHA1_SECRET, 1111111111, 14050471, 8),
+	vector(SHA1_SECRET, 1234567890, 89005924, 8),
+	vector(SHA1_SECRET, 2000000000, 69279037, 8),
+	vector(SHA1_SECRET, 20000000000, 65353130, 8),
+];
+
+/// The TOTP HMAC-SHA256 test vectors from RFC 6238.
+pub const TOTP_SHA256: &[Vector] = &[
+	vector(SHA256_SECRET, 59, 46119246, 8),
+	vector(SHA256_SECRET, 1111111109, 68084774, 8),
+	vector(SHA256_SECRET, 1111111111, 67062674, 8),
+	vector(SHA256_SECRET, 1234567890, 91819424, 8),
+	vector(SHA256_SECRET, 2000000000, 90698825, 8),
+	vector(SHA256_SECRET, 20000000000, 77737706, 8),
+];
+
+/// The TOTP HMAC-SHA512 test vectors from RFC 6238.
+pub const TOTP_SHA512: &[Vector] = &[
+	vector(SHA512_SECRET, 59, 90693936, 8),
+	vector(SHA512_SECRET, 1111111109, 25091201, 8),
+	vector(SHA512_SECRET, 1111111111, 99943326, 8),
+	vector(SHA512_SECRET, 1234567890, 93441116, 8),
+	vector(SHA512_SECRET, 2000000000, 38618901, 8),
+	vector(SHA512_SECRET, 20000000000, 47863826, 8),
+];
+
+/// A test vector that a generator failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+	/// The failed test vector.
+	pub vector: Vector,
+	/// The code that was generated instead, or the error that occurred.
+	pub got: Result<u64, OtpError>,
+}
+
+/// Returns the TOTP test vectors for the given algorithm.
+pub fn totp_vectors(algorithm: Algorithm) -> &'static [Vector] {
+	match algorithm {
+		Algorithm::Sha1 => TOTP_SHA1,
+		Algorithm::Sha256 => TOTP_SHA256,
+		Algorithm::Sha512 => TOTP_SHA512,
+	}
+}
+
+/// Runs [Hotp] with the hash function `D` against every test vector for its algorithm,
+/// returning the first mismatch, if any.
+///
+/// ```rust
+/// # #[cfg(feature = "sha1")]
+/// # {
+/// use sha1::Sha1;
+///
+/// assert_eq!(otpee::vectors::verify_all::<Sha1>(), Ok(()));
+/// # }
+/// ```
+pub fn verify_all<D: Digest + BlockSizeUser + FixedOutputReset + HashAlgorithm>(
+) -> Result<(), Mismatch> {
+	if D::ALGORITHM == Algorithm::Sha1 {
+		verify(HOTP_SHA1, |vector| {
+			Hotp::<D>::new(vector.secret, vector.digits)?
+				.code_at(vector.moving_factor)
+				.map(|otp| *otp)
+		})?;
+	}
+	verify(totp_vectors(D::ALGORITHM), |vector| {
+		Hotp::<D>::new(vector.secret, vector.digits)?
+			.code_at(vector.moving_factor / TOTP_INTERVAL)
+			.map(|otp| *otp)
+	})
+}
+
+/// Runs the given generator against a table of test vectors,
+/// returning the first mismatch, if any.
+pub fn verify<F: FnMut(&Vector) -> Result<u64, OtpError>>(
+	vectors: &[Vector],
+	mut generate: F,
+) -> Result<(), Mismatch> {
+	for vector in vectors {
+		let got = generate(vector);
+		if got != Ok(vector.code) {
+			return Err(Mismatch {
+				vector: *vector,
+				got,
+			});
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{verify, Mismatch, HOTP_SHA1};
+	use crate::{error::OtpError, hotp::Hotp};
+	use sha1::Sha1;
+
+	#[cfg(all(feature = "sha1", feature = "sha2"))]
+	#[test]
+	fn vectors_verify_all() {
+		assert_eq!(super::verify_all::<Sha1>(), Ok(()));
+		assert_eq!(super::verify_all::<sha2::Sha256>(), Ok(()));
+		assert_eq!(super::verify_all::<sha2::Sha512>(), Ok(()));
+	}
+
+	#[test]
+	fn vectors_mismatch() {
+		let result = verify(HOTP_SHA1, |vector| {
+			Hotp::<Sha1>::new(vector.secret, vector.digits)?
+				.code_at(vector.moving_factor + 1)
+				.map(|otp| *otp)
+		});
+		assert_eq!(
+			result,
+			Err(Mismatch {
+				vector: HOTP_SHA1[0],
+				got: Ok(287082),
+			})
+		);
+		let result = verify(HOTP_SHA1, |_| Err(OtpError::InvalidDigits { got: 0 }));
+		assert_eq!(
+			result.unwrap_err().got,
+			Err(OtpError::InvalidDigits { got: 0 })
+		);
+	}
+}
@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Run in a headless browser with `wasm-pack test --headless --firefox -- --features wasm`.
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+use otpee::Totp;
+use sha1::Sha1;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn js_date_code() {
+	let mut totp = Totp::<Sha1>::new_from_js_date(b"12345678901234567890", 8, 30, 1).unwrap();
+	let now = (js_sys::Date::now() / 1000.0) as u64;
+	assert!(now > 1_600_000_000);
+	let code = totp.code().unwrap();
+	// The time step may have just changed between reading the time and calculating the code.
+	assert!([now, now + 30]
+		.iter()
+		.any(|&time| totp.code_at_time(time).unwrap() == code));
+	assert!(totp.validate_code(*code));
+	assert!(!totp.validate_code(*code));
+}